@@ -1,21 +1,37 @@
 use std::fs;
-use std::path::Path;
-use shaderc::{Compiler, ShaderKind, CompileOptions};
+use std::path::{Path, PathBuf};
+use shaderc::{Compiler, ShaderKind, CompileOptions, IncludeType, ResolvedInclude};
+
+/// Overrides the directory the shaders, and the snippets they `#include`, are read from.
+const SHADER_DIR_VAR: &str = "BURST_SHADER_DIR";
 
 fn main() {
-    println!("cargo:rerun-if-changed=gapi/rendering/");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let shader_dir = std::env::var_os(SHADER_DIR_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| root.join("src/gapi/shaders"));
+    println!("cargo:rerun-if-env-changed={SHADER_DIR_VAR}");
+    // Also covers the included snippets, which live next to the shaders.
+    println!("cargo:rerun-if-changed={}", shader_dir.display());
 
     let mut compiler = Compiler::new().expect("Failed to create shader compiler");
     let mut options = CompileOptions::new().unwrap();
     options.set_optimization_level(shaderc::OptimizationLevel::Performance);
-    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
-    let vert_src = root.join("src/gapi/shaders/shader.vert");
-    let frag_src = root.join("src/gapi/shaders/shader.frag");
+    let include_dir = shader_dir.clone();
+    options.set_include_callback(move |requested, include_type, requesting, _depth| {
+        resolve_include(&include_dir, requested, include_type, requesting)
+    });
+    let vert_src = shader_dir.join("shader.vert");
+    let frag_src = shader_dir.join("shader.frag");
+    let cube_vert_src = shader_dir.join("cube.vert");
+    let debug_vert_src = shader_dir.join("debug.vert");
 
     // Just the filenames, not the full paths yet
     let shaders = [
         (vert_src.to_str().unwrap(), "vert.spv", ShaderKind::Vertex),
         (frag_src.to_str().unwrap(), "frag.spv", ShaderKind::Fragment),
+        (cube_vert_src.to_str().unwrap(), "cube_vert.spv", ShaderKind::Vertex),
+        (debug_vert_src.to_str().unwrap(), "debug_vert.spv", ShaderKind::Vertex),
     ];
 
     let out_dir = std::env::var("OUT_DIR").unwrap();
@@ -38,4 +54,30 @@ fn main() {
         fs::write(&dest_path, binary_result.as_binary_u8())
             .expect("Failed to write SPIR-V file");
     }
+}
+
+/// Resolves `#include "requested"` relative to the `requesting` shader first, then to the
+/// `shader_dir`, and `#include <requested>` only relative to the `shader_dir`.
+///
+/// The error is reported by the compiler prefixed with the file and line of the directive.
+fn resolve_include(
+    shader_dir: &Path,
+    requested: &str,
+    include_type: IncludeType,
+    requesting: &str,
+) -> Result<ResolvedInclude, String> {
+    let relative_dir = Path::new(requesting).parent().filter(|_| matches!(include_type, IncludeType::Relative));
+    let candidates = relative_dir.into_iter().chain([shader_dir]).map(|dir| dir.join(requested));
+    for path in candidates {
+        if let Ok(content) = fs::read_to_string(&path) {
+            return Ok(ResolvedInclude {
+                resolved_name: path.to_string_lossy().into_owned(),
+                content,
+            });
+        }
+    }
+    Err(format!(
+        "Cannot find \"{requested}\" included from {requesting}, searched next to it and in {}",
+        shader_dir.display()
+    ))
 }
\ No newline at end of file
@@ -1,41 +1,543 @@
 use crate::{debug_success, info_success};
 
-use crate::gapi::vulkan::commands::command_buffers::CommandBuffers;
+use crate::gapi::chunk_grid::{ChunkEvent, ChunkGrid};
+use crate::gapi::chunk_world::ChunkId;
+use crate::gapi::color::Color;
+use crate::gapi::debug_draw::{DebugDraw, DebugPipelines};
+use crate::gapi::errors::BurstError;
+use crate::gapi::frame_uniforms::{FrameStats, FrameUniformBuffers, FrameUniforms};
+use crate::gapi::material::{MaterialPalette, MaterialPaletteBuffer};
+use crate::gapi::scene::SceneState;
+use crate::gapi::viewport_layout::{ViewportLayout, ViewportRegion};
+use crate::gapi::voxel_render_mode::{CubeInstance, InstancedCubes, VoxelRenderMode};
+use crate::gapi::vulkan::commands::command_buffers::{CommandBuffer, CommandBuffers};
+use crate::gapi::vulkan::config::{FRAMES_IN_FLIGHT, MAX_FRAME_DELTA, MAX_SWAPCHAIN_RECREATIONS_PER_FRAME, VALIDATION_ENABLED};
 use crate::gapi::vulkan::commands::command_pool::CommandPool;
+use crate::gapi::vulkan::core::debug::Debugger;
+use crate::gapi::vulkan::core::device_selection::{DeviceSelectionPolicy, PowerSource};
+use crate::gapi::vulkan::core::quality::QualityDowngradePolicy;
 use crate::gapi::vulkan::core::entry::Entry;
 use crate::gapi::vulkan::core::instance::Instance;
 use crate::gapi::vulkan::core::logical_device::LogicalDevice;
 use crate::gapi::vulkan::core::queues::{QueueCapability, QueueRequest};
-use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::core::real_device::{BufferDeviceAddressSupport, RealDevice};
 use crate::gapi::vulkan::core::surface::Surface;
+use crate::gapi::vulkan::descriptors::descriptor_pool::FrameDescriptorSets;
+use crate::gapi::vulkan::descriptors::descriptor_set_layout::DescriptorSetLayout;
+use crate::gapi::vulkan::descriptors::material_set::{MaterialSet, FRAME_SET};
 use crate::gapi::vulkan::enums::extensions::{DeviceExtension, PORTABILITY_MACOS_VERSION};
+use crate::gapi::vulkan::guard::{failure_point, Guard};
+use crate::gapi::vulkan::memory::color_buffer::ColorBuffer;
+use crate::gapi::vulkan::memory::depth_buffer::{DepthBuffer, DepthFormatPreference};
 use crate::gapi::vulkan::memory::framebuffer::Framebuffer;
-use crate::gapi::vulkan::memory::swapchain::Swapchain;
-use crate::gapi::vulkan::pipeline::pipeline::Pipeline;
-use crate::gapi::vulkan::pipeline::render_pass::MyRenderPass;
-use crate::gapi::vulkan::pipeline::viewport::Viewport;
+use crate::gapi::vulkan::memory::sampler_cache::SamplerCache;
+use crate::gapi::vulkan::memory::staging::StagingUploader;
+use crate::gapi::vulkan::memory::swapchain::{PreTransformPolicy, PresentModePolicy, Swapchain, SwapchainConfig};
+use crate::gapi::vulkan::memory::texture_array::TextureArray;
+use crate::gapi::vulkan::pipeline::dynamic_rendering::{DynamicRendering, RenderingAttachments, RenderingFormats};
+use crate::gapi::vulkan::pipeline::pipeline::{Pipeline, PipelineConfig};
+use crate::gapi::vulkan::pipeline::pipeline_cache::PipelineCache;
+use crate::gapi::vulkan::pipeline::render_pass::{MsaaResolveMode, MyRenderPass};
+use crate::gapi::vulkan::pipeline::stages::per_fragment_tests_stage::{DepthConvention, DepthPass};
+use crate::gapi::vulkan::pipeline::stages::rasterization_stage::DepthBias;
+use crate::gapi::vulkan::pipeline::viewport::{Viewport, ViewportYFlip};
+use crate::gapi::vulkan::sync::frame_sync::FrameSync;
+use crate::gapi::vulkan::sync::render_result::RenderResult;
 use crate::window::MyWindow;
 use anyhow::{anyhow, bail, Context};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use log::{debug, info, trace, warn};
 use thiserror::Error;
 use vulkanalia::vk;
-use vulkanalia::vk::{HasBuilder, ShaderStageFlags};
+use vulkanalia::vk::{Handle, HasBuilder, ShaderStageFlags};
 
 const VERT_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/vert.spv"));
 const FRAG_DATA: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/frag.spv"));
 
+/// How often the app renders frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Renders frames back to back, even if nothing changed. This is what games want, as
+    /// something is usually animating, at the cost of keeping a CPU core busy.
+    #[default]
+    Continuous,
+    /// Only renders when something happens (input, resize, an explicit redraw request) and sleeps
+    /// in between. This suits tools and editors, which are idle most of the time.
+    OnDemand,
+}
+
+/// How the voxels are drawn, to debug the geometry.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DebugDrawMode {
+    /// Only the vertices.
+    #[default]
+    Points,
+    /// The edges of the triangles.
+    Wireframe,
+    /// Filled triangles.
+    Solid,
+}
+
+impl DebugDrawMode {
+    /// The mode drawing the primitives of `voxel_render_mode` as they are meant to be drawn.
+    pub fn for_voxel_render_mode(voxel_render_mode: VoxelRenderMode) -> Self {
+        match voxel_render_mode {
+            VoxelRenderMode::PointSprites => DebugDrawMode::Points,
+            VoxelRenderMode::InstancedCubes | VoxelRenderMode::GreedyMesh => DebugDrawMode::Solid,
+        }
+    }
+
+    /// The next mode, wrapping around.
+    pub fn next(self) -> Self {
+        match self {
+            DebugDrawMode::Points => DebugDrawMode::Wireframe,
+            DebugDrawMode::Wireframe => DebugDrawMode::Solid,
+            DebugDrawMode::Solid => DebugDrawMode::Points,
+        }
+    }
+
+    /// Overrides the topology and polygon mode of the `base` config for this mode.
+    fn pipeline_config(self, base: &PipelineConfig) -> PipelineConfig {
+        let (topology, polygon_mode) = match self {
+            DebugDrawMode::Points => (vk::PrimitiveTopology::POINT_LIST, vk::PolygonMode::FILL),
+            DebugDrawMode::Wireframe => (vk::PrimitiveTopology::TRIANGLE_LIST, vk::PolygonMode::LINE),
+            DebugDrawMode::Solid => (vk::PrimitiveTopology::TRIANGLE_LIST, vk::PolygonMode::FILL),
+        };
+        // The wireframe is meant to be drawn over the solid voxels, so its edges are pulled
+        // toward the camera to avoid z-fighting with the faces.
+        let depth_bias = match self {
+            DebugDrawMode::Wireframe => Some(DepthBias::EDGE_OVERLAY),
+            _ => base.depth_bias,
+        };
+        PipelineConfig {
+            topology,
+            polygon_mode,
+            depth_bias,
+            ..base.clone()
+        }
+    }
+}
+
+/// Runtime configuration of the [`App`].
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub redraw_mode: RedrawMode,
+    /// The color the frame is cleared to, in linear space, or sRGB-encoded with
+    /// [`OutputColorEncoding::ManualInShader`](crate::gapi::vulkan::memory::swapchain::OutputColorEncoding),
+    /// as the clear bypasses the fragment shader.
+    pub clear_color: Color,
+    /// How long to wait for the GPU to finish a frame, or for a swapchain image, before giving
+    /// up with [`BurstError::GpuTimeout`] instead of freezing.
+    pub frame_timeout: Duration,
+    /// The viewports the scene is drawn into, e.g. two halves of the frame for split-screen.
+    pub viewport_layout: ViewportLayout,
+    /// Whether the Y axis is flipped by the projection or by the viewports, see
+    /// [`ViewportYFlip`].
+    pub viewport_y_flip: ViewportYFlip,
+    /// Enables the validation layer and the debug messenger, on by default with the
+    /// `validation` feature.
+    pub validation: bool,
+    /// Part of the name of the physical device to use, e.g. "NVIDIA" or "Intel", if it is
+    /// suitable. Otherwise, the first suitable device is used.
+    pub preferred_device: Option<String>,
+    /// How the physical device is picked when none matches `preferred_device`.
+    pub device_selection_policy: DeviceSelectionPolicy,
+    pub swapchain: SwapchainConfig,
+    /// Samples per pixel for multisample anti-aliasing, `_1` disables it. Lowered to the highest
+    /// count the device supports by the `quality_downgrade_policy`.
+    pub msaa_samples: vk::SampleCountFlags,
+    /// Where the multisampled frame is resolved, see [`MsaaResolveMode`]. Falls back to
+    /// [`MsaaResolveMode::InRenderPass`] if the swapchain images can't be transfer destinations.
+    pub msaa_resolve_mode: MsaaResolveMode,
+    /// Enables sample shading with this minimum fraction (0 to 1) of the samples shaded per
+    /// pixel, e.g. 0.2. It smooths aliasing inside the polygons too, not only their edges like
+    /// plain MSAA, but shades up to `msaa_samples` times more fragments. Only has an effect with
+    /// MSAA, and is disabled if the device doesn't support it.
+    pub min_sample_shading: Option<f32>,
+    /// Width in pixels of the drawn lines, of the wireframe and of the [`DebugDraw`] lines.
+    /// Anything but 1 needs the `wideLines` feature, and is clamped to the range of the device by
+    /// the `quality_downgrade_policy`.
+    pub line_width: f32,
+    /// What happens when the MSAA samples, the line width or the anisotropy of the samplers
+    /// exceed what the device supports, see [`QualityDowngradePolicy`].
+    pub quality_downgrade_policy: QualityDowngradePolicy,
+    /// How many frames the CPU can prepare while the GPU is still rendering the previous ones.
+    pub frames_in_flight: usize,
+    /// Creates one queue of every queue family of the device, instead of only the ones the app
+    /// needs, see [`LogicalDevice::new_with_all_queues`].
+    pub all_queue_families: bool,
+    /// Draws the scene into the depth buffer first, then shades only the closest fragment of
+    /// each pixel. It helps when the fragments are expensive and heavily overdrawn, e.g. dense
+    /// voxel terrain seen from the side, at the cost of processing the geometry twice. It doesn't
+    /// pay off for cheap shaders or geometry-bound scenes.
+    pub depth_prepass: bool,
+    /// Which depth formats are tried first when the depth buffer is needed.
+    pub depth_format_preference: DepthFormatPreference,
+    /// Which end of the depth range is near, reverse-Z is much more precise with the
+    /// `D32_SFLOAT` format of [`DepthFormatPreference::HighPrecision`], see [`DepthConvention`].
+    pub depth_convention: DepthConvention,
+    /// Draws with `VK_KHR_dynamic_rendering` instead of a render pass and framebuffers, see
+    /// [`DynamicRendering`]. Needs Vulkan 1.2 with the extension (it is core since 1.3), and
+    /// doesn't work with `depth_prepass` nor [`MsaaResolveMode::Manual`], falling back to the
+    /// render pass otherwise.
+    pub dynamic_rendering: bool,
+    /// Makes the textures of the material set a large array indexed by material ID in the
+    /// shader, see [`App::set_material_texture`]. Needs `VK_EXT_descriptor_indexing`, falling
+    /// back to a single texture array per material set otherwise.
+    pub bindless_textures: bool,
+    /// Only picks devices supporting geometry shaders. The voxels don't need them, so devices
+    /// without them (e.g. Apple Silicon through MoltenVK) are accepted unless this is set.
+    pub require_geometry_shader: bool,
+    /// How the voxels are drawn, see [`VoxelRenderMode`] for the tradeoffs of each mode.
+    pub voxel_render_mode: VoxelRenderMode,
+    /// How many voxels can be drawn with [`VoxelRenderMode::InstancedCubes`].
+    pub cube_instance_capacity: usize,
+    /// Pauses the rendering while the window isn't focused, see [`App::set_focused`].
+    pub pause_when_unfocused: bool,
+    /// How many vertices of [`DebugDraw`] lines and points can be drawn per frame, a line takes
+    /// two.
+    pub debug_draw_capacity: usize,
+    /// The file the compiled pipelines are loaded from at startup and saved to by
+    /// [`App::destroy`], see [`PipelineCache`]. `None` only caches them while the app runs.
+    pub pipeline_cache_path: Option<PathBuf>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            redraw_mode: RedrawMode::default(),
+            clear_color: Color::default(),
+            frame_timeout: Duration::from_secs(5),
+            viewport_layout: ViewportLayout::default(),
+            viewport_y_flip: ViewportYFlip::default(),
+            validation: VALIDATION_ENABLED,
+            preferred_device: None,
+            device_selection_policy: DeviceSelectionPolicy::default(),
+            swapchain: SwapchainConfig::default(),
+            msaa_samples: vk::SampleCountFlags::_1,
+            msaa_resolve_mode: MsaaResolveMode::default(),
+            min_sample_shading: None,
+            line_width: 1.0,
+            quality_downgrade_policy: QualityDowngradePolicy::default(),
+            frames_in_flight: FRAMES_IN_FLIGHT,
+            all_queue_families: false,
+            depth_prepass: false,
+            depth_format_preference: DepthFormatPreference::default(),
+            depth_convention: DepthConvention::default(),
+            dynamic_rendering: false,
+            bindless_textures: false,
+            require_geometry_shader: false,
+            voxel_render_mode: VoxelRenderMode::default(),
+            cube_instance_capacity: 65536,
+            pause_when_unfocused: false,
+            debug_draw_capacity: 65536,
+            pipeline_cache_path: None,
+        }
+    }
+}
+
+/// Builds an [`App`], configuring it step by step instead of filling a whole [`AppConfig`].
+///
+/// ```ignore
+/// let app = AppBuilder::default()
+///     .msaa(vk::SampleCountFlags::_4)
+///     .present_mode_policy(PresentModePolicy::VSync)
+///     .build(&window)?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AppBuilder {
+    config: AppConfig,
+}
+
+impl AppBuilder {
+    /// Starts from an existing configuration.
+    pub fn from_config(config: AppConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn redraw_mode(mut self, redraw_mode: RedrawMode) -> Self {
+        self.config.redraw_mode = redraw_mode;
+        self
+    }
+
+    pub fn clear_color(mut self, clear_color: Color) -> Self {
+        self.config.clear_color = clear_color;
+        self
+    }
+
+    pub fn frame_timeout(mut self, frame_timeout: Duration) -> Self {
+        self.config.frame_timeout = frame_timeout;
+        self
+    }
+
+    pub fn viewport_layout(mut self, viewport_layout: ViewportLayout) -> Self {
+        self.config.viewport_layout = viewport_layout;
+        self
+    }
+
+    pub fn viewport_y_flip(mut self, viewport_y_flip: ViewportYFlip) -> Self {
+        self.config.viewport_y_flip = viewport_y_flip;
+        self
+    }
+
+    pub fn validation(mut self, validation: bool) -> Self {
+        self.config.validation = validation;
+        self
+    }
+
+    pub fn preferred_device(mut self, name: impl Into<String>) -> Self {
+        self.config.preferred_device = Some(name.into());
+        self
+    }
+
+    pub fn device_selection_policy(mut self, policy: DeviceSelectionPolicy) -> Self {
+        self.config.device_selection_policy = policy;
+        self
+    }
+
+    pub fn swapchain(mut self, swapchain: SwapchainConfig) -> Self {
+        self.config.swapchain = swapchain;
+        self
+    }
+
+    pub fn swapchain_image_usage(mut self, image_usage: vk::ImageUsageFlags) -> Self {
+        self.config.swapchain.image_usage = image_usage;
+        self
+    }
+
+    pub fn present_mode_policy(mut self, policy: PresentModePolicy) -> Self {
+        self.config.swapchain.present_mode_policy = policy;
+        self
+    }
+
+    pub fn pre_transform_policy(mut self, policy: PreTransformPolicy) -> Self {
+        self.config.swapchain.pre_transform = policy;
+        self
+    }
+
+    pub fn msaa(mut self, samples: vk::SampleCountFlags) -> Self {
+        self.config.msaa_samples = samples;
+        self
+    }
+
+    pub fn msaa_resolve_mode(mut self, msaa_resolve_mode: MsaaResolveMode) -> Self {
+        self.config.msaa_resolve_mode = msaa_resolve_mode;
+        self
+    }
+
+    pub fn sample_shading(mut self, min_sample_shading: f32) -> Self {
+        self.config.min_sample_shading = Some(min_sample_shading);
+        self
+    }
+
+    pub fn line_width(mut self, line_width: f32) -> Self {
+        self.config.line_width = line_width;
+        self
+    }
+
+    pub fn quality_downgrade_policy(mut self, policy: QualityDowngradePolicy) -> Self {
+        self.config.quality_downgrade_policy = policy;
+        self
+    }
+
+    pub fn frames_in_flight(mut self, frames_in_flight: usize) -> Self {
+        self.config.frames_in_flight = frames_in_flight;
+        self
+    }
+
+    pub fn all_queue_families(mut self, all_queue_families: bool) -> Self {
+        self.config.all_queue_families = all_queue_families;
+        self
+    }
+
+    pub fn depth_prepass(mut self, depth_prepass: bool) -> Self {
+        self.config.depth_prepass = depth_prepass;
+        self
+    }
+
+    pub fn depth_format_preference(mut self, depth_format_preference: DepthFormatPreference) -> Self {
+        self.config.depth_format_preference = depth_format_preference;
+        self
+    }
+
+    pub fn depth_convention(mut self, depth_convention: DepthConvention) -> Self {
+        self.config.depth_convention = depth_convention;
+        self
+    }
+
+    pub fn dynamic_rendering(mut self, dynamic_rendering: bool) -> Self {
+        self.config.dynamic_rendering = dynamic_rendering;
+        self
+    }
+
+    pub fn bindless_textures(mut self, bindless_textures: bool) -> Self {
+        self.config.bindless_textures = bindless_textures;
+        self
+    }
+
+    pub fn require_geometry_shader(mut self, require_geometry_shader: bool) -> Self {
+        self.config.require_geometry_shader = require_geometry_shader;
+        self
+    }
+
+    pub fn voxel_render_mode(mut self, voxel_render_mode: VoxelRenderMode) -> Self {
+        self.config.voxel_render_mode = voxel_render_mode;
+        self
+    }
+
+    pub fn cube_instance_capacity(mut self, cube_instance_capacity: usize) -> Self {
+        self.config.cube_instance_capacity = cube_instance_capacity;
+        self
+    }
+
+    pub fn pause_when_unfocused(mut self, pause_when_unfocused: bool) -> Self {
+        self.config.pause_when_unfocused = pause_when_unfocused;
+        self
+    }
+
+    pub fn debug_draw_capacity(mut self, debug_draw_capacity: usize) -> Self {
+        self.config.debug_draw_capacity = debug_draw_capacity;
+        self
+    }
+
+    pub fn pipeline_cache_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.pipeline_cache_path = Some(path.into());
+        self
+    }
+
+    /// Creates the app with this configuration.
+    ///
+    /// # Errors
+    /// If the configuration is invalid, or if any Vulkan object fails to be created.
+    pub fn build(self, window: &MyWindow) -> anyhow::Result<App> {
+        if self.config.frames_in_flight == 0 {
+            bail!("At least one frame in flight is needed.");
+        }
+        if self.config.voxel_render_mode == VoxelRenderMode::GreedyMesh {
+            bail!("The greedy mesh voxel render mode is not implemented yet.");
+        }
+        App::from_config(window, self.config)
+    }
+}
+
+/// The pipelines drawing a [`DebugDrawMode`].
+struct ModePipelines {
+    main: Pipeline,
+    /// The depth-only pipeline of the prepass, drawing the same geometry, if enabled.
+    depth_prepass: Option<Pipeline>,
+}
+
+impl ModePipelines {
+    fn destroy(&self, device: &LogicalDevice) {
+        self.main.destroy(device);
+        if let Some(depth_prepass) = &self.depth_prepass {
+            depth_prepass.destroy(device);
+        }
+    }
+}
+
+/// The materials set with [`App::set_materials`], and the resources their set points to.
+struct Materials {
+    set: MaterialSet,
+    textures: TextureArray,
+    palette: MaterialPaletteBuffer,
+    /// The textures set with [`App::set_material_texture`], by index in the bindless array.
+    bindless_textures: HashMap<u32, TextureArray>,
+}
+
+impl Materials {
+    fn destroy(&self, device: &LogicalDevice) {
+        self.set.destroy(device);
+        self.textures.destroy(device);
+        self.palette.destroy(device);
+        self.bindless_textures.values().for_each(|textures| textures.destroy(device));
+    }
+}
+
+/// Records extra commands into the frame's command buffer, see [`App::set_overlay_recorder`].
+pub type OverlayRecorder = Box<dyn Fn(&CommandBuffer, &LogicalDevice)>;
+
+/// Advances the simulation by a time step in seconds, see [`App::set_update_fn`].
+pub type UpdateFn = Box<dyn FnMut(f32)>;
+
 /// Our Vulkan app.
 pub struct App {
+    config: AppConfig,
     entry: Entry,
     instance: Instance,
+    /// Reports the validation messages for the whole lifetime of the app, `None` without
+    /// validation.
+    debugger: Option<Debugger>,
+    /// The physical device the logical device was created from. [`RealDevice`] borrows the
+    /// instance, so only the handle is kept.
+    real_device: vk::PhysicalDevice,
     device: LogicalDevice,
     surface: Surface,
     swapchain: Swapchain,
+    /// Set when the swapchain no longer matches the window and must be recreated before the next
+    /// frame.
+    swapchain_outdated: bool,
+    /// How many frames in a row were skipped because the swapchain stayed out of date, see
+    /// [`App::acquire_next_image`].
+    skipped_outdated_frames: u32,
+    /// Every pipeline is created with it, see [`PipelineConfig::pipeline_cache`].
+    pipeline_cache: PipelineCache,
+    pipeline_config: PipelineConfig,
+    /// The multisampled color attachment, `None` without multisampling.
+    color_buffer: Option<ColorBuffer>,
+    /// The resolve mode actually used, see [`AppConfig::msaa_resolve_mode`].
+    msaa_resolve_mode: MsaaResolveMode,
+    depth_buffer: Option<DepthBuffer>,
     render_pass: MyRenderPass,
-    pipeline: Pipeline,
+    /// Replaces the render pass when drawing, `None` without [`AppConfig::dynamic_rendering`] or
+    /// if the device doesn't support it. The render pass and framebuffers are still created.
+    dynamic_rendering: Option<DynamicRendering>,
+    /// The layout of the per frame sets, see [`FRAME_SET`].
+    descriptor_set_layout: DescriptorSetLayout,
+    descriptor_sets: FrameDescriptorSets,
+    /// The buffers of binding 0 of the per frame sets.
+    uniforms: FrameUniformBuffers,
+    /// The camera written to the frame uniforms, see [`App::set_view_projection`].
+    view_projection: [[f32; 4]; 4],
+    frame_stats: FrameStats,
+    /// The layout of the material sets, which every voxel pipeline is created with.
+    material_set_layout: DescriptorSetLayout,
+    /// The materials bound to the voxel pipelines, `None` until [`App::set_materials`].
+    materials: Option<Materials>,
+    /// The samplers of the textures, destroyed with the app.
+    samplers: SamplerCache,
+    /// The pipelines of each debug draw mode, built the first time the mode is used.
+    pipelines: HashMap<DebugDrawMode, ModePipelines>,
+    debug_draw_mode: DebugDrawMode,
+    /// Whether the device supports the LINE polygon mode used by [`DebugDrawMode::Wireframe`].
+    wireframe_supported: bool,
     framebuffers: Vec<Framebuffer>,
     command_pool: CommandPool,
+    /// One per frame in flight, see [`CommandBuffers`].
     command_buffers: CommandBuffers,
+    /// The scene version and swapchain image the command buffer of each frame in flight was last
+    /// recorded for, `None` if never recorded or invalidated.
+    recorded_command_buffers: Vec<Option<(u64, usize)>>,
+    scene: SceneState,
+    overlay_recorder: Option<OverlayRecorder>,
+    update_fn: Option<UpdateFn>,
+    /// The buffers of the voxels, only with [`VoxelRenderMode::InstancedCubes`].
+    cubes: Option<InstancedCubes>,
+    debug_draw: DebugDraw,
+    debug_pipelines: DebugPipelines,
+    frame_sync: FrameSync,
+    /// The frame in flight being prepared, in `0..config.frames_in_flight`.
+    current_frame: usize,
+    /// Set by [`App::destroy`], after which the Vulkan objects are dangling handles.
+    destroyed: bool,
+    /// Set by [`App::set_paused`].
+    paused: bool,
+    /// Whether the window lost the focus, only pauses with `config.pause_when_unfocused`.
+    unfocused: bool,
 }
 #[derive(Debug, Error)]
 #[error("Missing {0}.")]
@@ -47,16 +549,45 @@ pub(crate) struct SuitabilityError(pub &'static str);
 /// Vulkan is a wrapper around the Vulkan Driver, which is a platform-agnostic abstraction for
 /// the actual GPU hardware interface.
 impl App {
-    /// Creates our Vulkan app.
+    /// Creates our Vulkan app with the default configuration, see [`AppBuilder`] to configure it.
     pub fn new(window: &MyWindow) -> anyhow::Result<Self> {
+        AppBuilder::default().build(window)
+    }
+
+    fn from_config(window: &MyWindow, mut config: AppConfig) -> anyhow::Result<Self> {
         info!("Creating Entry...");
         let entry = Entry::new()?;
         info_success!("Entry Created! Loader Version: {}", entry.version()?);
         info!("Creating Instance...");
-        let instance = Instance::new(&entry, window)?;
+        // The core objects are guarded until the app is fully built, so that a failure in any of
+        // the later steps destroys them instead of leaking them.
+        let instance = Guard::new(Instance::new(&entry, window, config.validation)?, Instance::destroy);
+        failure_point()?;
         info_success!("Instance Created!");
+        let instance_ref: &Instance = &instance;
+        // The messenger chained to the instance only reports the messages of its creation and
+        // destruction, this one reports everything in between.
+        let debugger = if config.validation {
+            info!("Creating debug messenger...");
+            let debugger = Debugger::new(&instance, Debugger::DEFAULT_MESSAGE_TYPES)
+                .with_context(|| "Failed to create debug messenger.")?;
+            info_success!("Debug messenger created!");
+            Some(debugger)
+        } else {
+            None
+        };
+        let debugger = Guard::new(debugger, |debugger: &Option<Debugger>| {
+            if let Some(debugger) = debugger {
+                debugger.destroy(instance_ref);
+            }
+        });
+        failure_point()?;
         info!("Creating Surface...");
-        let surface = Surface::new(&instance, window)?;
+        let surface = Guard::new(Surface::new(&instance, window)?, |surface: &Surface| {
+            surface.destroy(instance_ref)
+        });
+        let surface_ref: &Surface = &surface;
+        failure_point()?;
         info_success!("Surface Created!");
         let requests: Vec<QueueRequest> = vec![QueueRequest {
             capabilities: vec![QueueCapability::Graphics],
@@ -70,7 +601,14 @@ impl App {
             required_extensions.push(DeviceExtension::KhrPortabilitySubset);
         }
         info!("Selecting physical device...");
-        let real_device = Self::pick_real_device(&instance, &surface, window)?;
+        let real_device = Self::pick_real_device(
+            &instance,
+            &surface,
+            window,
+            config.preferred_device.as_deref(),
+            config.device_selection_policy,
+            config.require_geometry_shader,
+        )?;
         info_success!(
             "Physical device selected: {}",
             real_device.get_properties().device_name
@@ -78,70 +616,447 @@ impl App {
         if real_device.get_properties().device_type != vk::PhysicalDeviceType::DISCRETE_GPU {
             warn!("This selected physical device is not discrete.");
         }
+        let workarounds = real_device.get_workarounds();
+        // The whole extension picture, to triage features that don't work on some GPUs.
+        if log::log_enabled!(log::Level::Debug) {
+            let mut instance_extensions = entry
+                .get_available_instance_extensions()?
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            instance_extensions.sort_unstable();
+            debug!(
+                "Available instance extensions ({}):\n\t{}",
+                instance_extensions.len(),
+                instance_extensions.join("\n\t")
+            );
+            debug!("{}", real_device.dump_extensions()?);
+        }
+        // Older devices only provide buffer device addresses through the extension.
+        if real_device.buffer_device_address_support() == BufferDeviceAddressSupport::KhrExtension {
+            required_extensions.push(DeviceExtension::KhrBufferDeviceAddress);
+        }
+        let dynamic_rendering = config.dynamic_rendering && Self::check_dynamic_rendering(&real_device, &config);
+        if dynamic_rendering {
+            required_extensions.push(DeviceExtension::KhrDynamicRendering);
+        }
+        let bindless_textures = config.bindless_textures && real_device.supports_descriptor_indexing();
+        if bindless_textures {
+            info!("Using bindless textures.");
+            required_extensions.push(DeviceExtension::ExtDescriptorIndexing);
+        } else if config.bindless_textures {
+            warn!("The device doesn't support descriptor indexing, using a texture array per material set instead of bindless textures.");
+        }
+        if workarounds.is_empty() {
+            info!("No driver workarounds needed for vendor {:?}.", real_device.get_vendor());
+        } else {
+            warn!("Driver workarounds active for vendor {:?}: {}", real_device.get_vendor(), workarounds);
+        }
         info!("Creating logical device...");
-        let device = LogicalDevice::new(
-            &real_device,
-            &instance,
-            &surface,
-            &requests,
-            &required_extensions,
-        )?;
+        let device = if config.all_queue_families {
+            LogicalDevice::new_with_all_queues(&real_device, &instance, &surface, &required_extensions)?
+        } else {
+            LogicalDevice::new(
+                &real_device,
+                &instance,
+                &surface,
+                &requests,
+                &required_extensions,
+            )?
+        };
+        // Everything created from the device is guarded after it, so it is destroyed first.
+        let device = Guard::new(device, |device: &LogicalDevice| {
+            device.resources().report_leaks();
+            device.destroy();
+        });
+        let device_ref: &LogicalDevice = &device;
+        failure_point()?;
+        if device.get_queues().graphics.is_empty() || device.get_queues().present.is_empty() {
+            bail!("The device has no graphics or present queue.");
+        }
+        if !device.get_queues().is_unified_present() {
+            info!("The graphics queue family can't present, presenting from a separate queue.");
+        }
         info_success!("Logical device created!");
 
+        let pipeline_cache = PipelineCache::new(&real_device.get_properties(), &device, config.pipeline_cache_path.as_deref())?;
+        let pipeline_cache = Guard::new(pipeline_cache, |pipeline_cache| pipeline_cache.destroy(device_ref));
+        failure_point()?;
+
         info!("Creating swapchain...");
-        let swapchain = Swapchain::new(&window, &real_device, &device, &surface).with_context(|| "Failed to create swapchain.")?;
+        // The frame is resolved into the swapchain images with a transfer.
+        if config.msaa_resolve_mode == MsaaResolveMode::Manual {
+            config.swapchain.image_usage |= vk::ImageUsageFlags::TRANSFER_DST;
+        }
+        let swapchain = Swapchain::new(&window, &real_device, &device, &surface, &config.swapchain, None).with_context(|| "Failed to create swapchain.")?;
+        let swapchain = Guard::new(swapchain, |swapchain| swapchain.destroy(device_ref, surface_ref));
+        failure_point()?;
         info_success!("Swapchain created!");
 
         info!("Creating viewport...");
-        let viewport = Viewport::new(&swapchain);
+        let viewport = Viewport::new(&swapchain, config.viewport_y_flip);
         info_success!("Viewport created!");
 
+        let mut pipeline_config = PipelineConfig {
+            samples: Self::check_msaa_samples(&real_device, config.msaa_samples, config.quality_downgrade_policy)?,
+            line_width: Self::check_line_width(&real_device, config.line_width, config.quality_downgrade_policy)?,
+            min_sample_shading: Self::check_sample_shading(&real_device, config.min_sample_shading),
+            topology: config.voxel_render_mode.topology(),
+            voxel_render_mode: config.voxel_render_mode,
+            depth_pass: if config.depth_prepass {
+                DepthPass::AfterPrepass
+            } else {
+                DepthPass::Disabled
+            },
+            depth_convention: config.depth_convention,
+            output_color_encoding: swapchain.output_color_encoding(),
+            pipeline_cache: pipeline_cache.get_vk(),
+            ..PipelineConfig::default()
+        };
+        if pipeline_config.output_color_encoding != config.swapchain.output_color_encoding {
+            warn!(
+                "The swapchain format {:?} needs {:?} instead of the configured {:?}.",
+                swapchain.format, pipeline_config.output_color_encoding, config.swapchain.output_color_encoding
+            );
+        }
+
+        let msaa_resolve_mode = Self::check_msaa_resolve_mode(&swapchain, config.msaa_resolve_mode);
+        let color_buffer = Self::create_color_buffer(&real_device, &device, &swapchain, &pipeline_config, msaa_resolve_mode)?;
+        let color_buffer = Guard::new(color_buffer, |color_buffer: &Option<ColorBuffer>| {
+            if let Some(color_buffer) = color_buffer {
+                color_buffer.destroy(device_ref);
+            }
+        });
+        failure_point()?;
+        let depth_buffer = Self::create_depth_buffer(&real_device, &device, &swapchain, &pipeline_config, config.depth_format_preference)?;
+        let depth_buffer = Guard::new(depth_buffer, |depth_buffer: &Option<DepthBuffer>| {
+            if let Some(depth_buffer) = depth_buffer {
+                depth_buffer.destroy(device_ref);
+            }
+        });
+        failure_point()?;
+
         info!("Creating render pass...");
-        let render_pass = MyRenderPass::new(&swapchain, &device).with_context(|| "Failed to create render pass.")?;
+        let depth_format = depth_buffer.as_ref().map(DepthBuffer::get_format);
+        let render_pass = MyRenderPass::new(&swapchain, &device, depth_format, pipeline_config.samples, msaa_resolve_mode, config.depth_prepass, config.depth_convention).with_context(|| "Failed to create render pass.")?;
+        let render_pass = Guard::new(render_pass, |render_pass| render_pass.destroy(device_ref));
+        failure_point()?;
         info_success!("Render pass created!");
+        let dynamic_rendering = dynamic_rendering.then(|| DynamicRendering {
+            formats: RenderingFormats {
+                color: swapchain.format,
+                depth: depth_format,
+            },
+            depth_convention: config.depth_convention,
+        });
+        pipeline_config.dynamic_rendering = dynamic_rendering.map(|dynamic_rendering| dynamic_rendering.formats);
+        if dynamic_rendering.is_some() {
+            info!("Drawing with dynamic rendering instead of the render pass.");
+        }
+
+        info!("Creating descriptor sets...");
+        // Binding 0 holds the frame uniforms: the camera, time and frame index.
+        let uniforms_binding = FrameUniforms::descriptor_binding(0);
+        let descriptor_set_layout = DescriptorSetLayout::new(&device, &[uniforms_binding])
+            .with_context(|| "Failed to create descriptor set layout.")?;
+        let descriptor_set_layout = Guard::new(descriptor_set_layout, |layout| layout.destroy(device_ref));
+        failure_point()?;
+        let descriptor_sets = FrameDescriptorSets::new(&device, &descriptor_set_layout, config.frames_in_flight)
+            .with_context(|| "Failed to create descriptor sets.")?;
+        let descriptor_sets = Guard::new(descriptor_sets, |descriptor_sets| descriptor_sets.destroy(device_ref));
+        failure_point()?;
+        let uniforms = FrameUniformBuffers::new(&real_device, &device, descriptor_sets.get_all(), 0)
+            .with_context(|| "Failed to create frame uniform buffers.")?;
+        let uniforms = Guard::new(uniforms, |uniforms| uniforms.destroy(device_ref));
+        failure_point()?;
+        let material_set_layout = MaterialSet::create_layout(&device, bindless_textures)?;
+        let material_set_layout = Guard::new(material_set_layout, |layout| layout.destroy(device_ref));
+        failure_point()?;
+        info_success!("Descriptor sets created!");
 
         info!("Creating pipeline...");
-        let pipeline = Pipeline::new(&device, &viewport, &render_pass).with_context(|| "Failed to create pipeline.")?;
+        let debug_draw_mode = DebugDrawMode::for_voxel_render_mode(config.voxel_render_mode);
+        let voxel_set_layouts = [&*descriptor_set_layout, &*material_set_layout];
+        let pipeline = Self::create_mode_pipelines(&device, &viewport, &render_pass, &voxel_set_layouts, &pipeline_config, debug_draw_mode).with_context(|| "Failed to create pipeline.")?;
+        let pipeline = Guard::new(pipeline, |pipeline| pipeline.destroy(device_ref));
+        failure_point()?;
+        let debug_pipelines = DebugDraw::create_pipelines(&device, &viewport, &render_pass, &descriptor_set_layout, &pipeline_config)
+            .with_context(|| "Failed to create debug draw pipelines.")?;
+        let debug_pipelines = Guard::new(debug_pipelines, |pipelines| pipelines.destroy(device_ref));
+        failure_point()?;
         info_success!("Pipeline created!");
 
         info!("Creating framebuffers...");
-        let framebuffers = swapchain
-            .image_views
-            .iter()
-            .map(|image_view| {
-                let attachments = std::slice::from_ref(image_view);
-                Framebuffer::new(&render_pass, attachments, &swapchain, &device)
-            })
-            .collect::<Vec<Framebuffer>>();
+        let framebuffers = Self::create_framebuffers(&device, &swapchain, &render_pass, color_buffer.as_ref(), depth_buffer.as_ref(), msaa_resolve_mode);
+        let framebuffers = Guard::new(framebuffers, |framebuffers: &Vec<Framebuffer>| {
+            framebuffers.iter().for_each(|framebuffer| framebuffer.destroy(device_ref));
+        });
+        failure_point()?;
         info_success!("Framebuffers created!");
 
         info!("Creating command pool...");
         let command_pool = CommandPool::new(&device).with_context(|| "Failed to create command pool.")?;
+        // The command buffers are freed with the pool.
+        let command_pool = Guard::new(command_pool, |command_pool| command_pool.destroy(device_ref));
+        failure_point()?;
         info_success!("Command pool created!");
 
         info!("Creating command buffers...");
-        let command_buffers = CommandBuffers::new(&device, &framebuffers, &command_pool)
+        let command_buffers = CommandBuffers::new(&device, &command_pool, config.frames_in_flight)
             .with_context(|| "Failed to create command buffers.")?;
+        failure_point()?;
         info_success!("CommandBuffers created!");
 
+        let frames_in_flight = config.frames_in_flight;
+
+        info!("Creating sync objects...");
+        let frame_sync = FrameSync::new(&device, config.frames_in_flight, swapchain.image_views.len())
+            .with_context(|| "Failed to create sync objects.")?;
+        let frame_sync = Guard::new(frame_sync, |frame_sync| frame_sync.destroy(device_ref));
+        failure_point()?;
+        info_success!("Sync objects created!");
+
+        let cubes = match config.voxel_render_mode {
+            VoxelRenderMode::InstancedCubes => Some(
+                InstancedCubes::new(&real_device, &device, config.cube_instance_capacity)
+                    .with_context(|| "Failed to create instanced cubes.")?,
+            ),
+            _ => None,
+        };
+        let cubes = Guard::new(cubes, |cubes: &Option<InstancedCubes>| {
+            if let Some(cubes) = cubes {
+                cubes.destroy(device_ref);
+            }
+        });
+        failure_point()?;
+
+        let debug_draw = DebugDraw::new(&real_device, &device, config.frames_in_flight, config.debug_draw_capacity)
+            .with_context(|| "Failed to create debug draw.")?;
+        let debug_draw = Guard::new(debug_draw, |debug_draw| debug_draw.destroy(device_ref));
+        failure_point()?;
 
-        let app = Self {
+        let wireframe_supported = real_device.get_features().fill_mode_non_solid == vk::TRUE;
+        let real_device = *real_device.get_vk();
+
+        // Released in reverse creation order, the guards borrow the device and the surface.
+        let debug_draw = debug_draw.into_inner();
+        let cubes = cubes.into_inner();
+        let frame_sync = frame_sync.into_inner();
+        let command_pool = command_pool.into_inner();
+        let framebuffers = framebuffers.into_inner();
+        let debug_pipelines = debug_pipelines.into_inner();
+        let pipelines = HashMap::from([(debug_draw_mode, pipeline.into_inner())]);
+        let material_set_layout = material_set_layout.into_inner();
+        let uniforms = uniforms.into_inner();
+        let descriptor_sets = descriptor_sets.into_inner();
+        let descriptor_set_layout = descriptor_set_layout.into_inner();
+        let render_pass = render_pass.into_inner();
+        let depth_buffer = depth_buffer.into_inner();
+        let color_buffer = color_buffer.into_inner();
+        let swapchain = swapchain.into_inner();
+        let pipeline_cache = pipeline_cache.into_inner();
+        let device = device.into_inner();
+        let surface = surface.into_inner();
+        let debugger = debugger.into_inner();
+        let instance = instance.into_inner();
+        Ok(Self {
+            config,
             entry,
             instance,
+            debugger,
+            real_device,
             device,
             surface,
             swapchain,
+            swapchain_outdated: false,
+            skipped_outdated_frames: 0,
+            pipeline_cache,
+            pipeline_config,
+            color_buffer,
+            msaa_resolve_mode,
+            depth_buffer,
             render_pass,
-            pipeline,
+            dynamic_rendering,
+            descriptor_set_layout,
+            descriptor_sets,
+            uniforms,
+            view_projection: FrameUniforms::IDENTITY,
+            frame_stats: FrameStats::default(),
+            material_set_layout,
+            materials: None,
+            samplers: SamplerCache::new(config.quality_downgrade_policy),
+            pipelines,
+            debug_draw_mode,
+            wireframe_supported,
             framebuffers,
             command_pool,
+            recorded_command_buffers: vec![None; frames_in_flight],
             command_buffers,
+            scene: SceneState::default(),
+            overlay_recorder: None,
+            update_fn: None,
+            cubes,
+            debug_draw,
+            debug_pipelines,
+            frame_sync,
+            current_frame: 0,
+            destroyed: false,
+            paused: false,
+            unfocused: false,
+        })
+    }
+
+    /// Creates the depth buffer if the pipeline needs one.
+    /// It is only needed for the stencil test and the depth prepass for now, as depth testing is
+    /// otherwise still disabled.
+    fn create_depth_buffer(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        swapchain: &Swapchain,
+        pipeline_config: &PipelineConfig,
+        preference: DepthFormatPreference,
+    ) -> anyhow::Result<Option<DepthBuffer>> {
+        let stencil = pipeline_config.stencil.is_some();
+        if !stencil && pipeline_config.depth_pass == DepthPass::Disabled {
+            return Ok(None);
+        }
+        info!("Creating depth buffer...");
+        let depth_buffer = DepthBuffer::new(real_device, device, swapchain.extent, stencil, preference, pipeline_config.samples)
+            .with_context(|| "Failed to create depth buffer.")?;
+        let float_depth = matches!(
+            depth_buffer.get_format(),
+            vk::Format::D32_SFLOAT | vk::Format::D32_SFLOAT_S8_UINT
+        );
+        if pipeline_config.depth_convention == DepthConvention::ReverseZ && !float_depth {
+            warn!(
+                "Reverse-Z depth with the {:?} format is no more precise than the standard convention, it needs D32_SFLOAT.",
+                depth_buffer.get_format()
+            );
+        }
+        info_success!("Depth buffer created!");
+        Ok(Some(depth_buffer))
+    }
+
+    /// Creates the multisampled color attachment if the pipeline uses multisampling.
+    fn create_color_buffer(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        swapchain: &Swapchain,
+        pipeline_config: &PipelineConfig,
+        resolve: MsaaResolveMode,
+    ) -> anyhow::Result<Option<ColorBuffer>> {
+        if pipeline_config.samples == vk::SampleCountFlags::_1 {
+            return Ok(None);
+        }
+        info!("Creating multisampled color buffer...");
+        let color_buffer = ColorBuffer::new(real_device, device, swapchain.extent, swapchain.format, pipeline_config.samples, resolve)
+            .with_context(|| "Failed to create multisampled color buffer.")?;
+        info_success!("Multisampled color buffer created!");
+        Ok(Some(color_buffer))
+    }
+
+    /// Whether [`AppConfig::dynamic_rendering`] can be used, logging why not.
+    fn check_dynamic_rendering(real_device: &RealDevice, config: &AppConfig) -> bool {
+        if !real_device.supports_dynamic_rendering() {
+            warn!("The device doesn't support dynamic rendering, using the render pass instead.");
+            return false;
+        }
+        // Dynamic rendering has no subpasses, and resolves when the rendering ends.
+        if config.depth_prepass {
+            warn!("Dynamic rendering doesn't support the depth prepass, using the render pass instead.");
+            return false;
+        }
+        if config.msaa_resolve_mode == MsaaResolveMode::Manual {
+            warn!("Dynamic rendering doesn't support the manual MSAA resolve, using the render pass instead.");
+            return false;
+        }
+        true
+    }
+
+    /// Returns `requested`, unless it is [`MsaaResolveMode::Manual`] and the frame can't be
+    /// resolved into the swapchain images.
+    fn check_msaa_resolve_mode(swapchain: &Swapchain, requested: MsaaResolveMode) -> MsaaResolveMode {
+        if requested == MsaaResolveMode::Manual && !swapchain.supports_transfer_dst() {
+            warn!("The swapchain images can't be transfer destinations, resolving MSAA in the render pass instead.");
+            return MsaaResolveMode::InRenderPass;
+        }
+        requested
+    }
+
+    /// Returns `requested` if the device supports it for both color and depth attachments,
+    /// otherwise the highest supported count below it, following the `policy`.
+    fn check_msaa_samples(
+        real_device: &RealDevice,
+        requested: vk::SampleCountFlags,
+        policy: QualityDowngradePolicy,
+    ) -> anyhow::Result<vk::SampleCountFlags> {
+        let supported = real_device
+            .supported_sample_counts()
+            .into_iter()
+            .rev()
+            .find(|count| count.bits() <= requested.bits())
+            .unwrap_or(vk::SampleCountFlags::_1);
+        let samples = policy.apply("MSAA samples", requested, supported)?;
+        info!("MSAA samples: {samples:?}");
+        Ok(samples)
+    }
+
+    /// Returns `requested` if the device supports lines that wide, otherwise the closest
+    /// supported width, following the `policy`.
+    fn check_line_width(real_device: &RealDevice, requested: f32, policy: QualityDowngradePolicy) -> anyhow::Result<f32> {
+        let supported = match real_device.capabilities().line_width_range {
+            Some([min, max]) => requested.clamp(min, max),
+            None => 1.0,
         };
-        info!("Recording command buffers...");
-        app.record_command_buffers().with_context(|| "Failed to record command buffers.")?;
-        info_success!("Command buffers recorded!");
+        let line_width = policy.apply("Line width", requested, supported)?;
+        if line_width != 1.0 {
+            info!("Line width: {line_width}");
+        }
+        Ok(line_width)
+    }
 
-        Ok(app)
+    /// Returns `requested` if the device supports sample shading, otherwise disables it.
+    fn check_sample_shading(real_device: &RealDevice, requested: Option<f32>) -> Option<f32> {
+        let min_sample_shading = requested?;
+        if real_device.get_features().sample_rate_shading != vk::TRUE {
+            warn!("Sample shading is not supported by the device, disabling it.");
+            return None;
+        }
+        let clamped = min_sample_shading.clamp(0.0, 1.0);
+        if clamped != min_sample_shading {
+            warn!("Minimum sample shading {min_sample_shading} is out of 0..=1, using {clamped}.");
+        }
+        info!("Minimum sample shading: {clamped}");
+        Some(clamped)
+    }
+
+    /// Creates one framebuffer per swapchain image, sharing the multisampled color buffer and the
+    /// depth buffer if there are any.
+    /// The attachments are in the order of the render pass: color, depth, then resolve.
+    fn create_framebuffers(
+        device: &LogicalDevice,
+        swapchain: &Swapchain,
+        render_pass: &MyRenderPass,
+        color_buffer: Option<&ColorBuffer>,
+        depth_buffer: Option<&DepthBuffer>,
+        resolve: MsaaResolveMode,
+    ) -> Vec<Framebuffer> {
+        swapchain
+            .image_views
+            .iter()
+            .map(|image_view| {
+                // With multisampling, the swapchain image is the resolve attachment, if resolved
+                // in the render pass.
+                let mut attachments = vec![color_buffer.map_or(image_view, ColorBuffer::get_view)];
+                if let Some(depth_buffer) = depth_buffer {
+                    attachments.push(depth_buffer.get_view());
+                }
+                if color_buffer.is_some() && resolve == MsaaResolveMode::InRenderPass {
+                    attachments.push(image_view);
+                }
+                Framebuffer::new(render_pass, &attachments, swapchain, device)
+            })
+            .collect::<Vec<Framebuffer>>()
     }
 
     /// Function that returns a `SuitabilityError` if a supplied physical device does not support everything we require.
@@ -156,17 +1071,21 @@ impl App {
         real_device: &RealDevice,
         surface: &Surface,
         window: &MyWindow,
+        require_geometry_shader: bool,
     ) -> anyhow::Result<()> {
         let device_name = real_device.get_properties().device_name.to_string();
         trace!("Checking \"{device_name}\"'s features...");
         // Optional features like texture compression, 64-bit floats, and multi-viewport rendering.
         let features = real_device.get_features();
-        // We require support for geometry rendering.
-        if features.geometry_shader != vk::TRUE {
+        // Geometry shaders are only required if configured, the voxels are drawn without them.
+        if features.geometry_shader == vk::TRUE {
+            info!("{device_name} supports geometry shaders.");
+        } else if require_geometry_shader {
             bail!(SuitabilityError("Missing geometry shader support."));
+        } else {
+            info!("{device_name} does not support geometry shaders, they are not required.");
         }
 
-        info!("{device_name} supports geometry rendering.");
         info!("Checking \"{device_name}\"'s extensions...");
         let supported_extensions =
             real_device
@@ -199,7 +1118,7 @@ impl App {
         // It is mandatory to check the swapchain information AFTER checking for the swapchain
         // extension, swapchain support is only available if the extension is supported.
         let swapchain = real_device.get_swapchain_info(surface)?;
-        if swapchain.formats.is_empty() || swapchain.present_modes.is_empty() {
+        if !swapchain.is_adequate() {
             bail!(SuitabilityError("Insufficient swapchain support."));
         }
 
@@ -207,80 +1126,1155 @@ impl App {
         Ok(())
     }
 
+    /// Picks the first suitable physical device whose name contains `preferred` (ignoring case)
+    /// if any does, otherwise the suitable one ranked best by the `policy`.
+    ///
+    /// # Errors
+    /// [`BurstError::NoPhysicalDevices`] if there are no devices at all, otherwise an error if
+    /// none of them is suitable, with the reasons logged at debug level.
     fn pick_real_device<'a>(
         instance: &'a Instance,
         surface: &Surface,
         window: &MyWindow,
+        preferred: Option<&str>,
+        policy: DeviceSelectionPolicy,
+        require_geometry_shader: bool,
     ) -> anyhow::Result<RealDevice<'a>> /* Returned RealDevice's lifetime is bound to Instance */
     {
+        let fast_startup = policy == DeviceSelectionPolicy::FastStartup;
         let available_devices = instance.enumerate_real_devices()?;
-        debug!(
-            "Picking physical device between available devices: {:?}.",
-            available_devices
-                .iter()
-                .map(|d| d.get_properties().device_name.to_string())
-                .collect::<Vec<_>>()
-        );
+        // Told apart from the devices that exist but aren't suitable, as the fix is different.
+        if available_devices.is_empty() {
+            return Err(BurstError::NoPhysicalDevices.into());
+        }
+        // Logging a device queries it, which fast startup avoids for the devices after the pick.
+        if !fast_startup {
+            for real_dev in &available_devices {
+                debug!("Found physical device: {:?}", real_dev.get_summary());
+            }
+        }
+        let available_count = available_devices.len();
+        let mut suitable_devices = Vec::new();
         for real_dev in available_devices {
             let properties = real_dev.get_properties();
-            if let Err(error) = Self::check_real_device(&real_dev, surface, window) {
+            if let Err(error) = Self::check_real_device(&real_dev, surface, window, require_geometry_shader) {
                 debug!(
                     "Skipping physical device (`{}`): {error}",
                     properties.device_name
                 );
+            } else if fast_startup
+                && preferred.is_none_or(|preferred| {
+                    properties.device_name.to_string().to_lowercase().contains(&preferred.to_lowercase())
+                })
+            {
+                // The devices are only queried when checked or logged, so the remaining ones never are.
+                info!(
+                    "Selected physical device (`{}`) with fast startup, without checking the other devices.",
+                    properties.device_name
+                );
+                return Ok(real_dev);
             } else {
-                debug!("Selected physical device (`{}`).", properties.device_name);
-                return anyhow::Ok(real_dev);
+                suitable_devices.push(real_dev);
             }
         }
 
-        Err(anyhow!("Failed to find suitable physical device."))
+        if suitable_devices.is_empty() {
+            return Err(anyhow!(
+                "Failed to find suitable physical device, none of the {available_count} found meets the requirements."
+            ));
+        }
+
+        let name_of = |real_dev: &RealDevice| real_dev.get_properties().device_name.to_string();
+        let preferred_index = preferred.and_then(|preferred| {
+            let preferred = preferred.to_lowercase();
+            let index = suitable_devices
+                .iter()
+                .position(|real_dev| name_of(real_dev).to_lowercase().contains(&preferred));
+            if index.is_none() {
+                warn!("No suitable physical device matches the preferred device \"{preferred}\".");
+            }
+            index
+        });
+        let real_dev = suitable_devices.remove(preferred_index.unwrap_or_else(|| {
+            let power_source = match policy {
+                DeviceSelectionPolicy::FirstSuitable | DeviceSelectionPolicy::FastStartup => None,
+                DeviceSelectionPolicy::PowerAware => {
+                    let power_source = PowerSource::detect();
+                    match power_source {
+                        Some(power_source) => info!("Picking the physical device for {power_source:?} power."),
+                        None => warn!("Failed to determine the power source, picking the first suitable device."),
+                    }
+                    power_source
+                }
+            };
+            // The first of the best ranked devices, `max_by_key` returning the last one.
+            suitable_devices
+                .iter()
+                .enumerate()
+                .max_by_key(|(index, real_dev)| {
+                    (policy.score(real_dev.get_properties().device_type, power_source), Reverse(*index))
+                })
+                .map_or(0, |(index, _)| index)
+        }));
+        debug!("Selected physical device (`{}`).", name_of(&real_dev));
+        Ok(real_dev)
     }
 
-    fn record_command_buffers(&self) -> anyhow::Result<()> {
-        self.command_buffers.record_all(
-            &self.device,
-            &self.framebuffers,
-            |command_buffer, framebuffer| {
-                // 1. Start Render Pass
-                self.render_pass.begin(&self.device, framebuffer, command_buffer, &self.swapchain);
+    /// Records the command buffer of the frame in flight `frame`, which selects the descriptor
+    /// set to bind, to render into the swapchain image `image_index`.
+    ///
+    /// The steps share the attachments, the render pass orders them with its subpass
+    /// dependencies (see `MyRenderPass::incoming_dependencies` for the whole chain), and the
+    /// manual resolve and dynamic rendering with pipeline barriers.
+    fn record_command_buffer(&self, image_index: usize, frame: usize) -> anyhow::Result<()> {
+        let command_buffer = self.command_buffers.get(frame);
+        let framebuffer = &self.framebuffers[image_index];
+        command_buffer.record(&self.device, framebuffer, |command_buffer, framebuffer| {
+            // 1. Start Render Pass, or the dynamic rendering into the attachments
+            let output = self.swapchain.image(image_index);
+            match &self.dynamic_rendering {
+                Some(dynamic_rendering) => {
+                    let attachments = RenderingAttachments {
+                        output: (output, &self.swapchain.image_views[image_index]),
+                        multisampled: self
+                            .color_buffer
+                            .as_ref()
+                            .map(|color_buffer| (color_buffer.get_image(), color_buffer.get_view())),
+                        depth: self.depth_buffer.as_ref(),
+                    };
+                    dynamic_rendering.begin(&self.device, command_buffer, &attachments, self.swapchain.extent, self.config.clear_color);
+                }
+                None => self.render_pass.begin(&self.device, framebuffer, command_buffer, self.swapchain.extent, self.config.clear_color),
+            }
+
+            // 2. Fill the depth buffer with the depth prepass, if enabled
+            let pipelines = self.current_pipelines();
+            if let Some(depth_prepass) = &pipelines.depth_prepass {
+                self.draw_scene(command_buffer, depth_prepass, frame);
+                self.render_pass.next_subpass(&self.device, *command_buffer.get_vk());
+            }
+
+            // 3. Draw the scene
+            self.draw_scene(command_buffer, &pipelines.main, frame);
 
-                // 2. Bind Pipeline
-                self.pipeline.bind(&self.device, command_buffer);
+            // 4. Draw the debug lines and points over the whole frame
+            let (viewport, scissor) = ViewportRegion::FULL.to_vk(self.swapchain.extent, self.config.viewport_y_flip);
+            self.device.set_viewport(*command_buffer.get_vk(), &[viewport]);
+            self.device.set_scissor(*command_buffer.get_vk(), &[scissor]);
+            self.debug_draw.draw(&self.device, command_buffer, &self.debug_pipelines, frame);
 
-                // 3. Draw
-                unsafe {
-                    self.device.draw(*command_buffer.get_vk(), 3, 1, 0, 0);
+            // 5. Draw the overlay over the whole frame
+            if let Some(overlay_recorder) = &self.overlay_recorder {
+                let (viewport, scissor) = ViewportRegion::FULL.to_vk(self.swapchain.extent, self.config.viewport_y_flip);
+                self.device.set_viewport(*command_buffer.get_vk(), &[viewport]);
+                self.device.set_scissor(*command_buffer.get_vk(), &[scissor]);
+                overlay_recorder(command_buffer, &self.device);
+            }
+
+            // 6. End Render Pass
+            match &self.dynamic_rendering {
+                Some(dynamic_rendering) => {
+                    dynamic_rendering.end(&self.device, command_buffer, output, vk::ImageLayout::PRESENT_SRC_KHR)
                 }
+                None => self.render_pass.end(&self.device, *command_buffer.get_vk()),
+            }
 
-                // 4. End Render Pass
-                self.render_pass.end(&self.device, *command_buffer.get_vk());
+            // 7. Resolve the samples into the swapchain image, if not done by the render pass
+            if let (MsaaResolveMode::Manual, Some(color_buffer)) = (self.msaa_resolve_mode, &self.color_buffer) {
+                self.record_manual_resolve(command_buffer, color_buffer, image_index);
+            }
 
-                Ok(())
-            },
+            Ok(())
+        })
+    }
+
+    /// Resolves the multisampled `color_buffer` into the swapchain image `image_index`, and
+    /// transitions it for presenting, see [`MsaaResolveMode::Manual`].
+    fn record_manual_resolve(&self, command_buffer: &CommandBuffer, color_buffer: &ColorBuffer, image_index: usize) {
+        let image = self.swapchain.image(image_index);
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+        let barrier = |(old_layout, new_layout), src_access, dst_access| {
+            vk::ImageMemoryBarrier::builder()
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .build()
+        };
+        // The submit waits for the image to be acquired at the color attachment output stage,
+        // which the barrier chains to the resolve.
+        let to_transfer = barrier(
+            (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+            vk::AccessFlags::empty(),
+            vk::AccessFlags::TRANSFER_WRITE,
+        );
+        self.device.pipeline_barrier(
+            *command_buffer.get_vk(),
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::TRANSFER,
+            &[],
+            &[to_transfer],
+        );
+        let subresource = vk::ImageSubresourceLayers::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+        let region = vk::ImageResolve::builder()
+            .src_subresource(subresource)
+            .dst_subresource(subresource)
+            .extent(vk::Extent3D {
+                width: self.swapchain.extent.width,
+                height: self.swapchain.extent.height,
+                depth: 1,
+            })
+            .build();
+        self.device.resolve_image(
+            *command_buffer.get_vk(),
+            (color_buffer.get_image(), vk::ImageLayout::TRANSFER_SRC_OPTIMAL),
+            (image, vk::ImageLayout::TRANSFER_DST_OPTIMAL),
+            &[region],
+        );
+        // Presenting waits on the render finished semaphore, which makes the writes visible.
+        let to_present = barrier(
+            (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::PRESENT_SRC_KHR),
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::empty(),
+        );
+        self.device.pipeline_barrier(
+            *command_buffer.get_vk(),
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            &[],
+            &[to_present],
+        );
+    }
+
+    /// Binds `pipeline` and the descriptor set of the frame in flight `frame`, and draws the
+    /// scene once into each viewport of the layout.
+    fn draw_scene(&self, command_buffer: &CommandBuffer, pipeline: &Pipeline, frame: usize) {
+        pipeline.bind(&self.device, command_buffer);
+        pipeline.bind_descriptor_sets(
+            &self.device,
+            command_buffer,
+            FRAME_SET,
+            &[self.descriptor_sets.get(frame)],
+        );
+        if let Some(materials) = &self.materials {
+            materials.set.bind(&self.device, command_buffer, pipeline);
+        }
+        for region in &self.config.viewport_layout.regions {
+            let (viewport, scissor) = region.to_vk(self.swapchain.extent, self.config.viewport_y_flip);
+            self.device.set_viewport(*command_buffer.get_vk(), &[viewport]);
+            self.device.set_scissor(*command_buffer.get_vk(), &[scissor]);
+            match &self.cubes {
+                Some(cubes) => cubes.draw(&self.device, command_buffer, pipeline),
+                None => self.device.draw(*command_buffer.get_vk(), 3, 1, 0, 0),
+            }
+        }
+    }
+
+    /// Replaces the materials the voxels are drawn with: the `palette`, and the face textures,
+    /// one layer of `texture_extent` texels in `texture_format` per element of `texture_layers`,
+    /// see [`TextureArray::new`].
+    ///
+    /// A new material set is created rather than rewriting the bound one, which the frames in
+    /// flight may still read, and the old one is destroyed once they are done.
+    ///
+    /// # Errors
+    /// If the palette is empty, or the texture array or the set fail to be created.
+    pub fn set_materials(
+        &mut self,
+        palette: &MaterialPalette,
+        texture_extent: vk::Extent2D,
+        texture_format: vk::Format,
+        texture_layers: &[&[u8]],
+    ) -> anyhow::Result<()> {
+        let real_device = RealDevice::new(&self.instance, self.real_device);
+        let palette = Guard::new(palette.upload(&real_device, &self.device)?, |palette| palette.destroy(&self.device));
+        let mut uploader = StagingUploader::new(&self.device)?;
+        let textures = TextureArray::new(
+            &real_device,
+            &self.device,
+            &mut uploader,
+            &mut self.samplers,
+            texture_extent,
+            texture_format,
+            texture_layers,
+        );
+        uploader.destroy(&self.device);
+        let textures = Guard::new(textures?, |textures| textures.destroy(&self.device));
+        let set = MaterialSet::new(&self.device, &self.material_set_layout, &textures, &palette)?;
+
+        self.device.device_wait_idle()?;
+        // The bindless textures are kept, pointed to by the new set too.
+        let mut bindless_textures = HashMap::new();
+        if let Some(mut old) = self.materials.take() {
+            bindless_textures = std::mem::take(&mut old.bindless_textures);
+            old.destroy(&self.device);
+        }
+        for (&index, bindless) in &bindless_textures {
+            set.write_texture(&self.device, index, bindless);
+        }
+        self.materials = Some(Materials {
+            set,
+            textures: textures.into_inner(),
+            palette: palette.into_inner(),
+            bindless_textures,
+        });
+        // The recorded command buffers bind the old set.
+        self.scene.mark_dirty();
+        Ok(())
+    }
+
+    /// Sets the element `index` of the bindless textures, a texture array like the one of
+    /// [`App::set_materials`], which is the element 0. See [`AppConfig::bindless_textures`].
+    ///
+    /// The set is update-after-bind, so the recorded command buffers stay valid, but the
+    /// element may still be read by the frames in flight, which are waited for first.
+    ///
+    /// # Errors
+    /// If bindless textures aren't used, [`App::set_materials`] wasn't called yet, `index` is 0
+    /// or beyond the capacity, or the texture array fails to be created.
+    pub fn set_material_texture(
+        &mut self,
+        index: u32,
+        texture_extent: vk::Extent2D,
+        texture_format: vk::Format,
+        texture_layers: &[&[u8]],
+    ) -> anyhow::Result<()> {
+        let capacity = MaterialSet::texture_capacity(&self.material_set_layout);
+        if capacity == 1 {
+            bail!("Bindless textures are not in use, see AppConfig::bindless_textures.");
+        }
+        if index == 0 || index >= capacity {
+            bail!("Bindless texture index {index} is out of 1..{capacity}.");
+        }
+        if self.materials.is_none() {
+            bail!("Bindless textures can only be set after the materials.");
+        }
+        let real_device = RealDevice::new(&self.instance, self.real_device);
+        let mut uploader = StagingUploader::new(&self.device)?;
+        let textures = TextureArray::new(
+            &real_device,
+            &self.device,
+            &mut uploader,
+            &mut self.samplers,
+            texture_extent,
+            texture_format,
+            texture_layers,
+        );
+        uploader.destroy(&self.device);
+        let textures = Guard::new(textures?, |textures| textures.destroy(&self.device));
+
+        self.device.device_wait_idle()?;
+        let Some(materials) = &mut self.materials else {
+            unreachable!("The materials were checked above.");
+        };
+        materials.set.write_texture(&self.device, index, &textures);
+        if let Some(old) = materials.bindless_textures.insert(index, textures.into_inner()) {
+            old.destroy(&self.device);
+        }
+        Ok(())
+    }
+
+    /// Replaces the voxels drawn with [`VoxelRenderMode::InstancedCubes`].
+    ///
+    /// Waits for the frames in flight, since they read the instances.
+    ///
+    /// # Errors
+    /// If the app uses another voxel render mode, or there are more instances than
+    /// [`AppConfig::cube_instance_capacity`].
+    pub fn set_cube_instances(&mut self, instances: &[CubeInstance]) -> anyhow::Result<()> {
+        let Some(cubes) = &mut self.cubes else {
+            bail!(
+                "Cube instances can't be drawn with the {:?} voxel render mode.",
+                self.config.voxel_render_mode
+            );
+        };
+        self.device.device_wait_idle()?;
+        cubes.write(&self.device, instances)?;
+        self.scene.mark_dirty();
+        Ok(())
+    }
+
+    /// The lines and points to draw over the next frame, e.g. to visualize chunk bounds.
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// Streams the chunks of `grid` around `center`, see [`ChunkGrid::stream`], and replaces
+    /// the drawn cube instances with the ones of the loaded chunks if any chunk was loaded or
+    /// unloaded.
+    ///
+    /// # Errors
+    /// Like [`App::set_cube_instances`].
+    pub fn stream_chunks<C, F>(
+        &mut self,
+        grid: &mut ChunkGrid<C>,
+        center: ChunkId,
+        radius: u32,
+        load: F,
+    ) -> anyhow::Result<Vec<ChunkEvent>>
+    where
+        C: AsRef<[CubeInstance]>,
+        F: FnMut(ChunkId) -> C,
+    {
+        let events = grid.stream(center, radius, load);
+        if !events.is_empty() {
+            self.set_cube_instances(&grid.cube_instances())
+                .with_context(|| "Failed to update the streamed chunks.")?;
+        }
+        Ok(events)
+    }
+
+    /// Sets the function that records extra commands into the frame, e.g. a debug UI.
+    ///
+    /// It is called inside the main subpass of the render pass (see
+    /// [`MyRenderPass::main_subpass`], which its pipelines must be created for), after the scene
+    /// is drawn and before the render pass ends, with the viewport and scissor covering the whole frame. It must bind its own pipeline
+    /// and descriptor sets, and only record commands allowed inside a render pass.
+    ///
+    /// Overlays usually change every frame, so while one is set the command buffers are recorded
+    /// every frame instead of being reused.
+    pub fn set_overlay_recorder(&mut self, overlay_recorder: OverlayRecorder) {
+        self.overlay_recorder = Some(overlay_recorder);
+        self.scene.mark_dirty();
+    }
+
+    /// Removes the overlay recorder, see [`App::set_overlay_recorder`].
+    pub fn clear_overlay_recorder(&mut self) {
+        self.overlay_recorder = None;
+        self.scene.mark_dirty();
+    }
+
+    /// Sets the callback called at the start of every rendered frame, before the commands are
+    /// recorded, with the seconds since the previous frame. Moving the camera or animating by
+    /// this time step keeps the simulation speed independent of the frame rate.
+    ///
+    /// The step is clamped to [`MAX_FRAME_DELTA`], so after a stall, or resuming from a pause,
+    /// the simulation only moves by one small step instead of catching up at once.
+    pub fn set_update_fn(&mut self, update_fn: UpdateFn) {
+        self.update_fn = Some(update_fn);
+    }
+
+    pub fn redraw_mode(&self) -> RedrawMode {
+        self.config.redraw_mode
+    }
+
+    /// Pauses or resumes the rendering, e.g. while an editor is minimized to the tray.
+    ///
+    /// While paused, [`App::render`] returns right away, without acquiring, submitting nor
+    /// presenting anything, so the GPU idles. The event loop keeps processing the window
+    /// events, and should stop requesting redraws to not spin the CPU either.
+    ///
+    /// A frame is always rendered whole within [`App::render`], so pausing can't interrupt one.
+    /// The frames already submitted finish on their own and signal their in-flight fences, which
+    /// the next frame waits for as usual after resuming. The frame pacing simply resumes with
+    /// the next frame in flight, and a swapchain recreation requested while paused (e.g. by a
+    /// resize) happens on the first frame after resuming.
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused != paused {
+            info!("Rendering {}.", if paused { "paused" } else { "resumed" });
+        }
+        self.paused = paused;
+    }
+
+    /// Tells the app whether the window has the focus, which pauses the rendering while it
+    /// doesn't if `pause_when_unfocused` is set, see [`App::set_paused`].
+    pub fn set_focused(&mut self, focused: bool) {
+        self.unfocused = !focused;
+        if self.config.pause_when_unfocused {
+            debug!("Window focused: {focused}, rendering paused: {}.", self.is_paused());
+        }
+    }
+
+    /// Whether [`App::render`] currently skips the frames, explicitly paused or unfocused.
+    pub fn is_paused(&self) -> bool {
+        self.paused || (self.config.pause_when_unfocused && self.unfocused)
+    }
+
+    /// Requests the swapchain to be recreated before the next frame, e.g. when the size of the
+    /// window in physical pixels changed.
+    pub fn request_swapchain_recreation(&mut self) {
+        self.swapchain_outdated = true;
+    }
+
+    /// Recreates the swapchain and everything that depends on its images, format or extent.
+    ///
+    /// Creating a swapchain with a zero extent is invalid, so this must be deferred while the
+    /// window is minimized, see [`MyWindow::is_minimized`].
+    fn recreate_swapchain(&mut self, window: &MyWindow) -> anyhow::Result<()> {
+        info!("Recreating swapchain for window size {:?}...", window.size());
+        // None of the resources can be in use by the frames in flight.
+        self.device.device_wait_idle()?;
+        self.destroy_swapchain_dependents();
+        self.pipelines.clear();
+
+        let real_device = RealDevice::new(&self.instance, self.real_device);
+        // The old swapchain is only destroyed once replaced, it is passed to the new one.
+        let swapchain = Swapchain::new(window, &real_device, &self.device, &self.surface, &self.config.swapchain, Some(&self.swapchain))
+            .with_context(|| "Failed to recreate swapchain.")?;
+        std::mem::replace(&mut self.swapchain, swapchain).destroy(&self.device, &self.surface);
+        self.color_buffer = Self::create_color_buffer(&real_device, &self.device, &self.swapchain, &self.pipeline_config, self.msaa_resolve_mode)?;
+        self.depth_buffer = Self::create_depth_buffer(
+            &real_device,
+            &self.device,
+            &self.swapchain,
+            &self.pipeline_config,
+            self.config.depth_format_preference,
+        )?;
+        let depth_format = self.depth_buffer.as_ref().map(DepthBuffer::get_format);
+        self.render_pass = MyRenderPass::new(&self.swapchain, &self.device, depth_format, self.pipeline_config.samples, self.msaa_resolve_mode, self.config.depth_prepass, self.config.depth_convention)
+            .with_context(|| "Failed to recreate render pass.")?;
+        // The swapchain format may have changed.
+        if let Some(dynamic_rendering) = &mut self.dynamic_rendering {
+            dynamic_rendering.formats = RenderingFormats {
+                color: self.swapchain.format,
+                depth: depth_format,
+            };
+            self.pipeline_config.dynamic_rendering = Some(dynamic_rendering.formats);
+        }
+        // The pipelines of the other modes are rebuilt when they are used again.
+        self.build_pipeline(self.debug_draw_mode)
+            .with_context(|| "Failed to recreate pipeline.")?;
+        let viewport = Viewport::new(&self.swapchain, self.config.viewport_y_flip);
+        self.debug_pipelines = DebugDraw::create_pipelines(
+            &self.device,
+            &viewport,
+            &self.render_pass,
+            &self.descriptor_set_layout,
+            &self.pipeline_config,
         )
+        .with_context(|| "Failed to recreate debug draw pipelines.")?;
+        self.framebuffers = Self::create_framebuffers(&self.device, &self.swapchain, &self.render_pass, self.color_buffer.as_ref(), self.depth_buffer.as_ref(), self.msaa_resolve_mode);
+        // The command buffers reference the destroyed framebuffers.
+        self.recorded_command_buffers.fill(None);
+
+        // The swapchain may have a different number of images.
+        self.frame_sync
+            .reset_images(&self.device, self.framebuffers.len())
+            .with_context(|| "Failed to recreate sync objects.")?;
+        self.swapchain_outdated = false;
+        info_success!("Swapchain recreated!");
+        Ok(())
+    }
+
+    /// Destroys the swapchain and everything that depends on it, in reverse creation order.
+    fn destroy_swapchain(&self) {
+        self.destroy_swapchain_dependents();
+        self.swapchain.destroy(&self.device, &self.surface);
+    }
+
+    /// Destroys everything that depends on the swapchain images, format or extent, but not the
+    /// swapchain itself.
+    fn destroy_swapchain_dependents(&self) {
+        self.framebuffers
+            .iter()
+            .for_each(|framebuffer| framebuffer.destroy(&self.device));
+        self.pipelines
+            .values()
+            .for_each(|pipeline| pipeline.destroy(&self.device));
+        self.debug_pipelines.destroy(&self.device);
+        self.render_pass.destroy(&self.device);
+        if let Some(depth_buffer) = &self.depth_buffer {
+            depth_buffer.destroy(&self.device);
+        }
+        if let Some(color_buffer) = &self.color_buffer {
+            color_buffer.destroy(&self.device);
+        }
+    }
+
+    /// The pipelines of the current debug draw mode, which must have been built.
+    fn current_pipelines(&self) -> &ModePipelines {
+        &self.pipelines[&self.debug_draw_mode]
+    }
+
+    /// Creates the pipeline drawing `mode`, and the depth-only one of its prepass if `base`
+    /// draws after one.
+    fn create_mode_pipelines(
+        device: &LogicalDevice,
+        viewport: &Viewport,
+        render_pass: &MyRenderPass,
+        descriptor_set_layouts: &[&DescriptorSetLayout],
+        base: &PipelineConfig,
+        mode: DebugDrawMode,
+    ) -> anyhow::Result<ModePipelines> {
+        let config = mode.pipeline_config(base);
+        let main = Pipeline::new(device, viewport, render_pass, descriptor_set_layouts, &config)?;
+        if config.depth_pass != DepthPass::AfterPrepass {
+            return Ok(ModePipelines {
+                main,
+                depth_prepass: None,
+            });
+        }
+        // The prepass draws the same geometry as the mode, so that the depths match exactly.
+        let prepass_config = PipelineConfig {
+            depth_pass: DepthPass::Prepass,
+            ..config
+        };
+        match Pipeline::new(device, viewport, render_pass, descriptor_set_layouts, &prepass_config) {
+            Ok(depth_prepass) => Ok(ModePipelines {
+                main,
+                depth_prepass: Some(depth_prepass),
+            }),
+            Err(e) => {
+                main.destroy(device);
+                Err(e.context("Failed to create the depth prepass pipeline"))
+            }
+        }
+    }
+
+    /// Builds the pipeline of `mode` if it wasn't built yet.
+    fn build_pipeline(&mut self, mode: DebugDrawMode) -> anyhow::Result<()> {
+        if self.pipelines.contains_key(&mode) {
+            return Ok(());
+        }
+        debug!("Building pipeline for debug draw mode {mode:?}...");
+        let viewport = Viewport::new(&self.swapchain, self.config.viewport_y_flip);
+        let pipeline = Self::create_mode_pipelines(
+            &self.device,
+            &viewport,
+            &self.render_pass,
+            &[&self.descriptor_set_layout, &self.material_set_layout],
+            &self.pipeline_config,
+            mode,
+        )
+        .with_context(|| format!("Failed to create pipeline for debug draw mode {mode:?}."))?;
+        self.pipelines.insert(mode, pipeline);
+        Ok(())
+    }
+
+    /// Switches how the voxels are drawn, building the pipeline of the mode if needed.
+    /// Unsupported modes are skipped with a warning.
+    pub fn set_debug_draw_mode(&mut self, mode: DebugDrawMode) -> anyhow::Result<()> {
+        if mode == DebugDrawMode::Wireframe && !self.wireframe_supported {
+            warn!("Wireframe debug draw mode is not supported by the device, ignoring it.");
+            return Ok(());
+        }
+        self.build_pipeline(mode)?;
+        info!("Debug draw mode: {mode:?}");
+        self.debug_draw_mode = mode;
+        // The recorded command buffers bind the pipeline of the previous mode.
+        self.scene.mark_dirty();
+        Ok(())
+    }
+
+    /// Switches to the next debug draw mode, skipping the unsupported ones.
+    pub fn cycle_debug_draw_mode(&mut self) -> anyhow::Result<()> {
+        let mut mode = self.debug_draw_mode.next();
+        if mode == DebugDrawMode::Wireframe && !self.wireframe_supported {
+            mode = mode.next();
+        }
+        self.set_debug_draw_mode(mode)
+    }
+
+    /// Switches to the present `mode`, recreating the swapchain before the next frame. It stays
+    /// in use over the next recreations, instead of the mode of the present mode policy.
+    pub fn set_present_mode(&mut self, mode: vk::PresentModeKHR) -> anyhow::Result<()> {
+        let real_device = RealDevice::new(&self.instance, self.real_device);
+        let supported = self.surface.present_modes(&real_device)?;
+        if !supported.contains(&mode) {
+            bail!("The present mode {mode:?} is not supported by the surface, which supports {supported:?}.");
+        }
+        info!("Present mode: {mode:?}");
+        self.config.swapchain.present_mode = Some(mode);
+        self.request_swapchain_recreation();
+        Ok(())
+    }
+
+    /// Switches to the next present mode supported by the surface, to compare their latency and
+    /// power usage.
+    pub fn cycle_present_mode(&mut self) -> anyhow::Result<()> {
+        let real_device = RealDevice::new(&self.instance, self.real_device);
+        let supported = self.surface.present_modes(&real_device)?;
+        let current = supported
+            .iter()
+            .position(|mode| *mode == self.swapchain.present_mode);
+        let next = current.map_or(0, |current| (current + 1) % supported.len());
+        let Some(&mode) = supported.get(next) else {
+            bail!("The surface reports no supported present modes.");
+        };
+        self.set_present_mode(mode)
+    }
+
+    /// Writes a plain text snapshot of the Vulkan configuration to `path`, to attach to bug reports.
+    ///
+    /// It includes the selected device, the enabled layers and extensions, the swapchain
+    /// configuration, the queue family assignments and some key device limits.
+    pub fn write_diagnostics(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let report = self
+            .diagnostics_report()
+            .with_context(|| "Failed to format the diagnostics report.")?;
+        std::fs::write(path, report)
+            .with_context(|| format!("Failed to write diagnostics to {}.", path.display()))?;
+        info!("Diagnostics written to {}.", path.display());
+        Ok(())
+    }
+
+    fn diagnostics_report(&self) -> Result<String, std::fmt::Error> {
+        let real_device = RealDevice::new(&self.instance, self.real_device);
+        let properties = real_device.get_properties();
+        let limits = properties.limits;
+        let queues = self.device.get_queues();
+        let mut report = String::new();
+
+        writeln!(report, "[Device]")?;
+        writeln!(report, "name: {}", properties.device_name)?;
+        writeln!(report, "type: {:?}", properties.device_type)?;
+        writeln!(report, "vendor id: {:#06x}", properties.vendor_id)?;
+        writeln!(report, "device id: {:#06x}", properties.device_id)?;
+        writeln!(report, "api version: {}", vulkanalia::Version::from(properties.api_version))?;
+        writeln!(report, "driver version: {:#x}", properties.driver_version)?;
+        writeln!(report)?;
+
+        writeln!(report, "[Instance]")?;
+        writeln!(report, "layers: {:?}", self.instance.get_enabled_layers())?;
+        writeln!(report, "extensions: {:?}", self.instance.get_enabled_extensions())?;
+        writeln!(report, "device extensions: {:?}", self.device.get_enabled_extensions())?;
+        writeln!(report)?;
+
+        writeln!(report, "[Swapchain]")?;
+        writeln!(report, "format: {:?}", self.swapchain.format)?;
+        writeln!(report, "color space: {:?}", self.swapchain.color_space)?;
+        writeln!(report, "present mode: {:?}", self.swapchain.present_mode)?;
+        writeln!(report, "extent: {}x{}", self.swapchain.extent.width, self.swapchain.extent.height)?;
+        writeln!(report, "images: {}", self.swapchain.image_views.len())?;
+        writeln!(report)?;
+
+        writeln!(report, "[Queues]")?;
+        writeln!(report, "graphics family: {} ({} queues)", queues.graphics_family_index, queues.graphics.len())?;
+        writeln!(report, "present family: {} ({} queues)", queues.present_family_index, queues.present.len())?;
+        writeln!(report, "unified present: {}", queues.is_unified_present())?;
+        writeln!(report, "compute family: {:?} ({} queues)", self.device.compute_family_index(), queues.compute.len())?;
+        writeln!(report, "transfer family: {:?} ({} queues)", self.device.transfer_family_index(), queues.transfer.len())?;
+        writeln!(report)?;
+
+        writeln!(report, "[Limits]")?;
+        writeln!(report, "max image dimension 2D: {}", limits.max_image_dimension_2d)?;
+        writeln!(report, "max uniform buffer range: {}", limits.max_uniform_buffer_range)?;
+        writeln!(report, "max storage buffer range: {}", limits.max_storage_buffer_range)?;
+        writeln!(report, "max push constants size: {}", limits.max_push_constants_size)?;
+        writeln!(report, "max memory allocation count: {}", limits.max_memory_allocation_count)?;
+        writeln!(report, "max bound descriptor sets: {}", limits.max_bound_descriptor_sets)?;
+        writeln!(report, "max draw indirect count: {}", limits.max_draw_indirect_count)?;
+        writeln!(report, "max sampler anisotropy: {}", limits.max_sampler_anisotropy)?;
+        writeln!(report, "framebuffer color sample counts: {:?}", limits.framebuffer_color_sample_counts)?;
+        writeln!(report, "framebuffer depth sample counts: {:?}", limits.framebuffer_depth_sample_counts)?;
+
+        Ok(report)
+    }
+
+    /// Marks the scene as changed, so the command buffers are recorded again before being used.
+    pub fn mark_scene_dirty(&mut self) {
+        self.scene.mark_dirty();
+    }
+
+    /// Sets the column-major matrix from world space to clip space, used from the next frame.
+    /// See [`projection`](crate::gapi::projection) for projections following Vulkan's
+    /// conventions.
+    ///
+    /// It is read from the frame uniforms, written every frame, so the recorded command buffers
+    /// stay valid and the scene doesn't need to be marked dirty.
+    ///
+    /// The matrix is for the upright display, with
+    /// [`PreTransformPolicy::MatchDisplay`] on a rotated display it is rotated like the
+    /// swapchain images before being written, and the aspect ratio must be the one of the
+    /// window, not of the rotated swapchain extent.
+    pub fn set_view_projection(&mut self, view_projection: [[f32; 4]; 4]) {
+        self.view_projection = view_projection;
+    }
+
+    /// The time and count of the frames rendered so far.
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
     }
 
     fn select_swapchain_surface_format() {}
     /// Renders a frame for our Vulkan app.
+    ///
+    /// Does nothing once the app is destroyed, e.g. for a redraw event delivered while the event
+    /// loop is exiting, nor while it is paused, see [`App::set_paused`].
     pub fn render(&mut self, window: &MyWindow) -> anyhow::Result<()> {
+        if self.destroyed {
+            debug!("Ignoring render request, the app is destroyed.");
+            return Ok(());
+        }
+        if self.is_paused() {
+            trace!("Ignoring render request, the app is paused.");
+            return Ok(());
+        }
+        // A minimized window may have a zero extent surface (e.g. on Windows), which no swapchain
+        // can be created for, and presenting to the current one fails. The frame is skipped and
+        // the swapchain recreated once the window is restored.
+        if window.is_minimized() {
+            trace!("Skipping frame, the window is minimized.");
+            self.swapchain_outdated = true;
+            return Ok(());
+        }
+        let delta = self.frame_stats.tick().min(MAX_FRAME_DELTA);
+        if let Some(update_fn) = &mut self.update_fn {
+            update_fn(delta.as_secs_f32());
+        }
+        let frame = self.current_frame;
+        let in_flight_fence = self.frame_sync.in_flight_fence(frame);
+
+        // Wait for the GPU to finish the last frame that used these resources. Only after this
+        // can the descriptor set of this frame be updated.
+        let timeout = self.config.frame_timeout;
+        self.device
+            .wait_for_fences(&[in_flight_fence], true, timeout)
+            .with_context(|| format!("Failed to wait for frame {frame}."))?;
+        self.debug_draw.flush(&self.device, frame)?;
+        // The projection is rotated like the swapchain images, which the display rotates back.
+        let view_projection = self.swapchain.pre_rotate_projection(self.view_projection);
+        let uniforms = FrameUniforms::new(view_projection, &self.frame_stats);
+        self.uniforms.write(&self.device, frame, &uniforms)?;
+
+        let Some((image_index, suboptimal)) = self.acquire_next_image(window, frame)? else {
+            return Ok(());
+        };
+
+        // The image may still be rendered into by another frame in flight, whose render finished
+        // semaphore we are about to signal again.
+        self.frame_sync.claim_image(&self.device, image_index, frame, timeout)?;
+
+        // The command buffer of this frame is no longer executing, its fence was waited above.
+        // Mostly static scenes don't need re-recording every frame. The command buffer is reused
+        // as long as the scene didn't change, and it was recorded for the same swapchain image
+        // (it uses that image's framebuffer).
+        // Beginning a command buffer resets it (the pool has RESET_COMMAND_BUFFER), so a reused
+        // command buffer must not be begun nor reset, just submitted again.
+        let recorded_for = (self.scene.version(), image_index);
+        // The debug primitives change every frame, like the overlay, and the buffer is only
+        // drawn up to what was flushed when recording.
+        let has_debug_primitives = self.debug_draw.has_vertices(frame);
+        let reusable = self.overlay_recorder.is_none()
+            && !has_debug_primitives
+            && self.recorded_command_buffers[frame] == Some(recorded_for);
+        if !reusable {
+            trace!("Re-recording command buffer {frame} for {recorded_for:?}.");
+            self.record_command_buffer(image_index, frame)
+                .with_context(|| format!("Failed to record command buffer {frame}."))?;
+            self.recorded_command_buffers[frame] =
+                (!has_debug_primitives).then_some(recorded_for);
+        }
+
+        // Don't write to the image until it's been acquired, that is, until the color
+        // attachment output stage.
+        let wait_semaphores = &[self.frame_sync.image_available(frame)];
+        let wait_stages = &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let command_buffers = &[*self.command_buffers.get(frame).get_vk()];
+        let signal_semaphores = &[self.frame_sync.render_finished(image_index)];
+        let submit_info = vk::SubmitInfo::builder()
+            .wait_semaphores(wait_semaphores)
+            .wait_dst_stage_mask(wait_stages)
+            .command_buffers(command_buffers)
+            .signal_semaphores(signal_semaphores);
+
+        let queues = self.device.get_queues();
+        let graphics_queue = queues.graphics_queue()?;
+        // Reset right before the submit that signals it again, nothing may fail in between.
+        self.device.reset_fences(&[in_flight_fence])?;
+        let result = self.device.queue_submit_frame(graphics_queue, &[submit_info], in_flight_fence);
+        if !result.is_recoverable() {
+            // Otherwise the next render or wait on this frame would hang on the reset fence.
+            if let Err(err) = self.frame_sync.replace_in_flight_fence(&self.device, frame) {
+                warn!("Failed to replace the fence of frame {frame}: {err:?}");
+            }
+            bail!("Failed to submit frame {frame}: {result:?}");
+        }
+
+        // The present queue may be of another family than the graphics one, see
+        // `Queues::is_unified_present`. The render finished semaphore, signaled by the graphics
+        // submit and waited by the present, orders them across the queues. The swapchain images
+        // are then shared concurrently between both families, so no ownership transfer is
+        // needed, see `Swapchain::get_sharing_mode`.
+        let swapchains = &[self.swapchain.get_vk()];
+        let image_indices = &[image_index as u32];
+        let present_info = vk::PresentInfoKHR::builder()
+            .wait_semaphores(signal_semaphores)
+            .swapchains(swapchains)
+            .image_indices(image_indices);
+        // The image is presented anyway when suboptimal, the swapchain is recreated before the
+        // next frame, see `RenderResult::Suboptimal`.
+        let result = self.device.queue_present_khr(queues.present_queue()?, &present_info);
+        if !result.is_recoverable() {
+            bail!("Failed to present swapchain image: {result:?}");
+        }
+        if result.requires_recreate() {
+            debug!("Swapchain {result:?} on present, recreating it next frame.");
+            self.swapchain_outdated = true;
+        } else if suboptimal {
+            debug!("Swapchain suboptimal on acquire, recreating it next frame.");
+            self.swapchain_outdated = true;
+        }
 
+        self.current_frame = (self.current_frame + 1) % self.frame_sync.frames_in_flight();
+        self.frame_stats.advance();
 
         Ok(())
     }
 
+    /// Acquires the next swapchain image for the frame in flight `frame`, recreating the
+    /// swapchain first if it is out of date.
+    ///
+    /// While the window is being resized, the surface can keep changing and every new swapchain
+    /// be out of date again by the time an image is acquired from it. Instead of spinning until
+    /// the resize ends, the frame is skipped after [`MAX_SWAPCHAIN_RECREATIONS_PER_FRAME`]
+    /// recreations, and a redraw requested to try again on the next tick. Returns `None` when
+    /// the frame is skipped.
+    ///
+    /// Returns the image index with whether the swapchain is suboptimal, in which case the
+    /// caller recreates it once the image is presented, see [`RenderResult::Suboptimal`].
+    fn acquire_next_image(&mut self, window: &MyWindow, frame: usize) -> anyhow::Result<Option<(usize, bool)>> {
+        let mut recreations = 0;
+        loop {
+            if self.swapchain_outdated {
+                if recreations == MAX_SWAPCHAIN_RECREATIONS_PER_FRAME {
+                    self.skipped_outdated_frames += 1;
+                    warn!(
+                        "Swapchain still out of date after {recreations} recreations, skipping the frame ({} skipped in a row).",
+                        self.skipped_outdated_frames
+                    );
+                    window.request_redraw();
+                    return Ok(None);
+                }
+                recreations += 1;
+                self.recreate_swapchain(window)?;
+            }
+            let timeout = self.config.frame_timeout;
+            let acquired = self.device.acquire_next_image_khr(
+                self.swapchain.get_vk(),
+                timeout,
+                self.frame_sync.image_available(frame),
+                vk::Fence::null(),
+            );
+            match acquired {
+                Ok((image_index, suboptimal)) => {
+                    if self.skipped_outdated_frames > 0 {
+                        info!("Swapchain up to date again after {} skipped frames.", self.skipped_outdated_frames);
+                        self.skipped_outdated_frames = 0;
+                    }
+                    return Ok(Some((image_index as usize, suboptimal)));
+                }
+                // The semaphore isn't signaled, so acquiring again with it is valid.
+                Err(RenderResult::OutOfDate) => {
+                    debug!("Swapchain out of date on acquire (recreation {recreations}/{MAX_SWAPCHAIN_RECREATIONS_PER_FRAME}).");
+                    self.swapchain_outdated = true;
+                }
+                Err(RenderResult::Timeout) => {
+                    return Err(BurstError::GpuTimeout(timeout)).with_context(|| "Failed to acquire swapchain image.");
+                }
+                Err(result) => bail!("Failed to acquire swapchain image: {result:?}"),
+            }
+        }
+    }
+
     /// Destroys our Vulkan app.
-    pub fn destroy(&self) {
+    ///
+    /// Objects are destroyed in the reverse order of their dependencies:
+    /// 1. Wait for the device to be idle, nothing can be destroyed while the last frames are
+    ///    still being rendered, then save the pipeline cache to its file, if it has one.
+    /// 2. The sync objects, which nothing waits on anymore.
+    /// 3. The command buffers and their pool, which reference the framebuffers and pipelines.
+    /// 4. The framebuffers, the pipelines, the render pass, the attachments and the swapchain,
+    ///    see [`App::destroy_swapchain`].
+    /// 5. The buffers, descriptors, textures and samplers of the scene.
+    /// 6. The pipeline cache, and the surface, which can only be destroyed once no swapchain
+    ///    uses it.
+    /// 7. The device, once the [`ResourceTracker`](crate::gapi::vulkan::core::resource_tracker::ResourceTracker)
+    ///    confirmed everything created from it was destroyed.
+    /// 8. The debug messenger, which reports the destruction of everything before.
+    /// 9. The instance, which the surface, the device and the messenger were created from.
+    ///
+    /// Failing to wait for the device or to save the pipeline cache is only logged, the rest is
+    /// destroyed anyway, as the app can't be used afterwards. Calling it again does nothing.
+    pub fn destroy(&mut self) {
+        if self.destroyed {
+            debug!("The Vulkan App is already destroyed.");
+            return;
+        }
+        self.destroyed = true;
         info!("Destroying Vulkan App...");
+        if let Err(e) = self.device.device_wait_idle() {
+            warn!("Failed to wait for the device before destroying the app: {}", e);
+        }
+        if let Err(e) = self.pipeline_cache.save(&self.device) {
+            warn!("Failed to save the pipeline cache: {}", e);
+        }
+
+        self.frame_sync.destroy(&self.device);
+        self.command_buffers.free(&self.device, &self.command_pool);
         self.command_pool.destroy(&self.device);
-        self.framebuffers
-            .iter()
-            .for_each(|framebuffer| framebuffer.destroy(&self.device));
-        self.pipeline.destroy(&self.device);
-        self.render_pass.destroy(&self.device);
-        self.swapchain.destroy(&self.device);
+        self.destroy_swapchain();
+
+        if let Some(cubes) = &self.cubes {
+            cubes.destroy(&self.device);
+        }
+        self.debug_draw.destroy(&self.device);
+        self.uniforms.destroy(&self.device);
+        self.descriptor_sets.destroy(&self.device);
+        self.descriptor_set_layout.destroy(&self.device);
+        if let Some(materials) = &self.materials {
+            materials.destroy(&self.device);
+        }
+        self.material_set_layout.destroy(&self.device);
+        self.samplers.destroy(&self.device);
+
+        self.pipeline_cache.destroy(&self.device);
         self.surface.destroy(&self.instance);
+        // Everything created from the device must be destroyed by now, only checked in debug
+        // builds. Not while already panicking, as a second panic would abort.
+        let no_leaks = self.device.resources().report_leaks();
+        debug_assert!(no_leaks || std::thread::panicking(), "Vulkan objects leaked, see the errors above.");
         self.device.destroy();
+        if let Some(debugger) = &self.debugger {
+            debugger.destroy(&self.instance);
+        }
         self.instance.destroy();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gapi::vulkan::core::resource_tracker::ResourceTracker;
+    use crate::gapi::vulkan::test_support::{create_window, for_each_failure_point, lock_gpu, with_app};
+    use std::cell::Cell;
+    use std::fs;
+
+    #[test]
+    #[ignore = "needs a Vulkan device and a display"]
+    fn a_failure_at_any_creation_step_leaks_nothing() -> anyhow::Result<()> {
+        let _gpu = lock_gpu();
+        let window = create_window()?;
+        // The second config also creates the optional objects: the multisampled color buffer,
+        // the depth buffer, the prepass pipeline and the instanced cubes.
+        let builders = [
+            AppBuilder::default(),
+            AppBuilder::default()
+                .voxel_render_mode(VoxelRenderMode::InstancedCubes)
+                .msaa(vk::SampleCountFlags::_4)
+                .depth_prepass(true),
+        ];
+        for builder in builders {
+            // The leak reports and the validation errors before the last attempt.
+            let before = Cell::new((0, 0));
+            let attempt = || {
+                before.set((ResourceTracker::leak_report_count(), Debugger::validation_error_count()));
+                builder.clone().validation(true).build(&window)
+            };
+            let check = |fail_at| {
+                let (leak_reports, validation_errors) = before.get();
+                assert_eq!(
+                    ResourceTracker::leak_report_count(),
+                    leak_reports,
+                    "Failing at failure point {fail_at} leaked Vulkan objects, see the logs."
+                );
+                assert_eq!(
+                    Debugger::validation_error_count(),
+                    validation_errors,
+                    "Failing at failure point {fail_at} made the validation layer report errors, see the logs."
+                );
+            };
+            for_each_failure_point(attempt, check).destroy();
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device and a display"]
+    fn recreating_the_swapchain_image_views_replaces_each_of_them() -> anyhow::Result<()> {
+        with_app(AppBuilder::default(), |app, _| {
+            let views = app.swapchain.image_views.len();
+            assert!(views > 0);
+
+            app.swapchain.recreate_image_views(&app.device)?;
+            // As many views as images, the old ones being destroyed.
+            assert_eq!(app.swapchain.image_views.len(), views);
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device and a display"]
+    fn render_after_destroy_does_nothing() -> anyhow::Result<()> {
+        with_app(AppBuilder::default(), |app, window| {
+            app.render(window)?;
+            let frame = app.current_frame;
+            app.destroy();
+            // Would use the destroyed objects, if it weren't ignored.
+            app.render(window)?;
+            app.render(window)?;
+            assert_eq!(app.current_frame, frame);
+            // Destroying again does nothing either, and `with_app` destroys it a third time.
+            app.destroy();
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device and a display"]
+    fn renders_several_frames_without_reusing_a_pending_semaphore() -> anyhow::Result<()> {
+        // MAILBOX or IMMEDIATE usually get more swapchain images than there are frames in flight.
+        let swapchain = SwapchainConfig {
+            present_mode_policy: PresentModePolicy::Uncapped,
+            ..SwapchainConfig::default()
+        };
+        with_app(AppBuilder::default().swapchain(swapchain), |app, window| {
+            for _ in 0..4 * app.swapchain.image_views.len() {
+                app.render(window)?;
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device and a display"]
+    fn allocates_one_command_buffer_per_frame_in_flight() -> anyhow::Result<()> {
+        with_app(AppBuilder::default(), |app, window| {
+            // Not one per swapchain image, which is usually 3 with the default present mode.
+            assert_eq!(app.command_buffers.get_buffers().len(), FRAMES_IN_FLIGHT);
+            for _ in 0..FRAMES_IN_FLIGHT + 1 {
+                app.render(window)?;
+            }
+            app.recreate_swapchain(window)?;
+            assert_eq!(app.command_buffers.get_buffers().len(), FRAMES_IN_FLIGHT);
+            assert_eq!(app.recorded_command_buffers.len(), FRAMES_IN_FLIGHT);
+            app.render(window)
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device and a display"]
+    fn shuts_down_cleanly_and_saves_the_pipeline_cache() -> anyhow::Result<()> {
+        let path = {
+            // The first GPU test writes to the environment, see `lock_gpu`.
+            let _gpu = lock_gpu();
+            std::env::temp_dir().join(format!("burst-pipeline-cache-{}.bin", std::process::id()))
+        };
+        let _ = fs::remove_file(&path);
+        with_app(AppBuilder::default().pipeline_cache_path(&path), |app, window| app.render(window))?;
+        let saved = fs::read(&path).with_context(|| "The pipeline cache wasn't saved")?;
+        assert!(!saved.is_empty());
+
+        // The next run starts from the saved cache, and saves it again.
+        with_app(AppBuilder::default().pipeline_cache_path(&path), |app, window| app.render(window))?;
+        assert!(fs::metadata(&path)?.len() > 0);
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
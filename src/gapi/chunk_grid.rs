@@ -0,0 +1,225 @@
+use crate::gapi::chunk_world::ChunkId;
+use crate::gapi::voxel_render_mode::CubeInstance;
+use log::debug;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+/// What changed in a [`ChunkGrid`] when streaming around the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkEvent {
+    Loaded(ChunkId),
+    Unloaded(ChunkId),
+}
+
+/// The loaded chunks of a large world, keyed by their position in the grid of chunks.
+///
+/// Streaming keeps the chunks within a radius of the camera loaded, see
+/// [`ChunkGrid::stream`]. What a chunk holds is up to the user, e.g. its voxels, and the grid
+/// has no Vulkan objects.
+#[derive(Debug)]
+pub struct ChunkGrid<C> {
+    /// Length of the edge of a chunk in world units.
+    chunk_size: f32,
+    chunks: HashMap<ChunkId, C>,
+}
+
+impl<C> ChunkGrid<C> {
+    pub fn new(chunk_size: f32) -> Self {
+        Self {
+            chunk_size,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// The chunk containing the world space `position`.
+    pub fn chunk_at(&self, position: [f32; 3]) -> ChunkId {
+        let [x, y, z] = position.map(|c| (c / self.chunk_size).floor() as i32);
+        ChunkId::new(x, y, z)
+    }
+
+    /// The chunks whose position is at most `radius` chunks away from `center`, loaded or not,
+    /// sorted by id. The chunks past the edges of the `i32` grid are left out.
+    pub fn chunks_in_radius(center: ChunkId, radius: u32) -> Vec<ChunkId> {
+        // The squared distances overflow an i32 from a radius of 2^15, and an i64 near u32::MAX.
+        let r = i64::from(radius);
+        let r_squared = i128::from(r) * i128::from(r);
+        let squared = |d: i64| i128::from(d) * i128::from(d);
+        let range = |c: i32| (i64::from(c) - r).max(i64::from(i32::MIN))..=(i64::from(c) + r).min(i64::from(i32::MAX));
+        let [cx, cy, cz] = [center.x, center.y, center.z].map(i64::from);
+        let mut ids = Vec::new();
+        for x in range(center.x) {
+            for y in range(center.y) {
+                for z in range(center.z) {
+                    if squared(x - cx) + squared(y - cy) + squared(z - cz) <= r_squared {
+                        // In the i32 grid, as the ranges are clamped to it.
+                        ids.push(ChunkId::new(x as i32, y as i32, z as i32));
+                    }
+                }
+            }
+        }
+        ids
+    }
+
+    /// Loads the chunks within `radius` of `center` that aren't yet with `load`, and unloads the
+    /// ones further away, returning what changed so that e.g. the instance buffer is only
+    /// rewritten when needed.
+    ///
+    /// The events are sorted: the unloads first, then the loads, each by id.
+    pub fn stream<F>(&mut self, center: ChunkId, radius: u32, mut load: F) -> Vec<ChunkEvent>
+    where
+        F: FnMut(ChunkId) -> C,
+    {
+        let wanted = Self::chunks_in_radius(center, radius);
+        let mut unloaded: Vec<ChunkId> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|id| wanted.binary_search(id).is_err())
+            .collect();
+        unloaded.sort_unstable();
+        let mut events: Vec<ChunkEvent> = unloaded
+            .into_iter()
+            .map(|id| {
+                self.chunks.remove(&id);
+                ChunkEvent::Unloaded(id)
+            })
+            .collect();
+        for id in wanted {
+            if let Entry::Vacant(entry) = self.chunks.entry(id) {
+                entry.insert(load(id));
+                events.push(ChunkEvent::Loaded(id));
+            }
+        }
+        if !events.is_empty() {
+            debug!(
+                "Streamed chunks around {center:?}: {} events, {} chunks loaded.",
+                events.len(),
+                self.chunks.len()
+            );
+        }
+        events
+    }
+
+    /// Adds a chunk, or replaces it after it changed.
+    pub fn insert(&mut self, id: ChunkId, chunk: C) -> Option<C> {
+        self.chunks.insert(id, chunk)
+    }
+
+    pub fn remove(&mut self, id: ChunkId) -> Option<C> {
+        self.chunks.remove(&id)
+    }
+
+    pub fn get(&self, id: ChunkId) -> Option<&C> {
+        self.chunks.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: ChunkId) -> Option<&mut C> {
+        self.chunks.get_mut(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// The loaded chunks, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (ChunkId, &C)> {
+        self.chunks.iter().map(|(id, chunk)| (*id, chunk))
+    }
+
+    pub fn chunk_size(&self) -> f32 {
+        self.chunk_size
+    }
+}
+
+impl<C: AsRef<[CubeInstance]>> ChunkGrid<C> {
+    /// The cubes of all the loaded chunks, sorted by chunk id so the draw order is stable, to
+    /// pass to [`App::set_cube_instances`](crate::gapi::app::App::set_cube_instances).
+    pub fn cube_instances(&self) -> Vec<CubeInstance> {
+        let mut ids: Vec<ChunkId> = self.chunks.keys().copied().collect();
+        ids.sort_unstable();
+        ids.iter()
+            .flat_map(|id| self.chunks[id].as_ref().iter().copied())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A chunk of a single cube, its offset is the x of its chunk id.
+    fn cube(id: ChunkId) -> Vec<CubeInstance> {
+        vec![CubeInstance::new([id.x as f32, 0.0, 0.0], 1.0, Default::default())]
+    }
+
+    #[test]
+    fn finds_the_chunk_of_a_position() {
+        let grid = ChunkGrid::<()>::new(16.0);
+        assert_eq!(grid.chunk_at([0.0, 15.9, 16.0]), ChunkId::new(0, 0, 1));
+        assert_eq!(grid.chunk_at([-0.1, -16.0, -16.1]), ChunkId::new(-1, -1, -2));
+    }
+
+    #[test]
+    fn lists_the_chunks_in_a_sphere_sorted() {
+        let center = ChunkId::new(5, -3, 0);
+        assert_eq!(ChunkGrid::<()>::chunks_in_radius(center, 0), [center]);
+        let ids = ChunkGrid::<()>::chunks_in_radius(center, 1);
+        assert_eq!(ids.len(), 7);
+        let ids = ChunkGrid::<()>::chunks_in_radius(center, 2);
+        // The 3 x 3 x 3 cube, whose corners are sqrt(3) away, and the 6 chunks 2 away along the
+        // axes.
+        assert_eq!(ids.len(), 27 + 6);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn leaves_out_the_chunks_past_the_edges_of_the_grid() {
+        let corner = ChunkId::new(i32::MAX, i32::MIN, 0);
+        let ids = ChunkGrid::<()>::chunks_in_radius(corner, 1);
+        assert_eq!(
+            ids,
+            [
+                ChunkId::new(i32::MAX - 1, i32::MIN, 0),
+                ChunkId::new(i32::MAX, i32::MIN, -1),
+                corner,
+                ChunkId::new(i32::MAX, i32::MIN, 1),
+                ChunkId::new(i32::MAX, i32::MIN + 1, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn streams_the_chunks_around_the_camera() {
+        let mut grid = ChunkGrid::new(1.0);
+        let events = grid.stream(ChunkId::new(0, 0, 0), 1, cube);
+        assert_eq!(events.len(), 7);
+        assert!(events.iter().all(|event| matches!(event, ChunkEvent::Loaded(_))));
+        assert!(grid.stream(ChunkId::new(0, 0, 0), 1, cube).is_empty());
+
+        // The unloads first, then the loads, each sorted by id.
+        let events = grid.stream(ChunkId::new(0, 1, 0), 1, cube);
+        let unloaded = [[-1, 0, 0], [0, -1, 0], [0, 0, -1], [0, 0, 1], [1, 0, 0]];
+        let loaded = [[-1, 1, 0], [0, 1, -1], [0, 1, 1], [0, 2, 0], [1, 1, 0]];
+        let expected: Vec<ChunkEvent> = unloaded
+            .map(|[x, y, z]| ChunkEvent::Unloaded(ChunkId::new(x, y, z)))
+            .into_iter()
+            .chain(loaded.map(|[x, y, z]| ChunkEvent::Loaded(ChunkId::new(x, y, z))))
+            .collect();
+        assert_eq!(events, expected);
+        assert_eq!(grid.len(), 7);
+    }
+
+    #[test]
+    fn collects_the_cubes_by_chunk_id() {
+        let mut grid = ChunkGrid::new(1.0);
+        for x in [2, -1, 0] {
+            grid.insert(ChunkId::new(x, 0, 0), cube(ChunkId::new(x, 0, 0)));
+        }
+        let offsets: Vec<f32> = grid.cube_instances().iter().map(|instance| instance.offset[0]).collect();
+        assert_eq!(offsets, [-1.0, 0.0, 2.0]);
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+/// Position of a chunk in the grid of chunks, in chunks and not in voxels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkId {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl ChunkId {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// The chunk sharing the given face with this one.
+    pub fn neighbor(self, face: Face) -> Self {
+        let [dx, dy, dz] = face.normal();
+        Self::new(self.x + dx, self.y + dy, self.z + dz)
+    }
+}
+
+/// A face of a chunk, named after the direction of its outward normal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Face {
+    NegX,
+    PosX,
+    NegY,
+    PosY,
+    NegZ,
+    PosZ,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::NegX,
+        Face::PosX,
+        Face::NegY,
+        Face::PosY,
+        Face::NegZ,
+        Face::PosZ,
+    ];
+
+    pub fn normal(self) -> [i32; 3] {
+        match self {
+            Face::NegX => [-1, 0, 0],
+            Face::PosX => [1, 0, 0],
+            Face::NegY => [0, -1, 0],
+            Face::PosY => [0, 1, 0],
+            Face::NegZ => [0, 0, -1],
+            Face::PosZ => [0, 0, 1],
+        }
+    }
+
+    /// The face of the neighbor that touches this one.
+    pub fn opposite(self) -> Self {
+        match self {
+            Face::NegX => Face::PosX,
+            Face::PosX => Face::NegX,
+            Face::NegY => Face::PosY,
+            Face::PosY => Face::NegY,
+            Face::NegZ => Face::PosZ,
+            Face::PosZ => Face::NegZ,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Which faces of a chunk are fully solid, i.e. every voxel of the layer along the face is
+/// opaque. Anything else counts as empty, even a single missing voxel, as light gets through it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaceMask(u8);
+
+impl FaceMask {
+    /// No solid face, e.g. a chunk of air or a chunk that isn't meshed yet.
+    pub const EMPTY: Self = Self(0);
+    /// Every face is solid, e.g. a chunk of stone.
+    pub const SOLID: Self = Self(0b11_1111);
+
+    pub fn with(self, face: Face, solid: bool) -> Self {
+        if solid {
+            Self(self.0 | face.bit())
+        } else {
+            Self(self.0 & !face.bit())
+        }
+    }
+
+    pub fn is_solid(self, face: Face) -> bool {
+        self.0 & face.bit() != 0
+    }
+}
+
+/// A plane `normal · p + distance = 0`, with the normal pointing inside the frustum.
+type Plane = [f32; 4];
+
+/// The six planes bounding what the camera sees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a column-major view-projection matrix, the layout of the GLSL
+    /// `mat4`, with Vulkan's clip space depth from 0 to 1.
+    pub fn from_view_projection(m: [[f32; 4]; 4]) -> Self {
+        let row = |i: usize| [m[0][i], m[1][i], m[2][i], m[3][i]];
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let add = |a: [f32; 4], b: [f32; 4]| std::array::from_fn(|i| a[i] + b[i]);
+        let sub = |a: [f32; 4], b: [f32; 4]| std::array::from_fn(|i| a[i] - b[i]);
+        Self {
+            planes: [add(r3, r0), sub(r3, r0), add(r3, r1), sub(r3, r1), r2, sub(r3, r2)],
+        }
+    }
+
+    /// Whether the box may be inside the frustum. Boxes crossing a corner of the frustum may
+    /// pass while being outside, which only costs drawing them.
+    pub fn intersects_box(&self, min: [f32; 3], max: [f32; 3]) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner of the box the furthest along the normal, if it is behind the plane
+            // the whole box is.
+            let corner: [f32; 3] =
+                std::array::from_fn(|i| if plane[i] >= 0.0 { max[i] } else { min[i] });
+            plane[0] * corner[0] + plane[1] * corner[1] + plane[2] * corner[2] + plane[3] >= 0.0
+        })
+    }
+}
+
+/// What [`ChunkWorld::visible_chunks`] needs to know about the camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// Position of the eye in world space.
+    pub position: [f32; 3],
+    pub frustum: Frustum,
+}
+
+/// The loaded chunks and their solid faces, used to pick the chunks to draw.
+///
+/// # Occlusion
+/// Besides frustum culling, a chunk is skipped when each of its faces that points toward the
+/// camera touches a fully solid face of a neighbor. Any line of sight into the chunk enters it
+/// through one of those faces, right after crossing the opaque layer of the neighbor, so the
+/// chunk can't be seen. The test is conservative: missing neighbors count as empty, and a chunk
+/// is never skipped when the camera is inside it or inside one of the neighbors it relies on.
+/// It only catches chunks sealed by their direct neighbors (e.g. inside a cave wall), not
+/// chunks hidden behind distant hills.
+#[derive(Debug, Default)]
+pub struct ChunkWorld {
+    /// Length of the edge of a chunk in world units.
+    chunk_size: f32,
+    chunks: HashMap<ChunkId, FaceMask>,
+}
+
+impl ChunkWorld {
+    pub fn new(chunk_size: f32) -> Self {
+        Self {
+            chunk_size,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Adds a chunk, or updates its faces after it changed.
+    pub fn insert(&mut self, id: ChunkId, faces: FaceMask) {
+        self.chunks.insert(id, faces);
+    }
+
+    pub fn remove(&mut self, id: ChunkId) {
+        self.chunks.remove(&id);
+    }
+
+    /// The loaded chunks that may be visible from the camera, sorted by id so the draw order is
+    /// stable.
+    pub fn visible_chunks(&self, camera: &Camera) -> Vec<ChunkId> {
+        let mut visible: Vec<ChunkId> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|&id| {
+                let (min, max) = self.bounds(id);
+                camera.frustum.intersects_box(min, max) && !self.is_occluded(id, camera.position)
+            })
+            .collect();
+        visible.sort_unstable();
+        visible
+    }
+
+    /// The world space corners of the chunk.
+    fn bounds(&self, id: ChunkId) -> ([f32; 3], [f32; 3]) {
+        let min = [id.x, id.y, id.z].map(|c| c as f32 * self.chunk_size);
+        (min, min.map(|c| c + self.chunk_size))
+    }
+
+    fn contains(&self, id: ChunkId, point: [f32; 3]) -> bool {
+        let (min, max) = self.bounds(id);
+        (0..3).all(|i| min[i] <= point[i] && point[i] <= max[i])
+    }
+
+    /// Whether the neighbors of the chunk hide it from `eye`, see the [struct docs](Self).
+    fn is_occluded(&self, id: ChunkId, eye: [f32; 3]) -> bool {
+        if self.contains(id, eye) {
+            return false;
+        }
+        let (min, max) = self.bounds(id);
+        Face::ALL.into_iter().all(|face| {
+            // A face is seen from the side its normal points to, faces seen edge-on or from
+            // behind can't let anything in.
+            let facing_eye = match face {
+                Face::NegX => eye[0] < min[0],
+                Face::PosX => eye[0] > max[0],
+                Face::NegY => eye[1] < min[1],
+                Face::PosY => eye[1] > max[1],
+                Face::NegZ => eye[2] < min[2],
+                Face::PosZ => eye[2] > max[2],
+            };
+            if !facing_eye {
+                return true;
+            }
+            let neighbor = id.neighbor(face);
+            !self.contains(neighbor, eye)
+                && self
+                    .chunks
+                    .get(&neighbor)
+                    .is_some_and(|faces| faces.is_solid(face.opposite()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAVE: ChunkId = ChunkId::new(0, 0, 0);
+
+    /// A frustum containing everything within 500 units of the origin.
+    fn everything() -> Frustum {
+        let scale = 1.0 / 1000.0;
+        Frustum::from_view_projection([
+            [scale, 0.0, 0.0, 0.0],
+            [0.0, scale, 0.0, 0.0],
+            [0.0, 0.0, scale, 0.0],
+            [0.0, 0.0, 0.5, 1.0],
+        ])
+    }
+
+    fn camera(position: [f32; 3]) -> Camera {
+        Camera {
+            position,
+            frustum: everything(),
+        }
+    }
+
+    /// The chunk of air at the origin, sealed in the 26 chunks of stone around it.
+    fn sealed_cave() -> ChunkWorld {
+        let mut world = ChunkWorld::new(1.0);
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    world.insert(ChunkId::new(x, y, z), FaceMask::SOLID);
+                }
+            }
+        }
+        world.insert(CAVE, FaceMask::EMPTY);
+        world
+    }
+
+    #[test]
+    fn skips_the_inside_of_a_sealed_cave() {
+        let world = sealed_cave();
+        let visible = world.visible_chunks(&camera([-10.0, 0.5, 0.5]));
+        assert!(!visible.contains(&CAVE));
+        // The wall facing the camera has no neighbor in front of it.
+        for y in -1..=1 {
+            for z in -1..=1 {
+                assert!(visible.contains(&ChunkId::new(-1, y, z)));
+            }
+        }
+        // The stone behind the cave is kept, only its neighbor is checked, and air hides nothing.
+        assert!(visible.contains(&ChunkId::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn keeps_the_cave_when_the_camera_is_inside_or_it_is_open() {
+        let world = sealed_cave();
+        // Only the walls are seen from inside, the chunks at the edges and corners are behind
+        // them.
+        let mut expected: Vec<ChunkId> = Face::ALL.into_iter().map(|face| CAVE.neighbor(face)).collect();
+        expected.push(CAVE);
+        expected.sort_unstable();
+        assert_eq!(world.visible_chunks(&camera([0.5, 0.5, 0.5])), expected);
+
+        // A camera in the wall in front of the cave sees into it.
+        assert!(world.visible_chunks(&camera([-0.5, 0.5, 0.5])).contains(&CAVE));
+
+        // A hole in the wall facing the camera opens the cave.
+        let mut world = sealed_cave();
+        world.insert(ChunkId::new(-1, 0, 0), FaceMask::SOLID.with(Face::PosX, false));
+        assert!(world.visible_chunks(&camera([-10.0, 0.5, 0.5])).contains(&CAVE));
+        // So does a missing wall.
+        let mut world = sealed_cave();
+        world.remove(ChunkId::new(-1, 0, 0));
+        assert!(world.visible_chunks(&camera([-10.0, 0.5, 0.5])).contains(&CAVE));
+    }
+
+    #[test]
+    fn skips_chunks_outside_of_the_frustum() {
+        let mut world = ChunkWorld::new(1.0);
+        let mut ids = Vec::new();
+        for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    ids.push(ChunkId::new(x, y, z));
+                    world.insert(ChunkId::new(x, y, z), FaceMask::EMPTY);
+                }
+            }
+        }
+        // Keeps x from -3.5 to 0.5, mapped to the depth from 1 to 0.
+        let frustum = Frustum::from_view_projection([
+            [0.1, 0.0, -0.25, 0.0],
+            [0.0, 0.1, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 0.125, 1.0],
+        ]);
+        let camera = Camera {
+            position: [-3.0, 0.5, 0.5],
+            frustum,
+        };
+        ids.retain(|id| id.x <= 0);
+        assert_eq!(world.visible_chunks(&camera), ids);
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{bail, Context};
+use vulkanalia::vk;
+
+/// An RGBA color with `f32` components, usually between 0 and 1.
+///
+/// The components are stored as given, a color doesn't know whether it is sRGB-encoded or
+/// linear. Colors picked by humans (e.g. hex codes) are sRGB, while shaders, blending and
+/// `*_SRGB` attachments work with linear values, so convert with [`Color::srgb_to_linear`].
+///
+/// It has the same layout as `[f32; 4]` and the GLSL `vec4`, so it can be uploaded as is.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Self = Self::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::rgb(1.0, 1.0, 1.0);
+    pub const TRANSPARENT: Self = Self::rgba(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// An opaque color.
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::rgba(r, g, b, 1.0)
+    }
+
+    /// Parses an sRGB hex code like `#ff8800` or `#ff880080`, the `#` is optional.
+    ///
+    /// # Errors
+    /// If the code doesn't have 6 or 8 hex digits.
+    pub fn from_hex(hex: &str) -> anyhow::Result<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        // `from_str_radix` accepts a leading `+`, so each digit is checked beforehand.
+        if !(digits.len() == 6 || digits.len() == 8) || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            bail!("Invalid hex color \"{hex}\", expected 6 or 8 hex digits.");
+        }
+        let channel = |i: usize| -> anyhow::Result<f32> {
+            let byte = u8::from_str_radix(&digits[i..i + 2], 16)
+                .with_context(|| format!("Invalid hex color \"{hex}\""))?;
+            Ok(byte as f32 / 255.0)
+        };
+        let alpha = if digits.len() == 8 { channel(6)? } else { 1.0 };
+        Ok(Self::rgba(channel(0)?, channel(2)?, channel(4)?, alpha))
+    }
+
+    /// Decodes the sRGB-encoded color components to linear ones, alpha is always linear.
+    pub fn srgb_to_linear(self) -> Self {
+        fn decode(c: f32) -> f32 {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        Self::rgba(decode(self.r), decode(self.g), decode(self.b), self.a)
+    }
+
+    /// Encodes the linear color components to sRGB ones, alpha is always linear.
+    pub fn linear_to_srgb(self) -> Self {
+        fn encode(c: f32) -> f32 {
+            if c <= 0.0031308 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        }
+        Self::rgba(encode(self.r), encode(self.g), encode(self.b), self.a)
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// The components clamped between 0 and 1 and quantized to bytes, as read back by a
+    /// `R8G8B8A8_UNORM` attribute or image.
+    pub fn to_rgba8(self) -> [u8; 4] {
+        self.to_array().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self::BLACK
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Self::rgba(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+impl From<Color> for vk::ClearColorValue {
+    fn from(color: Color) -> Self {
+        vk::ClearColorValue {
+            float32: color.to_array(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Color, b: Color) {
+        for (x, y) in a.to_array().into_iter().zip(b.to_array()) {
+            assert!((x - y).abs() < 1e-5, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn parses_hex_codes() {
+        assert_eq!(Color::from_hex("#ff0000").unwrap(), Color::rgb(1.0, 0.0, 0.0));
+        assert_eq!(Color::from_hex("00ff00").unwrap(), Color::rgb(0.0, 1.0, 0.0));
+        assert_eq!(Color::from_hex("#0000FF00").unwrap(), Color::rgba(0.0, 0.0, 1.0, 0.0));
+        assert_eq!(Color::from_hex("#ff880080").unwrap().to_array(), [1.0, 136.0 / 255.0, 0.0, 128.0 / 255.0]);
+    }
+
+    #[test]
+    fn rejects_invalid_hex_codes() {
+        for hex in ["", "#", "#fff", "#ff00000", "#ff0000000", "#gg0000", "#+f0000", "+f0000", "#ff 000", "#ff00é"] {
+            assert!(Color::from_hex(hex).is_err(), "{hex:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn srgb_and_linear_round_trip() {
+        for c in [0.0, 0.002, 0.04, 0.2, 0.5, 0.8, 1.0] {
+            let color = Color::rgba(c, c / 2.0, 1.0 - c, 0.5);
+            assert_close(color.srgb_to_linear().linear_to_srgb(), color);
+            assert_close(color.linear_to_srgb().srgb_to_linear(), color);
+        }
+        // Alpha is never converted, and mid grey is about 0.214 in linear.
+        assert_close(Color::rgba(0.5, 0.5, 0.5, 0.5).srgb_to_linear(), Color::rgba(0.21404, 0.21404, 0.21404, 0.5));
+    }
+}
@@ -0,0 +1,271 @@
+use crate::gapi::color::Color;
+use crate::gapi::vulkan::commands::command_buffers::CommandBuffer;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::descriptors::descriptor_set_layout::DescriptorSetLayout;
+use crate::gapi::vulkan::memory::vertex_buffer::VertexBuffer;
+use crate::gapi::vulkan::pipeline::pipeline::{Pipeline, PipelineConfig, ShaderProgram};
+use crate::gapi::vulkan::pipeline::render_pass::MyRenderPass;
+use crate::gapi::vulkan::pipeline::stages::per_fragment_tests_stage::DepthPass;
+use crate::gapi::vulkan::pipeline::viewport::Viewport;
+use anyhow::Context;
+use log::{debug, warn};
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// An end of a debug line, or a debug point.
+///
+/// The layout matches the vertex input of the debug vertex shader, a `vec3` position followed
+/// by a `vec3` color.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugVertex {
+    pub position: [f32; 3],
+    /// Linear RGB color, the alpha of the [`Color`] is dropped as nothing is blended.
+    pub color: [f32; 3],
+}
+
+impl DebugVertex {
+    fn new(position: [f32; 3], color: Color) -> Self {
+        Self {
+            position,
+            color: [color.r, color.g, color.b],
+        }
+    }
+}
+
+/// The vertices of a frame in flight, written by the CPU while the other frames are drawn.
+struct DebugFrame {
+    /// The lines first, then the points.
+    buffer: VertexBuffer,
+    line_vertex_count: u32,
+    point_count: u32,
+}
+
+/// Immediate-mode drawing of lines and points, to visualize frustums, chunk bounds, raycasts...
+///
+/// The primitives are accumulated between two frames with [`DebugDraw::line`] and
+/// [`DebugDraw::point`], flushed into the vertex buffer of the frame in flight when it is
+/// rendered, and drawn after the scene, without depth test so they are never hidden. They are
+/// then forgotten, so they must be added again every frame to stay visible.
+///
+/// As there is no camera yet, the positions are in clip space, like the voxels.
+pub struct DebugDraw {
+    lines: Vec<DebugVertex>,
+    points: Vec<DebugVertex>,
+    frames: Vec<DebugFrame>,
+    /// How many vertices fit in the buffer of a frame, lines and points together.
+    capacity: usize,
+}
+
+/// The pipelines of the [`DebugDraw`], which depend on the render pass like the voxel ones.
+pub struct DebugPipelines {
+    lines: Pipeline,
+    points: Pipeline,
+}
+
+impl DebugPipelines {
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.points.destroy(device);
+        self.lines.destroy(device);
+    }
+}
+
+impl DebugDraw {
+    const BINDING: u32 = 0;
+
+    /// Creates the vertex buffers of `frames_in_flight` frames, of `capacity` vertices each.
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        frames_in_flight: usize,
+        capacity: usize,
+    ) -> anyhow::Result<Self> {
+        let mut frames = Vec::with_capacity(frames_in_flight);
+        for frame in 0..frames_in_flight {
+            // Rewritten by the CPU every frame, so it stays in host visible memory.
+            let buffer = VertexBuffer::new::<DebugVertex>(
+                real_device,
+                device,
+                capacity,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            );
+            match buffer {
+                Ok(buffer) => frames.push(DebugFrame {
+                    buffer,
+                    line_vertex_count: 0,
+                    point_count: 0,
+                }),
+                Err(e) => {
+                    frames.iter().for_each(|frame| frame.buffer.destroy(device));
+                    return Err(e.context(format!("Failed to create the debug draw buffer of frame {frame}")));
+                }
+            }
+        }
+        debug!("Created debug draw buffers of {capacity} vertices for {frames_in_flight} frames.");
+        Ok(Self {
+            lines: Vec::new(),
+            points: Vec::new(),
+            frames,
+            capacity,
+        })
+    }
+
+    /// The vertex input of the debug vertex shader, see [`DebugVertex`].
+    pub fn vertex_input() -> (
+        Vec<vk::VertexInputBindingDescription>,
+        Vec<vk::VertexInputAttributeDescription>,
+    ) {
+        let bindings = vec![vk::VertexInputBindingDescription::builder()
+            .binding(Self::BINDING)
+            .stride(size_of::<DebugVertex>() as u32)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .build()];
+        let attributes = vec![
+            vk::VertexInputAttributeDescription::builder()
+                .binding(Self::BINDING)
+                .location(0)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(0)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(Self::BINDING)
+                .location(1)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(size_of::<[f32; 3]>() as u32)
+                .build(),
+        ];
+        (bindings, attributes)
+    }
+
+    /// Creates the pipelines drawing the lines and the points into `render_pass`, from the
+    /// configuration of the voxel pipelines.
+    pub fn create_pipelines(
+        device: &LogicalDevice,
+        viewport: &Viewport,
+        render_pass: &MyRenderPass,
+        descriptor_set_layout: &DescriptorSetLayout,
+        base: &PipelineConfig,
+    ) -> anyhow::Result<DebugPipelines> {
+        let config = |topology| PipelineConfig {
+            program: ShaderProgram::DebugPrimitives,
+            topology,
+            polygon_mode: vk::PolygonMode::FILL,
+            stencil: None,
+            depth_bias: None,
+            depth_pass: DepthPass::Disabled,
+            ..base.clone()
+        };
+        let lines = Pipeline::new(
+            device,
+            viewport,
+            render_pass,
+            &[descriptor_set_layout],
+            &config(vk::PrimitiveTopology::LINE_LIST),
+        )
+        .with_context(|| "Failed to create the debug line pipeline")?;
+        let points = Pipeline::new(
+            device,
+            viewport,
+            render_pass,
+            &[descriptor_set_layout],
+            &config(vk::PrimitiveTopology::POINT_LIST),
+        );
+        match points {
+            Ok(points) => Ok(DebugPipelines { lines, points }),
+            Err(e) => {
+                lines.destroy(device);
+                Err(e.context("Failed to create the debug point pipeline"))
+            }
+        }
+    }
+
+    /// Adds a line from `a` to `b` to the next frame.
+    pub fn line(&mut self, a: [f32; 3], b: [f32; 3], color: Color) {
+        self.lines.push(DebugVertex::new(a, color));
+        self.lines.push(DebugVertex::new(b, color));
+    }
+
+    /// Adds a point at `p` to the next frame, of the size of the voxel points.
+    pub fn point(&mut self, p: [f32; 3], color: Color) {
+        self.points.push(DebugVertex::new(p, color));
+    }
+
+    /// Whether the frame has anything to draw since its last flush.
+    pub fn has_vertices(&self, frame: usize) -> bool {
+        let frame = &self.frames[frame];
+        frame.line_vertex_count > 0 || frame.point_count > 0
+    }
+
+    /// Writes the primitives added since the last flush into the buffer of `frame`, and
+    /// forgets them. The frame must not be in flight anymore.
+    ///
+    /// The primitives that don't fit in the buffer are dropped with a warning.
+    pub fn flush(&mut self, device: &LogicalDevice, frame: usize) -> anyhow::Result<()> {
+        let line_vertex_count = self.lines.len().min(self.capacity / 2 * 2);
+        let point_count = self.points.len().min(self.capacity - line_vertex_count);
+        if line_vertex_count < self.lines.len() || point_count < self.points.len() {
+            warn!(
+                "Dropping {} debug lines and {} debug points over the capacity of {} vertices.",
+                (self.lines.len() - line_vertex_count) / 2,
+                self.points.len() - point_count,
+                self.capacity
+            );
+        }
+        let debug_frame = &mut self.frames[frame];
+        if line_vertex_count + point_count > 0 {
+            let mut vertices = Vec::with_capacity(line_vertex_count + point_count);
+            vertices.extend_from_slice(&self.lines[..line_vertex_count]);
+            vertices.extend_from_slice(&self.points[..point_count]);
+            debug_frame
+                .buffer
+                .write(device, &vertices)
+                .with_context(|| format!("Failed to flush the debug draw of frame {frame}"))?;
+        }
+        debug_frame.line_vertex_count = line_vertex_count as u32;
+        debug_frame.point_count = point_count as u32;
+        self.lines.clear();
+        self.points.clear();
+        Ok(())
+    }
+
+    /// Records the draws of what was flushed for `frame`, skipping the empty ones. The
+    /// descriptor sets, viewport and scissor must already be set.
+    pub fn draw(
+        &self,
+        device: &LogicalDevice,
+        command_buffer: &CommandBuffer,
+        pipelines: &DebugPipelines,
+        frame: usize,
+    ) {
+        let debug_frame = &self.frames[frame];
+        if debug_frame.line_vertex_count > 0 {
+            pipelines.lines.bind(device, command_buffer);
+            pipelines
+                .lines
+                .bind_vertex_buffers(device, command_buffer, Self::BINDING, &[&debug_frame.buffer], &[0]);
+            device.draw(*command_buffer.get_vk(), debug_frame.line_vertex_count, 1, 0, 0);
+        }
+        if debug_frame.point_count > 0 {
+            pipelines.points.bind(device, command_buffer);
+            pipelines
+                .points
+                .bind_vertex_buffers(device, command_buffer, Self::BINDING, &[&debug_frame.buffer], &[0]);
+            // The points follow the lines in the buffer.
+            device.draw(
+                *command_buffer.get_vk(),
+                debug_frame.point_count,
+                1,
+                debug_frame.line_vertex_count,
+                0,
+            );
+        }
+    }
+
+    /// Destroys the buffers, which must not be in use by a frame in flight.
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.frames
+            .iter()
+            .for_each(|frame| frame.buffer.destroy(device));
+    }
+}
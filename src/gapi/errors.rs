@@ -0,0 +1,23 @@
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors of the engine that callers may want to tell apart, instead of only reporting them.
+#[derive(Debug, Error)]
+pub enum BurstError {
+    /// The GPU didn't finish a frame in time, it is probably hung.
+    #[error("The GPU didn't finish the frame within {0:?}, it may be hung.")]
+    GpuTimeout(Duration),
+    /// The surface is already used by another swapchain, or by another API (e.g. OpenGL).
+    #[error(
+        "The window surface is already in use. Ensure the previous swapchain is destroyed, or \
+         passed as old_swapchain when creating the new one."
+    )]
+    SurfaceInUse,
+    /// The Vulkan loader is installed, but it found no device, e.g. without a GPU driver
+    /// (installable client driver) supporting Vulkan.
+    #[error(
+        "No Vulkan-capable GPU found. Check that a GPU driver with Vulkan support is installed, \
+         e.g. with `vulkaninfo`."
+    )]
+    NoPhysicalDevices,
+}
@@ -0,0 +1,184 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::memory::buffer::Buffer;
+use anyhow::Context;
+use log::debug;
+use std::mem::offset_of;
+use std::time::{Duration, Instant};
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// The uniforms rewritten every frame, read by the shaders at binding 0 of the per frame set.
+///
+/// The layout matches the std140 layout of the shader block, see `common.glsl`:
+/// ```glsl
+/// layout(set = 0, binding = 0) uniform FrameUniforms {
+///     mat4 view_projection;
+///     float time_seconds;
+///     uint frame_index;
+/// } frame;
+/// ```
+/// In std140, a `mat4` is four `vec4` columns aligned to 16 bytes, and scalars are aligned to
+/// their size, so `time_seconds` and `frame_index` are packed right after the matrix. The block
+/// size is rounded up to a multiple of 16 bytes, hence the padding. A `vec3` would be aligned to
+/// 16 bytes like a `vec4`, which `[f32; 3]` isn't, so prefer `vec4` or scalars when adding
+/// fields, and check the offsets below still match.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameUniforms {
+    /// Column-major, from world space to clip space.
+    pub view_projection: [[f32; 4]; 4],
+    /// Seconds since the app started, to animate the shader effects.
+    pub time_seconds: f32,
+    /// How many frames were rendered before this one, wrapping around.
+    pub frame_index: u32,
+    _padding: [u32; 2],
+}
+
+// The std140 offsets of the shader block.
+const _: () = {
+    assert!(offset_of!(FrameUniforms, view_projection) == 0);
+    assert!(offset_of!(FrameUniforms, time_seconds) == 64);
+    assert!(offset_of!(FrameUniforms, frame_index) == 68);
+    assert!(size_of::<FrameUniforms>() == 80);
+};
+
+impl FrameUniforms {
+    pub const IDENTITY: [[f32; 4]; 4] = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+
+    pub fn new(view_projection: [[f32; 4]; 4], stats: &FrameStats) -> Self {
+        Self {
+            view_projection,
+            time_seconds: stats.elapsed_seconds(),
+            frame_index: stats.frame_index() as u32,
+            _padding: [0; 2],
+        }
+    }
+
+    /// The layout binding of the uniforms, at `binding`. Both stages read them, the fragment
+    /// shader for time based effects.
+    pub fn descriptor_binding(binding: u32) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding::builder()
+            .binding(binding)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+            .build()
+    }
+}
+
+/// The time and count of the rendered frames.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameStats {
+    start: Instant,
+    frames: u64,
+    /// When [`FrameStats::tick`] was last called.
+    last_tick: Option<Instant>,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            frames: 0,
+            last_tick: None,
+        }
+    }
+}
+
+impl FrameStats {
+    /// Seconds since the stats were created. An `f32` loses the millisecond precision after a
+    /// few hours, which is fine for animations.
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+
+    /// How many frames were rendered so far.
+    pub fn frame_index(&self) -> u64 {
+        self.frames
+    }
+
+    /// The time since the previous call, zero on the first one.
+    pub fn tick(&mut self) -> Duration {
+        let now = Instant::now();
+        let delta = self.last_tick.map_or(Duration::ZERO, |last_tick| now - last_tick);
+        self.last_tick = Some(now);
+        delta
+    }
+
+    /// Counts a rendered frame.
+    pub fn advance(&mut self) {
+        self.frames += 1;
+    }
+}
+
+/// One uniform buffer per frame in flight, each pointed to by the per frame set of that frame.
+///
+/// Like the descriptor sets, the buffer of a frame must only be written after waiting for that
+/// frame's in flight fence.
+pub struct FrameUniformBuffers {
+    buffers: Vec<Buffer>,
+}
+
+impl FrameUniformBuffers {
+    /// Creates the buffers of `frames_in_flight` frames, and points `binding` of each of the
+    /// `descriptor_sets` to the buffer of its frame.
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        descriptor_sets: &[vk::DescriptorSet],
+        binding: u32,
+    ) -> anyhow::Result<Self> {
+        let size = size_of::<FrameUniforms>() as vk::DeviceSize;
+        let mut buffers = Vec::with_capacity(descriptor_sets.len());
+        for frame in 0..descriptor_sets.len() {
+            // Rewritten by the CPU every frame, so it stays in host visible memory.
+            let buffer = Buffer::new(
+                real_device,
+                device,
+                size,
+                vk::BufferUsageFlags::UNIFORM_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE,
+            );
+            match buffer {
+                Ok(buffer) => buffers.push(buffer),
+                Err(e) => {
+                    buffers.iter().for_each(|buffer| buffer.destroy(device));
+                    return Err(e.context(format!("Failed to create the uniform buffer of frame {frame}")));
+                }
+            }
+        }
+        for (buffer, &set) in buffers.iter().zip(descriptor_sets) {
+            let buffer_info = vk::DescriptorBufferInfo::builder()
+                .buffer(buffer.get_vk())
+                .offset(0)
+                .range(size);
+            let buffer_infos = &[buffer_info];
+            let write = vk::WriteDescriptorSet::builder()
+                .dst_set(set)
+                .dst_binding(binding)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+                .buffer_info(buffer_infos);
+            device.update_descriptor_sets(&[write]);
+        }
+        debug!("Created the uniform buffers of {} frames ({size} bytes each).", buffers.len());
+        Ok(Self { buffers })
+    }
+
+    /// Writes the uniforms of the frame in flight `frame`.
+    pub fn write(&self, device: &LogicalDevice, frame: usize, uniforms: &FrameUniforms) -> anyhow::Result<()> {
+        self.buffers[frame]
+            .write(device, std::slice::from_ref(uniforms))
+            .with_context(|| format!("Failed to write the uniforms of frame {frame}"))
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.buffers.iter().for_each(|buffer| buffer.destroy(device));
+    }
+}
@@ -0,0 +1,159 @@
+use crate::gapi::color::Color;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::memory::buffer::Buffer;
+use anyhow::{bail, Context};
+use log::debug;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// Index of a material in the [`MaterialPalette`], stored per voxel instance.
+pub type MaterialId = u8;
+
+/// Maximum number of materials, so any [`MaterialId`] is a valid index.
+pub const MAX_MATERIALS: usize = MaterialId::MAX as usize + 1;
+
+/// How a voxel surface looks, as read by the fragment shader.
+///
+/// The layout matches the std430 layout of the shader struct:
+/// ```glsl
+/// struct Material {
+///     vec4 color;
+///     uint texture_index;
+///     float roughness;
+/// };
+/// ```
+/// Its size is rounded up to the alignment of `vec4`, hence the padding.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    /// Linear RGBA color, multiplied with the texture.
+    pub color: Color,
+    /// Layer of the texture array, [`Material::NO_TEXTURE`] for a plain color.
+    pub texture_index: u32,
+    /// From 0 (mirror) to 1 (completely diffuse).
+    pub roughness: f32,
+    _padding: [u32; 2],
+}
+
+impl Material {
+    pub const NO_TEXTURE: u32 = u32::MAX;
+
+    pub fn new(color: Color, texture_index: u32, roughness: f32) -> Self {
+        Self {
+            color,
+            texture_index,
+            roughness,
+            _padding: [0; 2],
+        }
+    }
+
+    /// An untextured material.
+    pub fn colored(color: Color, roughness: f32) -> Self {
+        Self::new(color, Self::NO_TEXTURE, roughness)
+    }
+}
+
+/// The materials voxels can reference by [`MaterialId`].
+///
+/// It is uploaded as a storage buffer, which the fragment shader indexes with the material ID of
+/// each voxel instance.
+#[derive(Clone, Debug, Default)]
+pub struct MaterialPalette {
+    materials: Vec<Material>,
+}
+
+/// The palette uploaded to the GPU.
+pub struct MaterialPaletteBuffer {
+    buffer: Buffer,
+}
+
+impl MaterialPalette {
+    /// Adds a material to the palette.
+    ///
+    /// # Errors
+    /// If the palette already holds [`MAX_MATERIALS`] materials.
+    pub fn add(&mut self, material: Material) -> anyhow::Result<MaterialId> {
+        if self.materials.len() >= MAX_MATERIALS {
+            bail!("The material palette is full ({MAX_MATERIALS} materials).");
+        }
+        self.materials.push(material);
+        Ok((self.materials.len() - 1) as MaterialId)
+    }
+
+    pub fn get(&self, id: MaterialId) -> Option<&Material> {
+        self.materials.get(id as usize)
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    /// The layout binding of the palette for the fragment shader, at `binding`.
+    pub fn descriptor_binding(binding: u32) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding::builder()
+            .binding(binding)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .build()
+    }
+
+    /// Uploads the palette to a storage buffer.
+    ///
+    /// # Errors
+    /// If the palette is empty, as Vulkan doesn't allow empty buffers.
+    pub fn upload(
+        &self,
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+    ) -> anyhow::Result<MaterialPaletteBuffer> {
+        if self.materials.is_empty() {
+            bail!("Failed to upload an empty material palette.");
+        }
+        let size = size_of_val(self.materials.as_slice()) as vk::DeviceSize;
+        // The palette rarely changes, so host visible memory is good enough for now.
+        let buffer = Buffer::new(
+            real_device,
+            device,
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .with_context(|| "Failed to create material palette buffer")?;
+        buffer.write(device, &self.materials)?;
+        debug!("Uploaded {} materials ({size} bytes).", self.materials.len());
+        Ok(MaterialPaletteBuffer { buffer })
+    }
+}
+
+impl MaterialPaletteBuffer {
+    /// Points `binding` of `descriptor_set` to the palette.
+    pub fn write_descriptor(
+        &self,
+        device: &LogicalDevice,
+        descriptor_set: vk::DescriptorSet,
+        binding: u32,
+    ) {
+        let buffer_info = vk::DescriptorBufferInfo::builder()
+            .buffer(self.buffer.get_vk())
+            .offset(0)
+            .range(self.buffer.get_size());
+        let buffer_infos = &[buffer_info];
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(binding)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(buffer_infos);
+        device.update_descriptor_sets(&[write]);
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.buffer.destroy(device);
+    }
+}
@@ -1,2 +1,14 @@
 pub mod app;
+pub mod chunk_grid;
+pub mod chunk_world;
+pub mod color;
+pub mod debug_draw;
+pub mod errors;
+pub mod frame_uniforms;
+pub mod material;
+pub mod projection;
+pub mod scene;
+pub mod viewport_layout;
+pub mod voxel_model;
+pub mod voxel_render_mode;
 mod vulkan;
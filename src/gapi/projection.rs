@@ -0,0 +1,119 @@
+use crate::gapi::vulkan::pipeline::stages::per_fragment_tests_stage::DepthConvention;
+use crate::gapi::vulkan::pipeline::viewport::ViewportYFlip;
+
+/// # Projections
+/// Column-major projection matrices following Vulkan's conventions, to pass (multiplied by the
+/// view) to [`App::set_view_projection`](crate::gapi::app::App::set_view_projection).
+///
+/// The view space is right-handed, with the camera looking down -Z and Y up, like in OpenGL.
+/// The matrices map it to Vulkan's clip space:
+/// - Y points down, flipped like [`ViewportYFlip::ProjectionMatrix`] does, so they are meant for
+///   the default viewports, not for [`ViewportYFlip::NegativeHeightViewport`], which would flip
+///   Y twice.
+/// - The depth goes from 0 at `near` to 1 at `far`, except for [`perspective_reverse_z_vk`].
+///
+/// `near` and `far` are distances in front of the camera, both positive.
+///
+/// The vertical field of view `fov_y` is in radians, and the `aspect` ratio is the width over
+/// the height of the window.
+pub fn perspective_vk(fov_y: f32, aspect: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    let focal_length = 1.0 / (fov_y / 2.0).tan();
+    let projection = [
+        [focal_length / aspect, 0.0, 0.0, 0.0],
+        [0.0, focal_length, 0.0, 0.0],
+        [0.0, 0.0, far / (near - far), -1.0],
+        [0.0, 0.0, near * far / (near - far), 0.0],
+    ];
+    ViewportYFlip::ProjectionMatrix.apply_to_projection(projection)
+}
+
+/// Same as [`perspective_vk`], with the depth going from 1 at `near` to 0 at `far`, for
+/// [`DepthConvention::ReverseZ`].
+pub fn perspective_reverse_z_vk(fov_y: f32, aspect: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    DepthConvention::ReverseZ.apply_to_projection(perspective_vk(fov_y, aspect, near, far))
+}
+
+/// Maps the box from `left` to `right`, `bottom` to `top` and `near` to `far` in view space to
+/// the clip space, without perspective, e.g. for shadow maps or 2D overlays.
+pub fn orthographic_vk(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> [[f32; 4]; 4] {
+    let projection = [
+        [2.0 / (right - left), 0.0, 0.0, 0.0],
+        [0.0, 2.0 / (top - bottom), 0.0, 0.0],
+        [0.0, 0.0, 1.0 / (near - far), 0.0],
+        [
+            -(right + left) / (right - left),
+            -(top + bottom) / (top - bottom),
+            near / (near - far),
+            1.0,
+        ],
+    ];
+    ViewportYFlip::ProjectionMatrix.apply_to_projection(projection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEAR: f32 = 0.1;
+    const FAR: f32 = 100.0;
+
+    /// The normalized device coordinates of the view space `point`.
+    fn project(projection: [[f32; 4]; 4], point: [f32; 3]) -> [f32; 3] {
+        let [x, y, z] = point;
+        let clip: [f32; 4] = [0, 1, 2, 3].map(|row| {
+            projection[0][row] * x + projection[1][row] * y + projection[2][row] * z + projection[3][row]
+        });
+        [clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3]]
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "{actual} isn't {expected}");
+    }
+
+    #[test]
+    fn perspective_maps_near_to_0_and_far_to_1() {
+        let projection = perspective_vk(1.0, 1.5, NEAR, FAR);
+        assert_close(project(projection, [0.0, 0.0, -NEAR])[2], 0.0);
+        assert_close(project(projection, [0.0, 0.0, -FAR])[2], 1.0);
+    }
+
+    #[test]
+    fn reverse_z_maps_near_to_1_and_far_to_0() {
+        let projection = perspective_reverse_z_vk(1.0, 1.5, NEAR, FAR);
+        assert_close(project(projection, [0.0, 0.0, -NEAR])[2], 1.0);
+        assert_close(project(projection, [0.0, 0.0, -FAR])[2], 0.0);
+        // Closer is greater, for the compare op of the convention.
+        let middle = project(projection, [0.0, 0.0, -1.0])[2];
+        let farther = project(projection, [0.0, 0.0, -10.0])[2];
+        assert!(0.0 < farther && farther < middle && middle < 1.0, "{farther} and {middle}");
+    }
+
+    #[test]
+    fn perspective_flips_y_to_vulkan_clip_space() {
+        let fov_y = std::f32::consts::FRAC_PI_2;
+        let projection = perspective_vk(fov_y, 2.0, NEAR, FAR);
+        // At the top edge of the field of view, 45 degrees up, Y is -1 in Vulkan's Y down.
+        let top = project(projection, [0.0, 1.0, -1.0]);
+        assert_close(top[1], -1.0);
+        // X isn't flipped, and is scaled by the aspect ratio.
+        let right = project(projection, [2.0, 0.0, -1.0]);
+        assert_close(right[0], 1.0);
+        // Flipping it again gives back the Y up meant for the negative height viewports.
+        let unflipped = ViewportYFlip::ProjectionMatrix.apply_to_projection(projection);
+        assert_close(project(unflipped, [0.0, 1.0, -1.0])[1], 1.0);
+        assert_eq!(ViewportYFlip::NegativeHeightViewport.apply_to_projection(projection), projection);
+    }
+
+    #[test]
+    fn orthographic_maps_the_box_to_the_clip_volume() {
+        let projection = orthographic_vk(-4.0, 4.0, -2.0, 2.0, NEAR, FAR);
+        let [x, y, z] = project(projection, [-4.0, 2.0, -NEAR]);
+        assert_close(x, -1.0);
+        assert_close(y, -1.0);
+        assert_close(z, 0.0);
+        let [x, y, z] = project(projection, [4.0, -2.0, -FAR]);
+        assert_close(x, 1.0);
+        assert_close(y, 1.0);
+        assert_close(z, 1.0);
+    }
+}
@@ -0,0 +1,19 @@
+/// Tracks whether what we render changed, to know when the recorded command buffers are stale.
+///
+/// Every change (camera moved, chunk changed...) bumps the version, and a command buffer recorded
+/// at an older version has to be recorded again.
+#[derive(Debug, Default)]
+pub struct SceneState {
+    version: u64,
+}
+
+impl SceneState {
+    /// Marks the scene as changed, invalidating every recorded command buffer.
+    pub fn mark_dirty(&mut self) {
+        self.version = self.version.wrapping_add(1);
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
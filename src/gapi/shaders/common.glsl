@@ -0,0 +1,11 @@
+// Declarations shared by the shaders, included with #include "common.glsl".
+
+// Specialized when creating the pipeline, see PipelineConfig::point_size.
+layout(constant_id = 0) const float POINT_SIZE = 3.0;
+
+// Rewritten every frame, the std140 layout must match FrameUniforms in frame_uniforms.rs.
+layout(set = 0, binding = 0) uniform FrameUniforms {
+    mat4 view_projection;
+    float time_seconds;
+    uint frame_index;
+} frame;
@@ -0,0 +1,22 @@
+#version 450
+
+#include "common.glsl"
+
+// A corner of the unit cube, per vertex.
+layout(location = 0) in vec3 inPosition;
+// The offset of the voxel in xyz and its scale in w, per instance, see CubeInstance.
+layout(location = 1) in vec4 inInstance;
+// The linear color of the voxel, per instance.
+layout(location = 2) in vec4 inColor;
+
+layout(location = 0) out vec3 fragColor;
+
+void main() {
+    // There is no camera yet, so the cubes are placed directly in clip space.
+    vec3 position = inPosition * inInstance.w + inInstance.xyz;
+    gl_Position = vec4(position, 1.0);
+    gl_PointSize = POINT_SIZE;
+    // Shades the color along the y axis of the cube, so that the faces of a single colored cube
+    // can be told apart without lighting.
+    fragColor = inColor.rgb * (0.75 + 0.25 * (inPosition.y + 0.5));
+}
@@ -0,0 +1,16 @@
+#version 450
+
+#include "common.glsl"
+
+// An end of a line or a point, see DebugVertex.
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inColor;
+
+layout(location = 0) out vec3 fragColor;
+
+void main() {
+    // There is no camera yet, so the primitives are placed directly in clip space.
+    gl_Position = vec4(inPosition, 1.0);
+    gl_PointSize = POINT_SIZE;
+    fragColor = inColor;
+}
@@ -1,9 +1,20 @@
 #version 450
 
+// Specialized when creating the pipeline, see PipelineConfig::output_color_encoding.
+// Set when writing to a UNORM swapchain image, which doesn't encode the colors to sRGB itself.
+layout(constant_id = 0) const bool MANUAL_GAMMA = false;
+
 layout(location = 0) in vec3 fragColor;
 
 layout(location = 0) out vec4 outColor;
 
+vec3 linearToSrgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
 void main() {
-    outColor = vec4(fragColor, 1.0);
-}
\ No newline at end of file
+    vec3 color = MANUAL_GAMMA ? linearToSrgb(fragColor) : fragColor;
+    outColor = vec4(color, 1.0);
+}
@@ -0,0 +1,95 @@
+use crate::gapi::vulkan::pipeline::viewport::ViewportYFlip;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// A sub-rectangle of the render target, in normalized coordinates where `(0, 0)` is the
+/// top-left corner and `(1, 1)` the bottom-right one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewportRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ViewportRegion {
+    pub const FULL: Self = Self::new(0.0, 0.0, 1.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// The viewport and scissor covering this region of a target of size `extent`, the viewport
+    /// being flipped according to `y_flip`.
+    ///
+    /// The scissor is rounded to whole pixels, so that the regions of a layout that tile the
+    /// target don't overlap or leave gaps between them.
+    pub fn to_vk(&self, extent: vk::Extent2D, y_flip: ViewportYFlip) -> (vk::Viewport, vk::Rect2D) {
+        let (width, height) = (extent.width as f32, extent.height as f32);
+        let viewport = vk::Viewport::builder()
+            .x(self.x * width)
+            .y(self.y * height)
+            .width(self.width * width)
+            .height(self.height * height)
+            .min_depth(0.0)
+            .max_depth(1.0)
+            .build();
+        let viewport = y_flip.apply_to_viewport(viewport);
+        let x0 = (self.x * width).round() as i32;
+        let y0 = (self.y * height).round() as i32;
+        let x1 = ((self.x + self.width) * width).round() as i32;
+        let y1 = ((self.y + self.height) * height).round() as i32;
+        let scissor = vk::Rect2D::builder()
+            .offset(vk::Offset2D { x: x0, y: y0 })
+            .extent(vk::Extent2D {
+                width: (x1 - x0).max(0) as u32,
+                height: (y1 - y0).max(0) as u32,
+            })
+            .build();
+        (viewport, scissor)
+    }
+}
+
+/// How the frame is split into viewports, the scene is drawn once into each of them.
+///
+/// For example, a left/right split renders the scene twice within the same render pass, which is
+/// what local multiplayer split-screen needs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewportLayout {
+    pub regions: Vec<ViewportRegion>,
+}
+
+impl ViewportLayout {
+    /// A single viewport covering the whole frame.
+    pub fn single() -> Self {
+        Self {
+            regions: vec![ViewportRegion::FULL],
+        }
+    }
+
+    /// Two viewports side by side, left then right.
+    pub fn split_left_right() -> Self {
+        Self {
+            regions: vec![
+                ViewportRegion::new(0.0, 0.0, 0.5, 1.0),
+                ViewportRegion::new(0.5, 0.0, 0.5, 1.0),
+            ],
+        }
+    }
+
+    /// Two viewports stacked, top then bottom.
+    pub fn split_top_bottom() -> Self {
+        Self {
+            regions: vec![
+                ViewportRegion::new(0.0, 0.0, 1.0, 0.5),
+                ViewportRegion::new(0.0, 0.5, 1.0, 0.5),
+            ],
+        }
+    }
+}
+
+impl Default for ViewportLayout {
+    fn default() -> Self {
+        Self::single()
+    }
+}
@@ -0,0 +1,297 @@
+use crate::gapi::color::Color;
+use crate::gapi::material::{Material, MaterialId, MaterialPalette};
+use crate::gapi::voxel_render_mode::CubeInstance;
+use anyhow::{bail, Context};
+use log::{debug, info, trace, warn};
+use std::fs;
+use std::path::Path;
+
+/// A voxel of a [`VoxelModel`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Voxel {
+    /// Position in the grid of the model, in voxels, with Y up.
+    pub position: [u8; 3],
+    /// Index in [`VoxelModel::colors`], and of its material in [`VoxelModel::materials`].
+    pub material: MaterialId,
+}
+
+/// A voxel model loaded from a MagicaVoxel `.vox` file, see [`VoxelModel::load`].
+///
+/// Displaying it with instanced cubes:
+/// ```ignore
+/// let model = VoxelModel::load("assets/castle.vox")?;
+/// app.set_cube_instances(&model.cube_instances(0.1))?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct VoxelModel {
+    /// The number of voxels along each axis, with Y up.
+    pub size: [u32; 3],
+    pub voxels: Vec<Voxel>,
+    /// The linear colors of the palette of the model, indexed by [`Voxel::material`].
+    pub colors: Vec<Color>,
+}
+
+impl VoxelModel {
+    /// Colors in a `.vox` palette, the index 0 of the file is the empty voxel.
+    const PALETTE_SIZE: usize = 255;
+    /// The positions are stored as bytes, so a model is at most 256 voxels along each axis.
+    const MAX_SIZE: u32 = 256;
+
+    /// Loads the `.vox` file at `path`.
+    ///
+    /// MagicaVoxel models are Z up, so the voxels are rotated to the Y up of the renderer,
+    /// keeping the handedness: `(x, y, z)` in the file is `(x, z, -y)` in the model, moved back
+    /// to positive positions.
+    ///
+    /// Only the first model of the file is loaded, the scene graph and the materials of newer
+    /// versions are ignored.
+    ///
+    /// # Errors
+    /// If the file can't be read, isn't a `.vox` file, is truncated, or has voxels outside of
+    /// the size of the model.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("Failed to read the voxel model {}", path.display()))?;
+        let model = Self::parse(&bytes).with_context(|| format!("Failed to load the voxel model {}", path.display()))?;
+        info!(
+            "Loaded the voxel model {} of size {:?} with {} voxels.",
+            path.display(),
+            model.size,
+            model.voxels.len()
+        );
+        Ok(model)
+    }
+
+    /// Parses the content of a `.vox` file, see [`VoxelModel::load`].
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(4)? != b"VOX " {
+            bail!("Not a .vox file, the magic number is missing.");
+        }
+        let version = reader.u32()?;
+        debug!("Parsing a .vox file of version {version}.");
+        let (id, _, children) = reader.chunk()?;
+        if id != b"MAIN" {
+            bail!("Expected the MAIN chunk, found {}.", String::from_utf8_lossy(id));
+        }
+
+        let mut size = None;
+        let mut xyzi = None;
+        let mut palette = None;
+        let mut models = 0;
+        let mut children = Reader::new(children);
+        while !children.is_empty() {
+            let (id, content, _) = children.chunk()?;
+            let mut content = Reader::new(content);
+            match id {
+                b"SIZE" => {
+                    models += 1;
+                    if size.is_none() {
+                        size = Some([content.u32()?, content.u32()?, content.u32()?]);
+                    }
+                }
+                b"XYZI" if xyzi.is_none() => xyzi = Some(content),
+                b"RGBA" => palette = Some(Self::parse_palette(&mut content)?),
+                _ => trace!("Skipping the .vox chunk {}.", String::from_utf8_lossy(id)),
+            }
+        }
+        if models > 1 {
+            warn!("The .vox file has {models} models, only the first one is loaded.");
+        }
+        let (Some(file_size), Some(mut xyzi)) = (size, xyzi) else {
+            bail!("The .vox file has no model.");
+        };
+        let colors = palette.unwrap_or_else(|| {
+            warn!("The .vox file has no palette, MagicaVoxel's default one isn't bundled, using white.");
+            vec![Color::WHITE; Self::PALETTE_SIZE]
+        });
+
+        if file_size.iter().any(|&c| c > Self::MAX_SIZE) {
+            bail!("The model size {file_size:?} is above the {} voxels of the format.", Self::MAX_SIZE);
+        }
+        let [size_x, size_y, size_z] = file_size;
+        let count = xyzi.u32()?;
+        // Each voxel takes 4 bytes, checked before trusting the count with an allocation.
+        if count as usize > xyzi.remaining() / 4 {
+            bail!("The model has {count} voxels, but its XYZI chunk only holds {} bytes of them.", xyzi.remaining());
+        }
+        let mut voxels = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let [x, y, z, color_index] = [xyzi.u8()?, xyzi.u8()?, xyzi.u8()?, xyzi.u8()?];
+            if x as u32 >= size_x || y as u32 >= size_y || z as u32 >= size_z || color_index == 0 {
+                bail!("Invalid voxel ({x}, {y}, {z}) of color {color_index} in a model of size {file_size:?}.");
+            }
+            voxels.push(Voxel {
+                position: [x, z, (size_y - 1 - y as u32) as u8],
+                material: color_index - 1,
+            });
+        }
+        Ok(Self {
+            size: [size_x, size_z, size_y],
+            voxels,
+            colors,
+        })
+    }
+
+    /// The colors of the `RGBA` chunk, whose entry `i` is the color index `i + 1` of the voxels.
+    fn parse_palette(content: &mut Reader) -> anyhow::Result<Vec<Color>> {
+        (0..Self::PALETTE_SIZE)
+            .map(|_| {
+                let [r, g, b, a] = [content.u8()?, content.u8()?, content.u8()?, content.u8()?];
+                let srgb = Color::rgba(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0);
+                Ok(srgb.srgb_to_linear())
+            })
+            .collect()
+    }
+
+    /// The untextured materials of the palette, with the given `roughness`, to pass to
+    /// [`App::set_materials`](crate::gapi::app::App::set_materials).
+    pub fn materials(&self, roughness: f32) -> anyhow::Result<MaterialPalette> {
+        let mut palette = MaterialPalette::default();
+        for &color in &self.colors {
+            palette.add(Material::colored(color, roughness))?;
+        }
+        Ok(palette)
+    }
+
+    /// A cube of edge `voxel_size` per voxel, colored from the palette, with the model centered
+    /// on the origin. To pass to
+    /// [`App::set_cube_instances`](crate::gapi::app::App::set_cube_instances), or to use as a
+    /// chunk of a [`ChunkGrid`](crate::gapi::chunk_grid::ChunkGrid).
+    pub fn cube_instances(&self, voxel_size: f32) -> Vec<CubeInstance> {
+        let center = self.size.map(|c| c as f32 / 2.0);
+        self.voxels
+            .iter()
+            .map(|voxel| {
+                let offset = [0, 1, 2].map(|i| (voxel.position[i] as f32 + 0.5 - center[i]) * voxel_size);
+                let color = self.colors.get(voxel.material as usize).copied().unwrap_or(Color::WHITE);
+                CubeInstance::new(offset, voxel_size, color)
+            })
+            .collect()
+    }
+}
+
+/// Reads the little-endian values and the chunks of a `.vox` file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.offset >= self.bytes.len()
+    }
+
+    /// The number of bytes left to read.
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.offset)
+    }
+
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .with_context(|| format!("The .vox file is truncated at byte {}.", self.offset))?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// The id, the content and the children of the next chunk.
+    fn chunk(&mut self) -> anyhow::Result<(&'a [u8], &'a [u8], &'a [u8])> {
+        let id = self.take(4)?;
+        let content_len = self.u32()? as usize;
+        let children_len = self.u32()? as usize;
+        Ok((id, self.take(content_len)?, self.take(children_len)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(content);
+        bytes.extend_from_slice(children);
+        bytes
+    }
+
+    /// A `.vox` file of a single model of `size`, with an XYZI chunk of `count` voxels followed
+    /// by `voxels`, as `[x, y, z, color index]`.
+    fn vox_file(size: [u32; 3], count: u32, voxels: &[[u8; 4]]) -> Vec<u8> {
+        let size_content: Vec<u8> = size.iter().flat_map(|c| c.to_le_bytes()).collect();
+        let mut xyzi_content = count.to_le_bytes().to_vec();
+        xyzi_content.extend(voxels.iter().flatten());
+        let mut children = chunk(b"SIZE", &size_content, &[]);
+        children.extend(chunk(b"XYZI", &xyzi_content, &[]));
+        let mut bytes = b"VOX ".to_vec();
+        bytes.extend_from_slice(&150u32.to_le_bytes());
+        bytes.extend(chunk(b"MAIN", &[], &children));
+        bytes
+    }
+
+    #[test]
+    fn parses_a_minimal_model_to_y_up() {
+        let model = VoxelModel::parse(&vox_file([2, 3, 4], 2, &[[0, 0, 0, 1], [1, 2, 3, 5]])).unwrap();
+        assert_eq!(model.size, [2, 4, 3]);
+        assert_eq!(
+            model.voxels,
+            [
+                Voxel {
+                    position: [0, 0, 2],
+                    material: 0
+                },
+                Voxel {
+                    position: [1, 3, 0],
+                    material: 4
+                },
+            ]
+        );
+        // Without an RGBA chunk, the palette is white.
+        assert_eq!(model.colors.len(), VoxelModel::PALETTE_SIZE);
+    }
+
+    #[test]
+    fn rejects_a_bad_magic_number() {
+        let mut bytes = vox_file([1, 1, 1], 1, &[[0, 0, 0, 1]]);
+        bytes[..4].copy_from_slice(b"VOXX");
+        assert!(VoxelModel::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_a_truncated_chunk() {
+        let bytes = vox_file([1, 1, 1], 1, &[[0, 0, 0, 1]]);
+        assert!(VoxelModel::parse(&bytes[..bytes.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn rejects_a_count_beyond_the_chunk_without_allocating_it() {
+        let bytes = vox_file([1, 1, 1], u32::MAX, &[[0, 0, 0, 1]]);
+        let error = VoxelModel::parse(&bytes).unwrap_err();
+        assert!(error.to_string().contains("XYZI"), "{error}");
+    }
+
+    #[test]
+    fn rejects_a_voxel_outside_of_the_model() {
+        assert!(VoxelModel::parse(&vox_file([2, 2, 2], 1, &[[2, 0, 0, 1]])).is_err());
+        // The color index 0 is the empty voxel.
+        assert!(VoxelModel::parse(&vox_file([2, 2, 2], 1, &[[0, 0, 0, 0]])).is_err());
+    }
+}
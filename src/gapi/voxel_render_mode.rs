@@ -0,0 +1,277 @@
+use crate::gapi::color::Color;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::commands::command_buffers::CommandBuffer;
+use crate::gapi::vulkan::guard::Guard;
+use crate::gapi::vulkan::memory::buffer::Buffer;
+use crate::gapi::vulkan::memory::vertex_buffer::VertexBuffer;
+use crate::gapi::vulkan::pipeline::pipeline::Pipeline;
+use anyhow::{bail, Context};
+use log::debug;
+use std::mem::offset_of;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// How the voxels are turned into primitives, which selects the vertex input, the topology and
+/// the draw call of the pipeline.
+///
+/// # Tradeoffs
+/// - `PointSprites`: one vertex per voxel and no mesh at all, the cheapest in memory and vertex
+///   work. The points are screen-aligned squares of a fixed size, so voxels don't look like
+///   cubes up close, and don't scale with the distance.
+/// - `InstancedCubes`: a single unit cube mesh, drawn once per voxel with a per-instance
+///   transform and color. Costs 20 bytes per voxel, but every face of every voxel is drawn, including
+///   the ones hidden between neighbors, so the vertex and fragment work grows with the volume.
+/// - `GreedyMesh`: merges the visible faces of neighboring voxels into large quads, so only the
+///   surface is drawn, with few triangles. The cheapest to draw, but every change of a chunk
+///   means meshing it again on the CPU. Not implemented yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum VoxelRenderMode {
+    #[default]
+    PointSprites,
+    InstancedCubes,
+    GreedyMesh,
+}
+
+impl VoxelRenderMode {
+    /// The topology the mode draws its primitives with.
+    pub fn topology(self) -> vk::PrimitiveTopology {
+        match self {
+            VoxelRenderMode::PointSprites => vk::PrimitiveTopology::POINT_LIST,
+            VoxelRenderMode::InstancedCubes | VoxelRenderMode::GreedyMesh => {
+                vk::PrimitiveTopology::TRIANGLE_LIST
+            }
+        }
+    }
+
+    /// The vertex buffer bindings and attributes the vertex shader of the mode reads.
+    pub fn vertex_input(
+        self,
+    ) -> (
+        Vec<vk::VertexInputBindingDescription>,
+        Vec<vk::VertexInputAttributeDescription>,
+    ) {
+        match self {
+            // The points are generated in the vertex shader for now.
+            VoxelRenderMode::PointSprites | VoxelRenderMode::GreedyMesh => (vec![], vec![]),
+            VoxelRenderMode::InstancedCubes => InstancedCubes::vertex_input(),
+        }
+    }
+}
+
+/// The transform and color of a voxel drawn with [`VoxelRenderMode::InstancedCubes`].
+///
+/// The layout matches the per-instance attributes of the cube vertex shader, a `vec4` with the
+/// offset in `xyz` and the scale in `w`, then the color.
+///
+/// Filling a chunk of 16³ voxels with a gradient along each axis:
+/// ```ignore
+/// let instances: Vec<CubeInstance> = (0..16 * 16 * 16)
+///     .map(|i| [i % 16, i / 16 % 16, i / 256].map(|c| c as f32 / 15.0))
+///     .map(|[x, y, z]| CubeInstance::new([x - 0.5, y - 0.5, z], 1.0 / 16.0, Color::rgb(x, y, z)))
+///     .collect();
+/// app.set_cube_instances(&instances)?;
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CubeInstance {
+    /// Position of the center of the cube.
+    pub offset: [f32; 3],
+    /// Length of the edges of the cube.
+    pub scale: f32,
+    /// Linear RGBA color of the whole cube, see [`Color::to_rgba8`]. The alpha is ignored.
+    pub color: [u8; 4],
+}
+
+impl CubeInstance {
+    pub fn new(offset: [f32; 3], scale: f32, color: Color) -> Self {
+        Self {
+            offset,
+            scale,
+            color: color.to_rgba8(),
+        }
+    }
+}
+
+/// A corner of the unit cube, centered on the origin.
+type CubeVertex = [f32; 3];
+
+/// The buffers of [`VoxelRenderMode::InstancedCubes`]: a unit cube mesh, and the transforms of
+/// the voxels it is instanced for.
+pub struct InstancedCubes {
+    vertex_buffer: VertexBuffer,
+    index_buffer: Buffer,
+    instance_buffer: VertexBuffer,
+    /// How many instances were last written.
+    instance_count: u32,
+}
+
+impl InstancedCubes {
+    const VERTICES: [CubeVertex; 8] = [
+        [-0.5, -0.5, -0.5],
+        [0.5, -0.5, -0.5],
+        [0.5, 0.5, -0.5],
+        [-0.5, 0.5, -0.5],
+        [-0.5, -0.5, 0.5],
+        [0.5, -0.5, 0.5],
+        [0.5, 0.5, 0.5],
+        [-0.5, 0.5, 0.5],
+    ];
+
+    /// Two triangles per face, clockwise when seen from outside the cube in Vulkan's y-down
+    /// framebuffer, the front face of the rasterization stage.
+    const INDICES: [u16; 36] = [
+        0, 2, 3, 2, 0, 1, // -z
+        4, 6, 5, 6, 4, 7, // +z
+        0, 7, 4, 7, 0, 3, // -x
+        1, 6, 2, 6, 1, 5, // +x
+        0, 5, 1, 5, 0, 4, // -y
+        3, 6, 7, 6, 3, 2, // +y
+    ];
+
+    const VERTEX_BINDING: u32 = 0;
+    const INSTANCE_BINDING: u32 = 1;
+
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        capacity: usize,
+    ) -> anyhow::Result<Self> {
+        if capacity == 0 {
+            bail!("Failed to create instanced cubes without capacity.");
+        }
+        // The mesh is tiny and written once, and the instances are rewritten by the CPU whenever
+        // the voxels change, so both stay in host visible memory.
+        let vertex_buffer = VertexBuffer::new::<CubeVertex>(
+            real_device,
+            device,
+            Self::VERTICES.len(),
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .with_context(|| "Failed to create the cube vertex buffer")?;
+        let vertex_buffer = Guard::new(vertex_buffer, |buffer| buffer.destroy(device));
+        let index_buffer = Buffer::new(
+            real_device,
+            device,
+            size_of_val(&Self::INDICES) as vk::DeviceSize,
+            vk::BufferUsageFlags::INDEX_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .with_context(|| "Failed to create the cube index buffer")?;
+        let index_buffer = Guard::new(index_buffer, |buffer| buffer.destroy(device));
+        let instance_buffer = VertexBuffer::new::<CubeInstance>(
+            real_device,
+            device,
+            capacity,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .with_context(|| "Failed to create the cube instance buffer")?;
+        let cubes = Self {
+            vertex_buffer: vertex_buffer.into_inner(),
+            index_buffer: index_buffer.into_inner(),
+            instance_buffer,
+            instance_count: 0,
+        };
+        let written = cubes
+            .vertex_buffer
+            .write(device, &Self::VERTICES)
+            .and_then(|()| cubes.index_buffer.write(device, &Self::INDICES));
+        if let Err(e) = written {
+            cubes.destroy(device);
+            return Err(e.context("Failed to write the cube mesh"));
+        }
+        debug!("Created instanced cubes for {capacity} instances.");
+        Ok(cubes)
+    }
+
+    /// The vertex input of the cube vertex shader: the corners of the cube per vertex, and the
+    /// [`CubeInstance`] per instance, its color normalized to floats.
+    fn vertex_input() -> (
+        Vec<vk::VertexInputBindingDescription>,
+        Vec<vk::VertexInputAttributeDescription>,
+    ) {
+        let bindings = vec![
+            vk::VertexInputBindingDescription::builder()
+                .binding(Self::VERTEX_BINDING)
+                .stride(size_of::<CubeVertex>() as u32)
+                .input_rate(vk::VertexInputRate::VERTEX)
+                .build(),
+            vk::VertexInputBindingDescription::builder()
+                .binding(Self::INSTANCE_BINDING)
+                .stride(size_of::<CubeInstance>() as u32)
+                .input_rate(vk::VertexInputRate::INSTANCE)
+                .build(),
+        ];
+        let attributes = vec![
+            vk::VertexInputAttributeDescription::builder()
+                .binding(Self::VERTEX_BINDING)
+                .location(0)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(0)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(Self::INSTANCE_BINDING)
+                .location(1)
+                .format(vk::Format::R32G32B32A32_SFLOAT)
+                .offset(offset_of!(CubeInstance, offset) as u32)
+                .build(),
+            vk::VertexInputAttributeDescription::builder()
+                .binding(Self::INSTANCE_BINDING)
+                .location(2)
+                .format(vk::Format::R8G8B8A8_UNORM)
+                .offset(offset_of!(CubeInstance, color) as u32)
+                .build(),
+        ];
+        (bindings, attributes)
+    }
+
+    /// Replaces the drawn instances.
+    /// The buffer must not be in use by a command buffer in flight.
+    ///
+    /// # Errors
+    /// If there are more instances than the capacity.
+    pub fn write(&mut self, device: &LogicalDevice, instances: &[CubeInstance]) -> anyhow::Result<()> {
+        if instances.len() > self.instance_buffer.capacity() {
+            bail!(
+                "Failed to write {} cube instances into a buffer of {}.",
+                instances.len(),
+                self.instance_buffer.capacity()
+            );
+        }
+        if !instances.is_empty() {
+            self.instance_buffer.write(device, instances)?;
+        }
+        self.instance_count = instances.len() as u32;
+        Ok(())
+    }
+
+    /// Records the draw of every written instance with the bound `pipeline`.
+    pub fn draw(&self, device: &LogicalDevice, command_buffer: &CommandBuffer, pipeline: &Pipeline) {
+        if self.instance_count == 0 {
+            return;
+        }
+        pipeline.bind_vertex_buffers(
+            device,
+            command_buffer,
+            Self::VERTEX_BINDING,
+            &[&self.vertex_buffer, &self.instance_buffer],
+            &[0, 0],
+        );
+        let command_buffer = *command_buffer.get_vk();
+        device.bind_index_buffer(command_buffer, self.index_buffer.get_vk(), 0, vk::IndexType::UINT16);
+        device.draw_indexed(
+            command_buffer,
+            Self::INDICES.len() as u32,
+            self.instance_count,
+            0,
+            0,
+            0,
+        );
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.instance_buffer.destroy(device);
+        self.index_buffer.destroy(device);
+        self.vertex_buffer.destroy(device);
+    }
+}
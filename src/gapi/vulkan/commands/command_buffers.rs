@@ -80,6 +80,19 @@ impl CommandBuffer {
     }
 }
 
+/// One primary command buffer per frame in flight, re-recorded when its frame is prepared.
+///
+/// # Frames, command buffers and swapchain images
+/// - The command buffer of the frame in flight `frame` is only recorded and submitted after
+///   waiting for that frame's in-flight fence, which is signaled once the GPU finished executing
+///   the previous submission of the same buffer. So a buffer is never recorded while it is still
+///   executing.
+/// - Which swapchain image a frame renders into is only known once it is acquired, and can be
+///   any of them. So the buffer is recorded for the framebuffer of the acquired image, and must
+///   be recorded again when the same frame gets another image.
+///
+/// The number of buffers is the number of frames in flight, independent of the swapchain image
+/// count, so the buffers outlive the swapchain recreations.
 pub struct CommandBuffers {
     command_buffers: Vec<CommandBuffer>,
 }
@@ -87,8 +100,8 @@ pub struct CommandBuffers {
 impl CommandBuffers {
     pub fn new(
         device: &LogicalDevice,
-        framebuffers: &[Framebuffer],
         command_pool: &CommandPool,
+        frames_in_flight: usize,
     ) -> anyhow::Result<Self> {
         // The level parameter specifies if the allocated command buffers are primary or secondary command buffers.
         //
@@ -100,7 +113,7 @@ impl CommandBuffers {
         let allocate_info = vk::CommandBufferAllocateInfo::builder()
             .command_pool(command_pool.get_vk())
             .level(level)
-            .command_buffer_count(framebuffers.len() as u32)
+            .command_buffer_count(frames_in_flight as u32)
             .build();
 
         debug!(
@@ -122,21 +135,18 @@ impl CommandBuffers {
         &self.command_buffers
     }
 
-    /// Records commands for all buffers.
-    /// The `recording_logic` closure is called for each image index.
-    pub fn record_all<F>(
-        &self,
-        device: &LogicalDevice,
-        framebuffers: &[Framebuffer],
-        recording_logic: F,
-    ) -> anyhow::Result<()>
-    where   F: Fn(&CommandBuffer, &Framebuffer) -> anyhow::Result<()>,
-    {
-        for (i, command_buffer) in self.command_buffers.iter().enumerate() {
-            let framebuffer = &framebuffers[i];
-            command_buffer.record(device, framebuffer, |cb, fb| recording_logic(cb, fb))?;
-        }
-        Ok(())
+    /// The command buffer of the frame in flight `frame`.
+    pub fn get(&self, frame: usize) -> &CommandBuffer {
+        &self.command_buffers[frame]
     }
 
+    /// Returns the command buffers to the pool they were allocated from.
+    pub fn free(&self, device: &LogicalDevice, command_pool: &CommandPool) {
+        let command_buffers = self
+            .command_buffers
+            .iter()
+            .map(|command_buffer| *command_buffer.get_vk())
+            .collect::<Vec<_>>();
+        device.free_command_buffers(command_pool.get_vk(), &command_buffers);
+    }
 }
@@ -12,11 +12,24 @@ pub struct CommandPool {
 
 impl CommandPool {
     pub fn new(device: &LogicalDevice) -> anyhow::Result<Self> {
-        let queues = device.get_queues();
+        // RESET_COMMAND_BUFFER allows re-recording each command buffer on its own, which we do
+        // every frame. Beginning a command buffer then implicitly resets it.
+        Self::for_family(
+            device,
+            device.graphics_family_index(),
+            vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+        )
+    }
 
+    /// Creates a pool for the command buffers submitted to the queues of `family_index`.
+    pub fn for_family(
+        device: &LogicalDevice,
+        family_index: u32,
+        flags: vk::CommandPoolCreateFlags,
+    ) -> anyhow::Result<Self> {
         let info = vk::CommandPoolCreateInfo::builder()
-            .flags(vk::CommandPoolCreateFlags::empty()) // Optional.
-            .queue_family_index(queues.graphics_family_index).build();
+            .flags(flags)
+            .queue_family_index(family_index).build();
         debug!("Created CommandPoolCreateInfo struct: {:#?}", info);
         let command_pool = device.create_command_pool(&info)
             .with_context(|| "Failed to create command pool")?;
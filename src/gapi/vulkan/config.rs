@@ -1,3 +1,23 @@
+use std::time::Duration;
+
 pub(crate) const VALIDATION_ENABLED: bool = cfg!(feature = "validation");
 pub(crate) const API_DUMP_ENABLED: bool = cfg!(feature = "api_dump");
 pub(crate) const LOADER_DEBUG_ENABLED: bool = cfg!(feature = "loader_debug");
+pub(crate) const VK_TRACE_ENABLED: bool = cfg!(feature = "vk-trace");
+
+/// Default of how many frames the CPU can prepare while the GPU is still rendering the previous
+/// ones. Resources updated every frame are duplicated this many times.
+pub(crate) const FRAMES_IN_FLIGHT: usize = 2;
+
+/// How many textures the bindless material set can hold, see `AppConfig::bindless_textures`.
+/// Devices with descriptor indexing support at least 500000 update-after-bind samplers per
+/// stage, so this is only bounded by the memory of the pool.
+pub(crate) const BINDLESS_TEXTURE_CAPACITY: u32 = 1024;
+
+/// How many times the swapchain can be recreated for a single frame while it stays out of date,
+/// e.g. during a resize, before the frame is skipped.
+pub(crate) const MAX_SWAPCHAIN_RECREATIONS_PER_FRAME: u32 = 3;
+
+/// The longest time step passed to the update callback, so that a stall (a breakpoint, a window
+/// drag, a slow frame) doesn't turn into one huge simulation step.
+pub(crate) const MAX_FRAME_DELTA: Duration = Duration::from_millis(100);
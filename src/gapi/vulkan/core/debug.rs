@@ -1,11 +1,29 @@
 use log::{debug, error, trace, warn};
-use std::ffi::CStr;
+use std::ffi::{c_char, CStr};
 use std::os::raw::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use vulkanalia::vk;
 use vulkanalia::vk::{
-    DebugUtilsMessengerCreateInfoEXT, DebugUtilsMessengerEXT, ExtDebugUtilsExtension, HasBuilder,
+    DebugUtilsMessengerCreateInfoEXT, DebugUtilsMessengerEXT, ExtDebugReportExtension,
+    ExtDebugUtilsExtension, HasBuilder,
 };
 use crate::gapi::vulkan::core::instance::Instance;
+use crate::gapi::vulkan::enums::extensions::InstanceExtension;
+
+/// The object delivering the validation messages to the callback, depending on the debug
+/// extension the instance was created with.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Messenger {
+    /// Through `VK_EXT_debug_utils`, the preferred one.
+    Utils(DebugUtilsMessengerEXT),
+    /// Through the legacy `VK_EXT_debug_report`, only used for compatibility with older Vulkan
+    /// installations that lack `VK_EXT_debug_utils`. Its messages have no type, and less
+    /// context about the objects involved.
+    Report(vk::DebugReportCallbackEXT),
+}
+
+/// How many errors the validation layer reported since the program started, of every instance.
+static VALIDATION_ERRORS: AtomicUsize = AtomicUsize::new(0);
 
 #[derive(Clone, Debug)]
 pub(crate) struct Debugger {
@@ -19,33 +37,66 @@ pub(crate) struct Debugger {
     /// /// Destroying the instance
     /// instance.destroy_debug_utils_messenger_ext(messenger, None);
     /// ```
-    messenger: DebugUtilsMessengerEXT,
+    messenger: Messenger,
 }
 impl Debugger {
-    pub fn new(instance: &Instance) -> anyhow::Result<Self> {
-        let debug_info = Self::get_debug_info();
-        let messenger = Self::create_messenger(&debug_info, instance)?;
+    /// Message types reported when the caller doesn't filter them: every type.
+    pub const DEFAULT_MESSAGE_TYPES: vk::DebugUtilsMessageTypeFlagsEXT =
+        vk::DebugUtilsMessageTypeFlagsEXT::from_bits_truncate(
+            vk::DebugUtilsMessageTypeFlagsEXT::GENERAL.bits()
+                | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION.bits()
+                | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE.bits(),
+        );
+
+    /// Creates the messenger, only reporting the messages of the given `message_type`.
+    /// See [`Debugger::debug_callback`] for the meaning of each type.
+    ///
+    /// If the instance was created with `VK_EXT_debug_report` instead of `VK_EXT_debug_utils`
+    /// (see `Instance::pick_debug_extension`), a legacy debug report callback is created, which
+    /// reports every message whatever its type.
+    pub fn new(
+        instance: &Instance,
+        message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    ) -> anyhow::Result<Self> {
+        let extensions = instance.get_enabled_extensions();
+        let use_report = !extensions.contains(&InstanceExtension::ExtDebugUtils)
+            && extensions.contains(&InstanceExtension::ExtDebugReport);
+        let messenger = if use_report {
+            Messenger::Report(Self::create_report_callback(instance)?)
+        } else {
+            let debug_info = Self::get_debug_info(message_type);
+            Messenger::Utils(Self::create_messenger(&debug_info, instance)?)
+        };
         Ok(Self { messenger })
     }
 
-    pub fn get_debug_info() -> DebugUtilsMessengerCreateInfoEXT {
+    pub fn get_debug_info(
+        message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    ) -> DebugUtilsMessengerCreateInfoEXT {
+        // Messages of types not included here never reach the callback, e.g. leaving out
+        // PERFORMANCE silences the performance warnings during early bring-up.
         let debug_info = DebugUtilsMessengerCreateInfoEXT::builder()
             .message_severity(vk::DebugUtilsMessageSeverityFlagsEXT::all())
-            .message_type(
-                vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
-                    | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
-                    | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
-            )
+            .message_type(message_type)
             .user_callback(Some(Self::debug_callback))
             .build();
         debug_info
     }
-    pub fn get_messenger(&self) -> &DebugUtilsMessengerEXT {
+    pub fn get_messenger(&self) -> &Messenger {
         &self.messenger
     }
 
-    pub fn add_instance_lifetime_messenger(info: &mut vk::InstanceCreateInfoBuilder) {
-        info.push_next(&mut Self::get_debug_info());
+    /// How many errors the validation layer reported so far, through any messenger, e.g. to
+    /// check that a sequence of calls is valid.
+    pub fn validation_error_count() -> usize {
+        VALIDATION_ERRORS.load(Ordering::Relaxed)
+    }
+
+    pub fn add_instance_lifetime_messenger(
+        info: &mut vk::InstanceCreateInfoBuilder,
+        message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    ) {
+        info.push_next(&mut Self::get_debug_info(message_type));
     }
 
     fn create_messenger(
@@ -60,12 +111,35 @@ impl Debugger {
         }
     }
 
+    fn create_report_callback(instance: &Instance) -> anyhow::Result<vk::DebugReportCallbackEXT> {
+        debug!("Adding legacy debug report callback.");
+        let report_info = vk::DebugReportCallbackCreateInfoEXT::builder()
+            .flags(
+                vk::DebugReportFlagsEXT::ERROR
+                    | vk::DebugReportFlagsEXT::WARNING
+                    | vk::DebugReportFlagsEXT::PERFORMANCE_WARNING
+                    | vk::DebugReportFlagsEXT::INFORMATION
+                    | vk::DebugReportFlagsEXT::DEBUG,
+            )
+            .callback(Some(Self::debug_report_callback));
+        unsafe {
+            Ok(instance
+                .get_vk()
+                .create_debug_report_callback_ext(&report_info, None)?)
+        }
+    }
+
     pub fn destroy(&self, instance: &Instance) {
         unsafe {
             debug!("Destroying messenger.");
-            instance
-                .get_vk()
-                .destroy_debug_utils_messenger_ext(self.messenger, None);
+            match self.messenger {
+                Messenger::Utils(messenger) => instance
+                    .get_vk()
+                    .destroy_debug_utils_messenger_ext(messenger, None),
+                Messenger::Report(callback) => instance
+                    .get_vk()
+                    .destroy_debug_report_callback_ext(callback, None),
+            }
         }
     }
 
@@ -110,6 +184,7 @@ impl Debugger {
         let message = unsafe { CStr::from_ptr(data.message) }.to_string_lossy();
 
         if severity >= vk::DebugUtilsMessageSeverityFlagsEXT::ERROR {
+            VALIDATION_ERRORS.fetch_add(1, Ordering::Relaxed);
             error!("({:?}) {}", type_, message);
         } else if severity >= vk::DebugUtilsMessageSeverityFlagsEXT::WARNING {
             warn!("({:?}) {}", type_, message);
@@ -121,4 +196,34 @@ impl Debugger {
 
         vk::FALSE
     }
+
+    /// The callback of the legacy `VK_EXT_debug_report`, logging like
+    /// [`Debugger::debug_callback`]. The report flags take the place of both the severity and
+    /// the type, and `layer_prefix` names the layer that reported the message.
+    extern "system" fn debug_report_callback(
+        flags: vk::DebugReportFlagsEXT,
+        _object_type: vk::DebugReportObjectTypeEXT,
+        _object: u64,
+        _location: usize,
+        _message_code: i32,
+        layer_prefix: *const c_char,
+        message: *const c_char,
+        _: *mut c_void,
+    ) -> vk::Bool32 {
+        let layer = unsafe { CStr::from_ptr(layer_prefix) }.to_string_lossy();
+        let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+
+        if flags.contains(vk::DebugReportFlagsEXT::ERROR) {
+            VALIDATION_ERRORS.fetch_add(1, Ordering::Relaxed);
+            error!("({layer}) {message}");
+        } else if flags.intersects(vk::DebugReportFlagsEXT::WARNING | vk::DebugReportFlagsEXT::PERFORMANCE_WARNING) {
+            warn!("({layer}) {message}");
+        } else if flags.contains(vk::DebugReportFlagsEXT::INFORMATION) {
+            debug!("({layer}) {message}");
+        } else {
+            trace!("({layer}) {message}");
+        }
+
+        vk::FALSE
+    }
 }
@@ -0,0 +1,187 @@
+use log::debug;
+use std::fs;
+use std::path::Path;
+use vulkanalia::vk;
+
+/// How the physical device is picked between the suitable ones, after
+/// [`AppConfig::preferred_device`](crate::gapi::app::AppConfig::preferred_device), which always
+/// wins.
+///
+/// It only affects the selection when the app is created. Unplugging the laptop afterward doesn't
+/// move the rendering to another GPU, that would mean recreating every Vulkan object.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeviceSelectionPolicy {
+    /// The first suitable device, in the order the driver lists them.
+    #[default]
+    FirstSuitable,
+    /// On laptops, prefers the integrated GPU on battery to save power, and the discrete one on
+    /// AC power, see [`PowerSource::detect`] for the supported platforms. Falls back to
+    /// [`DeviceSelectionPolicy::FirstSuitable`] when the power source can't be determined.
+    PowerAware,
+    /// The first device passing the requirements, or the first matching the preferred device if
+    /// any, without checking the devices after it. Faster to start on machines with many GPUs,
+    /// but a better device listed later is never considered.
+    FastStartup,
+}
+
+/// Where the machine draws its power from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerSource {
+    /// Plugged in, or a desktop.
+    Ac,
+    Battery,
+}
+
+impl PowerSource {
+    /// Where the machine draws its power from, `None` if it can't be determined.
+    ///
+    /// Only Linux is supported, through `/sys/class/power_supply`. On the other platforms it is
+    /// always `None`.
+    pub fn detect() -> Option<Self> {
+        if cfg!(target_os = "linux") {
+            Self::detect_sysfs(Path::new("/sys/class/power_supply"))
+        } else {
+            None
+        }
+    }
+
+    /// Reads the power supplies of the sysfs class `dir`: on AC power if any mains supply is
+    /// online, on battery if none is but there is a battery, and `None` without either.
+    ///
+    /// The supplies of peripherals, e.g. the battery of a wireless mouse, have the `Device` scope
+    /// and are skipped, they don't power the machine.
+    fn detect_sysfs(dir: &Path) -> Option<Self> {
+        let read = |path: &Path| fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+        let mut has_battery = false;
+        let mut has_mains = false;
+        for supply in fs::read_dir(dir).ok()?.flatten() {
+            let path = supply.path();
+            if read(&path.join("scope")).as_deref() == Some("Device") {
+                debug!("Skipping the power supply of a peripheral: {}", path.display());
+                continue;
+            }
+            match read(&path.join("type")).as_deref() {
+                Some("Mains") => {
+                    has_mains = true;
+                    if read(&path.join("online")).as_deref() == Some("1") {
+                        return Some(PowerSource::Ac);
+                    }
+                }
+                Some("Battery") => has_battery = true,
+                _ => {}
+            }
+        }
+        debug!("Power supplies: mains {has_mains}, battery {has_battery}.");
+        match (has_mains, has_battery) {
+            (_, true) => Some(PowerSource::Battery),
+            // A desktop without a battery may not report its supply at all.
+            (true, false) => Some(PowerSource::Ac),
+            (false, false) => None,
+        }
+    }
+}
+
+impl DeviceSelectionPolicy {
+    /// Ranks a device of `device_type` under the policy, the highest wins, and ties keep the
+    /// driver order. Every device ranks the same when the policy doesn't apply.
+    pub fn score(self, device_type: vk::PhysicalDeviceType, power_source: Option<PowerSource>) -> u32 {
+        let power_source = match (self, power_source) {
+            (DeviceSelectionPolicy::PowerAware, Some(power_source)) => power_source,
+            _ => return 0,
+        };
+        match (device_type, power_source) {
+            (vk::PhysicalDeviceType::DISCRETE_GPU, PowerSource::Ac) => 3,
+            (vk::PhysicalDeviceType::INTEGRATED_GPU, PowerSource::Ac) => 2,
+            (vk::PhysicalDeviceType::INTEGRATED_GPU, PowerSource::Battery) => 3,
+            (vk::PhysicalDeviceType::DISCRETE_GPU, PowerSource::Battery) => 2,
+            (vk::PhysicalDeviceType::VIRTUAL_GPU, _) => 1,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vulkanalia::vk::PhysicalDeviceType as Type;
+
+    /// A sysfs power supply: its name, then the content of its `type`, `online` and `scope`
+    /// files, `None` for a missing file.
+    type Supply<'a> = (&'a str, &'a str, Option<&'a str>, Option<&'a str>);
+
+    /// Detects the power source from a temporary sysfs class with the `supplies`.
+    fn detect(name: &str, supplies: &[Supply]) -> anyhow::Result<Option<PowerSource>> {
+        let dir = std::env::temp_dir().join(format!("burst-power-supply-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        for &(supply, type_, online, scope) in supplies {
+            let supply = dir.join(supply);
+            fs::create_dir_all(&supply)?;
+            // Like sysfs, the values end with a newline.
+            fs::write(supply.join("type"), format!("{type_}\n"))?;
+            for (file, value) in [("online", online), ("scope", scope)] {
+                if let Some(value) = value {
+                    fs::write(supply.join(file), format!("{value}\n"))?;
+                }
+            }
+        }
+        let detected = PowerSource::detect_sysfs(&dir);
+        fs::remove_dir_all(&dir)?;
+        Ok(detected)
+    }
+
+    #[test]
+    fn detects_mains_only_machines_as_ac() -> anyhow::Result<()> {
+        assert_eq!(detect("mains", &[("AC", "Mains", Some("1"), None)])?, Some(PowerSource::Ac));
+        // A desktop without a battery, even if its supply reports being offline.
+        assert_eq!(detect("mains-offline", &[("AC", "Mains", Some("0"), None)])?, Some(PowerSource::Ac));
+        Ok(())
+    }
+
+    #[test]
+    fn detects_the_battery_unless_the_mains_are_online() -> anyhow::Result<()> {
+        let unplugged = [("AC", "Mains", Some("0"), None), ("BAT0", "Battery", None, Some("System"))];
+        assert_eq!(detect("unplugged", &unplugged)?, Some(PowerSource::Battery));
+        let plugged = [("AC", "Mains", Some("1"), None), ("BAT0", "Battery", None, Some("System"))];
+        assert_eq!(detect("plugged", &plugged)?, Some(PowerSource::Ac));
+        assert_eq!(detect("battery", &[("BAT0", "Battery", None, None)])?, Some(PowerSource::Battery));
+        Ok(())
+    }
+
+    #[test]
+    fn skips_the_supplies_of_peripherals() -> anyhow::Result<()> {
+        let mouse = ("hidpp_battery_0", "Battery", Some("1"), Some("Device"));
+        assert_eq!(detect("peripheral", &[mouse])?, None);
+        // A USB charger online doesn't mean the laptop is plugged in.
+        let charger = ("usb-charger", "Mains", Some("1"), Some("Device"));
+        let laptop = [("BAT0", "Battery", None, Some("System")), charger, mouse];
+        assert_eq!(detect("peripheral-laptop", &laptop)?, Some(PowerSource::Battery));
+        assert_eq!(detect("empty", &[])?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn power_aware_prefers_the_integrated_gpu_on_battery_and_the_discrete_one_on_ac() {
+        let power_aware = DeviceSelectionPolicy::PowerAware;
+        let ac = Some(PowerSource::Ac);
+        let battery = Some(PowerSource::Battery);
+        assert!(power_aware.score(Type::DISCRETE_GPU, ac) > power_aware.score(Type::INTEGRATED_GPU, ac));
+        assert!(power_aware.score(Type::INTEGRATED_GPU, battery) > power_aware.score(Type::DISCRETE_GPU, battery));
+        for power_source in [ac, battery] {
+            assert!(power_aware.score(Type::DISCRETE_GPU, power_source) > power_aware.score(Type::VIRTUAL_GPU, power_source));
+            assert!(power_aware.score(Type::VIRTUAL_GPU, power_source) > power_aware.score(Type::CPU, power_source));
+        }
+    }
+
+    #[test]
+    fn other_policies_or_an_unknown_power_source_rank_every_device_the_same() {
+        let types = [Type::DISCRETE_GPU, Type::INTEGRATED_GPU, Type::VIRTUAL_GPU, Type::CPU, Type::OTHER];
+        for device_type in types {
+            assert_eq!(DeviceSelectionPolicy::PowerAware.score(device_type, None), 0);
+            for policy in [DeviceSelectionPolicy::FirstSuitable, DeviceSelectionPolicy::FastStartup] {
+                assert_eq!(policy.score(device_type, Some(PowerSource::Ac)), 0);
+                assert_eq!(policy.score(device_type, Some(PowerSource::Battery)), 0);
+            }
+        }
+    }
+}
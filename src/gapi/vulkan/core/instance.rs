@@ -1,13 +1,13 @@
 use std::ffi::c_char;
-use crate::gapi::vulkan::config::{API_DUMP_ENABLED, VALIDATION_ENABLED};
+use crate::gapi::vulkan::config::API_DUMP_ENABLED;
 use crate::{debug_success, info_success, trace_success};
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use log::{debug, info, trace, warn};
 use vulkanalia::vk::{HasBuilder, InstanceV1_0, PhysicalDevice};
 use vulkanalia::{vk, Instance as VkInstance, Version, VkResult};
 use crate::gapi::vulkan::core::debug::Debugger;
 use crate::gapi::vulkan::core::entry::Entry;
-use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::core::real_device::{DeviceSummary, RealDevice};
 use crate::gapi::vulkan::enums::extensions::{InstanceExtension, PORTABILITY_MACOS_VERSION};
 use crate::gapi::vulkan::enums::layers::InstanceLayer;
 use crate::window::MyWindow;
@@ -34,6 +34,10 @@ use crate::window::MyWindow;
 ///
 pub(crate) struct Instance {
     instance: VkInstance,
+    /// The extensions the instance was created with.
+    extensions: Vec<InstanceExtension>,
+    /// The layers the instance was created with.
+    layers: Vec<InstanceLayer>,
 }
 
 impl Instance {
@@ -48,21 +52,36 @@ impl Instance {
     /// # Details
     /// - First the constructor gathers the configuration data (flags, extensions, etc.) defined
     /// within the `Instance` class.
-    /// - Then, if validation is enabled, we add a validation layer.
+    /// - Then, if `validation` is enabled, we add a validation layer and a debug extension, see
+    /// [`Self::pick_debug_extension`].
     ///
     /// # Errors
     ///
     /// Returns error if the machine is Mac and the Vulkan version that the machine has does not
     /// support portability to macOS.
     ///
-    pub fn new(entry: &Entry, window: &MyWindow) -> anyhow::Result<Self> {
+    pub fn new(entry: &Entry, window: &MyWindow, validation: bool) -> anyhow::Result<Self> {
+        Self::create(entry, Some(window), validation)
+    }
+
+    /// Creates an instance without the surface extensions, to use the GPU without a window, e.g.
+    /// to render offscreen in tests. No surface can be created from it.
+    pub fn new_headless(entry: &Entry, validation: bool) -> anyhow::Result<Self> {
+        Self::create(entry, None, validation)
+    }
 
+    fn create(entry: &Entry, window: Option<&MyWindow>, validation: bool) -> anyhow::Result<Self> {
         info!("Checking if system is compatible with Vulkan...");
         Self::check_compatibility(entry)?;
         info_success!("System is compatible with Vulkan!");
 
+        let debug_extension = if validation || API_DUMP_ENABLED {
+            Some(Self::pick_debug_extension(entry)?)
+        } else {
+            None
+        };
         info!("Getting configured instance extensions...");
-        let extensions = Self::get_required_extensions(window);
+        let extensions = Self::get_required_extensions(window, debug_extension)?;
         let extension_names: Vec<*const c_char> = extensions
             .iter()
             .map(|ext| ext.name_ptr())
@@ -74,7 +93,7 @@ impl Instance {
         info_success!("Requested Instance extensions are available!");
 
         info!("Getting configured instance layers...");
-        let layers = Self::get_required_layers();
+        let layers = Self::get_required_layers(validation);
         let layer_names: Vec<*const c_char> = layers
             .iter()
             .map(|layer| layer.name_ptr())
@@ -113,17 +132,22 @@ impl Instance {
             .flags(flags);
         trace_success!("InstanceCreateInfo built!: \n\t{:?}", info);
 
-        // Add debug messages for creation and destruction of the Vulkan instance.
-        if VALIDATION_ENABLED {
+        // Add debug messages for creation and destruction of the Vulkan instance. The legacy
+        // debug report messenger only reports what happens once it is created.
+        if validation && debug_extension == Some(InstanceExtension::ExtDebugUtils) {
             debug!("{}", "Adding lifetime messenger to Instance.");
-            Debugger::add_instance_lifetime_messenger(&mut info);
+            Debugger::add_instance_lifetime_messenger(&mut info, Debugger::DEFAULT_MESSAGE_TYPES);
             debug_success!("Lifetime messenger added to Instance!");
         }
         trace!("Creating vulkan instance...");
         let instance = entry.create_instance(&info, None)?;
         info_success!("Vulkan Instance created!");
 
-        Ok(Self { instance })
+        Ok(Self {
+            instance,
+            extensions,
+            layers,
+        })
     }
 
 
@@ -158,28 +182,93 @@ impl Instance {
         Ok(())
     }
 
-    fn config_required_extensions(window: &MyWindow) -> Vec<InstanceExtension> {
-        let mut required_exts: Vec<InstanceExtension> = window
+    /// Checks that the window reported the extensions needed to present to it.
+    ///
+    /// The window must require `VK_KHR_surface` and at least one platform surface extension
+    /// (e.g. `VK_KHR_xcb_surface`). Anything else means the windowing backend is not usable, and
+    /// we'd rather fail here than later with a confusing surface creation error.
+    ///
+    /// # Errors
+    /// - If the window extensions can't be queried.
+    /// - If the returned set is empty or is missing the surface or platform surface extension.
+    fn check_window_extensions(window: &MyWindow) -> anyhow::Result<()> {
+        let window_extensions = window
             .get_required_extensions()
+            .with_context(|| "Failed to query the window's required instance extensions.")?;
+        if window_extensions.is_empty() {
+            return Err(anyhow!(
+                "The window did not report any required instance extensions, the windowing \
+                backend is likely not supported (e.g. running headless)."
+            ));
+        }
+        let surface_name = vk::KHR_SURFACE_EXTENSION.name;
+        if !window_extensions.iter().any(|ext| **ext == surface_name) {
+            return Err(anyhow!(
+                "The window's required extensions {:?} are missing {}.",
+                window_extensions,
+                surface_name
+            ));
+        }
+        let has_platform_surface = window_extensions
+            .iter()
+            .any(|ext| **ext != surface_name && ext.to_string().ends_with("_surface"));
+        if !has_platform_surface {
+            return Err(anyhow!(
+                "The window's required extensions {:?} are missing a platform surface extension.",
+                window_extensions
+            ));
+        }
+        trace!("Window extensions are valid: {:?}", window_extensions);
+        Ok(())
+    }
+
+    /// Picks the extension the validation messages are delivered through.
+    ///
+    /// `VK_EXT_debug_utils` is preferred. Older Vulkan installations may only have the legacy
+    /// `VK_EXT_debug_report`, which is then used instead, for compatibility. If neither is
+    /// available, `VK_EXT_debug_utils` is still returned, so that the availability check reports
+    /// it missing.
+    fn pick_debug_extension(entry: &Entry) -> anyhow::Result<InstanceExtension> {
+        let available = entry.get_available_extensions(None)?;
+        if !available.contains(&InstanceExtension::ExtDebugUtils)
+            && available.contains(&InstanceExtension::ExtDebugReport)
+        {
+            warn!(
+                "{} is not available, falling back to the legacy {} for the validation messages.",
+                InstanceExtension::ExtDebugUtils,
+                InstanceExtension::ExtDebugReport
+            );
+            return Ok(InstanceExtension::ExtDebugReport);
+        }
+        Ok(InstanceExtension::ExtDebugUtils)
+    }
+
+    fn config_required_extensions(
+        window: Option<&MyWindow>,
+        debug_extension: Option<InstanceExtension>,
+    ) -> anyhow::Result<Vec<InstanceExtension>> {
+        let window_extensions = match window {
+            Some(window) => window.get_required_extensions()?,
+            None => &[],
+        };
+        let mut required_exts: Vec<InstanceExtension> = window_extensions
             .iter()
             .map(|ext| InstanceExtension::from_name(*ext))
             .collect::<Vec<_>>();
-        if VALIDATION_ENABLED || API_DUMP_ENABLED {
-            required_exts.push(InstanceExtension::ExtDebugUtils);
-        }
+        required_exts.extend(debug_extension);
         if cfg!(target_os = "macos") {
             required_exts.push(InstanceExtension::KhrGetPhysicalDeviceProperties2);
             required_exts.push(InstanceExtension::KhrPortabilityEnumeration);
         }
-        required_exts
+        Ok(required_exts)
     }
 
-    fn config_required_layers() -> Vec<InstanceLayer> {
+    fn config_required_layers(validation: bool) -> Vec<InstanceLayer> {
         let mut layers: Vec<InstanceLayer> = vec![];
-        if VALIDATION_ENABLED && API_DUMP_ENABLED {
+        if validation && API_DUMP_ENABLED {
             layers.push(InstanceLayer::ApiDump);
         }
-        if VALIDATION_ENABLED {
+        if validation {
             layers.push(InstanceLayer::Validation);
         }
         layers
@@ -188,21 +277,32 @@ impl Instance {
     /// Collects and returns the required extensions for the Vulkan instance.
     ///
     /// # Parameters
-    /// - `window`: The window handler ([`MyWindow`]) that knows its required extensions.
+    /// - `window`: The window handler ([`MyWindow`]) that knows its required extensions, `None`
+    /// for a headless instance.
+    /// - `debug_extension`: The extension the validation messages are delivered through, if any.
     ///
     /// # Returns
     /// - A vector of [`ExtensionStr`] that contains the required extensions for the Vulkan instance.
-    fn get_required_extensions(window: &MyWindow) -> Vec<InstanceExtension> {
-        let extensions = Self::config_required_extensions(window);
+    ///
+    /// # Errors
+    /// - If the window's required extensions are not usable, see [`Self::check_window_extensions`].
+    fn get_required_extensions(
+        window: Option<&MyWindow>,
+        debug_extension: Option<InstanceExtension>,
+    ) -> anyhow::Result<Vec<InstanceExtension>> {
+        if let Some(window) = window {
+            Self::check_window_extensions(window)?;
+        }
+        let extensions = Self::config_required_extensions(window, debug_extension)?;
         info!("Required Extension: {:?}", extensions);
-        extensions
+        Ok(extensions)
     }
 
     /// Collects and returns the required layers for the Vulkan instance.
     /// # Returns
     /// A list of all the [layers](Instance) required by [`Instance`]
-    fn get_required_layers() -> Vec<InstanceLayer> {
-        let layers = Self::config_required_layers();
+    fn get_required_layers(validation: bool) -> Vec<InstanceLayer> {
+        let layers = Self::config_required_layers(validation);
         info!("Required Layers: {:?}", layers);
         layers
     }
@@ -227,11 +327,21 @@ impl Instance {
                 .into_iter()
                 .map(|device| RealDevice::new(self, device))
                 .collect::<Vec<_>>();
-            info!("Physical devices found: \n\t{:?}", physical_devices);
+            // Only the count, printing a device queries its properties.
+            info!("Physical devices found: {}", physical_devices.len());
             Ok(physical_devices)
         }
     }
 
+    /// Lists the physical devices with their driver, so a specific one can be picked (e.g. to
+    /// avoid a software rasterizer).
+    pub fn list_devices(&self) -> VkResult<Vec<DeviceSummary>> {
+        Ok(self
+            .enumerate_real_devices()?
+            .iter()
+            .map(RealDevice::get_summary)
+            .collect())
+    }
 
     pub fn destroy(&self) {
         debug!("Destroying instance");
@@ -239,6 +349,14 @@ impl Instance {
             self.instance.destroy_instance(None);
         }
     }
+    pub fn get_enabled_extensions(&self) -> &[InstanceExtension] {
+        &self.extensions
+    }
+
+    pub fn get_enabled_layers(&self) -> &[InstanceLayer] {
+        &self.layers
+    }
+
     pub fn get_vk(&self) -> &VkInstance {
         &self.instance
     }
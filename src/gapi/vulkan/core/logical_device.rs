@@ -1,13 +1,18 @@
 use crate::gapi::vulkan::core::instance::Instance;
-use crate::gapi::vulkan::core::queues::{QueueRequest, Queues};
-use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::core::queues::{QueueCapability, QueueFamily, QueueRequest, Queues};
+use crate::gapi::vulkan::core::real_device::{BufferDeviceAddressSupport, DeviceCapabilities, RealDevice};
+use crate::gapi::vulkan::core::resource_tracker::{ResourceKind, ResourceTracker};
 use crate::gapi::vulkan::core::surface::Surface;
 use crate::gapi::vulkan::enums::extensions::DeviceExtension;
+use crate::gapi::vulkan::sync::render_result::RenderResult;
+use crate::gapi::errors::BurstError;
 use anyhow::Context;
-use log::{info, trace};
+use std::time::Duration;
+use crate::trace_vk;
+use log::{error, info};
 use vulkanalia::vk::{
-    Cast, DeviceV1_0, GraphicsPipelineCreateInfo, HasBuilder, ImageViewCreateInfoBuilder,
-    KhrSwapchainExtension, PhysicalDeviceFeatures, Pipeline, PipelineCache, Queue,
+    Cast, DeviceV1_0, DeviceV1_2, GraphicsPipelineCreateInfo, Handle, HasBuilder, ImageViewCreateInfoBuilder,
+    KhrBufferDeviceAddressExtension, KhrDynamicRenderingExtension, KhrSwapchainExtension, PhysicalDeviceFeatures, Pipeline, PipelineCache, Queue,
     SwapchainCreateInfoKHR, SwapchainKHR,
 };
 use vulkanalia::{vk, Device};
@@ -22,6 +27,12 @@ pub struct LogicalDevice {
     /// The Vulkan device handle.
     device: Device,
     queues: Queues,
+    /// The device extensions the device was created with.
+    extensions: Vec<DeviceExtension>,
+    /// The draw limits of the physical device, to validate draw calls in debug builds.
+    capabilities: DeviceCapabilities,
+    /// Counts the objects created and destroyed through the wrappers, in debug builds.
+    resources: ResourceTracker,
 }
 
 impl LogicalDevice {
@@ -34,16 +45,133 @@ impl LogicalDevice {
     ) -> anyhow::Result<Self> {
         let resolved_families = Queues::resolve_queue_requests(real_device, surface, requests)
             .with_context(|| format!("Failed to resolve queue requests: {:?}", requests))?;
+        Self::create(real_device, instance, &resolved_families, extensions)
+    }
 
-        let queue_infos = Queues::create_queue_infos(&resolved_families);
+    /// Creates the device with one queue of every queue family, instead of only the requested
+    /// ones, see [`Queues::resolve_all_families`].
+    ///
+    /// The queues are still grouped by capability in [`LogicalDevice::get_queues`], a family
+    /// with several capabilities has its queue in each group.
+    pub fn new_with_all_queues(
+        real_device: &RealDevice,
+        instance: &Instance,
+        surface: &Surface,
+        extensions: &[DeviceExtension],
+    ) -> anyhow::Result<Self> {
+        let families = Queues::resolve_all_families(real_device, surface)
+            .with_context(|| "Failed to list the queue families")?;
+        Self::create(real_device, instance, &families, extensions)
+    }
+
+    /// Creates a device with a single compute queue and no surface, to use the GPU as a compute
+    /// backend in headless tools, e.g. to mesh voxels without displaying them.
+    ///
+    /// The swapchain extension isn't enabled, so the swapchain operations are unavailable, and
+    /// there are no graphics nor present queues: [`Queues::graphics_queue`] and
+    /// [`Queues::present_queue`] return an error, use [`Queues::compute_queue`] instead.
+    pub fn new_compute_only(real_device: &RealDevice, instance: &Instance) -> anyhow::Result<Self> {
+        let requests = [QueueRequest {
+            capabilities: vec![QueueCapability::Compute],
+            require_present: false,
+            count: 1,
+        }];
+        // Older devices only provide buffer device addresses through the extension.
+        let mut extensions = Vec::new();
+        if real_device.buffer_device_address_support() == BufferDeviceAddressSupport::KhrExtension {
+            extensions.push(DeviceExtension::KhrBufferDeviceAddress);
+        }
+        info!("Creating compute only device...");
+        Self::new_headless(real_device, instance, &requests, &extensions)
+            .with_context(|| "Failed to create a compute only device")
+    }
+
+    /// Creates a device without a surface, with the `requests` queues, none of which can
+    /// present, e.g. to render offscreen. Like with [`LogicalDevice::new_compute_only`], the
+    /// swapchain operations are unavailable.
+    pub fn new_headless(
+        real_device: &RealDevice,
+        instance: &Instance,
+        requests: &[QueueRequest],
+        extensions: &[DeviceExtension],
+    ) -> anyhow::Result<Self> {
+        let families = Queues::resolve_headless_requests(real_device, requests)
+            .with_context(|| format!("Failed to resolve headless queue requests: {requests:?}"))?;
+        Self::create(real_device, instance, &families, extensions)
+    }
+
+    fn create(
+        real_device: &RealDevice,
+        instance: &Instance,
+        resolved_families: &[QueueFamily],
+        extensions: &[DeviceExtension],
+    ) -> anyhow::Result<Self> {
+        let queue_infos = Queues::create_queue_infos(resolved_families);
 
         let ext_names = extensions.iter().map(|e| e.name_ptr()).collect::<Vec<_>>();
-        let features = PhysicalDeviceFeatures::builder().geometry_shader(true);
+        // Non-solid fill modes are only used to debug, so they are enabled only if available.
+        let fill_mode_non_solid = real_device.get_features().fill_mode_non_solid == vk::TRUE;
+        // Without it, every indirect draw is limited to a single command.
+        let multi_draw_indirect = real_device.get_features().multi_draw_indirect == vk::TRUE;
+        // Sample shading is optional, and only enabled in the pipelines if configured.
+        let sample_rate_shading = real_device.get_features().sample_rate_shading == vk::TRUE;
+        // Geometry shaders are only required when the app is configured to, see
+        // `AppConfig::require_geometry_shader`.
+        let geometry_shader = real_device.get_features().geometry_shader == vk::TRUE;
+        // Anisotropic filtering is optional, samplers only use it when available, see
+        // `SamplerCache::get`.
+        let sampler_anisotropy = real_device.get_features().sampler_anisotropy == vk::TRUE;
+        // Lines wider than a pixel are optional, see `AppConfig::line_width`.
+        let wide_lines = real_device.get_features().wide_lines == vk::TRUE;
+        let features = PhysicalDeviceFeatures::builder()
+            .geometry_shader(geometry_shader)
+            .sampler_anisotropy(sampler_anisotropy)
+            .wide_lines(wide_lines)
+            .fill_mode_non_solid(fill_mode_non_solid)
+            .multi_draw_indirect(multi_draw_indirect)
+            .sample_rate_shading(sample_rate_shading);
+        // Buffer device addresses are enabled if available, which needs the extension to be
+        // requested by the caller on devices older than Vulkan 1.2.
+        let buffer_device_address = match real_device.buffer_device_address_support() {
+            BufferDeviceAddressSupport::Core => true,
+            BufferDeviceAddressSupport::KhrExtension => {
+                extensions.contains(&DeviceExtension::KhrBufferDeviceAddress)
+            }
+            BufferDeviceAddressSupport::Unsupported => false,
+        };
+        let mut address_features = vk::PhysicalDeviceBufferDeviceAddressFeatures::builder()
+            .buffer_device_address(buffer_device_address);
+
+        // The extension is only requested when the device supports the feature, see
+        // `RealDevice::supports_dynamic_rendering`.
+        let dynamic_rendering = extensions.contains(&DeviceExtension::KhrDynamicRendering);
+        let mut dynamic_rendering_features =
+            vk::PhysicalDeviceDynamicRenderingFeatures::builder().dynamic_rendering(true);
 
-        let create_info = vk::DeviceCreateInfo::builder()
+        // The extension is only requested when the device supports the features of bindless
+        // textures, see `RealDevice::supports_descriptor_indexing`. The features of extensions
+        // are not part of `PhysicalDeviceFeatures`, they are enabled by chaining their struct to
+        // the `DeviceCreateInfo` with `push_next`, and each struct can only be chained once.
+        let descriptor_indexing = extensions.contains(&DeviceExtension::ExtDescriptorIndexing);
+        let mut descriptor_indexing_features = vk::PhysicalDeviceDescriptorIndexingFeatures::builder()
+            .descriptor_binding_partially_bound(true)
+            .descriptor_binding_sampled_image_update_after_bind(true)
+            .runtime_descriptor_array(true)
+            .shader_sampled_image_array_non_uniform_indexing(true);
+
+        let mut create_info = vk::DeviceCreateInfo::builder()
             .queue_create_infos(&queue_infos)
             .enabled_extension_names(&ext_names)
             .enabled_features(&features);
+        if buffer_device_address {
+            create_info = create_info.push_next(&mut address_features);
+        }
+        if dynamic_rendering {
+            create_info = create_info.push_next(&mut dynamic_rendering_features);
+        }
+        if descriptor_indexing {
+            create_info = create_info.push_next(&mut descriptor_indexing_features);
+        }
 
         let device = unsafe {
             instance
@@ -51,9 +179,65 @@ impl LogicalDevice {
                 .create_device(*real_device.get_vk(), &create_info, None)?
         };
 
-        let queues = Queues::new(&device, &resolved_families)?;
+        let queues = Queues::new(&device, resolved_families)?;
 
-        Ok(Self { device, queues })
+        Ok(Self {
+            device,
+            queues,
+            extensions: extensions.to_vec(),
+            capabilities: DeviceCapabilities {
+                buffer_device_address,
+                ..real_device.capabilities()
+            },
+            resources: ResourceTracker::new(),
+        })
+    }
+
+    /// The draw limits of the physical device, see [`DeviceCapabilities`].
+    pub fn capabilities(&self) -> DeviceCapabilities {
+        self.capabilities
+    }
+
+    /// The objects created through this device that are still alive, see [`ResourceTracker`].
+    pub fn resources(&self) -> &ResourceTracker {
+        &self.resources
+    }
+
+    /// Counts `handle` as destroyed, destroying a null handle does nothing.
+    fn untrack(&self, kind: ResourceKind, handle: impl Handle) {
+        if !handle.is_null() {
+            self.resources.destroyed(kind, 1);
+        }
+    }
+
+    /// Logs an error for each draw parameter that would overflow a `u32` range or exceed the
+    /// limits of the device. Only called in debug builds, to point at the faulty draw instead of
+    /// a lost device later on.
+    ///
+    /// Checks that:
+    /// - `first + count` and `first_instance + instance_count` don't overflow.
+    /// - The last element, `first + count - 1`, is at most `max_element`, the
+    ///   `max_draw_indexed_index_value` of the device for indexed draws. The index values
+    ///   themselves are not checked, only the code writing the index buffer knows them.
+    /// - The last instance index is at most the `max_multiview_instance_index` of the device.
+    fn validate_draw(&self, draw: &str, count: u32, first: u32, instance_count: u32, first_instance: u32, max_element: Option<u32>) {
+        match first.checked_add(count) {
+            None => error!("{draw}: the range of {count} elements from {first} overflows u32."),
+            Some(end) => {
+                if let Some(max_element) = max_element.filter(|&max_element| count > 0 && end - 1 > max_element) {
+                    error!("{draw}: the range of {count} elements from {first} exceeds the device limit of {max_element}.");
+                }
+            }
+        }
+        match first_instance.checked_add(instance_count) {
+            None => error!("{draw}: the range of {instance_count} instances from {first_instance} overflows u32."),
+            Some(end) => {
+                let max_instance = self.capabilities.max_multiview_instance_index;
+                if let Some(max_instance) = max_instance.filter(|&max_instance| instance_count > 0 && end - 1 > max_instance) {
+                    error!("{draw}: the range of {instance_count} instances from {first_instance} exceeds the device limit of {max_instance}.");
+                }
+            }
+        }
     }
 
     fn get_vk_queue(&self, family_index: u32, queue_index: u32) -> Queue {
@@ -65,7 +249,7 @@ impl LogicalDevice {
         pipeline_cache: PipelineCache,
         create_info: &[impl Cast<Target = GraphicsPipelineCreateInfo> + std::fmt::Debug],
     ) -> anyhow::Result<Vec<Pipeline>> {
-        trace!(
+        trace_vk!(
             "Calling create_graphics_pipelines with info: {:?}",
             create_info
         );
@@ -74,6 +258,7 @@ impl LogicalDevice {
                 .create_graphics_pipelines(pipeline_cache, create_info, None)
                 .map_err(|e| anyhow::anyhow!("Failed to create graphics pipeline: {}", e))?
         };
+        self.resources.created(ResourceKind::Pipeline, pipelines.len());
 
         let () = match success_code {
             vk::SuccessCode::SUCCESS => (),
@@ -116,7 +301,7 @@ impl LogicalDevice {
         &self,
         create_info: &vk::PipelineLayoutCreateInfo,
     ) -> anyhow::Result<vk::PipelineLayout> {
-        trace!(
+        trace_vk!(
             "Calling create_pipeline_layout with info: {:?}",
             create_info
         );
@@ -124,35 +309,88 @@ impl LogicalDevice {
             self.device
                 .create_pipeline_layout(create_info, None)
                 .map_err(|e| anyhow::anyhow!("Failed to create pipeline layout: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::PipelineLayout, 1))
         }
     }
 
     pub fn destroy_pipeline(&self, pipeline: vk::Pipeline) {
-        trace!("Calling destroy_pipeline for pipeline: {:?}", pipeline);
+        trace_vk!("Calling destroy_pipeline for pipeline: {:?}", pipeline);
         unsafe {
             self.device.destroy_pipeline(pipeline, None);
         }
+        self.untrack(ResourceKind::Pipeline, pipeline);
     }
 
     pub fn destroy_pipeline_layout(&self, layout: vk::PipelineLayout) {
-        trace!(
+        trace_vk!(
             "Calling destroy_pipeline_layout for pipeline layout: {:?}",
             layout
         );
         unsafe {
             self.device.destroy_pipeline_layout(layout, None);
         }
+        self.untrack(ResourceKind::PipelineLayout, layout);
     }
 
+    pub fn create_pipeline_cache(
+        &self,
+        create_info: &vk::PipelineCacheCreateInfo,
+    ) -> anyhow::Result<PipelineCache> {
+        trace_vk!("Calling create_pipeline_cache with info: {:?}", create_info);
+        unsafe {
+            self.device
+                .create_pipeline_cache(create_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to create pipeline cache: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::PipelineCache, 1))
+        }
+    }
+
+    pub fn get_pipeline_cache_data(&self, pipeline_cache: PipelineCache) -> anyhow::Result<Vec<u8>> {
+        trace_vk!(
+            "Calling get_pipeline_cache_data for pipeline cache: {:?}",
+            pipeline_cache
+        );
+        unsafe {
+            self.device
+                .get_pipeline_cache_data(pipeline_cache)
+                .map_err(|e| anyhow::anyhow!("Failed to get pipeline cache data: {}", e))
+        }
+    }
+
+    pub fn destroy_pipeline_cache(&self, pipeline_cache: PipelineCache) {
+        trace_vk!(
+            "Calling destroy_pipeline_cache for pipeline cache: {:?}",
+            pipeline_cache
+        );
+        unsafe {
+            self.device.destroy_pipeline_cache(pipeline_cache, None);
+        }
+        self.untrack(ResourceKind::PipelineCache, pipeline_cache);
+    }
+
+    /// # Errors
+    /// [`BurstError::SurfaceInUse`] if the surface already has a swapchain that wasn't passed as
+    /// `old_swapchain`. Also fails on a device created without the swapchain extension, see
+    /// [`LogicalDevice::new_compute_only`].
     pub fn create_swapchain_khr(
         &self,
         info: &SwapchainCreateInfoKHR,
     ) -> anyhow::Result<SwapchainKHR> {
-        trace!("Calling create_swapchain_khr with info: {:?}", info);
+        if !self.extensions.contains(&DeviceExtension::KhrSwapchain) {
+            anyhow::bail!("Failed to create swapchain: the device was created without {:?}.", DeviceExtension::KhrSwapchain);
+        }
+        trace_vk!("Calling create_swapchain_khr with info: {:?}", info);
         unsafe {
             self.device
                 .create_swapchain_khr(info, None)
-                .map_err(|e| anyhow::anyhow!("Failed to create swapchain: {}", e))
+                .map_err(|e| match e {
+                    vk::ErrorCode::NATIVE_WINDOW_IN_USE_KHR => {
+                        error!("Failed to create swapchain: {}", e);
+                        BurstError::SurfaceInUse.into()
+                    }
+                    e => anyhow::anyhow!("Failed to create swapchain: {}", e),
+                })
+                .inspect(|_| self.resources.created(ResourceKind::Swapchain, 1))
         }
     }
 
@@ -160,75 +398,82 @@ impl LogicalDevice {
         &self,
         create_info: &vk::RenderPassCreateInfo,
     ) -> anyhow::Result<vk::RenderPass> {
-        trace!("Calling create_render_pass with info: {:?}", create_info);
+        trace_vk!("Calling create_render_pass with info: {:?}", create_info);
         unsafe {
             self.device
                 .create_render_pass(create_info, None)
                 .map_err(|e| anyhow::anyhow!("Failed to create render pass: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::RenderPass, 1))
         }
     }
 
     pub fn destroy_render_pass(&self, render_pass: vk::RenderPass) {
-        trace!(
+        trace_vk!(
             "Calling destroy_render_pass for render pass: {:?}",
             render_pass
         );
         unsafe {
             self.device.destroy_render_pass(render_pass, None);
         }
+        self.untrack(ResourceKind::RenderPass, render_pass);
     }
 
     pub fn create_shader_module(
         &self,
         create_info: &vk::ShaderModuleCreateInfo,
     ) -> anyhow::Result<vk::ShaderModule> {
-        trace!("Calling create_shader_module with info: {:?}", create_info);
+        trace_vk!("Calling create_shader_module with info: {:?}", create_info);
         unsafe {
             self.device
                 .create_shader_module(create_info, None)
                 .map_err(|e| anyhow::anyhow!("Failed to create shader module: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::ShaderModule, 1))
         }
     }
 
     pub fn destroy_shader_module(&self, shader_module: vk::ShaderModule) {
-        trace!(
+        trace_vk!(
             "Calling destroy_shader_module for shader module: {:?}",
             shader_module
         );
         unsafe {
             self.device.destroy_shader_module(shader_module, None);
         }
+        self.untrack(ResourceKind::ShaderModule, shader_module);
     }
 
     pub fn destroy_swapchain_khr(&self, swapchain: SwapchainKHR) {
-        trace!(
+        trace_vk!(
             "Calling destroy_swapchain_khr for swapchain: {:?}",
             swapchain
         );
         unsafe {
             self.device.destroy_swapchain_khr(swapchain, None);
         }
+        self.untrack(ResourceKind::Swapchain, swapchain);
     }
 
     pub fn destroy_image_view(&self, image_view: vk::ImageView) {
-        trace!(
+        trace_vk!(
             "Calling destroy_image_view for image view: {:?}",
             image_view
         );
         unsafe {
             self.device.destroy_image_view(image_view, None);
         }
+        self.untrack(ResourceKind::ImageView, image_view);
     }
 
     pub(crate) fn create_image_view(
         &self,
         create_info: &ImageViewCreateInfoBuilder,
     ) -> anyhow::Result<vk::ImageView> {
-        trace!("Calling create_image_view with info: {:?}", create_info);
+        trace_vk!("Calling create_image_view with info: {:?}", create_info);
         unsafe {
             self.device
                 .create_image_view(create_info, None)
                 .map_err(|e| anyhow::anyhow!("Failed to create image view: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::ImageView, 1))
         }
     }
 
@@ -236,7 +481,7 @@ impl LogicalDevice {
         &self,
         swapchain: SwapchainKHR,
     ) -> anyhow::Result<Vec<vk::Image>> {
-        trace!(
+        trace_vk!(
             "Calling get_swapchain_images_khr for swapchain: {:?}",
             swapchain
         );
@@ -251,51 +496,55 @@ impl LogicalDevice {
         &self,
         create_info: &vk::FramebufferCreateInfo,
     ) -> anyhow::Result<vk::Framebuffer> {
-        trace!("Calling create_framebuffer with info: {:?}", create_info);
+        trace_vk!("Calling create_framebuffer with info: {:?}", create_info);
         unsafe {
             self.device
                 .create_framebuffer(create_info, None)
                 .map_err(|e| anyhow::anyhow!("Failed to create framebuffer: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::Framebuffer, 1))
         }
     }
 
     pub fn destroy_framebuffer(&self, framebuffer: vk::Framebuffer) {
-        trace!(
+        trace_vk!(
             "Calling destroy_framebuffer for framebuffer: {:?}",
             framebuffer
         );
         unsafe {
             self.device.destroy_framebuffer(framebuffer, None);
         }
+        self.untrack(ResourceKind::Framebuffer, framebuffer);
     }
 
     pub fn create_command_pool(
         &self,
         create_info: &vk::CommandPoolCreateInfo,
     ) -> anyhow::Result<vk::CommandPool> {
-        trace!("Calling create_command_pool with info: {:?}", create_info);
+        trace_vk!("Calling create_command_pool with info: {:?}", create_info);
         unsafe {
             self.device
                 .create_command_pool(create_info, None)
                 .map_err(|e| anyhow::anyhow!("Failed to create command pool: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::CommandPool, 1))
         }
     }
 
     pub fn destroy_command_pool(&self, command_pool: vk::CommandPool) {
-        trace!(
+        trace_vk!(
             "Calling destroy_command_pool for command pool: {:?}",
             command_pool
         );
         unsafe {
             self.device.destroy_command_pool(command_pool, None);
         }
+        self.untrack(ResourceKind::CommandPool, command_pool);
     }
 
     pub fn allocate_command_buffers(
         &self,
         create_info: &vk::CommandBufferAllocateInfo,
     ) -> anyhow::Result<Vec<vk::CommandBuffer>> {
-        trace!("Calling create_command_buffers with info: {:?}", create_info);
+        trace_vk!("Calling create_command_buffers with info: {:?}", create_info);
         unsafe {
             self.device
                 .allocate_command_buffers(create_info)
@@ -303,12 +552,27 @@ impl LogicalDevice {
         }
     }
 
+    pub fn free_command_buffers(
+        &self,
+        command_pool: vk::CommandPool,
+        command_buffers: &[vk::CommandBuffer],
+    ) {
+        trace_vk!(
+            "Calling free_command_buffers for command buffers: {:?} from pool: {:?}",
+            command_buffers,
+            command_pool
+        );
+        unsafe {
+            self.device.free_command_buffers(command_pool, command_buffers);
+        }
+    }
+
     pub fn begin_command_buffer(
         &self,
         command_buffer: vk::CommandBuffer,
         begin_info: &vk::CommandBufferBeginInfo,
     ) -> anyhow::Result<()> {
-        trace!(
+        trace_vk!(
             "Calling begin_command_buffer for command buffer: {:?} with info: {:?}",
             command_buffer,
             begin_info
@@ -320,8 +584,30 @@ impl LogicalDevice {
         }
     }
 
+    pub fn set_viewport(&self, command_buffer: vk::CommandBuffer, viewports: &[vk::Viewport]) {
+        trace_vk!(
+            "Calling set_viewport for command buffer: {:?} with viewports: {:?}",
+            command_buffer,
+            viewports
+        );
+        unsafe {
+            self.device.cmd_set_viewport(command_buffer, 0, viewports);
+        }
+    }
+
+    pub fn set_scissor(&self, command_buffer: vk::CommandBuffer, scissors: &[vk::Rect2D]) {
+        trace_vk!(
+            "Calling set_scissor for command buffer: {:?} with scissors: {:?}",
+            command_buffer,
+            scissors
+        );
+        unsafe {
+            self.device.cmd_set_scissor(command_buffer, 0, scissors);
+        }
+    }
+
     pub fn draw(&self, command_buffer: vk::CommandBuffer, vertex_count: u32, instance_count: u32, first_vertex: u32, first_instance: u32) {
-        trace!(
+        trace_vk!(
             "Calling draw for command buffer: {:?} with vertex count: {}, instance count: {}, first vertex: {}, first instance: {}",
             command_buffer,
             vertex_count,
@@ -329,13 +615,201 @@ impl LogicalDevice {
             first_vertex,
             first_instance
         );
+        if cfg!(debug_assertions) {
+            self.validate_draw("draw", vertex_count, first_vertex, instance_count, first_instance, None);
+        }
         unsafe {
             self.device.cmd_draw(command_buffer, vertex_count, instance_count, first_vertex, first_instance);
         }
     }
 
+    pub fn bind_vertex_buffers(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        first_binding: u32,
+        buffers: &[vk::Buffer],
+        offsets: &[vk::DeviceSize],
+    ) {
+        trace_vk!(
+            "Calling bind_vertex_buffers for command buffer: {:?} with buffers: {:?} at offsets: {:?} from binding {}",
+            command_buffer,
+            buffers,
+            offsets,
+            first_binding
+        );
+        unsafe {
+            self.device
+                .cmd_bind_vertex_buffers(command_buffer, first_binding, buffers, offsets);
+        }
+    }
+
+    pub fn bind_index_buffer(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        offset: vk::DeviceSize,
+        index_type: vk::IndexType,
+    ) {
+        trace_vk!(
+            "Calling bind_index_buffer for command buffer: {:?} with buffer: {:?} at offset: {} and index type: {:?}",
+            command_buffer,
+            buffer,
+            offset,
+            index_type
+        );
+        unsafe {
+            self.device
+                .cmd_bind_index_buffer(command_buffer, buffer, offset, index_type);
+        }
+    }
+
+    pub fn draw_indexed(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    ) {
+        trace_vk!(
+            "Calling draw_indexed for command buffer: {:?} with index count: {}, instance count: {}, first index: {}, vertex offset: {}, first instance: {}",
+            command_buffer,
+            index_count,
+            instance_count,
+            first_index,
+            vertex_offset,
+            first_instance
+        );
+        if cfg!(debug_assertions) {
+            self.validate_draw(
+                "draw_indexed",
+                index_count,
+                first_index,
+                instance_count,
+                first_instance,
+                Some(self.capabilities.max_draw_indexed_index_value),
+            );
+        }
+        unsafe {
+            self.device.cmd_draw_indexed(
+                command_buffer,
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            );
+        }
+    }
+
+    pub fn cmd_draw_indexed_indirect(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        offset: vk::DeviceSize,
+        draw_count: u32,
+        stride: u32,
+    ) {
+        trace_vk!(
+            "Calling cmd_draw_indexed_indirect for command buffer: {:?} with buffer: {:?} at offset: {}, draw count: {}, stride: {}",
+            command_buffer,
+            buffer,
+            offset,
+            draw_count,
+            stride
+        );
+        if cfg!(debug_assertions) {
+            let capabilities = self.capabilities;
+            if !capabilities.multi_draw_indirect && draw_count > 1 {
+                error!(
+                    "cmd_draw_indexed_indirect: draw count {draw_count} needs the multi_draw_indirect feature, the device only allows 0 or 1."
+                );
+            } else if draw_count > capabilities.max_draw_indirect_count {
+                error!(
+                    "cmd_draw_indexed_indirect: draw count {draw_count} exceeds the device limit max_draw_indirect_count of {}.",
+                    capabilities.max_draw_indirect_count
+                );
+            }
+        }
+        unsafe {
+            self.device
+                .cmd_draw_indexed_indirect(command_buffer, buffer, offset, draw_count, stride);
+        }
+    }
+
+    pub fn copy_buffer(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        src: vk::Buffer,
+        dst: vk::Buffer,
+        regions: &[vk::BufferCopy],
+    ) {
+        trace_vk!(
+            "Calling copy_buffer for command buffer: {:?} from {:?} to {:?} with regions: {:?}",
+            command_buffer,
+            src,
+            dst,
+            regions
+        );
+        unsafe {
+            self.device.cmd_copy_buffer(command_buffer, src, dst, regions);
+        }
+    }
+
+    pub fn copy_buffer_to_image(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        src: vk::Buffer,
+        dst: vk::Image,
+        dst_layout: vk::ImageLayout,
+        regions: &[vk::BufferImageCopy],
+    ) {
+        trace_vk!(
+            "Calling copy_buffer_to_image for command buffer: {:?} from {:?} to {:?} in layout {:?} with regions: {:?}",
+            command_buffer,
+            src,
+            dst,
+            dst_layout,
+            regions
+        );
+        unsafe {
+            self.device
+                .cmd_copy_buffer_to_image(command_buffer, src, dst, dst_layout, regions);
+        }
+    }
+
+    pub fn pipeline_barrier(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        src_stage_mask: vk::PipelineStageFlags,
+        dst_stage_mask: vk::PipelineStageFlags,
+        buffer_barriers: &[vk::BufferMemoryBarrier],
+        image_barriers: &[vk::ImageMemoryBarrier],
+    ) {
+        trace_vk!(
+            "Calling pipeline_barrier for command buffer: {:?} from {:?} to {:?} with buffer barriers: {:?} and image barriers: {:?}",
+            command_buffer,
+            src_stage_mask,
+            dst_stage_mask,
+            buffer_barriers,
+            image_barriers
+        );
+        unsafe {
+            self.device.cmd_pipeline_barrier(
+                command_buffer,
+                src_stage_mask,
+                dst_stage_mask,
+                vk::DependencyFlags::empty(),
+                &[] as &[vk::MemoryBarrier],
+                buffer_barriers,
+                image_barriers,
+            );
+        }
+    }
+
     pub fn end_command_buffer(&self, command_buffer: vk::CommandBuffer) -> anyhow::Result<()> {
-        trace!(
+        trace_vk!(
             "Calling end_command_buffer for command buffer: {:?}",
             command_buffer
         );
@@ -352,7 +826,7 @@ impl LogicalDevice {
         pipeline_bind_point: vk::PipelineBindPoint,
         pipeline: vk::Pipeline,
     ) {
-        trace!(
+        trace_vk!(
             "Calling bind_pipeline for command buffer: {:?} with pipeline: {:?} at bind point: {:?}",
             command_buffer,
             pipeline,
@@ -369,7 +843,7 @@ impl LogicalDevice {
         begin_info: &vk::RenderPassBeginInfo,
         contents: vk::SubpassContents,
     ) {
-        trace!(
+        trace_vk!(
             "Calling begin_render_pass with info: {:?} and contents: {:?}",
             begin_info,
             contents
@@ -381,8 +855,19 @@ impl LogicalDevice {
         }
     }
 
+    pub fn next_subpass(&self, command_buffer: vk::CommandBuffer, contents: vk::SubpassContents) {
+        trace_vk!(
+            "Calling next_subpass for command buffer: {:?} with contents: {:?}",
+            command_buffer,
+            contents
+        );
+        unsafe {
+            self.device.cmd_next_subpass(command_buffer, contents);
+        }
+    }
+
     pub fn end_render_pass(&self, command_buffer: vk::CommandBuffer) {
-        trace!(
+        trace_vk!(
             "Calling end_render_pass for command buffer: {:?}",
             command_buffer
         );
@@ -391,6 +876,514 @@ impl LogicalDevice {
         }
     }
 
+    /// Begins rendering into the attachments of `rendering_info`, without a render pass. The
+    /// device must have been created with [`DeviceExtension::KhrDynamicRendering`].
+    pub fn cmd_begin_rendering(&self, command_buffer: vk::CommandBuffer, rendering_info: &vk::RenderingInfo) {
+        trace_vk!(
+            "Calling cmd_begin_rendering for command buffer: {:?} with info: {:?}",
+            command_buffer,
+            rendering_info
+        );
+        unsafe {
+            self.device.cmd_begin_rendering_khr(command_buffer, rendering_info);
+        }
+    }
+
+    /// Ends the rendering begun by [`LogicalDevice::cmd_begin_rendering`].
+    pub fn cmd_end_rendering(&self, command_buffer: vk::CommandBuffer) {
+        trace_vk!(
+            "Calling cmd_end_rendering for command buffer: {:?}",
+            command_buffer
+        );
+        unsafe {
+            self.device.cmd_end_rendering_khr(command_buffer);
+        }
+    }
+
+    pub fn create_image(&self, create_info: &vk::ImageCreateInfo) -> anyhow::Result<vk::Image> {
+        trace_vk!("Calling create_image with info: {:?}", create_info);
+        unsafe {
+            self.device
+                .create_image(create_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to create image: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::Image, 1))
+        }
+    }
+
+    pub fn resolve_image(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        (src, src_layout): (vk::Image, vk::ImageLayout),
+        (dst, dst_layout): (vk::Image, vk::ImageLayout),
+        regions: &[vk::ImageResolve],
+    ) {
+        trace_vk!(
+            "Calling resolve_image for command buffer: {:?} from {:?} in layout {:?} to {:?} in layout {:?} with regions: {:?}",
+            command_buffer,
+            src,
+            src_layout,
+            dst,
+            dst_layout,
+            regions
+        );
+        unsafe {
+            self.device
+                .cmd_resolve_image(command_buffer, src, src_layout, dst, dst_layout, regions);
+        }
+    }
+
+    pub fn create_sampler(&self, create_info: &vk::SamplerCreateInfo) -> anyhow::Result<vk::Sampler> {
+        trace_vk!("Calling create_sampler with info: {:?}", create_info);
+        unsafe {
+            self.device
+                .create_sampler(create_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to create sampler: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::Sampler, 1))
+        }
+    }
+
+    pub fn destroy_sampler(&self, sampler: vk::Sampler) {
+        trace_vk!("Calling destroy_sampler for sampler: {:?}", sampler);
+        unsafe {
+            self.device.destroy_sampler(sampler, None);
+        }
+        self.untrack(ResourceKind::Sampler, sampler);
+    }
+
+    pub fn destroy_image(&self, image: vk::Image) {
+        trace_vk!("Calling destroy_image for image: {:?}", image);
+        unsafe {
+            self.device.destroy_image(image, None);
+        }
+        self.untrack(ResourceKind::Image, image);
+    }
+
+    pub fn get_image_memory_requirements(&self, image: vk::Image) -> vk::MemoryRequirements {
+        trace_vk!("Calling get_image_memory_requirements for image: {:?}", image);
+        unsafe { self.device.get_image_memory_requirements(image) }
+    }
+
+    pub fn allocate_memory(
+        &self,
+        allocate_info: &vk::MemoryAllocateInfo,
+    ) -> anyhow::Result<vk::DeviceMemory> {
+        trace_vk!("Calling allocate_memory with info: {:?}", allocate_info);
+        unsafe {
+            self.device
+                .allocate_memory(allocate_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to allocate memory: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::Memory, 1))
+        }
+    }
+
+    pub fn free_memory(&self, memory: vk::DeviceMemory) {
+        trace_vk!("Calling free_memory for memory: {:?}", memory);
+        unsafe {
+            self.device.free_memory(memory, None);
+        }
+        self.untrack(ResourceKind::Memory, memory);
+    }
+
+    pub fn bind_image_memory(
+        &self,
+        image: vk::Image,
+        memory: vk::DeviceMemory,
+        offset: vk::DeviceSize,
+    ) -> anyhow::Result<()> {
+        trace_vk!(
+            "Calling bind_image_memory for image: {:?} with memory: {:?} at offset: {}",
+            image,
+            memory,
+            offset
+        );
+        unsafe {
+            self.device
+                .bind_image_memory(image, memory, offset)
+                .map_err(|e| anyhow::anyhow!("Failed to bind image memory: {}", e))
+        }
+    }
+
+    pub fn create_semaphore(
+        &self,
+        create_info: &vk::SemaphoreCreateInfo,
+    ) -> anyhow::Result<vk::Semaphore> {
+        trace_vk!("Calling create_semaphore with info: {:?}", create_info);
+        unsafe {
+            self.device
+                .create_semaphore(create_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to create semaphore: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::Semaphore, 1))
+        }
+    }
+
+    pub fn destroy_semaphore(&self, semaphore: vk::Semaphore) {
+        trace_vk!("Calling destroy_semaphore for semaphore: {:?}", semaphore);
+        unsafe {
+            self.device.destroy_semaphore(semaphore, None);
+        }
+        self.untrack(ResourceKind::Semaphore, semaphore);
+    }
+
+    pub fn create_fence(&self, create_info: &vk::FenceCreateInfo) -> anyhow::Result<vk::Fence> {
+        trace_vk!("Calling create_fence with info: {:?}", create_info);
+        unsafe {
+            self.device
+                .create_fence(create_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to create fence: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::Fence, 1))
+        }
+    }
+
+    pub fn destroy_fence(&self, fence: vk::Fence) {
+        trace_vk!("Calling destroy_fence for fence: {:?}", fence);
+        unsafe {
+            self.device.destroy_fence(fence, None);
+        }
+        self.untrack(ResourceKind::Fence, fence);
+    }
+
+    /// Waits for the `fences` to be signaled, at most for `timeout`.
+    ///
+    /// # Errors
+    /// [`BurstError::GpuTimeout`] if the fences weren't signaled in time.
+    pub fn wait_for_fences(
+        &self,
+        fences: &[vk::Fence],
+        wait_all: bool,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        trace_vk!(
+            "Calling wait_for_fences for fences: {:?} with wait all: {} and timeout: {:?}",
+            fences,
+            wait_all,
+            timeout
+        );
+        let success_code = unsafe {
+            self.device
+                .wait_for_fences(fences, wait_all, duration_to_nanos(timeout))
+                .map_err(|e| anyhow::anyhow!("Failed to wait for fences: {}", e))?
+        };
+        if success_code == vk::SuccessCode::TIMEOUT {
+            return Err(BurstError::GpuTimeout(timeout).into());
+        }
+        Ok(())
+    }
+
+    /// Whether `fence` is signaled, without waiting for it.
+    pub fn get_fence_status(&self, fence: vk::Fence) -> anyhow::Result<bool> {
+        trace_vk!("Calling get_fence_status for fence: {:?}", fence);
+        let success_code = unsafe {
+            self.device
+                .get_fence_status(fence)
+                .map_err(|e| anyhow::anyhow!("Failed to get fence status: {}", e))?
+        };
+        Ok(success_code == vk::SuccessCode::SUCCESS)
+    }
+
+    pub fn reset_fences(&self, fences: &[vk::Fence]) -> anyhow::Result<()> {
+        trace_vk!("Calling reset_fences for fences: {:?}", fences);
+        unsafe {
+            self.device
+                .reset_fences(fences)
+                .map_err(|e| anyhow::anyhow!("Failed to reset fences: {}", e))
+        }
+    }
+
+    /// Acquires the next presentable image of the swapchain, waiting at most for `timeout`.
+    ///
+    /// # Returns
+    /// The index of the acquired image, and whether the swapchain is suboptimal, see
+    /// [`RenderResult::Suboptimal`]. Otherwise the outcome, which can be
+    /// [`RenderResult::OutOfDate`], or [`RenderResult::Timeout`] if no image became available in
+    /// time.
+    pub fn acquire_next_image_khr(
+        &self,
+        swapchain: SwapchainKHR,
+        timeout: Duration,
+        semaphore: vk::Semaphore,
+        fence: vk::Fence,
+    ) -> Result<(u32, bool), RenderResult> {
+        trace_vk!(
+            "Calling acquire_next_image_khr for swapchain: {:?} with timeout: {:?}, semaphore: {:?} and fence: {:?}",
+            swapchain,
+            timeout,
+            semaphore,
+            fence
+        );
+        let result = unsafe {
+            self.device
+                .acquire_next_image_khr(swapchain, duration_to_nanos(timeout), semaphore, fence)
+        };
+        match result {
+            Ok((index, code)) => match RenderResult::from(code) {
+                RenderResult::Success => Ok((index, false)),
+                RenderResult::Suboptimal => Ok((index, true)),
+                result => Err(result),
+            },
+            Err(code) => Err(code.into()),
+        }
+    }
+
+    pub fn queue_submit(
+        &self,
+        queue: Queue,
+        submits: &[impl Cast<Target = vk::SubmitInfo> + std::fmt::Debug],
+        fence: vk::Fence,
+    ) -> anyhow::Result<()> {
+        trace_vk!(
+            "Calling queue_submit for queue: {:?} with submits: {:?} and fence: {:?}",
+            queue,
+            submits,
+            fence
+        );
+        unsafe {
+            self.device
+                .queue_submit(queue, submits, fence)
+                .map_err(|e| anyhow::anyhow!("Failed to submit to queue: {}", e))
+        }
+    }
+
+    /// Same as [`LogicalDevice::queue_submit`], for the render loop, which tells a lost device
+    /// apart from the other errors.
+    pub fn queue_submit_frame(
+        &self,
+        queue: Queue,
+        submits: &[impl Cast<Target = vk::SubmitInfo> + std::fmt::Debug],
+        fence: vk::Fence,
+    ) -> RenderResult {
+        trace_vk!(
+            "Calling queue_submit for queue: {:?} with submits: {:?} and fence: {:?}",
+            queue,
+            submits,
+            fence
+        );
+        match unsafe { self.device.queue_submit(queue, submits, fence) } {
+            Ok(_) => RenderResult::Success,
+            Err(code) => code.into(),
+        }
+    }
+
+    /// Queues an image for presentation.
+    ///
+    /// # Returns
+    /// The outcome, which can be [`RenderResult::Suboptimal`] or [`RenderResult::OutOfDate`],
+    /// so callers can recreate the swapchain.
+    pub fn queue_present_khr(&self, queue: Queue, present_info: &vk::PresentInfoKHR) -> RenderResult {
+        trace_vk!(
+            "Calling queue_present_khr for queue: {:?} with info: {:?}",
+            queue,
+            present_info
+        );
+        match unsafe { self.device.queue_present_khr(queue, present_info) } {
+            Ok(code) => code.into(),
+            Err(code) => code.into(),
+        }
+    }
+
+    pub fn device_wait_idle(&self) -> anyhow::Result<()> {
+        trace_vk!("Calling device_wait_idle");
+        unsafe {
+            self.device
+                .device_wait_idle()
+                .map_err(|e| anyhow::anyhow!("Failed to wait for device idle: {}", e))
+        }
+    }
+
+    pub fn create_descriptor_set_layout(
+        &self,
+        create_info: &vk::DescriptorSetLayoutCreateInfo,
+    ) -> anyhow::Result<vk::DescriptorSetLayout> {
+        trace_vk!(
+            "Calling create_descriptor_set_layout with info: {:?}",
+            create_info
+        );
+        unsafe {
+            self.device
+                .create_descriptor_set_layout(create_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to create descriptor set layout: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::DescriptorSetLayout, 1))
+        }
+    }
+
+    pub fn destroy_descriptor_set_layout(&self, layout: vk::DescriptorSetLayout) {
+        trace_vk!(
+            "Calling destroy_descriptor_set_layout for layout: {:?}",
+            layout
+        );
+        unsafe {
+            self.device.destroy_descriptor_set_layout(layout, None);
+        }
+        self.untrack(ResourceKind::DescriptorSetLayout, layout);
+    }
+
+    pub fn create_descriptor_pool(
+        &self,
+        create_info: &vk::DescriptorPoolCreateInfo,
+    ) -> anyhow::Result<vk::DescriptorPool> {
+        trace_vk!("Calling create_descriptor_pool with info: {:?}", create_info);
+        unsafe {
+            self.device
+                .create_descriptor_pool(create_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to create descriptor pool: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::DescriptorPool, 1))
+        }
+    }
+
+    pub fn destroy_descriptor_pool(&self, pool: vk::DescriptorPool) {
+        trace_vk!("Calling destroy_descriptor_pool for pool: {:?}", pool);
+        unsafe {
+            self.device.destroy_descriptor_pool(pool, None);
+        }
+        self.untrack(ResourceKind::DescriptorPool, pool);
+    }
+
+    pub fn allocate_descriptor_sets(
+        &self,
+        allocate_info: &vk::DescriptorSetAllocateInfo,
+    ) -> anyhow::Result<Vec<vk::DescriptorSet>> {
+        trace_vk!(
+            "Calling allocate_descriptor_sets with info: {:?}",
+            allocate_info
+        );
+        unsafe {
+            self.device
+                .allocate_descriptor_sets(allocate_info)
+                .map_err(|e| anyhow::anyhow!("Failed to allocate descriptor sets: {}", e))
+        }
+    }
+
+    pub fn bind_descriptor_sets(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        pipeline_bind_point: vk::PipelineBindPoint,
+        layout: vk::PipelineLayout,
+        first_set: u32,
+        descriptor_sets: &[vk::DescriptorSet],
+    ) {
+        trace_vk!(
+            "Calling bind_descriptor_sets for command buffer: {:?} with sets: {:?} from set {} at bind point: {:?}",
+            command_buffer,
+            descriptor_sets,
+            first_set,
+            pipeline_bind_point
+        );
+        unsafe {
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                pipeline_bind_point,
+                layout,
+                first_set,
+                descriptor_sets,
+                &[],
+            );
+        }
+    }
+
+    pub fn create_buffer(&self, create_info: &vk::BufferCreateInfo) -> anyhow::Result<vk::Buffer> {
+        trace_vk!("Calling create_buffer with info: {:?}", create_info);
+        unsafe {
+            self.device
+                .create_buffer(create_info, None)
+                .map_err(|e| anyhow::anyhow!("Failed to create buffer: {}", e))
+                .inspect(|_| self.resources.created(ResourceKind::Buffer, 1))
+        }
+    }
+
+    pub fn destroy_buffer(&self, buffer: vk::Buffer) {
+        trace_vk!("Calling destroy_buffer for buffer: {:?}", buffer);
+        unsafe {
+            self.device.destroy_buffer(buffer, None);
+        }
+        self.untrack(ResourceKind::Buffer, buffer);
+    }
+
+    /// The address of the buffer for shaders, the device must have been created with the
+    /// `bufferDeviceAddress` feature.
+    pub fn get_buffer_device_address(&self, buffer: vk::Buffer) -> vk::DeviceAddress {
+        trace_vk!("Calling get_buffer_device_address for buffer: {:?}", buffer);
+        let info = vk::BufferDeviceAddressInfo::builder().buffer(buffer);
+        // The core function is only loaded on Vulkan 1.2 devices.
+        unsafe {
+            if self.extensions.contains(&DeviceExtension::KhrBufferDeviceAddress) {
+                self.device.get_buffer_device_address_khr(&info)
+            } else {
+                self.device.get_buffer_device_address(&info)
+            }
+        }
+    }
+
+    pub fn get_buffer_memory_requirements(&self, buffer: vk::Buffer) -> vk::MemoryRequirements {
+        trace_vk!("Calling get_buffer_memory_requirements for buffer: {:?}", buffer);
+        unsafe { self.device.get_buffer_memory_requirements(buffer) }
+    }
+
+    pub fn bind_buffer_memory(
+        &self,
+        buffer: vk::Buffer,
+        memory: vk::DeviceMemory,
+        offset: vk::DeviceSize,
+    ) -> anyhow::Result<()> {
+        trace_vk!(
+            "Calling bind_buffer_memory for buffer: {:?} with memory: {:?} at offset: {}",
+            buffer,
+            memory,
+            offset
+        );
+        unsafe {
+            self.device
+                .bind_buffer_memory(buffer, memory, offset)
+                .map_err(|e| anyhow::anyhow!("Failed to bind buffer memory: {}", e))
+        }
+    }
+
+    pub fn map_memory(
+        &self,
+        memory: vk::DeviceMemory,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> anyhow::Result<*mut std::ffi::c_void> {
+        trace_vk!(
+            "Calling map_memory for memory: {:?} at offset: {} with size: {}",
+            memory,
+            offset,
+            size
+        );
+        unsafe {
+            self.device
+                .map_memory(memory, offset, size, vk::MemoryMapFlags::empty())
+                .map_err(|e| anyhow::anyhow!("Failed to map memory: {}", e))
+        }
+    }
+
+    pub fn unmap_memory(&self, memory: vk::DeviceMemory) {
+        trace_vk!("Calling unmap_memory for memory: {:?}", memory);
+        unsafe {
+            self.device.unmap_memory(memory);
+        }
+    }
+
+    /// Makes host writes to mapped non-coherent memory visible to the device.
+    /// The ranges must be aligned to `nonCoherentAtomSize`, or reach the end of the allocation.
+    pub fn flush_mapped_memory_ranges(&self, ranges: &[vk::MappedMemoryRange]) -> anyhow::Result<()> {
+        trace_vk!("Calling flush_mapped_memory_ranges with ranges: {:?}", ranges);
+        unsafe {
+            self.device
+                .flush_mapped_memory_ranges(ranges)
+                .map_err(|e| anyhow::anyhow!("Failed to flush mapped memory ranges: {}", e))
+        }
+    }
+
+    pub fn update_descriptor_sets(
+        &self,
+        writes: &[impl Cast<Target = vk::WriteDescriptorSet> + std::fmt::Debug],
+    ) {
+        trace_vk!("Calling update_descriptor_sets with writes: {:?}", writes);
+        unsafe {
+            self.device
+                .update_descriptor_sets(writes, &[] as &[vk::CopyDescriptorSet]);
+        }
+    }
+
     /// Returns a reference to the underlying Vulkan [`Device`].
     ///
     /// # Example
@@ -402,10 +1395,35 @@ impl LogicalDevice {
         &self.device
     }
 
+    pub fn get_enabled_extensions(&self) -> &[DeviceExtension] {
+        &self.extensions
+    }
+
     pub fn get_queues(&self) -> &Queues {
         &self.queues
     }
 
+    /// The queue family index of the graphics queues, e.g. to share resources with an external
+    /// library.
+    pub fn graphics_family_index(&self) -> u32 {
+        self.queues.graphics_family_index
+    }
+
+    /// The queue family index of the present queues.
+    pub fn present_family_index(&self) -> u32 {
+        self.queues.present_family_index
+    }
+
+    /// The queue family index of the compute queues, `None` if no compute queue was requested.
+    pub fn compute_family_index(&self) -> Option<u32> {
+        (!self.queues.compute.is_empty()).then_some(self.queues.compute_family_index)
+    }
+
+    /// The queue family index of the transfer queues, `None` if no transfer queue was requested.
+    pub fn transfer_family_index(&self) -> Option<u32> {
+        (!self.queues.transfer.is_empty()).then_some(self.queues.transfer_family_index)
+    }
+
     /// Destroys this logical device. Automatically frees all queues it owns.
     ///
     /// # Safety
@@ -417,3 +1435,8 @@ impl LogicalDevice {
         }
     }
 }
+
+/// Converts a timeout to the nanoseconds Vulkan expects, saturating at `u64::MAX` (no timeout).
+fn duration_to_nanos(timeout: Duration) -> u64 {
+    u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX)
+}
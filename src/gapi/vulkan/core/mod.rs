@@ -1,7 +1,11 @@
 pub mod debug;
+pub mod device_selection;
 pub mod entry;
 pub mod instance;
 pub mod logical_device;
+pub mod quality;
 pub mod queues;
 pub mod real_device;
-pub mod surface;
\ No newline at end of file
+pub mod resource_tracker;
+pub mod surface;
+pub mod workarounds;
\ No newline at end of file
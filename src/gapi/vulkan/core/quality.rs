@@ -0,0 +1,50 @@
+use anyhow::bail;
+use log::{debug, warn};
+use std::fmt::Debug;
+
+/// What happens when a quality setting asks for more than the device provides.
+///
+/// It governs:
+/// - the MSAA sample count, see `AppConfig::msaa_samples`, lowered to the highest supported
+///   count below it.
+/// - the sampler anisotropy, see [`SamplerDesc::anisotropy`](crate::gapi::vulkan::memory::sampler_cache::SamplerDesc),
+///   lowered to the device limit, or disabled without the `samplerAnisotropy` feature.
+/// - the line width, see `AppConfig::line_width`, clamped to the range of the device, or 1
+///   without the `wideLines` feature.
+///
+/// The default is [`QualityDowngradePolicy::ClampWithWarning`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QualityDowngradePolicy {
+    /// Silently uses the closest supported setting.
+    Clamp,
+    /// Fails, for apps that would rather not start than look different than designed.
+    Error,
+    /// Uses the closest supported setting, with a warning.
+    #[default]
+    ClampWithWarning,
+}
+
+impl QualityDowngradePolicy {
+    /// The value of the `setting` to use when `requested`, and `supported` is the closest one the
+    /// device provides.
+    ///
+    /// # Errors
+    /// If they differ and the policy is [`QualityDowngradePolicy::Error`].
+    pub fn apply<T: Debug + PartialEq>(self, setting: &str, requested: T, supported: T) -> anyhow::Result<T> {
+        if requested == supported {
+            return Ok(requested);
+        }
+        match self {
+            QualityDowngradePolicy::Clamp => {
+                debug!("{setting} {requested:?} is not supported, using {supported:?}.");
+            }
+            QualityDowngradePolicy::Error => {
+                bail!("{setting} {requested:?} is not supported by the device, at most {supported:?} is.");
+            }
+            QualityDowngradePolicy::ClampWithWarning => {
+                warn!("{setting} {requested:?} is not supported, using {supported:?}.");
+            }
+        }
+        Ok(supported)
+    }
+}
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
-use anyhow::bail;
-use log::{info, trace};
+use anyhow::{bail, Context};
+use log::{info, trace, warn};
 use vulkanalia::{vk, Device};
 use vulkanalia::vk::{DeviceV1_0, HasBuilder, Queue};
 use crate::gapi::vulkan::core::real_device::RealDevice;
@@ -99,6 +99,7 @@ pub struct Queues{
     pub compute: Vec<Queue>,
     pub compute_family_index: u32,
     pub transfer: Vec<Queue>,
+    pub transfer_family_index: u32,
 }
 
 
@@ -111,6 +112,63 @@ impl Queues{
         Self::extract_queues(device, &families)
     }
 
+    /// The queue frames are submitted to.
+    ///
+    /// # Errors
+    /// If the device has no graphics queue, e.g. none was requested.
+    pub fn graphics_queue(&self) -> anyhow::Result<Queue> {
+        Self::first(&self.graphics, "graphics")
+    }
+
+    /// The queue frames are presented with. It is the graphics queue when its family can present,
+    /// so the present is ordered after the rendering on a single queue, and a queue of another
+    /// family otherwise, see [`Queues::is_unified_present`].
+    ///
+    /// # Errors
+    /// If the device has no present queue, e.g. none can present to the surface.
+    pub fn present_queue(&self) -> anyhow::Result<Queue> {
+        if self.is_unified_present() {
+            self.graphics_queue()
+        } else {
+            Self::first(&self.present, "present")
+        }
+    }
+
+    /// The queue compute work is submitted to.
+    ///
+    /// # Errors
+    /// If the device has no compute queue, e.g. it wasn't requested.
+    pub fn compute_queue(&self) -> anyhow::Result<Queue> {
+        Self::first(&self.compute, "compute")
+    }
+
+    /// The queue uploads are submitted to.
+    ///
+    /// # Errors
+    /// If the device has no transfer queue, e.g. it wasn't requested.
+    pub fn transfer_queue(&self) -> anyhow::Result<Queue> {
+        Self::first(&self.transfer, "transfer")
+    }
+
+    /// The first of the `queues` of the `kind`, instead of panicking when a misconfigured request
+    /// left them empty.
+    fn first(queues: &[Queue], kind: &str) -> anyhow::Result<Queue> {
+        queues
+            .first()
+            .copied()
+            .with_context(|| format!("The device has no {kind} queue, check the requested queue families."))
+    }
+
+    /// Whether the frames are presented from the queue family they are rendered on. Otherwise the
+    /// graphics family can't present to the surface, and the swapchain images are shared between
+    /// the two families.
+    ///
+    /// The families are compared, not the queue handles: two queues of the same family are
+    /// different handles, but need no sharing between them.
+    pub fn is_unified_present(&self) -> bool {
+        self.graphics_family_index == self.present_family_index
+    }
+
     fn extract_queues(
         device: &Device,
         resolved_families: &[QueueFamily],
@@ -129,16 +187,18 @@ impl Queues{
 
         for res in resolved_families {
             let offset = family_offsets.entry(res.family_index).or_insert(0);
-            if res.capabilities.contains(&QueueCapability::Graphics) {
+            // When several families have a capability, the index is the one of the first queue
+            // of that capability, which is the one used by default.
+            if res.capabilities.contains(&QueueCapability::Graphics) && graphics.is_empty() {
                 graphics_family_index = res.family_index;
             }
-            if res.capabilities.contains(&QueueCapability::Compute) {
+            if res.capabilities.contains(&QueueCapability::Compute) && compute.is_empty() {
                 compute_family_index = res.family_index;
             }
-            if res.capabilities.contains(&QueueCapability::Transfer) {
+            if res.capabilities.contains(&QueueCapability::Transfer) && transfer.is_empty() {
                 transfer_family_index = res.family_index;
             }
-            if res.allows_present {
+            if res.allows_present && present.is_empty() {
                 present_family_index = res.family_index;
             }
             for i in 0..res.count {
@@ -171,30 +231,61 @@ impl Queues{
             compute,
             compute_family_index,
             transfer,
+            transfer_family_index,
         })
     }
 
-    fn extract_family_queues(real_device: &RealDevice, surface: &Surface) -> Vec<QueueFamily> {
+    /// Lists the queue families of the `real_device`, with their present support on `surface`.
+    /// Without a surface, no family allows present.
+    ///
+    /// # Errors
+    /// If querying the present support fails. It isn't treated as "no present support", as that
+    /// could hide a real problem and end up picking a family that can't present.
+    fn extract_family_queues(
+        real_device: &RealDevice,
+        surface: Option<&Surface>,
+    ) -> anyhow::Result<Vec<QueueFamily>> {
         real_device
             .get_queue_families_properties()
             .iter()
             .enumerate()
-            .filter_map(|(family_index, family)| {
+            .map(|(family_index, family)| {
                 let family_index = family_index as u32;
                 let capabilities = QueueCapability::from_flags(family.queue_flags);
-                let allows_present = real_device
-                    .supports_surface(family_index, surface)
-                    .unwrap_or(false);
+                let allows_present = match surface {
+                    Some(surface) => real_device
+                        .supports_surface(family_index, surface)
+                        .with_context(|| {
+                            format!("Failed to query present support of queue family {family_index}")
+                        })?,
+                    None => false,
+                };
                 let count = family.queue_count;
-                Some(QueueFamily {
+                Ok(QueueFamily {
                     family_index,
                     capabilities,
                     allows_present,
                     count,
                 })
             })
-            .collect::<Vec<_>>()
-            .into()
+            .collect()
+    }
+
+    /// Requests one queue of every queue family of the `real_device`, whatever its capabilities.
+    ///
+    /// Unlike [`Queues::resolve_queue_requests`], this gives a handle to every kind of queue the
+    /// device exposes, e.g. to schedule async compute or transfers by hand. The downside is that
+    /// the driver must allocate all of them, even those that are never used.
+    pub fn resolve_all_families(
+        real_device: &RealDevice,
+        surface: &Surface,
+    ) -> anyhow::Result<Vec<QueueFamily>> {
+        info!("Requesting one queue of every queue family...");
+        Ok(Self::extract_family_queues(real_device, Some(surface))?
+            .into_iter()
+            .filter(|family| family.count > 0)
+            .map(|family| QueueFamily { count: 1, ..family })
+            .collect())
     }
 
     /// Creates a minimal set of [`vk::DeviceQueueCreateInfo`] objects for the discovered family indices.
@@ -226,6 +317,9 @@ impl Queues{
     ///    and present support (if `require_present` is `true`).
     /// 3. Returns a list of [`QueueFamily`] records describing which family index each request ended up using.
     ///
+    /// See [`Queues::resolve_requests_in`] for what happens when no family both has the flags
+    /// and presents.
+    ///
     /// # Errors
     /// If any queue request cannot be satisfied by the current device (e.g., no family supports it),
     /// returns an error.
@@ -235,7 +329,49 @@ impl Queues{
         requests: &[QueueRequest],
     ) -> anyhow::Result<Vec<QueueFamily>> {
         info!("Finding suitable queue families for requested queues...");
+        let families = Self::extract_family_queues(real_device, Some(surface))?;
+        Self::resolve_requests_in(&families, requests)
+    }
+
+    /// Like [`Queues::resolve_queue_requests`], for a device without a surface, e.g. for
+    /// headless compute.
+    ///
+    /// # Errors
+    /// If a request requires present support, or cannot be satisfied by the device.
+    pub fn resolve_headless_requests(
+        real_device: &RealDevice,
+        requests: &[QueueRequest],
+    ) -> anyhow::Result<Vec<QueueFamily>> {
+        if let Some(request) = requests.iter().find(|request| request.require_present) {
+            bail!("Queue request {request:?} requires present support, but there is no surface.");
+        }
+        info!("Finding suitable queue families for requested headless queues...");
+        let families = Self::extract_family_queues(real_device, None)?;
+        Self::resolve_requests_in(&families, requests)
+    }
+
+    /// Resolves the `requests` against the given `families`, as listed by the device, see
+    /// [`Queues::resolve_queue_requests`].
+    ///
+    /// Some drivers have no family that does both graphics and present. A request needing both
+    /// is then split: its queues come from the first family with its flags, and one more queue
+    /// from the first family that can present, only used to present. The swapchain images are
+    /// then shared between the two families, see `Swapchain::get_sharing_mode`.
+    ///
+    /// A family may have fewer queues than requested, and getting a queue past its count returns
+    /// an invalid handle. The requested counts are therefore clamped to the queues the family has
+    /// left, see [`Queues::claim_queues`].
+    ///
+    /// # Errors
+    /// If a request has flags no family supports, or requires present support and no family can
+    /// present to the surface, or its family has no queue left.
+    pub(crate) fn resolve_requests_in(
+        families: &[QueueFamily],
+        requests: &[QueueRequest],
+    ) -> anyhow::Result<Vec<QueueFamily>> {
         let mut results = Vec::with_capacity(requests.len());
+        // How many queues of each family the previous requests already took.
+        let mut claimed = HashMap::<u32, u32>::new();
         // We need to fulfill all requests of families for the device
         for request in requests {
             info!(
@@ -243,48 +379,220 @@ impl Queues{
                 request.capabilities
             );
             let required_flags = &request.capabilities;
-            let properties = real_device.get_queue_families_properties();
-
-            // Now we go over the queue families of the device and try to find one that matches
-            for (family_index, props) in properties.iter().enumerate() {
-                // Make the index a u32 from usize to match Vulkan's expectations.
-                let family_index = family_index as u32;
-                let supports_present =
-                    real_device.supports_surface(family_index, surface)?;
-                // If we require present support, but this family doesn't support it, skip it.
-                if request.require_present && !supports_present {
-                    continue;
-                };
-
-                let supports_flags = required_flags
+            let supports_flags = |family: &&QueueFamily| {
+                required_flags
                     .iter()
-                    .all(|&flag| props.queue_flags.contains(flag.into()));
-
-                // If the family doesn't support all required flags, skip it.
-                if !supports_flags {
-                    continue;
-                }
+                    .all(|flag| family.capabilities.contains(flag))
+            };
 
-                // The first one that matches our requirements is the one we store to then use
+            // The first one that matches our requirements is the one we store to then use
+            let shared = families
+                .iter()
+                .filter(supports_flags)
+                .find(|family| family.allows_present || !request.require_present);
+            if let Some(family) = shared {
                 results.push(QueueFamily {
-                    family_index,
-                    count: request.count,
+                    family_index: family.family_index,
+                    count: Self::claim_queues(&mut claimed, family, request.count)?,
                     capabilities: required_flags.clone(),
-                    allows_present: supports_present,
+                    allows_present: family.allows_present,
                 });
-                // Then we stop searching a queue for this request, we go to the next one.
-                break;
+                continue;
             }
 
-            if results.is_empty() {
+            let Some(flags_family) = families.iter().find(supports_flags) else {
                 bail!(
                     "No suitable queue family found for {:#?}",
                     request.capabilities
                 );
-            }
+            };
+            // Only reached when present support is required, but no family with the flags has it.
+            let Some(present_family) = families.iter().find(|family| family.allows_present) else {
+                bail!("No queue family can present to the surface.");
+            };
+            info!(
+                "No queue family supports both {:?} and present, splitting them: family {} for {:?}, family {} for present.",
+                request.capabilities, flags_family.family_index, request.capabilities, present_family.family_index
+            );
+            results.push(QueueFamily {
+                family_index: flags_family.family_index,
+                count: Self::claim_queues(&mut claimed, flags_family, request.count)?,
+                capabilities: required_flags.clone(),
+                allows_present: false,
+            });
+            results.push(QueueFamily {
+                family_index: present_family.family_index,
+                count: Self::claim_queues(&mut claimed, present_family, 1)?,
+                capabilities: vec![],
+                allows_present: true,
+            });
         }
 
         Ok(results)
     }
 
-}
\ No newline at end of file
+    /// Takes up to `requested` queues of `family`, of those not `claimed` by previous requests,
+    /// and returns how many were taken.
+    ///
+    /// # Errors
+    /// If the family has no queue left.
+    fn claim_queues(claimed: &mut HashMap<u32, u32>, family: &QueueFamily, requested: u32) -> anyhow::Result<u32> {
+        let claimed = claimed.entry(family.family_index).or_insert(0);
+        let available = family.count - *claimed;
+        if available == 0 {
+            bail!(
+                "Queue family {} has no queue left, all of its {} queues are already requested.",
+                family.family_index,
+                family.count
+            );
+        }
+        let count = requested.min(available);
+        if count < requested {
+            warn!(
+                "Requested {requested} queues of family {}, but only {available} are available, clamping to {count}.",
+                family.family_index
+            );
+        }
+        *claimed += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vulkanalia::vk::Handle;
+    use super::QueueCapability::{Compute, Graphics, Transfer};
+
+    fn family(family_index: u32, count: u32, capabilities: &[QueueCapability], allows_present: bool) -> QueueFamily {
+        QueueFamily {
+            family_index,
+            count,
+            capabilities: capabilities.to_vec(),
+            allows_present,
+        }
+    }
+
+    fn request(capabilities: &[QueueCapability], require_present: bool, count: u32) -> QueueRequest {
+        QueueRequest {
+            capabilities: capabilities.to_vec(),
+            require_present,
+            count,
+        }
+    }
+
+    /// The family index, queue count and present support of each resolved family.
+    fn summary(families: &[QueueFamily]) -> Vec<(u32, u32, bool)> {
+        families
+            .iter()
+            .map(|family| (family.family_index, family.count, family.allows_present))
+            .collect()
+    }
+
+    #[test]
+    fn missing_queues_are_errors_instead_of_panics() {
+        // The queues of a compute-only device, see `LogicalDevice::new_compute_only`.
+        let queues = Queues {
+            graphics: vec![],
+            graphics_family_index: 0,
+            present: vec![],
+            present_family_index: 0,
+            compute: vec![Queue::null()],
+            compute_family_index: 1,
+            transfer: vec![],
+            transfer_family_index: 0,
+        };
+        assert!(queues.graphics_queue().is_err());
+        assert!(queues.present_queue().is_err());
+        assert!(queues.transfer_queue().is_err());
+        assert_eq!(queues.compute_queue().unwrap(), Queue::null());
+    }
+
+    #[test]
+    fn resolves_each_request_to_the_first_matching_family() {
+        let families = [
+            family(0, 16, &[Graphics, Compute, Transfer], true),
+            family(1, 2, &[Compute, Transfer], false),
+            family(2, 1, &[Transfer], false),
+        ];
+        let resolved = Queues::resolve_requests_in(
+            &families,
+            &[request(&[Graphics], true, 1), request(&[Compute], false, 1), request(&[Transfer], false, 1)],
+        )
+        .unwrap();
+        assert_eq!(summary(&resolved), [(0, 1, true), (0, 1, true), (0, 1, true)]);
+        assert_eq!(resolved[1].capabilities, [Compute]);
+    }
+
+    #[test]
+    fn resolves_graphics_and_present_of_different_families() {
+        // Only family 0 does graphics, and only family 1 presents.
+        let families = [family(0, 1, &[Graphics, Compute, Transfer], false), family(1, 1, &[Transfer], true)];
+        let resolved = Queues::resolve_requests_in(&families, &[request(&[Graphics], true, 1)]).unwrap();
+        assert_eq!(summary(&resolved), [(0, 1, false), (1, 1, true)]);
+        assert_eq!(resolved[0].capabilities, [Graphics]);
+        assert!(resolved[1].capabilities.is_empty());
+    }
+
+    #[test]
+    fn clamps_the_claimed_queues_to_those_left_in_the_family() {
+        let (small, other) = (family(0, 2, &[Graphics], false), family(1, 4, &[Compute], false));
+        let mut claimed = HashMap::new();
+        assert_eq!(Queues::claim_queues(&mut claimed, &small, 4).unwrap(), 2);
+        assert!(Queues::claim_queues(&mut claimed, &small, 1).is_err());
+        // The other families keep all of their queues.
+        assert_eq!(Queues::claim_queues(&mut claimed, &other, 3).unwrap(), 3);
+        assert_eq!(Queues::claim_queues(&mut claimed, &other, 3).unwrap(), 1);
+        assert_eq!(claimed, HashMap::from([(0, 2), (1, 4)]));
+
+        // Through the requests, a second request of an exhausted family is an error.
+        let exhausted = Queues::resolve_requests_in(
+            &[small],
+            &[request(&[Graphics], false, 4), request(&[Graphics], false, 1)],
+        );
+        assert!(exhausted.is_err());
+    }
+
+    #[test]
+    fn rejects_requests_no_family_satisfies() {
+        let families = [family(0, 1, &[Compute, Transfer], false), family(1, 1, &[Graphics], false)];
+        let unsupported = Queues::resolve_requests_in(&families[..1], &[request(&[Graphics], false, 1)]);
+        assert!(unsupported.is_err());
+        let no_present = Queues::resolve_requests_in(&families, &[request(&[Graphics], true, 1)]);
+        assert!(no_present.is_err());
+        assert!(Queues::resolve_requests_in(&[], &[request(&[Transfer], false, 1)]).is_err());
+    }
+
+    #[test]
+    fn presents_from_the_present_family_when_graphics_cannot() {
+        let families = [family(0, 2, &[Graphics, Compute, Transfer], false), family(1, 1, &[Transfer], true)];
+        let resolved = Queues::resolve_requests_in(
+            &families,
+            &[request(&[Graphics], true, 1), request(&[Transfer], false, 1)],
+        )
+        .unwrap();
+        // The present queue only takes the single queue of family 1, the transfer queue comes
+        // from what is left of family 0.
+        assert_eq!(summary(&resolved), [(0, 1, false), (1, 1, true), (0, 1, false)]);
+
+        let (graphics, present) = (Queue::from_raw(1), Queue::from_raw(2));
+        let mut queues = Queues {
+            graphics: vec![graphics],
+            graphics_family_index: resolved[0].family_index,
+            present: vec![present],
+            present_family_index: resolved[1].family_index,
+            compute: vec![],
+            compute_family_index: 0,
+            transfer: vec![],
+            transfer_family_index: 0,
+        };
+        assert!(!queues.is_unified_present());
+        assert_eq!(queues.graphics_queue().unwrap(), graphics);
+        assert_eq!(queues.present_queue().unwrap(), present);
+
+        // Within a family, the frames are presented from the queue they were rendered on.
+        queues.present_family_index = queues.graphics_family_index;
+        assert!(queues.is_unified_present());
+        assert_eq!(queues.present_queue().unwrap(), graphics);
+    }
+}
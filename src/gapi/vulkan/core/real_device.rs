@@ -1,15 +1,101 @@
 use vulkanalia::vk;
 use vulkanalia::vk::{
-    InstanceV1_0, KhrSurfaceExtension, PhysicalDevice as VkPhysicalDevice, PresentModeKHR,
-    QueueFamilyProperties, SurfaceCapabilitiesKHR, SurfaceFormatKHR,
+    HasBuilder, InstanceV1_0, InstanceV1_1, KhrSurfaceExtension, PhysicalDevice as VkPhysicalDevice,
+    QueueFamilyProperties,
 };
 use crate::gapi::vulkan::core::instance::Instance;
 use crate::gapi::vulkan::core::surface::Surface;
+use crate::gapi::vulkan::core::workarounds::{GpuVendor, Workarounds};
+use crate::gapi::vulkan::memory::swapchain::SwapchainSupport;
 
-pub(crate) struct SwapchainInfo {
-    pub(crate) capabilities: SurfaceCapabilitiesKHR,
-    pub(crate) formats: Vec<SurfaceFormatKHR>,
-    pub(crate) present_modes: Vec<PresentModeKHR>,
+/// The driver behind a physical device, e.g. to tell a software rasterizer (lavapipe) apart
+/// from the driver of a real GPU.
+#[derive(Clone, Debug)]
+pub struct DriverInfo {
+    pub driver_id: vk::DriverId,
+    pub driver_name: String,
+    /// Free-form driver version and build information.
+    pub driver_info: String,
+}
+
+/// A short description of a physical device, to choose between them.
+#[derive(Clone, Debug)]
+pub struct DeviceSummary {
+    pub vk_real_device: VkPhysicalDevice,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub api_version: vulkanalia::Version,
+    /// `None` if the device can't report its driver properties.
+    pub driver_info: Option<DriverInfo>,
+}
+
+/// The limits and optional features of a physical device that bound what a draw call can do.
+///
+/// Exceeding them is undefined behavior, which drivers usually report as a lost device long after
+/// the faulty draw, so the draw wrappers of [`LogicalDevice`](super::logical_device::LogicalDevice)
+/// check them in debug builds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeviceCapabilities {
+    /// The largest index value an indexed draw can use with 32-bit indices, at least `2^24 - 1`.
+    pub max_draw_indexed_index_value: u32,
+    /// The largest instance index a draw can generate in a multiview render pass, at least
+    /// `2^27 - 1`. It is the only limit Vulkan puts on the instances, `None` below Vulkan 1.1.
+    pub max_multiview_instance_index: Option<u32>,
+    /// The largest draw count of an indirect draw, only relevant with `multi_draw_indirect`.
+    pub max_draw_indirect_count: u32,
+    /// Whether an indirect draw can execute more than one command.
+    pub multi_draw_indirect: bool,
+    /// The sample counts usable by both the color and the depth attachments of a framebuffer.
+    pub framebuffer_sample_counts: vk::SampleCountFlags,
+    /// Whether buffers can have device addresses, see [`BufferDeviceAddressSupport`].
+    pub buffer_device_address: bool,
+    /// The largest anisotropy of a sampler, rounded down, `None` without the
+    /// `samplerAnisotropy` feature.
+    pub max_sampler_anisotropy: Option<u32>,
+    /// The largest absolute LOD bias of a sampler, at least 2.
+    pub max_sampler_lod_bias: f32,
+    /// The smallest and largest width of the rasterized lines, `None` without the `wideLines`
+    /// feature, where only 1 is supported.
+    pub line_width_range: Option<[f32; 2]>,
+}
+
+/// How a device provides the `bufferDeviceAddress` feature, which lets shaders dereference
+/// pointers to buffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferDeviceAddressSupport {
+    Unsupported,
+    /// Core since Vulkan 1.2.
+    Core,
+    /// Through [`DeviceExtension::KhrBufferDeviceAddress`](crate::gapi::vulkan::enums::extensions::DeviceExtension),
+    /// which must be enabled at device creation.
+    KhrExtension,
+}
+
+impl DeviceCapabilities {
+    /// Every sample count of [`Self::framebuffer_sample_counts`], from lowest to highest, e.g. to
+    /// offer only the valid MSAA options in a settings menu.
+    pub fn supported_sample_counts(&self) -> Vec<vk::SampleCountFlags> {
+        [
+            vk::SampleCountFlags::_1,
+            vk::SampleCountFlags::_2,
+            vk::SampleCountFlags::_4,
+            vk::SampleCountFlags::_8,
+            vk::SampleCountFlags::_16,
+            vk::SampleCountFlags::_32,
+            vk::SampleCountFlags::_64,
+        ]
+        .into_iter()
+        .filter(|&count| self.framebuffer_sample_counts.contains(count))
+        .collect()
+    }
+
+    /// The highest supported sample count, `_1` is always supported.
+    pub fn max_usable_sample_count(&self) -> vk::SampleCountFlags {
+        self.supported_sample_counts()
+            .last()
+            .copied()
+            .unwrap_or(vk::SampleCountFlags::_1)
+    }
 }
 
 pub struct RealDevice<'a> {
@@ -43,6 +129,205 @@ impl<'a> RealDevice<'a> {
         }
     }
 
+    /// Queries the driver of the device.
+    ///
+    /// # Returns
+    /// `None` if the device supports neither Vulkan 1.2, where driver properties are core, nor
+    /// `VK_KHR_driver_properties`.
+    pub fn get_driver_info(&self) -> Option<DriverInfo> {
+        let api_version = vulkanalia::Version::from(self.get_properties().api_version);
+        let has_extension = self
+            .supported_extensions()
+            .map(|extensions| {
+                extensions
+                    .iter()
+                    .any(|ext| ext.extension_name == vk::KHR_DRIVER_PROPERTIES_EXTENSION.name)
+            })
+            .unwrap_or(false);
+        // vkGetPhysicalDeviceProperties2 itself is core since Vulkan 1.1.
+        let supported = api_version >= vulkanalia::Version::V1_2_0
+            || (api_version >= vulkanalia::Version::V1_1_0 && has_extension);
+        if !supported {
+            return None;
+        }
+
+        let mut driver = vk::PhysicalDeviceDriverProperties::builder();
+        let mut properties = vk::PhysicalDeviceProperties2::builder().push_next(&mut driver);
+        unsafe {
+            self.instance
+                .get_vk()
+                .get_physical_device_properties2(self.vk_real_device, &mut properties)
+        };
+        Some(DriverInfo {
+            driver_id: driver.driver_id,
+            driver_name: driver.driver_name.to_string(),
+            driver_info: driver.driver_info.to_string(),
+        })
+    }
+
+    /// Summarizes the device, see [`DeviceSummary`].
+    pub fn get_summary(&self) -> DeviceSummary {
+        let properties = self.get_properties();
+        DeviceSummary {
+            vk_real_device: self.vk_real_device,
+            name: properties.device_name.to_string(),
+            device_type: properties.device_type,
+            api_version: vulkanalia::Version::from(properties.api_version),
+            driver_info: self.get_driver_info(),
+        }
+    }
+
+    pub fn get_vendor(&self) -> GpuVendor {
+        GpuVendor::from_vendor_id(self.get_properties().vendor_id)
+    }
+
+    /// The driver quirks to work around on this device, see [`Workarounds`].
+    pub fn get_workarounds(&self) -> Workarounds {
+        Workarounds::for_vendor(self.get_vendor())
+    }
+
+    /// The limits that bound draw calls on this device, see [`DeviceCapabilities`].
+    pub fn capabilities(&self) -> DeviceCapabilities {
+        let limits = self.get_properties().limits;
+        DeviceCapabilities {
+            max_draw_indexed_index_value: limits.max_draw_indexed_index_value,
+            max_multiview_instance_index: self.max_multiview_instance_index(),
+            max_draw_indirect_count: limits.max_draw_indirect_count,
+            multi_draw_indirect: self.get_features().multi_draw_indirect == vk::TRUE,
+            framebuffer_sample_counts: limits.framebuffer_color_sample_counts
+                & limits.framebuffer_depth_sample_counts,
+            buffer_device_address: self.buffer_device_address_support()
+                != BufferDeviceAddressSupport::Unsupported,
+            max_sampler_anisotropy: (self.get_features().sampler_anisotropy == vk::TRUE)
+                .then_some(limits.max_sampler_anisotropy as u32),
+            max_sampler_lod_bias: limits.max_sampler_lod_bias,
+            line_width_range: (self.get_features().wide_lines == vk::TRUE).then_some(limits.line_width_range),
+        }
+    }
+
+    /// The `maxMultiviewInstanceIndex` limit, `None` below Vulkan 1.1 where multiview and
+    /// vkGetPhysicalDeviceProperties2 became core.
+    fn max_multiview_instance_index(&self) -> Option<u32> {
+        let api_version = vulkanalia::Version::from(self.get_properties().api_version);
+        if api_version < vulkanalia::Version::V1_1_0 {
+            return None;
+        }
+        let mut multiview = vk::PhysicalDeviceMultiviewProperties::builder();
+        let mut properties = vk::PhysicalDeviceProperties2::builder().push_next(&mut multiview);
+        unsafe {
+            self.instance
+                .get_vk()
+                .get_physical_device_properties2(self.vk_real_device, &mut properties)
+        };
+        Some(multiview.max_multiview_instance_index)
+    }
+
+    /// Queries whether and how the device supports the `bufferDeviceAddress` feature.
+    pub fn buffer_device_address_support(&self) -> BufferDeviceAddressSupport {
+        let api_version = vulkanalia::Version::from(self.get_properties().api_version);
+        // vkGetPhysicalDeviceFeatures2 itself is core since Vulkan 1.1.
+        if api_version < vulkanalia::Version::V1_1_0 {
+            return BufferDeviceAddressSupport::Unsupported;
+        }
+        let source = if api_version >= vulkanalia::Version::V1_2_0 {
+            BufferDeviceAddressSupport::Core
+        } else {
+            let has_extension = self
+                .supported_extensions()
+                .map(|extensions| {
+                    extensions
+                        .iter()
+                        .any(|ext| ext.extension_name == vk::KHR_BUFFER_DEVICE_ADDRESS_EXTENSION.name)
+                })
+                .unwrap_or(false);
+            if !has_extension {
+                return BufferDeviceAddressSupport::Unsupported;
+            }
+            BufferDeviceAddressSupport::KhrExtension
+        };
+
+        let mut address = vk::PhysicalDeviceBufferDeviceAddressFeatures::builder();
+        let mut features = vk::PhysicalDeviceFeatures2::builder().push_next(&mut address);
+        unsafe {
+            self.instance
+                .get_vk()
+                .get_physical_device_features2(self.vk_real_device, &mut features)
+        };
+        if address.buffer_device_address == vk::TRUE {
+            source
+        } else {
+            BufferDeviceAddressSupport::Unsupported
+        }
+    }
+
+    /// Whether the device supports [`DeviceExtension::ExtDescriptorIndexing`](crate::gapi::vulkan::enums::extensions::DeviceExtension)
+    /// and the features of bindless textures: partially bound, update-after-bind arrays of
+    /// sampled images of runtime size, indexed non-uniformly.
+    pub fn supports_descriptor_indexing(&self) -> bool {
+        let api_version = vulkanalia::Version::from(self.get_properties().api_version);
+        let has_extension = self
+            .supported_extensions()
+            .map(|extensions| {
+                extensions
+                    .iter()
+                    .any(|ext| ext.extension_name == vk::EXT_DESCRIPTOR_INDEXING_EXTENSION.name)
+            })
+            .unwrap_or(false);
+        // vkGetPhysicalDeviceFeatures2 itself is core since Vulkan 1.1.
+        if api_version < vulkanalia::Version::V1_1_0 || !has_extension {
+            return false;
+        }
+
+        let mut indexing = vk::PhysicalDeviceDescriptorIndexingFeatures::builder();
+        let mut features = vk::PhysicalDeviceFeatures2::builder().push_next(&mut indexing);
+        unsafe {
+            self.instance
+                .get_vk()
+                .get_physical_device_features2(self.vk_real_device, &mut features)
+        };
+        indexing.descriptor_binding_partially_bound == vk::TRUE
+            && indexing.descriptor_binding_sampled_image_update_after_bind == vk::TRUE
+            && indexing.runtime_descriptor_array == vk::TRUE
+            && indexing.shader_sampled_image_array_non_uniform_indexing == vk::TRUE
+    }
+
+    /// Whether the device supports [`DeviceExtension::KhrDynamicRendering`](crate::gapi::vulkan::enums::extensions::DeviceExtension)
+    /// and its `dynamicRendering` feature. The extension needs Vulkan 1.2, which the instance
+    /// targets.
+    pub fn supports_dynamic_rendering(&self) -> bool {
+        let api_version = vulkanalia::Version::from(self.get_properties().api_version);
+        let has_extension = self
+            .supported_extensions()
+            .map(|extensions| {
+                extensions
+                    .iter()
+                    .any(|ext| ext.extension_name == vk::KHR_DYNAMIC_RENDERING_EXTENSION.name)
+            })
+            .unwrap_or(false);
+        if api_version < vulkanalia::Version::V1_2_0 || !has_extension {
+            return false;
+        }
+
+        let mut dynamic_rendering = vk::PhysicalDeviceDynamicRenderingFeatures::builder();
+        let mut features = vk::PhysicalDeviceFeatures2::builder().push_next(&mut dynamic_rendering);
+        unsafe {
+            self.instance
+                .get_vk()
+                .get_physical_device_features2(self.vk_real_device, &mut features)
+        };
+        dynamic_rendering.dynamic_rendering == vk::TRUE
+    }
+
+    /// See [`DeviceCapabilities::supported_sample_counts`].
+    pub fn supported_sample_counts(&self) -> Vec<vk::SampleCountFlags> {
+        self.capabilities().supported_sample_counts()
+    }
+
+    /// See [`DeviceCapabilities::max_usable_sample_count`].
+    pub fn max_usable_sample_count(&self) -> vk::SampleCountFlags {
+        self.capabilities().max_usable_sample_count()
+    }
+
     pub fn get_features(&self) -> vk::PhysicalDeviceFeatures {
         unsafe {
             self.instance
@@ -76,6 +361,33 @@ impl<'a> RealDevice<'a> {
         }
     }
 
+    /// The names of the extensions the device supports, sorted.
+    pub fn supported_device_extensions(&self) -> anyhow::Result<Vec<String>> {
+        let mut names = self
+            .supported_extensions()?
+            .iter()
+            .map(|ext| ext.extension_name.to_string())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        Ok(names)
+    }
+
+    /// Describes every extension the device supports with its version, one per line, to see
+    /// what a device actually supports when triaging a portability issue.
+    pub fn dump_extensions(&self) -> anyhow::Result<String> {
+        let mut extensions = self.supported_extensions()?;
+        extensions.sort_unstable_by_key(|ext| ext.extension_name.to_string());
+        let mut dump = format!(
+            "Device extensions of \"{}\" ({}):",
+            self.get_properties().device_name,
+            extensions.len()
+        );
+        for ext in &extensions {
+            dump.push_str(&format!("\n\t{} (v{})", ext.extension_name, ext.spec_version));
+        }
+        Ok(dump)
+    }
+
     pub fn supports_surface(&self, family_index: u32, surface: &Surface) -> anyhow::Result<bool> {
         unsafe {
             self.instance
@@ -138,8 +450,82 @@ impl<'a> RealDevice<'a> {
         }
     }
 
-    pub fn get_swapchain_info(&self, surface: &Surface) -> anyhow::Result<SwapchainInfo> {
-        Ok(SwapchainInfo {
+    pub fn get_memory_properties(&self) -> vk::PhysicalDeviceMemoryProperties {
+        unsafe {
+            self.instance
+                .get_vk()
+                .get_physical_device_memory_properties(self.vk_real_device)
+        }
+    }
+
+    pub fn get_format_properties(&self, format: vk::Format) -> vk::FormatProperties {
+        unsafe {
+            self.instance
+                .get_vk()
+                .get_physical_device_format_properties(self.vk_real_device, format)
+        }
+    }
+
+    /// Finds the first format in `candidates` that supports the `features` with the given `tiling`.
+    ///
+    /// # Errors
+    /// If none of the candidates is supported.
+    pub fn find_supported_format(
+        &self,
+        candidates: &[vk::Format],
+        tiling: vk::ImageTiling,
+        features: vk::FormatFeatureFlags,
+    ) -> anyhow::Result<vk::Format> {
+        candidates
+            .iter()
+            .cloned()
+            .find(|format| {
+                let properties = self.get_format_properties(*format);
+                match tiling {
+                    vk::ImageTiling::LINEAR => properties.linear_tiling_features.contains(features),
+                    vk::ImageTiling::OPTIMAL => properties.optimal_tiling_features.contains(features),
+                    _ => false,
+                }
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "None of the formats {:?} supports {:?} with {:?} tiling.",
+                    candidates,
+                    features,
+                    tiling
+                )
+            })
+    }
+
+    /// Finds the index of a memory type allowed by `type_bits` (from the memory requirements of a
+    /// resource) that has all the `properties`.
+    ///
+    /// # Errors
+    /// If no memory type is suitable.
+    pub fn find_memory_type_index(
+        &self,
+        type_bits: u32,
+        properties: vk::MemoryPropertyFlags,
+    ) -> anyhow::Result<u32> {
+        let memory = self.get_memory_properties();
+        (0..memory.memory_type_count)
+            .find(|i| {
+                let suitable = (type_bits & (1 << i)) != 0;
+                let memory_type = memory.memory_types[*i as usize];
+                suitable && memory_type.property_flags.contains(properties)
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Failed to find a memory type with {:?} in type bits {:#b}.",
+                    properties,
+                    type_bits
+                )
+            })
+    }
+
+    /// What the `surface` supports for the swapchains created for it, see [`SwapchainSupport`].
+    pub fn get_swapchain_info(&self, surface: &Surface) -> anyhow::Result<SwapchainSupport> {
+        Ok(SwapchainSupport {
             capabilities: self.get_surface_capabilities(surface)?,
             formats: self.get_surface_formats(surface)?,
             present_modes: self.get_surface_present_modes(surface)?,
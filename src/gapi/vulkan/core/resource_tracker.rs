@@ -0,0 +1,131 @@
+use log::{debug, error};
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+/// The kinds of Vulkan objects the [`ResourceTracker`] counts.
+///
+/// Command buffers, descriptor sets and swapchain images are left out, they are freed with their
+/// pool and swapchain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Buffer,
+    Memory,
+    Image,
+    ImageView,
+    Sampler,
+    ShaderModule,
+    PipelineLayout,
+    Pipeline,
+    PipelineCache,
+    RenderPass,
+    Framebuffer,
+    DescriptorSetLayout,
+    DescriptorPool,
+    CommandPool,
+    Semaphore,
+    Fence,
+    Swapchain,
+}
+
+impl ResourceKind {
+    pub const ALL: [ResourceKind; 17] = [
+        ResourceKind::Buffer,
+        ResourceKind::Memory,
+        ResourceKind::Image,
+        ResourceKind::ImageView,
+        ResourceKind::Sampler,
+        ResourceKind::ShaderModule,
+        ResourceKind::PipelineLayout,
+        ResourceKind::Pipeline,
+        ResourceKind::PipelineCache,
+        ResourceKind::RenderPass,
+        ResourceKind::Framebuffer,
+        ResourceKind::DescriptorSetLayout,
+        ResourceKind::DescriptorPool,
+        ResourceKind::CommandPool,
+        ResourceKind::Semaphore,
+        ResourceKind::Fence,
+        ResourceKind::Swapchain,
+    ];
+}
+
+/// How many times [`ResourceTracker::report_leaks`] found leaks since the program started, of
+/// every device.
+static LEAK_REPORTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts the live Vulkan objects of each kind created through the
+/// [`LogicalDevice`](super::logical_device::LogicalDevice) wrappers, to catch the objects that
+/// are never destroyed, e.g. on an error path.
+///
+/// Every object must be destroyed manually before the device, and the validation layer only
+/// reports the leaks when it is enabled, and without telling which code leaked them. The counts
+/// are checked by [`ResourceTracker::report_leaks`] when the app is destroyed.
+///
+/// Only counts in debug builds, it does nothing in release builds.
+#[derive(Debug, Default)]
+pub struct ResourceTracker {
+    /// Indexed by [`ResourceKind`].
+    live: [AtomicI64; ResourceKind::ALL.len()],
+}
+
+impl ResourceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts `count` objects of `kind` as created.
+    pub fn created(&self, kind: ResourceKind, count: usize) {
+        if cfg!(debug_assertions) {
+            self.live[kind as usize].fetch_add(count as i64, Ordering::Relaxed);
+        }
+    }
+
+    /// Counts `count` objects of `kind` as destroyed.
+    pub fn destroyed(&self, kind: ResourceKind, count: usize) {
+        if cfg!(debug_assertions) {
+            self.live[kind as usize].fetch_sub(count as i64, Ordering::Relaxed);
+        }
+    }
+
+    /// How many objects of `kind` are alive, always 0 in release builds. Negative if more were
+    /// destroyed than created, e.g. destroyed twice.
+    pub fn live(&self, kind: ResourceKind) -> i64 {
+        self.live[kind as usize].load(Ordering::Relaxed)
+    }
+
+    /// The kinds that have live objects, with their count.
+    pub fn leaks(&self) -> Vec<(ResourceKind, i64)> {
+        ResourceKind::ALL
+            .into_iter()
+            .map(|kind| (kind, self.live(kind)))
+            .filter(|&(_, live)| live != 0)
+            .collect()
+    }
+
+    /// Logs an error for every kind with live objects, meant to be called right before
+    /// destroying the device, once everything else is. Returns whether there were none.
+    pub fn report_leaks(&self) -> bool {
+        if !cfg!(debug_assertions) {
+            return true;
+        }
+        let leaks = self.leaks();
+        for (kind, live) in &leaks {
+            if *live > 0 {
+                error!("Leaked {live} Vulkan object(s) of kind {kind:?}.");
+            } else {
+                error!("Destroyed {} more Vulkan object(s) of kind {kind:?} than created.", -live);
+            }
+        }
+        if leaks.is_empty() {
+            debug!("Every tracked Vulkan object was destroyed.");
+        } else {
+            LEAK_REPORTS.fetch_add(1, Ordering::Relaxed);
+        }
+        leaks.is_empty()
+    }
+
+    /// How many times [`ResourceTracker::report_leaks`] found leaks so far, on any device, e.g.
+    /// to check that an error path destroying the device destroyed everything else first.
+    pub fn leak_report_count() -> usize {
+        LEAK_REPORTS.load(Ordering::Relaxed)
+    }
+}
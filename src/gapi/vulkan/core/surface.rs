@@ -1,6 +1,9 @@
+use std::cell::Cell;
+use vulkanalia::vk;
 use vulkanalia::vk::{KhrSurfaceExtension, SurfaceKHR};
 use vulkanalia::window as vk_window;
 use crate::gapi::vulkan::core::instance::Instance;
+use crate::gapi::vulkan::core::real_device::RealDevice;
 use crate::window::MyWindow;
 
 pub(crate) struct Surface {
@@ -13,21 +16,63 @@ pub(crate) struct Surface {
     /// Although the vk::SurfaceKHR object and its usage is platform-agnostic, its creation isn't because it depends on
     /// window system details. Fortunately, the vulkanalia crate provides a way to create a surface for a winit window
     /// that handles the platform differences for us.
-    vk_surface: SurfaceKHR
+    vk_surface: SurfaceKHR,
+    /// How many swapchains created for this surface are still alive.
+    /// They must all be destroyed before the surface.
+    live_swapchains: Cell<usize>,
 }
 
 impl Surface {
     pub fn new(instance: &Instance, window: &MyWindow) -> anyhow::Result<Self> {
         let vk_surface =
             unsafe { vk_window::create_surface(&instance.get_vk(), &window.get_winnit(), &window.get_winnit())? };
-        Ok(Self { vk_surface})
+        Ok(Self {
+            vk_surface,
+            live_swapchains: Cell::new(0),
+        })
     }
 
     pub fn get_vk(&self) -> SurfaceKHR {
         self.vk_surface
     }
 
+    /// The capabilities of the surface on `real_device`, e.g. its current extent and image count
+    /// limits.
+    ///
+    /// Like the other queries, it asks the driver on every call, as the answer changes with the
+    /// window (e.g. the extent after a resize).
+    pub fn capabilities(&self, real_device: &RealDevice) -> anyhow::Result<vk::SurfaceCapabilitiesKHR> {
+        real_device.get_surface_capabilities(self)
+    }
+
+    /// The formats and color spaces `real_device` can present to the surface in.
+    pub fn formats(&self, real_device: &RealDevice) -> anyhow::Result<Vec<vk::SurfaceFormatKHR>> {
+        real_device.get_surface_formats(self)
+    }
+
+    /// The present modes `real_device` supports for the surface.
+    pub fn present_modes(&self, real_device: &RealDevice) -> anyhow::Result<Vec<vk::PresentModeKHR>> {
+        real_device.get_surface_present_modes(self)
+    }
+
+    /// Keeps track of a swapchain created for this surface.
+    pub(crate) fn register_swapchain(&self) {
+        self.live_swapchains.set(self.live_swapchains.get() + 1);
+    }
+
+    /// Forgets a swapchain of this surface that was destroyed.
+    pub(crate) fn unregister_swapchain(&self) {
+        debug_assert!(self.live_swapchains.get() > 0, "No swapchain was registered for the surface.");
+        self.live_swapchains.set(self.live_swapchains.get().saturating_sub(1));
+    }
+
+    /// Destroys the surface. Its swapchains must be destroyed before, and the instance after.
     pub fn destroy(&self, instance: &Instance) {
+        debug_assert_eq!(
+            self.live_swapchains.get(),
+            0,
+            "The surface is destroyed while its swapchains are still alive."
+        );
         unsafe {
             instance.get_vk().destroy_surface_khr(self.vk_surface, None);
         }
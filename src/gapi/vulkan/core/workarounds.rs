@@ -0,0 +1,84 @@
+use std::fmt;
+
+/// The vendor of a GPU, from the PCI vendor ID in `VkPhysicalDeviceProperties::vendorID`.
+///
+/// Vendors without a PCI ID (e.g. Apple, or Mesa's software rasterizers) report a Khronos vendor
+/// ID instead, `VkVendorId`, which are `Unknown` here.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GpuVendor {
+    Amd,
+    Nvidia,
+    Intel,
+    Arm,
+    Qualcomm,
+    ImgTec,
+    Unknown(u32),
+}
+
+impl GpuVendor {
+    pub fn from_vendor_id(vendor_id: u32) -> Self {
+        match vendor_id {
+            0x1002 => Self::Amd,
+            0x10DE => Self::Nvidia,
+            0x8086 => Self::Intel,
+            0x13B5 => Self::Arm,
+            0x5143 => Self::Qualcomm,
+            0x1010 => Self::ImgTec,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Driver quirks we work around, enabled depending on the [`GpuVendor`].
+///
+/// To add one, add a flag here, document the affected drivers and the symptom, enable it in
+/// [`Workarounds::for_vendor`], name it in the [`fmt::Display`] impl so it gets logged, and apply
+/// it where the affected object is created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Workarounds {
+    /// Intel drivers on Windows have been reported to advertise `MAILBOX` while presenting with
+    /// `FIFO`-like latency and stutter. `FIFO` is picked instead, which behaves the same but
+    /// predictably.
+    pub avoid_mailbox_present_mode: bool,
+    /// Some AMD drivers block in `vkAcquireNextImageKHR` with only `min_image_count + 1`
+    /// swapchain images, so at least this many images are requested (triple buffering).
+    pub min_swapchain_image_count: Option<u32>,
+}
+
+impl Workarounds {
+    pub fn for_vendor(vendor: GpuVendor) -> Self {
+        let mut workarounds = Self::default();
+        match vendor {
+            GpuVendor::Intel if cfg!(target_os = "windows") => {
+                workarounds.avoid_mailbox_present_mode = true;
+            }
+            GpuVendor::Amd => {
+                workarounds.min_swapchain_image_count = Some(3);
+            }
+            _ => {}
+        }
+        workarounds
+    }
+
+    /// Whether no workaround is active.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl fmt::Display for Workarounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut active = Vec::new();
+        if self.avoid_mailbox_present_mode {
+            active.push("avoid MAILBOX present mode".to_string());
+        }
+        if let Some(count) = self.min_swapchain_image_count {
+            active.push(format!("at least {count} swapchain images"));
+        }
+        if active.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", active.join(", "))
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::descriptors::descriptor_set_layout::DescriptorSetLayout;
+use anyhow::Context;
+use log::debug;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// Descriptor sets can't be created directly, they are allocated from a pool, like command buffers.
+pub struct DescriptorPool {
+    vk_pool: vk::DescriptorPool,
+}
+
+impl DescriptorPool {
+    /// Creates a pool big enough for `set_count` sets of `layout`.
+    ///
+    /// The sets of an `UPDATE_AFTER_BIND_POOL` layout can only be allocated from an
+    /// `UPDATE_AFTER_BIND` pool, which the pool then is.
+    pub fn new(
+        device: &LogicalDevice,
+        layout: &DescriptorSetLayout,
+        set_count: u32,
+    ) -> anyhow::Result<Self> {
+        let pool_sizes = Self::pool_sizes(layout.get_bindings(), set_count);
+        let flags = if layout
+            .get_flags()
+            .contains(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+        {
+            vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND
+        } else {
+            vk::DescriptorPoolCreateFlags::empty()
+        };
+        let info = vk::DescriptorPoolCreateInfo::builder()
+            .flags(flags)
+            .pool_sizes(&pool_sizes)
+            .max_sets(set_count);
+        debug!("Created DescriptorPoolCreateInfo struct: {:#?}", info);
+
+        let vk_pool = device
+            .create_descriptor_pool(&info)
+            .with_context(|| format!("Failed to create descriptor pool for {set_count} sets"))?;
+
+        Ok(Self { vk_pool })
+    }
+
+    /// Computes how many descriptors of each type `set_count` sets with `bindings` need.
+    /// Bindings of the same type are merged into a single pool size.
+    pub fn pool_sizes(
+        bindings: &[vk::DescriptorSetLayoutBinding],
+        set_count: u32,
+    ) -> Vec<vk::DescriptorPoolSize> {
+        let mut pool_sizes: Vec<vk::DescriptorPoolSize> = Vec::new();
+        for binding in bindings {
+            let count = binding.descriptor_count * set_count;
+            match pool_sizes.iter_mut().find(|size| size.type_ == binding.descriptor_type) {
+                Some(size) => size.descriptor_count += count,
+                None => pool_sizes.push(
+                    vk::DescriptorPoolSize::builder()
+                        .type_(binding.descriptor_type)
+                        .descriptor_count(count)
+                        .build(),
+                ),
+            }
+        }
+        pool_sizes
+    }
+
+    /// Allocates `count` sets of `layout`.
+    pub fn allocate_sets(
+        &self,
+        device: &LogicalDevice,
+        layout: &DescriptorSetLayout,
+        count: usize,
+    ) -> anyhow::Result<Vec<vk::DescriptorSet>> {
+        // One layout per set to allocate, they could all be different.
+        let layouts = vec![layout.get_vk(); count];
+        let info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(self.vk_pool)
+            .set_layouts(&layouts);
+        debug!("Created DescriptorSetAllocateInfo struct: {:#?}", info);
+
+        device
+            .allocate_descriptor_sets(&info)
+            .with_context(|| format!("Failed to allocate {count} descriptor sets"))
+    }
+
+    pub fn get_vk(&self) -> vk::DescriptorPool {
+        self.vk_pool
+    }
+
+    /// Destroys the pool, and with it, all the sets allocated from it.
+    pub fn destroy(&self, device: &LogicalDevice) {
+        device.destroy_descriptor_pool(self.vk_pool);
+    }
+}
+
+/// One descriptor set per frame in flight, allocated from its own pool.
+///
+/// A descriptor set can't be updated while a command buffer using it is executing, so every
+/// frame in flight gets its own copy. The set of a frame must only be updated after waiting for
+/// that frame's in flight fence, i.e. once its previous command buffer has finished.
+pub struct FrameDescriptorSets {
+    pool: DescriptorPool,
+    sets: Vec<vk::DescriptorSet>,
+}
+
+impl FrameDescriptorSets {
+    pub fn new(
+        device: &LogicalDevice,
+        layout: &DescriptorSetLayout,
+        frames_in_flight: usize,
+    ) -> anyhow::Result<Self> {
+        let pool = DescriptorPool::new(device, layout, frames_in_flight as u32)?;
+        let sets = pool.allocate_sets(device, layout, frames_in_flight)?;
+        Ok(Self { pool, sets })
+    }
+
+    /// The set for the frame in flight `frame`.
+    pub fn get(&self, frame: usize) -> vk::DescriptorSet {
+        self.sets[frame]
+    }
+
+    pub fn get_all(&self) -> &[vk::DescriptorSet] {
+        &self.sets
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.pool.destroy(device);
+    }
+}
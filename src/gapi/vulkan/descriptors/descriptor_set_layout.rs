@@ -0,0 +1,70 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use anyhow::Context;
+use log::debug;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// Describes the resources (uniform buffers, samplers...) a shader can access through a
+/// descriptor set, in the same way a render pass describes the attachments of a framebuffer.
+pub struct DescriptorSetLayout {
+    vk_layout: vk::DescriptorSetLayout,
+    /// Kept to size the descriptor pools the sets of this layout are allocated from.
+    bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    /// Kept to create the descriptor pools with the matching flags.
+    flags: vk::DescriptorSetLayoutCreateFlags,
+}
+
+impl DescriptorSetLayout {
+    pub fn new(
+        device: &LogicalDevice,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+    ) -> anyhow::Result<Self> {
+        Self::with_binding_flags(device, bindings, &[], vk::DescriptorSetLayoutCreateFlags::empty())
+    }
+
+    /// Same as [`DescriptorSetLayout::new`], with the `binding_flags` of each binding, e.g.
+    /// `PARTIALLY_BOUND`, or none at all if empty. They need the features of descriptor
+    /// indexing, and `UPDATE_AFTER_BIND` bindings need the `UPDATE_AFTER_BIND_POOL` `flags`.
+    pub fn with_binding_flags(
+        device: &LogicalDevice,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+        binding_flags: &[vk::DescriptorBindingFlags],
+        flags: vk::DescriptorSetLayoutCreateFlags,
+    ) -> anyhow::Result<Self> {
+        let mut binding_flags_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::builder().binding_flags(binding_flags);
+        let mut info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .bindings(bindings)
+            .flags(flags);
+        if !binding_flags.is_empty() {
+            info = info.push_next(&mut binding_flags_info);
+        }
+        debug!("Created DescriptorSetLayoutCreateInfo struct: {:#?}", info);
+
+        let vk_layout = device
+            .create_descriptor_set_layout(&info)
+            .with_context(|| format!("Failed to create descriptor set layout with bindings: {bindings:?}"))?;
+
+        Ok(Self {
+            vk_layout,
+            bindings: bindings.to_vec(),
+            flags,
+        })
+    }
+
+    pub fn get_vk(&self) -> vk::DescriptorSetLayout {
+        self.vk_layout
+    }
+
+    pub fn get_bindings(&self) -> &[vk::DescriptorSetLayoutBinding] {
+        &self.bindings
+    }
+
+    pub fn get_flags(&self) -> vk::DescriptorSetLayoutCreateFlags {
+        self.flags
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        device.destroy_descriptor_set_layout(self.vk_layout);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::gapi::material::{MaterialPalette, MaterialPaletteBuffer};
+use crate::gapi::vulkan::commands::command_buffers::CommandBuffer;
+use crate::gapi::vulkan::config::BINDLESS_TEXTURE_CAPACITY;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::descriptors::descriptor_pool::DescriptorPool;
+use crate::gapi::vulkan::descriptors::descriptor_set_layout::DescriptorSetLayout;
+use crate::gapi::vulkan::memory::texture_array::TextureArray;
+use crate::gapi::vulkan::pipeline::pipeline::Pipeline;
+use anyhow::Context;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// Index of the set updated every frame, e.g. the camera.
+pub const FRAME_SET: u32 = 0;
+/// Index of the set of the materials, see [`MaterialSet`].
+pub const MATERIAL_SET: u32 = 1;
+
+/// The descriptor set of the materials (set [`MATERIAL_SET`]): the face textures and the
+/// material palette.
+///
+/// # Set partitioning
+/// The descriptors are split into sets by how often they change, from the least frequently
+/// bound to the most frequently bound:
+/// - Set 0 ([`FRAME_SET`]): per frame, rewritten every frame, one copy per frame in flight.
+/// - Set 1 ([`MATERIAL_SET`]): per material, only rebound when the material changes.
+///
+/// Binding a set keeps the sets of lower indices bound, as long as the pipeline layouts agree
+/// on them, so switching materials only rebinds set 1 and never the per frame set.
+///
+/// # Bindless textures
+/// With a bindless layout (see [`MaterialSet::create_layout`]), the textures binding is an array
+/// of [`BINDLESS_TEXTURE_CAPACITY`] texture arrays instead of a single one, indexed by material
+/// ID in the shader:
+/// ```glsl
+/// #extension GL_EXT_nonuniform_qualifier : require
+/// layout(set = 1, binding = 0) uniform sampler2DArray textures[];
+/// // ...
+/// texture(textures[nonuniformEXT(materialId)], uv);
+/// ```
+/// The array is partially bound, so the elements that were never written can stay empty as long
+/// as the shader doesn't read them. It is also update-after-bind, so writing a texture with
+/// [`MaterialSet::write_texture`] doesn't invalidate the command buffers the set was bound in.
+pub struct MaterialSet {
+    pool: DescriptorPool,
+    set: vk::DescriptorSet,
+}
+
+impl MaterialSet {
+    /// Binding of the [`TextureArray`].
+    pub const TEXTURES_BINDING: u32 = 0;
+    /// Binding of the [`MaterialPaletteBuffer`].
+    pub const PALETTE_BINDING: u32 = 1;
+
+    /// Creates the layout of the material sets, to create the pipelines with. A `bindless`
+    /// layout needs a device with descriptor indexing, see
+    /// `RealDevice::supports_descriptor_indexing`.
+    pub fn create_layout(device: &LogicalDevice, bindless: bool) -> anyhow::Result<DescriptorSetLayout> {
+        let textures = TextureArray::descriptor_binding(Self::TEXTURES_BINDING);
+        let palette = MaterialPalette::descriptor_binding(Self::PALETTE_BINDING);
+        if !bindless {
+            return DescriptorSetLayout::new(device, &[textures, palette])
+                .with_context(|| "Failed to create material set layout.");
+        }
+        let textures = vk::DescriptorSetLayoutBinding {
+            descriptor_count: BINDLESS_TEXTURE_CAPACITY,
+            ..textures
+        };
+        let binding_flags = [
+            vk::DescriptorBindingFlags::PARTIALLY_BOUND | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND,
+            vk::DescriptorBindingFlags::empty(),
+        ];
+        DescriptorSetLayout::with_binding_flags(
+            device,
+            &[textures, palette],
+            &binding_flags,
+            vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL,
+        )
+        .with_context(|| "Failed to create bindless material set layout.")
+    }
+
+    /// How many textures the sets of `layout` hold, 1 unless it is bindless.
+    pub fn texture_capacity(layout: &DescriptorSetLayout) -> u32 {
+        layout.get_bindings()[Self::TEXTURES_BINDING as usize].descriptor_count
+    }
+
+    /// Allocates a set of `layout` pointing to `textures` and `palette`, which must outlive it.
+    /// With a bindless layout, `textures` is the element 0 of the array.
+    pub fn new(
+        device: &LogicalDevice,
+        layout: &DescriptorSetLayout,
+        textures: &TextureArray,
+        palette: &MaterialPaletteBuffer,
+    ) -> anyhow::Result<Self> {
+        let pool = DescriptorPool::new(device, layout, 1)?;
+        let set = match pool.allocate_sets(device, layout, 1) {
+            Ok(sets) => sets[0],
+            Err(e) => {
+                pool.destroy(device);
+                return Err(e);
+            }
+        };
+
+        let material_set = Self { pool, set };
+        material_set.write_texture(device, 0, textures);
+        palette.write_descriptor(device, set, Self::PALETTE_BINDING);
+
+        Ok(material_set)
+    }
+
+    /// Points the element `index` of the textures binding to `textures`, which must outlive the
+    /// set, or until the element is written again. Only a bindless set has more than the element
+    /// 0, see [`MaterialSet::texture_capacity`].
+    ///
+    /// The element must not be in use by a command buffer in flight.
+    pub fn write_texture(&self, device: &LogicalDevice, index: u32, textures: &TextureArray) {
+        let image_infos = &[textures.descriptor_image_info()];
+        let write = vk::WriteDescriptorSet::builder()
+            .dst_set(self.set)
+            .dst_binding(Self::TEXTURES_BINDING)
+            .dst_array_element(index)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(image_infos);
+        device.update_descriptor_sets(&[write]);
+    }
+
+    pub fn get_vk(&self) -> vk::DescriptorSet {
+        self.set
+    }
+
+    /// Binds the set at [`MATERIAL_SET`], `pipeline` must have been created with its layout.
+    pub fn bind(&self, device: &LogicalDevice, command_buffer: &CommandBuffer, pipeline: &Pipeline) {
+        pipeline.bind_descriptor_sets(device, command_buffer, MATERIAL_SET, &[self.set]);
+    }
+
+    /// Destroys the pool and the set, which must not be in use by a command buffer in flight.
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.pool.destroy(device);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod descriptor_set_layout;
+pub mod descriptor_pool;
+pub mod material_set;
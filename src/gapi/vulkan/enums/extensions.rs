@@ -159,7 +159,11 @@ enum_impl! {
         KhrGetSurfaceCapabilities2 = vk::KHR_GET_SURFACE_CAPABILITIES2_EXTENSION.name,
 
         /// # VK_EXT_debug_report
-        /// TODO
+        /// The legacy predecessor of `VK_EXT_debug_utils`, only delivering messages to a callback.
+        ///
+        /// # Details
+        /// Only enabled for compatibility with older Vulkan installations that lack
+        /// `VK_EXT_debug_utils`, see `Debugger::new`.
         ExtDebugReport = vk::EXT_DEBUG_REPORT_EXTENSION.name,
 
         /// # VK_EXT_swapchain_colorspace
@@ -258,6 +262,31 @@ enum_impl! {
         /// requiring vertex attributes.
         KhrShaderDrawParameters = vk::KHR_SHADER_DRAW_PARAMETERS_EXTENSION.name,
 
+        /// # VK_KHR_buffer_device_address
+        /// Lets shaders dereference **pointers** to buffers, for GPU-driven rendering.
+        ///
+        /// ## Details
+        /// 1. Adds [`vk::PhysicalDeviceBufferDeviceAddressFeatures`], which must be chained at
+        ///    device creation with `bufferDeviceAddress` enabled.
+        /// 2. Adds `vkGetBufferDeviceAddressKHR`, for buffers created with the
+        ///    `SHADER_DEVICE_ADDRESS` usage and memory allocated with the `DEVICE_ADDRESS` flag.
+        /// 3. Promoted to core in Vulkan 1.2, only needed by older devices.
+        KhrBufferDeviceAddress = vk::KHR_BUFFER_DEVICE_ADDRESS_EXTENSION.name,
+
+        /// # VK_KHR_dynamic_rendering
+        /// Renders without render pass and framebuffer objects.
+        ///
+        /// ## Details
+        /// 1. Adds `vkCmdBeginRenderingKHR` / `vkCmdEndRenderingKHR`, which take the attachment
+        ///    views directly in a [`vk::RenderingInfo`].
+        /// 2. Pipelines chain a [`vk::PipelineRenderingCreateInfo`] with the attachment formats
+        ///    instead of referencing a render pass.
+        /// 3. Needs [`vk::PhysicalDeviceDynamicRenderingFeatures`] chained at device creation
+        ///    with `dynamicRendering` enabled.
+        /// 4. Promoted to core in Vulkan 1.3, but the instance targets 1.2, so the extension is
+        ///    always used.
+        KhrDynamicRendering = vk::KHR_DYNAMIC_RENDERING_EXTENSION.name,
+
         /// # VK_KHR_portability_subset
         /// Marks the device as implementing only a *subset* of Vulkan functionality
         /// via translation layers such as MoltenVK.
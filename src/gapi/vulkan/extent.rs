@@ -0,0 +1,71 @@
+use vulkanalia::vk;
+use winit::dpi::{LogicalSize, PhysicalSize};
+
+/// # Extents and rectangles
+/// Shorthands for the [`vk::Extent2D`] and [`vk::Rect2D`] built all over the renderer, naming
+/// the width and height so they can't be swapped by accident.
+///
+/// The swapchain, the viewports and the render areas are all in **physical pixels**. winit's
+/// [`PhysicalSize`] is already in pixels, a [`LogicalSize`] must first be scaled by the scale
+/// factor of the window, which is above 1 on HiDPI displays.
+pub(crate) fn extent2d(width: u32, height: u32) -> vk::Extent2D {
+    vk::Extent2D { width, height }
+}
+
+/// The rectangle covering the whole `extent`, e.g. the render area or the scissor of a frame.
+pub(crate) fn full_rect(extent: vk::Extent2D) -> vk::Rect2D {
+    vk::Rect2D {
+        offset: vk::Offset2D { x: 0, y: 0 },
+        extent,
+    }
+}
+
+/// The extent with the width and height swapped, e.g. for images rotated by a quarter turn.
+pub(crate) fn transposed(extent: vk::Extent2D) -> vk::Extent2D {
+    extent2d(extent.height, extent.width)
+}
+
+/// The extent of a window of `size`, as returned by `Window::inner_size`.
+pub(crate) fn from_window_size(size: PhysicalSize<u32>) -> vk::Extent2D {
+    extent2d(size.width, size.height)
+}
+
+/// The extent in pixels of a window of the logical `size`, on a display with `scale_factor`
+/// pixels per logical unit, rounded to the nearest pixel.
+pub(crate) fn from_logical_size(size: LogicalSize<f64>, scale_factor: f64) -> vk::Extent2D {
+    from_window_size(size.to_physical(scale_factor))
+}
+
+/// The window size of an `extent`, e.g. to resize the window to the swapchain.
+pub(crate) fn to_window_size(extent: vk::Extent2D) -> PhysicalSize<u32> {
+    PhysicalSize::new(extent.width, extent.height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_logical_sizes_to_pixels() {
+        let size = LogicalSize::new(1024.0, 768.0);
+        assert_eq!(from_logical_size(size, 1.0), extent2d(1024, 768));
+        assert_eq!(from_logical_size(size, 1.5), extent2d(1536, 1152));
+        assert_eq!(from_logical_size(size, 2.0), extent2d(2048, 1536));
+        // Fractional scaling rounds to the nearest pixel.
+        assert_eq!(from_logical_size(LogicalSize::new(333.0, 101.0), 1.5), extent2d(500, 152));
+    }
+
+    #[test]
+    fn clamps_logical_sizes_out_of_the_pixel_range() {
+        assert_eq!(from_logical_size(LogicalSize::new(-10.0, 0.2), 2.0), extent2d(0, 0));
+        assert_eq!(from_logical_size(LogicalSize::new(1e12, 1.0), 2.0), extent2d(u32::MAX, 2));
+    }
+
+    #[test]
+    fn converts_window_sizes_without_swapping_the_axes() {
+        let extent = from_window_size(PhysicalSize::new(800, 600));
+        assert_eq!(extent, extent2d(800, 600));
+        assert_eq!(to_window_size(extent), PhysicalSize::new(800, 600));
+        assert_eq!(transposed(extent), extent2d(600, 800));
+    }
+}
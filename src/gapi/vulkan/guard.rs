@@ -0,0 +1,89 @@
+use std::ops::Deref;
+
+/// # Scope Guard
+/// Holds a freshly created Vulkan object and destroys it when dropped, unless it was released
+/// with [`Guard::into_inner`].
+///
+/// Our wrappers have no `Drop`, since they are destroyed explicitly and in a specific order. This
+/// means that an early return with `?` between two creation steps leaks everything created
+/// before it. Wrapping each step's result in a guard makes those early returns clean up, in
+/// reverse creation order, as locals are dropped.
+pub(crate) struct Guard<T, F: FnOnce(&T)> {
+    value: Option<T>,
+    on_drop: Option<F>,
+}
+
+impl<T, F: FnOnce(&T)> Guard<T, F> {
+    /// Guards `value`, calling `on_drop` on it if the guard goes out of scope.
+    pub fn new(value: T, on_drop: F) -> Self {
+        Self {
+            value: Some(value),
+            on_drop: Some(on_drop),
+        }
+    }
+
+    /// Disarms the guard, handing the ownership of the value back to the caller.
+    pub fn into_inner(mut self) -> T {
+        self.on_drop = None;
+        self.value.take().expect("Guard value is only taken once.")
+    }
+}
+
+impl<T, F: FnOnce(&T)> Deref for Guard<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.as_ref().expect("Guard value is only taken once.")
+    }
+}
+
+impl<T, F: FnOnce(&T)> Drop for Guard<T, F> {
+    fn drop(&mut self) {
+        if let (Some(value), Some(on_drop)) = (self.value.take(), self.on_drop.take()) {
+            on_drop(&value);
+        }
+    }
+}
+
+/// Fails when a failure-injection test asks for it, see [`for_each_failure_point`], to check that
+/// an error at this step destroys everything guarded before it. Does nothing outside of the tests.
+///
+/// [`for_each_failure_point`]: crate::gapi::vulkan::test_support::for_each_failure_point
+pub(crate) fn failure_point() -> anyhow::Result<()> {
+    #[cfg(test)]
+    crate::gapi::vulkan::test_support::fail_if_injected()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gapi::vulkan::test_support::for_each_failure_point;
+    use std::cell::Cell;
+
+    #[test]
+    fn a_failure_at_any_step_drops_the_guarded_values() {
+        let live = Cell::new(0);
+        let create = || {
+            live.set(live.get() + 1);
+            Guard::new((), |_| live.set(live.get() - 1))
+        };
+        let mut failure_points = Vec::new();
+        let attempt = || -> anyhow::Result<[(); 3]> {
+            let first = create();
+            failure_point()?;
+            let second = create();
+            failure_point()?;
+            let third = create();
+            failure_point()?;
+            Ok([first.into_inner(), second.into_inner(), third.into_inner()])
+        };
+        let check = |fail_at| {
+            assert_eq!(live.get(), 0, "Failing at failure point {fail_at} leaked.");
+            failure_points.push(fail_at);
+        };
+        for_each_failure_point(attempt, check);
+        assert_eq!(failure_points, [0, 1, 2]);
+        assert_eq!(live.get(), 3);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::guard::Guard;
+use anyhow::{bail, Context};
+use log::{debug, error};
+use std::ops::Range;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// A Vulkan buffer with its own memory allocation.
+pub struct Buffer {
+    vk_buffer: vk::Buffer,
+    memory: vk::DeviceMemory,
+    /// The size requested on creation, the allocation may be bigger.
+    size: vk::DeviceSize,
+    /// The size of the allocation.
+    allocation_size: vk::DeviceSize,
+    /// Whether host writes are visible to the device without flushing them. Only meaningful for
+    /// `HOST_VISIBLE` memory.
+    coherent: bool,
+    /// The alignment of the flushed ranges of non-coherent memory.
+    non_coherent_atom_size: vk::DeviceSize,
+    usage: vk::BufferUsageFlags,
+}
+
+impl Buffer {
+    /// Creates a buffer of `size` bytes for `usage`, backed by memory with all the `properties`.
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        properties: vk::MemoryPropertyFlags,
+    ) -> anyhow::Result<Self> {
+        // Like the images, buffers can be owned by a single queue family at a time.
+        let info = vk::BufferCreateInfo::builder()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        debug!("Created BufferCreateInfo struct: {info:#?}");
+
+        let vk_buffer = device
+            .create_buffer(&info)
+            .with_context(|| format!("Failed to create buffer of {size} bytes for {usage:?}"))?;
+        let vk_buffer = Guard::new(vk_buffer, |buffer| device.destroy_buffer(*buffer));
+
+        let requirements = device.get_buffer_memory_requirements(*vk_buffer);
+        let memory_type_index =
+            real_device.find_memory_type_index(requirements.memory_type_bits, properties)?;
+        // The chosen memory type may have more properties than requested, e.g. be coherent even
+        // if only HOST_VISIBLE was asked for.
+        let memory_type = real_device.get_memory_properties().memory_types[memory_type_index as usize];
+        let coherent = memory_type
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_COHERENT);
+        // Buffers with a device address need memory that can be addressed too.
+        let mut allocate_flags =
+            vk::MemoryAllocateFlagsInfo::builder().flags(vk::MemoryAllocateFlags::DEVICE_ADDRESS);
+        let mut allocate_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index);
+        if usage.contains(vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS) {
+            allocate_info = allocate_info.push_next(&mut allocate_flags);
+        }
+        let memory = device
+            .allocate_memory(&allocate_info)
+            .with_context(|| format!("Failed to allocate memory for buffer {:?}", *vk_buffer))?;
+        let memory = Guard::new(memory, |memory| device.free_memory(*memory));
+        device.bind_buffer_memory(*vk_buffer, *memory, 0)?;
+
+        Ok(Self {
+            vk_buffer: vk_buffer.into_inner(),
+            memory: memory.into_inner(),
+            size,
+            allocation_size: requirements.size,
+            coherent,
+            non_coherent_atom_size: real_device.get_properties().limits.non_coherent_atom_size,
+            usage,
+        })
+    }
+
+    /// Maps the whole buffer, which must be `HOST_VISIBLE`, to write to it from the CPU.
+    /// It is unmapped when the returned [`MappedBuffer`] is dropped, flushing the written ranges
+    /// first if the memory isn't `HOST_COHERENT`.
+    ///
+    /// The whole allocation is mapped, not only the requested size, as the flushed ranges are
+    /// rounded up to the atom size and must stay within the mapped range.
+    pub fn map<'a>(&'a self, device: &'a LogicalDevice) -> anyhow::Result<MappedBuffer<'a>> {
+        let ptr = device
+            .map_memory(self.memory, 0, vk::WHOLE_SIZE as vk::DeviceSize)
+            .with_context(|| format!("Failed to map buffer {:?}", self.vk_buffer))?;
+        Ok(MappedBuffer {
+            buffer: self,
+            device,
+            ptr: ptr.cast(),
+            written: None,
+        })
+    }
+
+    /// Copies `data` to the start of the buffer, which must be `HOST_VISIBLE`.
+    pub fn write<T: Copy>(&self, device: &LogicalDevice, data: &[T]) -> anyhow::Result<()> {
+        let mut mapped = self.map(device)?;
+        mapped.write(0, data)?;
+        mapped.unmap()
+    }
+
+    pub fn get_vk(&self) -> vk::Buffer {
+        self.vk_buffer
+    }
+
+    /// The address shaders can dereference to read or write the buffer, e.g. for GPU-driven
+    /// rendering.
+    ///
+    /// Needs Vulkan 1.2 or `VK_KHR_buffer_device_address`, with the `bufferDeviceAddress`
+    /// feature enabled at device creation, and the buffer must have been created with the
+    /// `SHADER_DEVICE_ADDRESS` usage.
+    ///
+    /// # Errors
+    /// If the device doesn't support buffer device addresses, or the buffer lacks the usage.
+    pub fn device_address(&self, device: &LogicalDevice) -> anyhow::Result<vk::DeviceAddress> {
+        if !device.capabilities().buffer_device_address {
+            bail!(
+                "Failed to get the address of buffer {:?}: the device doesn't support buffer device addresses.",
+                self.vk_buffer
+            );
+        }
+        if !self.usage.contains(vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS) {
+            bail!(
+                "Failed to get the address of buffer {:?}: it wasn't created with the SHADER_DEVICE_ADDRESS usage ({:?}).",
+                self.vk_buffer,
+                self.usage
+            );
+        }
+        Ok(device.get_buffer_device_address(self.vk_buffer))
+    }
+
+    pub fn get_size(&self) -> vk::DeviceSize {
+        self.size
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        device.destroy_buffer(self.vk_buffer);
+        device.free_memory(self.memory);
+    }
+}
+
+/// A mapped [`Buffer`], unmapped when dropped.
+pub struct MappedBuffer<'a> {
+    buffer: &'a Buffer,
+    device: &'a LogicalDevice,
+    ptr: *mut u8,
+    /// The byte range written so far, `start..end`, to flush it if needed.
+    written: Option<(vk::DeviceSize, vk::DeviceSize)>,
+}
+
+impl MappedBuffer<'_> {
+    /// Copies `data` into the buffer at `offset` bytes.
+    pub fn write<T: Copy>(&mut self, offset: vk::DeviceSize, data: &[T]) -> anyhow::Result<()> {
+        let data_size = size_of_val(data) as vk::DeviceSize;
+        let end = offset + data_size;
+        if end > self.buffer.size {
+            bail!(
+                "Failed to write {} bytes at offset {} into buffer {:?} of {} bytes.",
+                data_size,
+                offset,
+                self.buffer.vk_buffer,
+                self.buffer.size
+            );
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr().cast::<u8>(),
+                self.ptr.add(offset as usize),
+                data_size as usize,
+            );
+        }
+        self.written = Some(match self.written {
+            Some((start, written_end)) => (start.min(offset), written_end.max(end)),
+            None => (offset, end),
+        });
+        Ok(())
+    }
+
+    /// Flushes the written range if the memory isn't coherent, so the device sees the writes.
+    pub fn flush(&mut self) -> anyhow::Result<()> {
+        let Some((start, end)) = self.written.take() else {
+            return Ok(());
+        };
+        if self.buffer.coherent {
+            return Ok(());
+        }
+        let (offset, size) = flush_range(
+            start..end,
+            self.buffer.non_coherent_atom_size,
+            self.buffer.allocation_size,
+        );
+        let range = vk::MappedMemoryRange::builder()
+            .memory(self.buffer.memory)
+            .offset(offset)
+            .size(size)
+            .build();
+        self.device
+            .flush_mapped_memory_ranges(&[range])
+            .with_context(|| format!("Failed to flush buffer {:?}", self.buffer.vk_buffer))
+    }
+
+    /// Flushes and unmaps the buffer, reporting the flush errors that dropping it only logs.
+    pub fn unmap(mut self) -> anyhow::Result<()> {
+        self.flush()
+    }
+}
+
+/// The `offset` and `size` of the flushed range covering the `written` bytes of a mapping of the
+/// whole allocation of `allocation_size` bytes.
+///
+/// The range must be aligned to the atom size, or reach the end of the mapping with
+/// `VK_WHOLE_SIZE`, as rounding it up may go past the allocation.
+fn flush_range(
+    written: Range<vk::DeviceSize>,
+    atom_size: vk::DeviceSize,
+    allocation_size: vk::DeviceSize,
+) -> (vk::DeviceSize, vk::DeviceSize) {
+    let atom = atom_size.max(1);
+    let offset = written.start / atom * atom;
+    let aligned_end = written.end.div_ceil(atom) * atom;
+    if aligned_end >= allocation_size {
+        (offset, vk::WHOLE_SIZE as vk::DeviceSize)
+    } else {
+        (offset, aligned_end - offset)
+    }
+}
+
+impl Drop for MappedBuffer<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("{e:?}");
+        }
+        self.device.unmap_memory(self.buffer.memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHOLE: vk::DeviceSize = vk::WHOLE_SIZE as vk::DeviceSize;
+
+    #[test]
+    fn flushed_ranges_are_aligned_to_the_atom_size() {
+        assert_eq!(flush_range(0..10, 64, 1024), (0, 64));
+        assert_eq!(flush_range(70..130, 64, 1024), (64, 128));
+        assert_eq!(flush_range(128..192, 64, 1024), (128, 64));
+        // Without an atom size, e.g. on a coherent-only device, the range is flushed as is.
+        assert_eq!(flush_range(3..7, 0, 1024), (3, 4));
+    }
+
+    #[test]
+    fn flushed_ranges_past_the_last_atom_reach_the_end_of_the_mapping() {
+        // A size that isn't a multiple of the atom: rounding up the end would go past it.
+        assert_eq!(flush_range(0..1000, 64, 1000), (0, WHOLE));
+        assert_eq!(flush_range(980..1000, 64, 1000), (960, WHOLE));
+        assert_eq!(flush_range(900..950, 256, 1000), (768, WHOLE));
+        // Ending exactly on the allocation.
+        assert_eq!(flush_range(0..1024, 64, 1024), (0, WHOLE));
+    }
+}
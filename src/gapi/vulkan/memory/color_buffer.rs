@@ -0,0 +1,102 @@
+use anyhow::Context;
+use log::debug;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::guard::Guard;
+use crate::gapi::vulkan::memory::image::{Image, ImageViewConfig};
+use crate::gapi::vulkan::pipeline::render_pass::MsaaResolveMode;
+
+/// The multisampled color attachment of the render pass, used for multisample anti-aliasing.
+///
+/// The swapchain images only have one sample per pixel, so the frame is rendered into this
+/// image and then resolved (averaged) into the swapchain image at the end of the render pass.
+/// Its contents are never needed afterward, so it is a transient attachment, which tiled GPUs
+/// can keep in on-chip memory. With [`MsaaResolveMode::Manual`] it is kept instead, to be
+/// resolved from [`ColorBuffer::get_image`] after the render pass.
+pub struct ColorBuffer {
+    vk_image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: Image,
+}
+
+impl ColorBuffer {
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        samples: vk::SampleCountFlags,
+        resolve: MsaaResolveMode,
+    ) -> anyhow::Result<Self> {
+        let usage = match resolve {
+            MsaaResolveMode::InRenderPass => {
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT
+            }
+            MsaaResolveMode::Manual => {
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC
+            }
+        };
+        let info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::_2D)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            // Multisampled images can't have mipmaps.
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(usage)
+            .samples(samples)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        debug!("Created ImageCreateInfo struct: {info:#?}");
+
+        let vk_image = device
+            .create_image(&info)
+            .with_context(|| "Failed to create multisampled color image")?;
+        let vk_image = Guard::new(vk_image, |image| device.destroy_image(*image));
+
+        let requirements = device.get_image_memory_requirements(*vk_image);
+        let memory_type_index = real_device.find_memory_type_index(
+            requirements.memory_type_bits,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let allocate_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index);
+        let memory = device
+            .allocate_memory(&allocate_info)
+            .with_context(|| "Failed to allocate multisampled color image memory")?;
+        let memory = Guard::new(memory, |memory| device.free_memory(*memory));
+        device.bind_image_memory(*vk_image, *memory, 0)?;
+
+        let view = Image::new(&vk_image, &format, &ImageViewConfig::default(), device)
+            .with_context(|| "Failed to create multisampled color image view")?;
+
+        Ok(Self {
+            vk_image: vk_image.into_inner(),
+            memory: memory.into_inner(),
+            view,
+        })
+    }
+
+    pub fn get_view(&self) -> &Image {
+        &self.view
+    }
+
+    /// The multisampled image, to resolve it manually.
+    pub fn get_image(&self) -> vk::Image {
+        self.vk_image
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.view.destroy(device);
+        device.destroy_image(self.vk_image);
+        device.free_memory(self.memory);
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::Context;
+use log::{debug, info};
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::guard::Guard;
+use crate::gapi::vulkan::memory::image::{Image, ImageViewConfig};
+
+/// Depth formats that also store stencil values, ordered by preference.
+const DEPTH_STENCIL_FORMATS: &[vk::Format] = &[
+    vk::Format::D24_UNORM_S8_UINT,
+    vk::Format::D32_SFLOAT_S8_UINT,
+];
+
+/// Which depth formats the [`DepthBuffer`] tries first. The stencil test always gets a format
+/// with a stencil component, whatever the preference.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthFormatPreference {
+    /// `D32_SFLOAT` first, the most precise depth, which reduces z-fighting on distant voxels.
+    /// It has no stencil component.
+    #[default]
+    HighPrecision,
+    /// `D24_UNORM_S8_UINT` first, for features that need a stencil (e.g. selection outlines)
+    /// even when the stencil test isn't configured yet.
+    WithStencil,
+    /// `D16_UNORM` first, the smallest and fastest to read and write, at the cost of precision.
+    Efficient,
+}
+
+impl DepthFormatPreference {
+    /// The formats to try in order, only the ones with a stencil component if `stencil` is true.
+    pub fn candidates(self, stencil: bool) -> Vec<vk::Format> {
+        let preferred: &[vk::Format] = match self {
+            DepthFormatPreference::HighPrecision => &[
+                vk::Format::D32_SFLOAT,
+                vk::Format::D32_SFLOAT_S8_UINT,
+                vk::Format::D24_UNORM_S8_UINT,
+            ],
+            DepthFormatPreference::WithStencil => DEPTH_STENCIL_FORMATS,
+            DepthFormatPreference::Efficient => &[
+                vk::Format::D16_UNORM,
+                vk::Format::X8_D24_UNORM_PACK32,
+                vk::Format::D24_UNORM_S8_UINT,
+                vk::Format::D32_SFLOAT,
+            ],
+        };
+        if !stencil {
+            return preferred.to_vec();
+        }
+        // Keeps the order of the preference for the formats with a stencil component, then the
+        // other stencil formats.
+        let mut candidates = Vec::new();
+        for &format in preferred.iter().chain(DEPTH_STENCIL_FORMATS) {
+            if DepthBuffer::has_stencil(format) && !candidates.contains(&format) {
+                candidates.push(format);
+            }
+        }
+        candidates
+    }
+}
+
+/// The depth (and stencil) attachment of the render pass.
+///
+/// Unlike the swapchain images, this image is created and owned by us, so we also have to
+/// allocate and free its memory.
+pub struct DepthBuffer {
+    vk_image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: Image,
+    format: vk::Format,
+}
+
+impl DepthBuffer {
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        extent: vk::Extent2D,
+        stencil: bool,
+        preference: DepthFormatPreference,
+        samples: vk::SampleCountFlags,
+    ) -> anyhow::Result<Self> {
+        let format = Self::find_format(real_device, stencil, preference)?;
+        info!("Depth buffer format: {:?} (preference {:?}, stencil {})", format, preference, stencil);
+
+        let info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::_2D)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(format)
+            // Optimal tiling lets the driver lay out the texels however is fastest, we never read
+            // them from the CPU.
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
+            // Must match the samples of the color attachment.
+            .samples(samples)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        debug!("Created ImageCreateInfo struct: {info:#?}");
+
+        let vk_image = device
+            .create_image(&info)
+            .with_context(|| "Failed to create depth image")?;
+        let vk_image = Guard::new(vk_image, |image| device.destroy_image(*image));
+
+        let requirements = device.get_image_memory_requirements(*vk_image);
+        let memory_type_index = real_device.find_memory_type_index(
+            requirements.memory_type_bits,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let allocate_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index);
+        let memory = device
+            .allocate_memory(&allocate_info)
+            .with_context(|| "Failed to allocate depth image memory")?;
+        let memory = Guard::new(memory, |memory| device.free_memory(*memory));
+        device.bind_image_memory(*vk_image, *memory, 0)?;
+
+        // The aspect mask of the view follows the format, so it includes the stencil aspect
+        // when the format has one.
+        let view = Image::new(&vk_image, &format, &ImageViewConfig::default(), device)
+            .with_context(|| "Failed to create depth image view")?;
+
+        Ok(Self {
+            vk_image: vk_image.into_inner(),
+            memory: memory.into_inner(),
+            view,
+            format,
+        })
+    }
+
+    /// Finds the first supported depth format of the `preference`, which also has a stencil
+    /// component if `stencil` is true.
+    pub fn find_format(
+        real_device: &RealDevice,
+        stencil: bool,
+        preference: DepthFormatPreference,
+    ) -> anyhow::Result<vk::Format> {
+        let candidates = preference.candidates(stencil);
+        real_device
+            .find_supported_format(
+                &candidates,
+                vk::ImageTiling::OPTIMAL,
+                vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
+            )
+            .with_context(|| "Failed to find a supported depth format")
+    }
+
+    /// Whether the `format` has a stencil component.
+    pub fn has_stencil(format: vk::Format) -> bool {
+        Image::aspect_mask(format).contains(vk::ImageAspectFlags::STENCIL)
+    }
+
+    pub fn get_view(&self) -> &Image {
+        &self.view
+    }
+
+    pub fn get_image(&self) -> vk::Image {
+        self.vk_image
+    }
+
+    pub fn get_format(&self) -> vk::Format {
+        self.format
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.view.destroy(device);
+        device.destroy_image(self.vk_image);
+        device.free_memory(self.memory);
+    }
+}
@@ -11,21 +11,31 @@ pub struct Framebuffer{
 }
 
 impl Framebuffer {
-    pub fn new(render_pass: &MyRenderPass, imgs: &[Image], swapchain: &Swapchain, device: &LogicalDevice) -> Self {
+    pub fn new(render_pass: &MyRenderPass, imgs: &[&Image], swapchain: &Swapchain, device: &LogicalDevice) -> Self {
+        Self::with_extent(render_pass, imgs, swapchain.extent, device).unwrap()
+    }
+
+    /// Creates a framebuffer of any size, e.g. for a [`RenderTarget`](super::render_target::RenderTarget).
+    pub fn with_extent(
+        render_pass: &MyRenderPass,
+        imgs: &[&Image],
+        extent: vk::Extent2D,
+        device: &LogicalDevice,
+    ) -> anyhow::Result<Self> {
         let attachments = imgs.iter().map(|image| *image.get_vk()).collect::<Vec<_>>();
         let create_info = vk::FramebufferCreateInfo::builder()
             .render_pass(render_pass.get_vk())
             .attachments(attachments.as_slice())
-            .width(swapchain.extent.width)
-            .height(swapchain.extent.height)
+            .width(extent.width)
+            .height(extent.height)
             .layers(1)
             .build();
         debug!("Created FramebufferCreateInfo struct: {create_info:#?}");
 
-        let framebuffer = device.create_framebuffer(&create_info).unwrap();
-        Self {
+        let framebuffer = device.create_framebuffer(&create_info)?;
+        Ok(Self {
             framebuffer
-        }
+        })
     }
 
     pub fn get_vk(&self) -> vk::Framebuffer {
@@ -4,6 +4,38 @@ use vulkanalia::vk;
 use vulkanalia::vk::HasBuilder;
 use crate::gapi::vulkan::core::logical_device::LogicalDevice;
 
+/// Which part of an image an [`Image`] view accesses, and how it interprets it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageViewConfig {
+    /// e.g. `_2D_ARRAY` for texture arrays, or `CUBE` for cubemaps, which must be compatible
+    /// with the image type and layer count.
+    pub view_type: vk::ImageViewType,
+    /// The aspects accessed, or `None` for all the aspects of the format, see
+    /// [`Image::aspect_mask`].
+    pub aspect: Option<vk::ImageAspectFlags>,
+    pub base_mip_level: u32,
+    /// How many mip levels from `base_mip_level`, `vk::REMAINING_MIP_LEVELS` for all of them.
+    pub level_count: u32,
+    pub base_array_layer: u32,
+    /// How many layers from `base_array_layer`, `vk::REMAINING_ARRAY_LAYERS` for all of them.
+    pub layer_count: u32,
+}
+
+impl Default for ImageViewConfig {
+    /// A 2D view of the first mip level and layer, which is all the swapchain images and the
+    /// attachments have.
+    fn default() -> Self {
+        Self {
+            view_type: vk::ImageViewType::_2D,
+            aspect: None,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Image{
     /// Image View is owned by use, and is referenced by the framebuffer.
@@ -18,7 +50,12 @@ pub struct Image{
 }
 
 impl Image{
-    pub fn new(image: &vk::Image, format: &vk::Format, device: &LogicalDevice) -> anyhow::Result<Self> {
+    pub fn new(
+        image: &vk::Image,
+        format: &vk::Format,
+        config: &ImageViewConfig,
+        device: &LogicalDevice,
+    ) -> anyhow::Result<Self> {
         // Define the color component mapping for the image view
         // This allows swizzle the color channels around.
         // For example, it allows to map all the channels to the red channel for a monochrome texture.
@@ -32,21 +69,22 @@ impl Image{
 
         // The subresource range for the image view describes the image's purpose and which part of
         // the image should be accessed.
-        // Our images will be used as color targets without any mipmapping levels or multiple layers.
+        // By default, our images are used as color or depth/stencil targets (depending on the
+        // format) without any mipmapping levels or multiple layers.
         let subresource_range = vk::ImageSubresourceRange::builder()
-            .aspect_mask(vk::ImageAspectFlags::COLOR)
-            .base_mip_level(0)
-            .level_count(1)
-            .base_array_layer(0)
-            .layer_count(1);
+            .aspect_mask(config.aspect.unwrap_or_else(|| Self::aspect_mask(*format)))
+            .base_mip_level(config.base_mip_level)
+            .level_count(config.level_count)
+            .base_array_layer(config.base_array_layer)
+            .layer_count(config.layer_count);
 
         debug!("Created ImageSubresourceRange struct: {subresource_range:#?}");
 
         let info = vk::ImageViewCreateInfo::builder()
             .image(*image)
-            // The view type represents how the image data should be interpreted
-            // In this case, as it is a 2D image, we use the 2D view type
-            .view_type(vk::ImageViewType::_2D)
+            // The view type represents how the image data should be interpreted, e.g. as a
+            // single 2D image or as an array of them
+            .view_type(config.view_type)
             .format(*format)
             .components(components)
             .subresource_range(subresource_range);
@@ -63,6 +101,41 @@ impl Image{
     }
 
 
+    /// Returns the aspects of an image with the given `format`.
+    /// Depth formats have a depth aspect, and a stencil aspect if they also store stencil
+    /// values (e.g. `D24_UNORM_S8_UINT`). Every other format is treated as a color format.
+    pub fn aspect_mask(format: vk::Format) -> vk::ImageAspectFlags {
+        match format {
+            vk::Format::D16_UNORM | vk::Format::X8_D24_UNORM_PACK32 | vk::Format::D32_SFLOAT => {
+                vk::ImageAspectFlags::DEPTH
+            }
+            vk::Format::S8_UINT => vk::ImageAspectFlags::STENCIL,
+            vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT => {
+                vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+            }
+            _ => vk::ImageAspectFlags::COLOR,
+        }
+    }
+
+    /// The bytes per texel of the uncompressed color formats the textures and render targets
+    /// use, `None` for the others.
+    pub fn texel_size(format: vk::Format) -> Option<vk::DeviceSize> {
+        match format {
+            vk::Format::R8_UNORM => Some(1),
+            vk::Format::R8G8_UNORM => Some(2),
+            vk::Format::B8G8R8A8_SRGB
+            | vk::Format::B8G8R8A8_UNORM
+            | vk::Format::R8G8B8A8_SRGB
+            | vk::Format::R8G8B8A8_UNORM
+            | vk::Format::R32_SFLOAT => Some(4),
+            vk::Format::R16G16B16A16_SFLOAT => Some(8),
+            vk::Format::R32G32B32A32_SFLOAT => Some(16),
+            _ => None,
+        }
+    }
+
     pub fn get_vk(&self) -> &vk::ImageView {
         &self.vk_image_view
     }
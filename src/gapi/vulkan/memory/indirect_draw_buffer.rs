@@ -0,0 +1,123 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::memory::buffer::Buffer;
+use anyhow::{bail, Context};
+use log::debug;
+use vulkanalia::vk;
+
+/// The draw parameters of many chunks, stored in a GPU buffer so they can be drawn with a single
+/// `vkCmdDrawIndexedIndirect`, instead of one draw call per chunk.
+///
+/// The culling step decides which chunks are drawn and writes their commands with
+/// [`IndirectDrawBuffer::write`].
+pub struct IndirectDrawBuffer {
+    buffer: Buffer,
+    /// How many commands fit in the buffer.
+    capacity: usize,
+    /// The commands last written, kept to draw them one by one when the device doesn't
+    /// support `multi_draw_indirect`.
+    commands: Vec<vk::DrawIndexedIndirectCommand>,
+    /// Whether a single indirect draw can execute more than one command.
+    multi_draw_supported: bool,
+}
+
+impl IndirectDrawBuffer {
+    const STRIDE: usize = size_of::<vk::DrawIndexedIndirectCommand>();
+
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        capacity: usize,
+    ) -> anyhow::Result<Self> {
+        if capacity == 0 {
+            bail!("Failed to create an indirect draw buffer without capacity.");
+        }
+        // The commands are rewritten by the CPU every time the visible chunks change.
+        let buffer = Buffer::new(
+            real_device,
+            device,
+            (capacity * Self::STRIDE) as vk::DeviceSize,
+            vk::BufferUsageFlags::INDIRECT_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .with_context(|| "Failed to create indirect draw buffer")?;
+
+        let multi_draw_supported = real_device.capabilities().multi_draw_indirect;
+        debug!(
+            "Created indirect draw buffer for {capacity} commands (multi draw indirect supported: {multi_draw_supported})."
+        );
+
+        Ok(Self {
+            buffer,
+            capacity,
+            commands: Vec::new(),
+            multi_draw_supported,
+        })
+    }
+
+    /// Replaces the commands in the buffer.
+    /// The buffer must not be in use by a command buffer in flight.
+    ///
+    /// # Errors
+    /// If there are more commands than the capacity of the buffer.
+    pub fn write(
+        &mut self,
+        device: &LogicalDevice,
+        commands: &[vk::DrawIndexedIndirectCommand],
+    ) -> anyhow::Result<()> {
+        if commands.len() > self.capacity {
+            bail!(
+                "Failed to write {} draw commands into an indirect draw buffer of {}.",
+                commands.len(),
+                self.capacity
+            );
+        }
+        if !commands.is_empty() {
+            self.buffer.write(device, commands)?;
+        }
+        self.commands = commands.to_vec();
+        Ok(())
+    }
+
+    /// Records the draws of all the written commands.
+    ///
+    /// Without `multi_draw_indirect`, the draw count of an indirect draw must be 0 or 1, so each
+    /// command is drawn with a direct draw instead.
+    pub fn draw(&self, device: &LogicalDevice, command_buffer: vk::CommandBuffer) {
+        if self.commands.is_empty() {
+            return;
+        }
+        if self.multi_draw_supported {
+            device.cmd_draw_indexed_indirect(
+                command_buffer,
+                self.buffer.get_vk(),
+                0,
+                self.commands.len() as u32,
+                Self::STRIDE as u32,
+            );
+        } else {
+            for command in &self.commands {
+                device.draw_indexed(
+                    command_buffer,
+                    command.index_count,
+                    command.instance_count,
+                    command.first_index,
+                    command.vertex_offset,
+                    command.first_instance,
+                );
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.buffer.destroy(device);
+    }
+}
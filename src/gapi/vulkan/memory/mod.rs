@@ -1,3 +1,13 @@
+pub mod buffer;
+pub mod color_buffer;
+pub mod depth_buffer;
 pub mod framebuffer;
 pub mod image;
-pub mod swapchain;
\ No newline at end of file
+pub mod indirect_draw_buffer;
+pub mod render_target;
+pub mod sampler_cache;
+pub mod staging;
+pub mod swapchain;
+pub mod texture_array;
+pub mod upload_manager;
+pub mod vertex_buffer;
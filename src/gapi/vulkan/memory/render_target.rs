@@ -0,0 +1,186 @@
+use crate::gapi::color::Color;
+use crate::gapi::viewport_layout::ViewportRegion;
+use crate::gapi::vulkan::commands::command_buffers::CommandBuffer;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::memory::depth_buffer::{DepthBuffer, DepthFormatPreference};
+use crate::gapi::vulkan::memory::framebuffer::Framebuffer;
+use crate::gapi::vulkan::memory::image::{Image, ImageViewConfig};
+use crate::gapi::vulkan::pipeline::render_pass::MyRenderPass;
+use crate::gapi::vulkan::pipeline::viewport::ViewportYFlip;
+use crate::gapi::vulkan::guard::Guard;
+use anyhow::Context;
+use log::debug;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// An image rendered into and then sampled by later passes, independent of the swapchain, e.g.
+/// a shadow map, a reflection or the input of a post-process effect.
+///
+/// It bundles the color image, an optional depth buffer, the render pass drawing into them and
+/// its framebuffer. Once recorded with [`RenderTarget::record`], the color image is in the
+/// `SHADER_READ_ONLY_OPTIMAL` layout, so the final composite pass can bind
+/// [`RenderTarget::color_view`] to a sampler and write the result to the swapchain.
+///
+/// There is a single color image, so a frame in flight must not render into it while another
+/// still samples it.
+pub struct RenderTarget {
+    vk_image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: Image,
+    depth_buffer: Option<DepthBuffer>,
+    render_pass: MyRenderPass,
+    framebuffer: Framebuffer,
+    extent: vk::Extent2D,
+    format: vk::Format,
+}
+
+impl RenderTarget {
+    /// Creates a target of `extent` pixels, with a depth buffer of the given preference if
+    /// `depth` is set.
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        extent: vk::Extent2D,
+        color_format: vk::Format,
+        depth: Option<DepthFormatPreference>,
+    ) -> anyhow::Result<Self> {
+        let info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::_2D)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .format(color_format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            // Drawn into by the render pass, then read by the shaders of the later passes.
+            .usage(vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED)
+            .samples(vk::SampleCountFlags::_1)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        debug!("Created ImageCreateInfo struct: {info:#?}");
+
+        let vk_image = device
+            .create_image(&info)
+            .with_context(|| "Failed to create render target image")?;
+        let vk_image = Guard::new(vk_image, |image| device.destroy_image(*image));
+
+        let requirements = device.get_image_memory_requirements(*vk_image);
+        let memory_type_index = real_device.find_memory_type_index(
+            requirements.memory_type_bits,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let allocate_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index);
+        let memory = device
+            .allocate_memory(&allocate_info)
+            .with_context(|| "Failed to allocate render target image memory")?;
+        let memory = Guard::new(memory, |memory| device.free_memory(*memory));
+        device.bind_image_memory(*vk_image, *memory, 0)?;
+
+        let view = Image::new(&vk_image, &color_format, &ImageViewConfig::default(), device)
+            .with_context(|| "Failed to create render target image view")?;
+        let view = Guard::new(view, |view| view.destroy(device));
+
+        let depth_buffer = depth
+            .map(|preference| {
+                let stencil = preference == DepthFormatPreference::WithStencil;
+                DepthBuffer::new(real_device, device, extent, stencil, preference, vk::SampleCountFlags::_1)
+            })
+            .transpose()
+            .with_context(|| "Failed to create render target depth buffer")?;
+        let depth_buffer = Guard::new(depth_buffer, |depth_buffer| {
+            if let Some(depth_buffer) = depth_buffer {
+                depth_buffer.destroy(device);
+            }
+        });
+
+        let depth_format = depth_buffer.as_ref().map(DepthBuffer::get_format);
+        let render_pass = MyRenderPass::offscreen(device, color_format, depth_format)
+            .with_context(|| "Failed to create render target render pass")?;
+        let render_pass = Guard::new(render_pass, |render_pass| render_pass.destroy(device));
+
+        // The attachments are in the order of the render pass: the color, then the depth.
+        let mut attachments = vec![&*view];
+        if let Some(depth_buffer) = depth_buffer.as_ref() {
+            attachments.push(depth_buffer.get_view());
+        }
+        let framebuffer = Framebuffer::with_extent(&render_pass, &attachments, extent, device)
+            .with_context(|| "Failed to create render target framebuffer")?;
+
+        debug!("Created render target of {extent:?} with format {color_format:?} and depth {depth_format:?}.");
+        Ok(Self {
+            vk_image: vk_image.into_inner(),
+            memory: memory.into_inner(),
+            view: view.into_inner(),
+            depth_buffer: depth_buffer.into_inner(),
+            render_pass: render_pass.into_inner(),
+            framebuffer,
+            extent,
+            format: color_format,
+        })
+    }
+
+    /// Records a pass drawing into the target: begins its render pass, cleared to
+    /// `clear_color`, covers the whole target with the viewport and scissor, flipped according to
+    /// `y_flip`, lets `draw` record
+    /// the draws, and ends the render pass.
+    ///
+    /// The pipelines bound by `draw` must be created for [`RenderTarget::render_pass`].
+    pub fn record<F>(
+        &self,
+        device: &LogicalDevice,
+        command_buffer: &CommandBuffer,
+        clear_color: Color,
+        y_flip: ViewportYFlip,
+        draw: F,
+    ) -> anyhow::Result<()>
+    where
+        F: FnOnce(&CommandBuffer) -> anyhow::Result<()>,
+    {
+        self.render_pass
+            .begin(device, &self.framebuffer, command_buffer, self.extent, clear_color);
+        let (viewport, scissor) = ViewportRegion::FULL.to_vk(self.extent, y_flip);
+        device.set_viewport(*command_buffer.get_vk(), &[viewport]);
+        device.set_scissor(*command_buffer.get_vk(), &[scissor]);
+        // The render pass must be ended even if the draws fail, for the command buffer to be
+        // reset cleanly.
+        let drawn = draw(command_buffer);
+        self.render_pass.end(device, *command_buffer.get_vk());
+        drawn
+    }
+
+    /// The render pass the pipelines drawing into the target must be created for.
+    pub fn render_pass(&self) -> &MyRenderPass {
+        &self.render_pass
+    }
+
+    /// The view of the color image, to sample it in the later passes.
+    pub fn color_view(&self) -> &Image {
+        &self.view
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    pub fn format(&self) -> vk::Format {
+        self.format
+    }
+
+    /// Destroys the target, which must not be in use by a command buffer in flight.
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.framebuffer.destroy(device);
+        self.render_pass.destroy(device);
+        if let Some(depth_buffer) = &self.depth_buffer {
+            depth_buffer.destroy(device);
+        }
+        self.view.destroy(device);
+        device.destroy_image(self.vk_image);
+        device.free_memory(self.memory);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::quality::QualityDowngradePolicy;
+use anyhow::{bail, Context};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// The parameters of a sampler, see [`SamplerCache`].
+///
+/// The default is a linear filter blending linearly between the mip levels, which is smooth at
+/// any distance. [`SamplerDesc::NEAREST_REPEAT`] keeps the texels sharp instead.
+#[derive(Clone, Copy, Debug)]
+pub struct SamplerDesc {
+    /// The magnification and minification filter.
+    pub filter: vk::Filter,
+    /// How the mip levels are blended, if the image has several.
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    /// What is read outside of the image, along every axis.
+    pub address_mode: vk::SamplerAddressMode,
+    /// How many samples are taken along the steepest axis of anisotropic filtering, e.g. 16,
+    /// `None` disables it. Lowered to what the device supports by the downgrade policy of the
+    /// [`SamplerCache`].
+    pub anisotropy: Option<u32>,
+    /// Added to the mip level the hardware picks, negative values sharpen distant faces at the
+    /// cost of aliasing, positive ones blur them. Clamped to the `max_sampler_lod_bias` of the
+    /// device.
+    pub mip_lod_bias: f32,
+    /// The most detailed mip level that can be sampled, e.g. 1 to never read the full
+    /// resolution.
+    pub min_lod: f32,
+    /// The least detailed mip level that can be sampled, e.g. to stop the small mips of an
+    /// atlas from blending neighbouring tiles. [`vk::LOD_CLAMP_NONE`] doesn't clamp.
+    pub max_lod: f32,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self {
+            filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_mode: vk::SamplerAddressMode::REPEAT,
+            anisotropy: None,
+            mip_lod_bias: 0.0,
+            min_lod: 0.0,
+            max_lod: vk::LOD_CLAMP_NONE,
+        }
+    }
+}
+
+impl SamplerDesc {
+    /// Sharp texels, repeated over merged faces, as used by the voxel textures.
+    pub const NEAREST_REPEAT: Self = Self {
+        filter: vk::Filter::NEAREST,
+        mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+        address_mode: vk::SamplerAddressMode::REPEAT,
+        anisotropy: None,
+        mip_lod_bias: 0.0,
+        min_lod: 0.0,
+        max_lod: vk::LOD_CLAMP_NONE,
+    };
+
+    pub const fn with_mipmap_mode(mut self, mipmap_mode: vk::SamplerMipmapMode) -> Self {
+        self.mipmap_mode = mipmap_mode;
+        self
+    }
+
+    pub const fn with_mip_lod_bias(mut self, mip_lod_bias: f32) -> Self {
+        self.mip_lod_bias = mip_lod_bias;
+        self
+    }
+
+    /// Only samples the mip levels between `min_lod` and `max_lod`.
+    pub const fn with_lod_range(mut self, min_lod: f32, max_lod: f32) -> Self {
+        self.min_lod = min_lod;
+        self.max_lod = max_lod;
+        self
+    }
+
+    /// The fields compared and hashed, with the floats by their bits: two descriptions only
+    /// share a sampler if they are exactly the same.
+    fn key(&self) -> impl Eq + Hash {
+        (
+            self.filter,
+            self.mipmap_mode,
+            self.address_mode,
+            self.anisotropy,
+            self.mip_lod_bias.to_bits(),
+            self.min_lod.to_bits(),
+            self.max_lod.to_bits(),
+        )
+    }
+}
+
+impl PartialEq for SamplerDesc {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SamplerDesc {}
+
+impl Hash for SamplerDesc {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// Shares one sampler between every texture with the same [`SamplerDesc`].
+///
+/// A sampler is only state describing how to read an image, it doesn't reference any, so there
+/// is no reason to create one per texture. The samplers are created the first time they are
+/// requested, and all destroyed together with [`SamplerCache::destroy`], so the textures only
+/// borrow their handle and must not destroy it.
+#[derive(Debug, Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerDesc, vk::Sampler>,
+    /// Applied when the anisotropy of a description exceeds what the device supports.
+    downgrade_policy: QualityDowngradePolicy,
+}
+
+impl SamplerCache {
+    pub fn new(downgrade_policy: QualityDowngradePolicy) -> Self {
+        Self {
+            samplers: HashMap::new(),
+            downgrade_policy,
+        }
+    }
+
+    /// The sampler of `desc`, created if it is the first time it is requested.
+    ///
+    /// The anisotropy is lowered to what the device supports, or disabled if it doesn't support
+    /// anisotropic filtering, following the [`QualityDowngradePolicy`] of the cache. The LOD bias
+    /// is clamped to what the device supports.
+    ///
+    /// # Errors
+    /// If `min_lod` is greater than `max_lod`, the anisotropy isn't supported and the policy is
+    /// [`QualityDowngradePolicy::Error`], or the sampler can't be created.
+    pub fn get(&mut self, device: &LogicalDevice, desc: SamplerDesc) -> anyhow::Result<vk::Sampler> {
+        if let Some(&sampler) = self.samplers.get(&desc) {
+            return Ok(sampler);
+        }
+        if desc.min_lod > desc.max_lod {
+            bail!("Invalid sampler LOD range {}..{} for {desc:?}", desc.min_lod, desc.max_lod);
+        }
+        let max_bias = device.capabilities().max_sampler_lod_bias;
+        let mip_lod_bias = desc.mip_lod_bias.clamp(-max_bias, max_bias);
+        if mip_lod_bias != desc.mip_lod_bias {
+            warn!("LOD bias {} exceeds the device limit of {max_bias}, clamping it.", desc.mip_lod_bias);
+        }
+        let supported = match (desc.anisotropy, device.capabilities().max_sampler_anisotropy) {
+            (Some(requested), Some(max)) => Some(requested.min(max)),
+            _ => None,
+        };
+        let anisotropy = self.downgrade_policy.apply("Sampler anisotropy", desc.anisotropy, supported)?;
+        let info = vk::SamplerCreateInfo::builder()
+            .mag_filter(desc.filter)
+            .min_filter(desc.filter)
+            .mipmap_mode(desc.mipmap_mode)
+            .address_mode_u(desc.address_mode)
+            .address_mode_v(desc.address_mode)
+            .address_mode_w(desc.address_mode)
+            .anisotropy_enable(anisotropy.is_some())
+            .max_anisotropy(anisotropy.unwrap_or(1) as f32)
+            .mip_lod_bias(mip_lod_bias)
+            .min_lod(desc.min_lod)
+            .max_lod(desc.max_lod);
+        let sampler = device
+            .create_sampler(&info)
+            .with_context(|| format!("Failed to create sampler for {desc:?}"))?;
+        debug!("Created sampler {sampler:?} for {desc:?}, {} cached.", self.samplers.len() + 1);
+        self.samplers.insert(desc, sampler);
+        Ok(sampler)
+    }
+
+    /// How many samplers were created.
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+
+    /// Destroys every sampler, which must no longer be used by a texture in use.
+    pub fn destroy(&mut self, device: &LogicalDevice) {
+        for (_, sampler) in self.samplers.drain() {
+            device.destroy_sampler(sampler);
+        }
+    }
+}
@@ -0,0 +1,598 @@
+use crate::gapi::vulkan::commands::command_pool::CommandPool;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::guard::Guard;
+use crate::gapi::vulkan::memory::buffer::Buffer;
+use crate::gapi::vulkan::sync::fence_pool::FencePool;
+use anyhow::{bail, Context};
+use log::{debug, info};
+use std::time::Duration;
+use vulkanalia::vk;
+use vulkanalia::vk::{Handle, HasBuilder};
+
+/// How long to wait for an upload to finish before giving up.
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Hands over a resource from the queue family that wrote it to the one that uses it.
+///
+/// Resources created with `EXCLUSIVE` sharing are owned by a single queue family. When the
+/// families differ, the source family must release the resource and the destination family
+/// acquire it, with a pair of barriers using the same family indices. Each half is recorded in a
+/// command buffer submitted to its own family, and the acquire must wait for the release.
+///
+/// When both families are the same, no ownership transfer is needed, and a single barrier with
+/// `QUEUE_FAMILY_IGNORED` is enough.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueFamilyTransfer {
+    pub src_family: u32,
+    pub dst_family: u32,
+}
+
+impl QueueFamilyTransfer {
+    pub fn new(src_family: u32, dst_family: u32) -> Self {
+        Self {
+            src_family,
+            dst_family,
+        }
+    }
+
+    /// Whether the families differ, so the release/acquire barrier pair is needed.
+    pub fn is_needed(&self) -> bool {
+        self.src_family != self.dst_family
+    }
+
+    /// The family indices to put in the barriers, ignored when no transfer is needed.
+    fn families(&self) -> (u32, u32) {
+        if self.is_needed() {
+            (self.src_family, self.dst_family)
+        } else {
+            (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+        }
+    }
+
+    /// The release half of a buffer transfer, recorded on the source family.
+    /// Its destination access is ignored, the acquire barrier makes the writes visible.
+    pub fn release_buffer(
+        &self,
+        buffer: vk::Buffer,
+        src_access: vk::AccessFlags,
+    ) -> vk::BufferMemoryBarrier {
+        let (src_family, dst_family) = self.families();
+        vk::BufferMemoryBarrier::builder()
+            .src_access_mask(src_access)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .src_queue_family_index(src_family)
+            .dst_queue_family_index(dst_family)
+            .buffer(buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE as vk::DeviceSize)
+            .build()
+    }
+
+    /// The acquire half of a buffer transfer, recorded on the destination family.
+    /// Its source access is ignored, the release barrier made the writes available.
+    pub fn acquire_buffer(
+        &self,
+        buffer: vk::Buffer,
+        dst_access: vk::AccessFlags,
+    ) -> vk::BufferMemoryBarrier {
+        let (src_family, dst_family) = self.families();
+        vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_access_mask(dst_access)
+            .src_queue_family_index(src_family)
+            .dst_queue_family_index(dst_family)
+            .buffer(buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE as vk::DeviceSize)
+            .build()
+    }
+
+    /// Records the barrier after a transfer write to `buffer` on the source family: the release
+    /// half if needed, otherwise a regular barrier making the write visible to its first use.
+    pub fn record_buffer_release(
+        &self,
+        device: &LogicalDevice,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        dst_stage: vk::PipelineStageFlags,
+        dst_access: vk::AccessFlags,
+    ) {
+        let release = self.release_buffer(buffer, vk::AccessFlags::TRANSFER_WRITE);
+        if self.is_needed() {
+            // The destination stage of a release is ignored.
+            device.pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                &[release],
+                &[],
+            );
+        } else {
+            // Fast path: a regular barrier makes the copy visible to the first use.
+            let barrier = vk::BufferMemoryBarrier {
+                dst_access_mask: dst_access,
+                ..release
+            };
+            device.pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                dst_stage,
+                &[barrier],
+                &[],
+            );
+        }
+    }
+
+    /// Records the acquire half of a buffer transfer on the destination family.
+    pub fn record_buffer_acquire(
+        &self,
+        device: &LogicalDevice,
+        command_buffer: vk::CommandBuffer,
+        buffer: vk::Buffer,
+        dst_stage: vk::PipelineStageFlags,
+        dst_access: vk::AccessFlags,
+    ) {
+        // The source stage of an acquire is ignored, the semaphore wait orders it.
+        device.pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            dst_stage,
+            &[self.acquire_buffer(buffer, dst_access)],
+            &[],
+        );
+    }
+
+    /// The release half of an image transfer, e.g. after copying a texture.
+    /// The layout transition must be the same in both halves, and is only executed once.
+    pub fn release_image(
+        &self,
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+        (old_layout, new_layout): (vk::ImageLayout, vk::ImageLayout),
+        src_access: vk::AccessFlags,
+    ) -> vk::ImageMemoryBarrier {
+        let (src_family, dst_family) = self.families();
+        vk::ImageMemoryBarrier::builder()
+            .src_access_mask(src_access)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(src_family)
+            .dst_queue_family_index(dst_family)
+            .image(image)
+            .subresource_range(subresource_range)
+            .build()
+    }
+
+    /// The acquire half of an image transfer, see [`QueueFamilyTransfer::release_image`].
+    pub fn acquire_image(
+        &self,
+        image: vk::Image,
+        subresource_range: vk::ImageSubresourceRange,
+        (old_layout, new_layout): (vk::ImageLayout, vk::ImageLayout),
+        dst_access: vk::AccessFlags,
+    ) -> vk::ImageMemoryBarrier {
+        let (src_family, dst_family) = self.families();
+        vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::empty())
+            .dst_access_mask(dst_access)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(src_family)
+            .dst_queue_family_index(dst_family)
+            .image(image)
+            .subresource_range(subresource_range)
+            .build()
+    }
+}
+
+/// The queues and command pools uploads are submitted with.
+///
+/// The copies are submitted to the transfer queue if the device has one, otherwise to the
+/// graphics queue. When the transfer queue belongs to another family, the ownership of the
+/// uploaded resources is handed over to the graphics family, see [`QueueFamilyTransfer`].
+pub(crate) struct UploadQueues {
+    pub transfer_queue: vk::Queue,
+    pub transfer_pool: CommandPool,
+    pub graphics_queue: vk::Queue,
+    /// Only created when the transfer family differs from the graphics one, to record the
+    /// acquire barriers.
+    pub graphics_pool: Option<CommandPool>,
+    pub ownership: QueueFamilyTransfer,
+}
+
+impl UploadQueues {
+    pub fn new(
+        device: &LogicalDevice,
+        pool_flags: vk::CommandPoolCreateFlags,
+    ) -> anyhow::Result<Self> {
+        let queues = device.get_queues();
+        let graphics_family = device.graphics_family_index();
+        let graphics_queue = queues.graphics_queue()?;
+        let (transfer_family, transfer_queue) = match device.transfer_family_index() {
+            Some(family) => (family, queues.transfer_queue()?),
+            None => (graphics_family, graphics_queue),
+        };
+        let ownership = QueueFamilyTransfer::new(transfer_family, graphics_family);
+        info!("Uploads use queue family {transfer_family} (graphics family {graphics_family}).");
+
+        let transfer_pool = CommandPool::for_family(device, transfer_family, pool_flags)
+            .with_context(|| "Failed to create the transfer command pool")?;
+        let graphics_pool = if ownership.is_needed() {
+            match CommandPool::for_family(device, graphics_family, pool_flags) {
+                Ok(pool) => Some(pool),
+                Err(e) => {
+                    transfer_pool.destroy(device);
+                    return Err(e.context("Failed to create the ownership acquire command pool"));
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(Self {
+            transfer_queue,
+            transfer_pool,
+            graphics_queue,
+            graphics_pool,
+            ownership,
+        })
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.transfer_pool.destroy(device);
+        if let Some(graphics_pool) = &self.graphics_pool {
+            graphics_pool.destroy(device);
+        }
+    }
+}
+
+/// Uploads data to device local memory through a host visible staging buffer, waiting for each
+/// upload to finish. See [`UploadManager`](super::upload_manager::UploadManager) to upload
+/// without waiting.
+pub struct StagingUploader {
+    queues: UploadQueues,
+    /// The fences of the uploads, reused as they are waited on one upload at a time.
+    fences: FencePool,
+}
+
+impl StagingUploader {
+    pub fn new(device: &LogicalDevice) -> anyhow::Result<Self> {
+        // The command buffers are only submitted once, and freed right after.
+        let queues = UploadQueues::new(device, vk::CommandPoolCreateFlags::TRANSIENT)?;
+        Ok(Self {
+            queues,
+            fences: FencePool::new(),
+        })
+    }
+
+    /// Creates a device local buffer for `usage` with `data` in it, and waits until it is
+    /// uploaded.
+    ///
+    /// `dst_stage` and `dst_access` describe the first use of the buffer on the graphics queue,
+    /// e.g. `VERTEX_INPUT` and `VERTEX_ATTRIBUTE_READ` for a vertex buffer.
+    ///
+    /// # Errors
+    /// If `data` is empty, as Vulkan buffers can't be, or the upload fails.
+    pub fn upload_buffer<T: Copy>(
+        &mut self,
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        data: &[T],
+        usage: vk::BufferUsageFlags,
+        dst_stage: vk::PipelineStageFlags,
+        dst_access: vk::AccessFlags,
+    ) -> anyhow::Result<Buffer> {
+        let size = size_of_val(data) as vk::DeviceSize;
+        if size == 0 {
+            bail!("Can't upload an empty buffer, Vulkan buffers can't be empty.");
+        }
+        let staging = Buffer::new(
+            real_device,
+            device,
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .with_context(|| "Failed to create the staging buffer")?;
+        let result = staging.write(device, data).and_then(|()| {
+            let buffer = Buffer::new(
+                real_device,
+                device,
+                size,
+                usage | vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            )?;
+            match self.copy_buffer(device, &staging, &buffer, dst_stage, dst_access) {
+                Ok(()) => Ok(buffer),
+                Err(e) => {
+                    buffer.destroy(device);
+                    Err(e)
+                }
+            }
+        });
+        staging.destroy(device);
+        result
+    }
+
+    /// Copies `layers` into the first layers of `image`, which must be in the `UNDEFINED`
+    /// layout and have the `TRANSFER_DST` usage, and waits until it is uploaded. The image ends
+    /// up in the `SHADER_READ_ONLY_OPTIMAL` layout, owned by the graphics family.
+    ///
+    /// Each layer holds the tightly packed texels of the first mip level, and they must all be
+    /// the same size. `dst_stage` is the first stage sampling the image, e.g. `FRAGMENT_SHADER`.
+    pub fn upload_image<T: Copy>(
+        &mut self,
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        layers: &[&[T]],
+        image: vk::Image,
+        extent: vk::Extent2D,
+        dst_stage: vk::PipelineStageFlags,
+    ) -> anyhow::Result<()> {
+        // The layers are copied from consecutive ranges of a single staging buffer.
+        let data = layers.concat();
+        let layer_count = layers.len() as u32;
+        let staging = Buffer::new(
+            real_device,
+            device,
+            size_of_val(data.as_slice()) as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .with_context(|| "Failed to create the staging buffer")?;
+        let result = staging.write(device, &data).and_then(|()| {
+            self.copy_buffer_to_image(device, &staging, image, extent, layer_count, dst_stage)
+        });
+        staging.destroy(device);
+        result
+    }
+
+    /// Copies `src` into the layers of `image`, transitioning it for the copy and then for
+    /// sampling, and waits for it, handing `image` over to the graphics family if needed.
+    fn copy_buffer_to_image(
+        &mut self,
+        device: &LogicalDevice,
+        src: &Buffer,
+        image: vk::Image,
+        extent: vk::Extent2D,
+        layer_count: u32,
+        dst_stage: vk::PipelineStageFlags,
+    ) -> anyhow::Result<()> {
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(layer_count)
+            .build();
+        let region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            // Zero means tightly packed.
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(
+                vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .mip_level(0)
+                    .base_array_layer(0)
+                    .layer_count(layer_count)
+                    .build(),
+            )
+            .image_offset(vk::Offset3D::default())
+            .image_extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .build();
+        let ready = (vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+        let ownership = &self.queues.ownership;
+        let copy = |command_buffer: vk::CommandBuffer| {
+            let to_transfer = vk::ImageMemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(image)
+                .subresource_range(subresource_range)
+                .build();
+            device.pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                &[],
+                &[to_transfer],
+            );
+            device.copy_buffer_to_image(
+                command_buffer,
+                src.get_vk(),
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[region],
+            );
+            let release = ownership.release_image(image, subresource_range, ready, vk::AccessFlags::TRANSFER_WRITE);
+            if ownership.is_needed() {
+                // The destination stage of a release is ignored.
+                device.pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    &[],
+                    &[release],
+                );
+            } else {
+                let barrier = vk::ImageMemoryBarrier {
+                    dst_access_mask: vk::AccessFlags::SHADER_READ,
+                    ..release
+                };
+                device.pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    dst_stage,
+                    &[],
+                    &[barrier],
+                );
+            }
+        };
+        let acquire = |command_buffer: vk::CommandBuffer| {
+            let acquire = ownership.acquire_image(image, subresource_range, ready, vk::AccessFlags::SHADER_READ);
+            device.pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                dst_stage,
+                &[],
+                &[acquire],
+            );
+        };
+        Self::submit(&self.queues, &mut self.fences, device, copy, acquire, dst_stage)
+    }
+
+    /// Copies the whole `src` into `dst` and waits for it, handing `dst` over to the graphics
+    /// family if needed.
+    fn copy_buffer(
+        &mut self,
+        device: &LogicalDevice,
+        src: &Buffer,
+        dst: &Buffer,
+        dst_stage: vk::PipelineStageFlags,
+        dst_access: vk::AccessFlags,
+    ) -> anyhow::Result<()> {
+        let region = vk::BufferCopy::builder().size(src.get_size()).build();
+        let ownership = &self.queues.ownership;
+        let copy = |command_buffer: vk::CommandBuffer| {
+            device.copy_buffer(command_buffer, src.get_vk(), dst.get_vk(), &[region]);
+            ownership.record_buffer_release(
+                device,
+                command_buffer,
+                dst.get_vk(),
+                dst_stage,
+                dst_access,
+            );
+        };
+        let acquire = |command_buffer: vk::CommandBuffer| {
+            ownership.record_buffer_acquire(
+                device,
+                command_buffer,
+                dst.get_vk(),
+                dst_stage,
+                dst_access,
+            );
+        };
+        Self::submit(&self.queues, &mut self.fences, device, copy, acquire, dst_stage)
+    }
+
+    /// Records and submits `transfer` to the transfer queue, and `acquire` to the graphics queue
+    /// after it if the families differ, then waits for both.
+    fn submit(
+        queues: &UploadQueues,
+        fences: &mut FencePool,
+        device: &LogicalDevice,
+        transfer: impl FnOnce(vk::CommandBuffer),
+        acquire: impl FnOnce(vk::CommandBuffer),
+        acquire_stage: vk::PipelineStageFlags,
+    ) -> anyhow::Result<()> {
+        // Guarded, so a failure to record or submit doesn't leak what was created before it.
+        let semaphore = match queues.graphics_pool {
+            Some(_) => device.create_semaphore(&vk::SemaphoreCreateInfo::builder())?,
+            None => vk::Semaphore::null(),
+        };
+        let semaphore = Guard::new(semaphore, |semaphore| {
+            if !semaphore.is_null() {
+                device.destroy_semaphore(*semaphore);
+            }
+        });
+        let transfer_cb = Self::record(device, &queues.transfer_pool, transfer)?;
+        let transfer_cb = Guard::new(transfer_cb, |transfer_cb| {
+            device.free_command_buffers(queues.transfer_pool.get_vk(), &[*transfer_cb]);
+        });
+        let acquire_cb = match &queues.graphics_pool {
+            Some(pool) => {
+                let acquire_cb = Self::record(device, pool, acquire)?;
+                Some(Guard::new(acquire_cb, |acquire_cb| {
+                    device.free_command_buffers(pool.get_vk(), &[*acquire_cb]);
+                }))
+            }
+            None => None,
+        };
+
+        fences
+            .acquire(device)
+            .and_then(|fence| {
+                let submitted = submit_transfer(
+                    device,
+                    (queues.transfer_queue, *transfer_cb),
+                    acquire_cb.as_deref().map(|acquire_cb| (queues.graphics_queue, *acquire_cb, acquire_stage)),
+                    *semaphore,
+                    fence,
+                );
+                if let Err(e) = submitted {
+                    fences.release_unused(fence);
+                    return Err(e);
+                }
+                // The graphics submission waits for the transfer one, so its fence covers both.
+                let waited = device.wait_for_fences(&[fence], true, UPLOAD_TIMEOUT);
+                fences.release(fence);
+                waited
+            })
+            .with_context(|| "Failed to upload data to the GPU")
+    }
+
+    /// Allocates a command buffer from `pool` and records `commands` in it, to submit once.
+    fn record(
+        device: &LogicalDevice,
+        pool: &CommandPool,
+        commands: impl FnOnce(vk::CommandBuffer),
+    ) -> anyhow::Result<vk::CommandBuffer> {
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(pool.get_vk())
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        let command_buffer = device.allocate_command_buffers(&allocate_info)?[0];
+        let command_buffer = Guard::new(command_buffer, |command_buffer| {
+            device.free_command_buffers(pool.get_vk(), &[*command_buffer]);
+        });
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        debug!("Created CommandBufferBeginInfo struct: {:#?}", begin_info);
+        device.begin_command_buffer(*command_buffer, &begin_info)?;
+        commands(*command_buffer);
+        device.end_command_buffer(*command_buffer)?;
+        Ok(command_buffer.into_inner())
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.fences.destroy(device);
+        self.queues.destroy(device);
+    }
+}
+
+/// Submits `transfer` to its queue, then `acquire` to its queue waiting at its stage for
+/// `semaphore`, which the transfer signals, if there is an ownership transfer. `fence` is
+/// signaled by the last submission.
+pub(crate) fn submit_transfer(
+    device: &LogicalDevice,
+    (transfer_queue, transfer_cb): (vk::Queue, vk::CommandBuffer),
+    acquire: Option<(vk::Queue, vk::CommandBuffer, vk::PipelineStageFlags)>,
+    semaphore: vk::Semaphore,
+    fence: vk::Fence,
+) -> anyhow::Result<()> {
+    let transfer_cbs = &[transfer_cb];
+    let semaphores = &[semaphore];
+    let transfer_submit = vk::SubmitInfo::builder().command_buffers(transfer_cbs);
+    let Some((acquire_queue, acquire_cb, acquire_stage)) = acquire else {
+        return device.queue_submit(transfer_queue, &[transfer_submit], fence);
+    };
+    let transfer_submit = transfer_submit.signal_semaphores(semaphores);
+    device.queue_submit(transfer_queue, &[transfer_submit], vk::Fence::null())?;
+    let acquire_cbs = &[acquire_cb];
+    let wait_stages = &[acquire_stage];
+    let acquire_submit = vk::SubmitInfo::builder()
+        .wait_semaphores(semaphores)
+        .wait_dst_stage_mask(wait_stages)
+        .command_buffers(acquire_cbs);
+    device.queue_submit(acquire_queue, &[acquire_submit], fence)
+}
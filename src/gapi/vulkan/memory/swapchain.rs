@@ -1,13 +1,370 @@
 use crate::window::MyWindow;
-use anyhow::Context;
+use anyhow::{bail, Context};
 use log::__private_api::loc;
-use log::{debug, info};
+use log::{debug, info, warn};
 use vulkanalia::vk;
 use vulkanalia::vk::{Format, Handle, HasBuilder};
 use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::queues::Queues;
 use crate::gapi::vulkan::core::real_device::RealDevice;
 use crate::gapi::vulkan::core::surface::Surface;
-use crate::gapi::vulkan::memory::image::Image;
+use crate::gapi::vulkan::extent::{extent2d, from_window_size, transposed};
+use crate::gapi::vulkan::memory::image::{Image, ImageViewConfig};
+
+/// Policy for how the swapchain images are shared between the graphics and present queue
+/// families.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageSharingPolicy {
+    /// Images are owned by one queue family at a time, which is faster but needs explicit
+    /// ownership transfers when graphics and present are different families.
+    /// Those transfers are not implemented yet, so it falls back to
+    /// [`ImageSharingPolicy::Concurrent`] when the families differ.
+    #[default]
+    Exclusive,
+    /// Images can be used across queue families without ownership transfers.
+    /// Simpler, and useful for debugging, but can be slower.
+    Concurrent,
+}
+
+/// Which present mode the [`Swapchain`] prefers. It falls back to `FIFO`, which is always
+/// supported, when the preferred one isn't.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentModePolicy {
+    /// `MAILBOX`: no tearing and low latency, as the newest frame replaces the queued one, at the
+    /// cost of rendering frames that are never shown.
+    #[default]
+    LowLatency,
+    /// `FIFO`: no tearing and the frame rate is capped to the refresh rate, which saves power.
+    VSync,
+    /// `IMMEDIATE`: frames are shown as soon as they are ready, uncapped but with tearing.
+    /// Falls back to `MAILBOX` first.
+    Uncapped,
+}
+
+/// Who encodes the colors written to the swapchain images from linear to sRGB gamma.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputColorEncoding {
+    /// Prefers an `SRGB` format, the hardware encodes the linear colors written by the fragment
+    /// shader, and the clear color is linear too.
+    #[default]
+    AutoSrgb,
+    /// Prefers a `UNORM` format, which stores the values as written, e.g. to apply tone mapping
+    /// before the encoding. The fragment shader must output gamma-encoded values, which it does
+    /// when the pipeline is created with this encoding, and the clear color must be pre-encoded
+    /// with [`Color::linear_to_srgb`](crate::gapi::color::Color::linear_to_srgb).
+    ManualInShader,
+}
+
+impl OutputColorEncoding {
+    /// The encoding writing into `format` needs: the `SRGB` formats encode by themselves, the
+    /// others store the values as written.
+    pub fn of_format(format: vk::Format) -> Self {
+        match format {
+            vk::Format::B8G8R8A8_SRGB
+            | vk::Format::R8G8B8A8_SRGB
+            | vk::Format::A8B8G8R8_SRGB_PACK32
+            | vk::Format::B8G8R8_SRGB
+            | vk::Format::R8G8B8_SRGB => OutputColorEncoding::AutoSrgb,
+            _ => OutputColorEncoding::ManualInShader,
+        }
+    }
+}
+
+/// Which transform the presentation engine applies to the swapchain images, relative to the
+/// orientation of the display.
+///
+/// Rotated displays (tablets, phones, some ARM boards) report a non-identity
+/// `current_transform`. Presenting identity images to them makes the compositor rotate every
+/// frame, which costs a copy on mobile GPUs. Pre-transformed images are presented as is, but the
+/// app must render them rotated: the swapchain extent is swapped for 90 and 270 degree
+/// rotations, and the projection rotated back, see [`Swapchain::pre_transform`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PreTransformPolicy {
+    /// `IDENTITY`, the compositor rotates the images if needed. Falls back to the current
+    /// transform if the surface doesn't support identity.
+    #[default]
+    Identity,
+    /// The `current_transform` of the surface, so the images are presented without rotation.
+    MatchDisplay,
+}
+
+/// Configuration of the [`Swapchain`] creation.
+#[derive(Clone, Debug)]
+pub struct SwapchainConfig {
+    pub image_sharing: ImageSharingPolicy,
+    pub present_mode_policy: PresentModePolicy,
+    /// A present mode to use instead of the one of `present_mode_policy`, if the surface
+    /// supports it, e.g. set at runtime by `App::set_present_mode` to compare the modes.
+    pub present_mode: Option<vk::PresentModeKHR>,
+    /// Allows tearing when a frame is late, instead of waiting a whole vblank for it.
+    /// Prefers `FIFO_RELAXED` over `FIFO` when `MAILBOX` isn't available, which judders less
+    /// when frames are occasionally late.
+    pub allow_late_frame_tearing: bool,
+    /// Surface formats to use, from most to least preferred. The first one supported by the
+    /// surface is picked, and only if none is, it falls back to the best ranked available format.
+    /// Useful for apps that need a specific format, e.g. `UNORM` to do the gamma correction
+    /// manually in the shaders instead of letting the hardware do it with `SRGB`.
+    pub preferred_formats: Vec<(vk::Format, vk::ColorSpaceKHR)>,
+    /// Selects the format ranked best when none of the preferred ones is supported.
+    pub output_color_encoding: OutputColorEncoding,
+    /// What the swapchain images are used for, e.g. `TRANSFER_SRC` to read a screenshot back,
+    /// or `TRANSFER_DST` and `STORAGE` for post-processing. `COLOR_ATTACHMENT` is always
+    /// included, as the frame is rendered into them, and the usages the surface doesn't support
+    /// are dropped with a warning.
+    pub image_usage: vk::ImageUsageFlags,
+    pub pre_transform: PreTransformPolicy,
+}
+
+impl Default for SwapchainConfig {
+    fn default() -> Self {
+        Self {
+            image_sharing: ImageSharingPolicy::default(),
+            present_mode_policy: PresentModePolicy::default(),
+            present_mode: None,
+            allow_late_frame_tearing: false,
+            preferred_formats: Vec::new(),
+            output_color_encoding: OutputColorEncoding::default(),
+            image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            pre_transform: PreTransformPolicy::default(),
+        }
+    }
+}
+
+/// What a surface supports for the swapchains created for it, as queried from the physical
+/// device, and the choice of the swapchain settings among it.
+#[derive(Clone, Debug)]
+pub struct SwapchainSupport {
+    pub capabilities: vk::SurfaceCapabilitiesKHR,
+    pub formats: Vec<vk::SurfaceFormatKHR>,
+    pub present_modes: Vec<vk::PresentModeKHR>,
+}
+
+impl SwapchainSupport {
+    /// Whether a swapchain can be created at all, a lost surface may report nothing.
+    pub fn is_adequate(&self) -> bool {
+        !self.formats.is_empty() && !self.present_modes.is_empty()
+    }
+
+    /// The first of the `preferred` formats that is supported, otherwise the supported one ranked
+    /// best for the `encoding`, see [`SwapchainSupport::rank_format`].
+    pub fn best_format(
+        &self,
+        preferred: &[(vk::Format, vk::ColorSpaceKHR)],
+        encoding: OutputColorEncoding,
+    ) -> anyhow::Result<vk::SurfaceFormatKHR> {
+        debug!("Supported surface formats: {:?}", self.formats);
+        // A lost surface or a misbehaving driver can report nothing, even though a surface must
+        // support at least one format.
+        if self.formats.is_empty() {
+            bail!("The surface reports no supported formats, it may have been lost.");
+        }
+        let found = preferred.iter().enumerate().find_map(|(level, &(format, color_space))| {
+            self.formats
+                .iter()
+                .find(|f| f.format == format && f.color_space == color_space)
+                .map(|f| (level, *f))
+        });
+        if let Some((level, surface_format)) = found {
+            info!(
+                "Using preferred surface format {:?} (preference {} of {}).",
+                surface_format,
+                level + 1,
+                preferred.len()
+            );
+            Self::check_color_encoding(&surface_format, encoding);
+            return Ok(surface_format);
+        }
+        if !preferred.is_empty() {
+            warn!(
+                "None of the preferred surface formats {:?} are supported, falling back to the best ranked one.",
+                preferred
+            );
+        }
+        let surface_format = self
+            .formats
+            .iter()
+            .cloned()
+            .rev()
+            .max_by_key(|format| Self::rank_format(format, encoding))
+            .ok_or_else(|| anyhow::anyhow!("Failed to find suitable swapchain format."))?;
+        Self::check_color_encoding(&surface_format, encoding);
+        Ok(surface_format)
+    }
+
+    /// Warns when the format and the color space of `surface_format` are unlikely to show the
+    /// colors right with the configured `encoding`, the usual cause of washed-out or too dark
+    /// frames:
+    /// - An `SRGB` format encodes to sRGB, which a color space other than `SRGB_NONLINEAR` (e.g.
+    ///   an HDR or linear one) doesn't expect.
+    /// - `SRGB_NONLINEAR` expects sRGB-encoded values, so with any other format the shader must
+    ///   encode them, i.e. [`OutputColorEncoding::ManualInShader`].
+    /// - With [`OutputColorEncoding::ManualInShader`] and an `SRGB` format, the colors would be
+    ///   encoded twice. The pipelines follow the format, but the clear color pre-encoded for it
+    ///   doesn't.
+    pub fn check_color_encoding(surface_format: &vk::SurfaceFormatKHR, encoding: OutputColorEncoding) {
+        let format_encoding = OutputColorEncoding::of_format(surface_format.format);
+        let srgb_color_space = surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR;
+        if format_encoding == OutputColorEncoding::AutoSrgb && !srgb_color_space {
+            warn!(
+                "The surface format {:?} encodes to sRGB, but the color space {:?} doesn't expect sRGB, colors will likely be wrong.",
+                surface_format.format, surface_format.color_space
+            );
+        } else if format_encoding != encoding {
+            warn!(
+                "The surface format {:?} needs {format_encoding:?}, but {encoding:?} is configured, colors will likely be washed out or too dark.",
+                surface_format.format
+            );
+        } else if encoding == OutputColorEncoding::ManualInShader && !srgb_color_space {
+            warn!(
+                "The shader encodes to sRGB, but the color space {:?} doesn't expect sRGB, colors will likely be wrong.",
+                surface_format.color_space
+            );
+        }
+    }
+
+    /// Ranks a surface format, the higher the better.
+    /// - 8 bits per channel sRGB formats (e.g. B8G8R8A8_SRGB) are preferred, as the hardware does
+    /// the gamma correction for us when writing linear colors. With
+    /// [`OutputColorEncoding::ManualInShader`], the UNORM ones are preferred instead.
+    /// - SRGB_NONLINEAR means that the color space is sRGB with nonlinear gamma correction, which
+    /// is the most common color space for images and displays.
+    pub fn rank_format(format: &vk::SurfaceFormatKHR, encoding: OutputColorEncoding) -> u32 {
+        let (srgb_rank, unorm_rank) = match encoding {
+            OutputColorEncoding::AutoSrgb => (2, 1),
+            OutputColorEncoding::ManualInShader => (1, 2),
+        };
+        let format_rank = match format.format {
+            vk::Format::B8G8R8A8_SRGB | vk::Format::R8G8B8A8_SRGB => srgb_rank,
+            vk::Format::B8G8R8A8_UNORM | vk::Format::R8G8B8A8_UNORM => unorm_rank,
+            _ => 0,
+        };
+        let color_space_rank = (format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR) as u32;
+        color_space_rank * 3 + format_rank
+    }
+
+    /// The present mode of `config` if supported, otherwise the first supported present mode of
+    /// its policy, falling back to FIFO. `MAILBOX` is skipped by the policy if `avoid_mailbox`,
+    /// see `Workarounds::avoid_mailbox_present_mode`.
+    pub fn best_present_mode(
+        &self,
+        config: &SwapchainConfig,
+        avoid_mailbox: bool,
+    ) -> anyhow::Result<vk::PresentModeKHR> {
+        debug!("Supported present modes: {:?}", self.present_modes);
+        // FIFO is guaranteed by the spec, but not by a lost surface or a misbehaving driver.
+        if self.present_modes.is_empty() {
+            bail!("The surface reports no supported present modes, it may have been lost.");
+        }
+        // The modes of the policy are tried in order, otherwise falling back to FIFO which is
+        // guaranteed to be supported.
+        // FIFO_RELAXED behaves like FIFO, but presents a late frame right away instead of waiting
+        // for the next vblank, which tears but avoids stuttering. Only used if we allow it.
+        if let Some(mode) = config.present_mode {
+            if self.present_modes.contains(&mode) {
+                return Ok(mode);
+            }
+            warn!("The present mode {mode:?} is no longer supported, falling back to the policy.");
+        }
+        let mut preferred = match config.present_mode_policy {
+            PresentModePolicy::LowLatency => vec![vk::PresentModeKHR::MAILBOX],
+            PresentModePolicy::VSync => vec![],
+            PresentModePolicy::Uncapped => {
+                vec![vk::PresentModeKHR::IMMEDIATE, vk::PresentModeKHR::MAILBOX]
+            }
+        };
+        if avoid_mailbox {
+            preferred.retain(|mode| *mode != vk::PresentModeKHR::MAILBOX);
+        }
+        if config.allow_late_frame_tearing {
+            preferred.push(vk::PresentModeKHR::FIFO_RELAXED);
+        }
+        preferred.push(vk::PresentModeKHR::FIFO);
+        preferred
+            .into_iter()
+            .find(|mode| self.present_modes.contains(mode))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Failed to find suitable swapchain present mode between: {:?}",
+                    self.present_modes
+                )
+            })
+    }
+
+    /// The resolution of the swapchain images: the one of the surface if it imposes it, otherwise
+    /// the `window_extent` clamped to what the surface supports.
+    pub fn choose_extent(&self, window_extent: vk::Extent2D) -> vk::Extent2D {
+        let capabilities = &self.capabilities;
+        // If the current_extent is not u32::MAX, it means we need to set it to current_extent
+        // otherwise, we can set the windows size ourselves and configure it,
+        // like clamping it to the min and max extents supported by the device.
+        if capabilities.current_extent.width != u32::MAX {
+            capabilities.current_extent
+        } else {
+            extent2d(
+                window_extent.width.clamp(
+                    capabilities.min_image_extent.width,
+                    capabilities.max_image_extent.width,
+                ),
+                window_extent.height.clamp(
+                    capabilities.min_image_extent.height,
+                    capabilities.max_image_extent.height,
+                ),
+            )
+        }
+    }
+
+    /// How many images to ask for, at least `min_image_count` if set, e.g. by a workaround.
+    pub fn image_count(&self, min_image_count: Option<u32>) -> u32 {
+        // The implementation specifies the minimum number that it requires to function
+        // However, simply sticking to this minimum means that we may sometimes have to wait on the
+        // driver to complete internal operations before we can acquire another image to render to.
+        // Therefore, it is recommended to request at least one more image than the minimum
+        let mut image_count = self.capabilities.min_image_count + 1;
+        if let Some(min_image_count) = min_image_count {
+            image_count = image_count.max(min_image_count);
+        }
+
+        // We should also make sure to not exceed the maximum number of images while doing this,
+        // where 0 is a special value that means that there is no maximum
+        if self.capabilities.max_image_count != 0 && image_count > self.capabilities.max_image_count {
+            image_count = self.capabilities.max_image_count;
+        }
+        image_count
+    }
+
+    /// The transform of the `policy`, if the surface supports it, otherwise the current one.
+    pub fn pre_transform(&self, policy: PreTransformPolicy) -> vk::SurfaceTransformFlagsKHR {
+        let current = self.capabilities.current_transform;
+        match policy {
+            PreTransformPolicy::Identity
+                if self
+                    .capabilities
+                    .supported_transforms
+                    .contains(vk::SurfaceTransformFlagsKHR::IDENTITY) =>
+            {
+                vk::SurfaceTransformFlagsKHR::IDENTITY
+            }
+            PreTransformPolicy::Identity => {
+                warn!("The surface doesn't support the identity transform, using {current:?}.");
+                current
+            }
+            PreTransformPolicy::MatchDisplay => current,
+        }
+    }
+
+    /// Returns the `requested` usages, plus `COLOR_ATTACHMENT`, that the surface supports. The
+    /// others are dropped with a warning.
+    pub fn image_usage(&self, requested: vk::ImageUsageFlags) -> vk::ImageUsageFlags {
+        let supported = self.capabilities.supported_usage_flags;
+        // The spec guarantees the surfaces support COLOR_ATTACHMENT.
+        let requested = requested | vk::ImageUsageFlags::COLOR_ATTACHMENT;
+        let unsupported = requested & !supported;
+        if !unsupported.is_empty() {
+            warn!("The surface doesn't support the swapchain image usages {unsupported:?}, dropping them.");
+        }
+        requested & (supported | vk::ImageUsageFlags::COLOR_ATTACHMENT)
+    }
+}
 
 pub(crate) struct Swapchain {
     // The swapchain handle from Vulkan.
@@ -21,7 +378,11 @@ pub(crate) struct Swapchain {
     /// and the subresource range (e.g. mip levels, array layers) that will be accessed.
     pub image_views: Vec<Image>,
     pub format: vk::Format,
+    pub color_space: vk::ColorSpaceKHR,
+    pub present_mode: vk::PresentModeKHR,
     pub extent: vk::Extent2D,
+    image_usage: vk::ImageUsageFlags,
+    pre_transform: vk::SurfaceTransformFlagsKHR,
 }
 
 impl Swapchain {
@@ -30,14 +391,17 @@ impl Swapchain {
         real_device: &RealDevice,
         logical_device: &LogicalDevice,
         surface: &Surface,
+        config: &SwapchainConfig,
+        old_swapchain: Option<&Swapchain>,
     ) -> anyhow::Result<Swapchain> {
         let support = real_device.get_swapchain_info(surface)?;
+        let workarounds = real_device.get_workarounds();
         let queues = logical_device.get_queues();
 
         // The surface format describes how the pixels in the swapchain images are stored and
         // interpreted. It includes the color format (e.g. RGBA, BGRA) and the color space
         // (e.g. sRGB).
-        let surface_format = Self::get_surface_format(&support.formats).with_context(|| {
+        let surface_format = support.best_format(&config.preferred_formats, config.output_color_encoding).with_context(|| {
             anyhow::anyhow!(
                 "Failed to find suitable swapchain surface format between: {:?}",
                 support.formats
@@ -46,50 +410,39 @@ impl Swapchain {
 
         // The present mode determines how images are presented to the screen.
         // It can affect latency, tearing, and power consumption.
-        let present_mode = Self::get_present_mode(&support.present_modes).with_context(|| {
-            anyhow::anyhow!(
-                "Failed to find suitable swapchain present mode between: {:?}",
-                support.present_modes
-            )
-        })?;
+        let present_mode = support.best_present_mode(config, workarounds.avoid_mailbox_present_mode)?;
 
-        let image_count = {
-            // The implementation specifies the minimum number that it requires to function
-            // However, simply sticking to this minimum means that we may sometimes have to wait on the
-            // driver to complete internal operations before we can acquire another image to render to.
-            // Therefore, it is recommended to request at least one more image than the minimum
-            let mut image_count = support.capabilities.min_image_count + 1;
-
-            // We should also make sure to not exceed the maximum number of images while doing this,
-            // where 0 is a special value that means that there is no maximum
-            if support.capabilities.max_image_count != 0
-                && image_count > support.capabilities.max_image_count
-            {
-                image_count = support.capabilities.max_image_count;
-            }
-            image_count
-        };
+        let image_count = support.image_count(workarounds.min_swapchain_image_count);
 
         // The Sharing Mode specifies how to handle swapchain images that will be used across
         // multiple queue families. That will be the case in our application if the graphics queue
         // family is different from the presentation queue.
-        // TODO: If the queue families for graphics and presentation are different, we use
-        //   CONCURRENT for simplicity, but EXCLUSIVE can offer better performance but it needs
-        //   explicit ownership transfers between the queues, which can be more complex to manage.
-        //   Improve this
-        let mut queue_family_indices = vec![];
-        let image_sharing_mode = if queues.graphics[0] != queues.present[0] {
-            queue_family_indices.push(queues.graphics[0].as_raw() as u32);
-            queue_family_indices.push(queues.present[0].as_raw() as u32);
-            vk::SharingMode::CONCURRENT
-        } else {
-            vk::SharingMode::EXCLUSIVE
-        };
+        let (image_sharing_mode, queue_family_indices) =
+            Self::get_sharing_mode(config.image_sharing, queues);
 
+        // The pre transform is applied by the presentation engine to the images, when presenting.
+        // Anything but the current transform of the surface makes the compositor rotate them.
+        let pre_transform = support.pre_transform(config.pre_transform);
+        if pre_transform != vk::SurfaceTransformFlagsKHR::IDENTITY {
+            info!("Presenting with the pre transform {pre_transform:?}, rotating the projection to compensate.");
+        }
+        if pre_transform.intersects(
+            vk::SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR
+                | vk::SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR_ROTATE_90
+                | vk::SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR_ROTATE_180
+                | vk::SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR_ROTATE_270,
+        ) {
+            warn!("The mirrored pre transform {pre_transform:?} is not compensated, the frame will be shown mirrored.");
+        }
 
         // The extent is the resolution of the swapchain images, which should match the resolution
         // of the window we are rendering to.
-        let extent = Self::get_extent(window, support.capabilities);
+        let mut extent = support.choose_extent(from_window_size(window.size()));
+        // The extent is in the orientation of the display, the images rotated by a quarter turn
+        // have it the other way around.
+        if Self::is_quarter_turn(pre_transform) {
+            extent = transposed(extent);
+        }
 
         // This specifies the amount of layers each image consists of. This is always 1 unless you
         // are developing a stereoscopic 3D application
@@ -101,8 +454,8 @@ impl Swapchain {
         // It is also possible to render images to a separate image first to perform
         // operations like post-processing. In that case it may be used a value like
         // vk::ImageUsageFlags::TRANSFER_DST instead and use a memory operation to transfer the
-        // rendered image to a swapchain image.
-        let image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT;
+        // rendered image to a swapchain image, see SwapchainConfig::image_usage.
+        let image_usage = support.image_usage(config.image_usage);
 
 
         // The composite_alpha method specifies if the alpha channel should be used for blending
@@ -117,20 +470,11 @@ impl Swapchain {
         // you'll get the best performance by enabling clipping.
         let clipped = true;
 
-        let pre_transform = vk::SurfaceTransformFlagsKHR::IDENTITY;
-
-        // This is used when you want to recreate the swapchain.
-        // With Vulkan, it's possible that your swapchain becomes invalid or unoptimized while your
-        // application is running, for example because the window was resized.
-        // In that case the swapchain actually needs to be recreated from scratch and a reference
-        // to the old one must be specified in this method (.old_swapchain) so that the driver can
-        // optimize the transition between the old and the new swapchain.
-        // By default is null, for now we are not implementing swapchain recreation.
-        // TODO: Implement swapchain recreation and use this field properly
-        let old_swapchain = vk::SwapchainKHR::null();
-
-
-
+        // When recreating the swapchain, e.g. because the window was resized, the one it replaces
+        // lets the driver reuse its resources and hand the images still being presented over.
+        // The old one is retired by this call, it can't be acquired from anymore but must still
+        // be destroyed by the caller.
+        let old_swapchain = old_swapchain.map_or(vk::SwapchainKHR::null(), |old| old.vk_swapchain);
 
         let swapchain_info = vk::SwapchainCreateInfoKHR::builder()
             .surface(surface.get_vk())
@@ -177,20 +521,150 @@ impl Swapchain {
                 )
             })?;
 
+        surface.register_swapchain();
+
         Ok(Self {
             vk_swapchain,
             images,
             format: surface_format.format,
+            color_space: surface_format.color_space,
+            present_mode,
             extent: swapchain_info.image_extent,
             image_views,
+            image_usage,
+            pre_transform,
         })
     }
 
-    fn get_vk(&self) -> vk::SwapchainKHR {
+    /// The transform the images are presented with, see [`PreTransformPolicy`].
+    ///
+    /// When it isn't `IDENTITY`, the projection must be rotated by the same angle, otherwise the
+    /// frame is shown rotated, see [`Swapchain::pre_rotate_projection`]. The app does it for the
+    /// frame uniforms. Anything drawn in pixels, e.g. the viewport regions or an overlay, must
+    /// account for [`Swapchain::extent`] being the rotated one.
+    pub(crate) fn pre_transform(&self) -> vk::SurfaceTransformFlagsKHR {
+        self.pre_transform
+    }
+
+    /// Rotates a column-major projection by the angle of the [`Swapchain::pre_transform`], in
+    /// clip space, so the presentation engine rotating the images back shows them upright.
+    ///
+    /// The mirrored transforms are not compensated, a warning is logged when the swapchain is
+    /// created with one.
+    pub(crate) fn pre_rotate_projection(&self, projection: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+        // The clip space x and y, rotated by the angle of the transform.
+        let transform = self.pre_transform;
+        let rotate: fn(f32, f32) -> (f32, f32) = if transform == vk::SurfaceTransformFlagsKHR::ROTATE_90 {
+            |x, y| (-y, x)
+        } else if transform == vk::SurfaceTransformFlagsKHR::ROTATE_180 {
+            |x, y| (-x, -y)
+        } else if transform == vk::SurfaceTransformFlagsKHR::ROTATE_270 {
+            |x, y| (y, -x)
+        } else {
+            return projection;
+        };
+        projection.map(|mut column| {
+            (column[0], column[1]) = rotate(column[0], column[1]);
+            column
+        })
+    }
+
+    /// Whether the `transform` rotates by 90 or 270 degrees, mirrored or not.
+    fn is_quarter_turn(transform: vk::SurfaceTransformFlagsKHR) -> bool {
+        transform.intersects(
+            vk::SurfaceTransformFlagsKHR::ROTATE_90
+                | vk::SurfaceTransformFlagsKHR::ROTATE_270
+                | vk::SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR_ROTATE_90
+                | vk::SurfaceTransformFlagsKHR::HORIZONTAL_MIRROR_ROTATE_270,
+        )
+    }
+
+    pub(crate) fn get_vk(&self) -> vk::SwapchainKHR {
         self.vk_swapchain
     }
 
-    fn create_image_views(
+    /// The swapchain image `index`, as returned by the acquire.
+    pub(crate) fn image(&self, index: usize) -> vk::Image {
+        self.images[index]
+    }
+
+    /// What the images can be used for, the configured usages the surface supports.
+    pub(crate) fn image_usage(&self) -> vk::ImageUsageFlags {
+        self.image_usage
+    }
+
+    /// Whether the images can be written by transfers (copies, blits, resolves), and not only
+    /// rendered into.
+    pub(crate) fn supports_transfer_dst(&self) -> bool {
+        self.image_usage.contains(vk::ImageUsageFlags::TRANSFER_DST)
+    }
+
+    /// The encoding the picked format actually needs, which differs from the configured one
+    /// when the surface doesn't support a format of the preferred kind.
+    pub(crate) fn output_color_encoding(&self) -> OutputColorEncoding {
+        OutputColorEncoding::of_format(self.format)
+    }
+
+    /// Chooses the image sharing mode from the `policy` and the graphics and present families.
+    ///
+    /// # Returns
+    /// The sharing mode and the queue family indices that share the images (empty if EXCLUSIVE).
+    fn get_sharing_mode(
+        policy: ImageSharingPolicy,
+        queues: &Queues,
+    ) -> (vk::SharingMode, Vec<u32>) {
+        let graphics_family = queues.graphics_family_index;
+        let present_family = queues.present_family_index;
+        let families_differ = !queues.is_unified_present();
+        let concurrent_indices = vec![graphics_family, present_family];
+        let (sharing_mode, queue_family_indices) = match policy {
+            ImageSharingPolicy::Concurrent if families_differ => {
+                (vk::SharingMode::CONCURRENT, concurrent_indices)
+            }
+            // CONCURRENT requires at least two distinct families, with a single one it is the
+            // same as EXCLUSIVE.
+            ImageSharingPolicy::Concurrent => (vk::SharingMode::EXCLUSIVE, vec![]),
+            ImageSharingPolicy::Exclusive if families_differ => {
+                // TODO: Implement the ownership transfer barriers to keep EXCLUSIVE here.
+                warn!(
+                    "EXCLUSIVE image sharing needs ownership transfers between the graphics \
+                    and present families, which are not implemented yet. Falling back to CONCURRENT."
+                );
+                (vk::SharingMode::CONCURRENT, concurrent_indices)
+            }
+            ImageSharingPolicy::Exclusive => (vk::SharingMode::EXCLUSIVE, vec![]),
+        };
+        info!(
+            "Image sharing policy: {:?}, graphics family: {}, present family: {} (families differ: {}), using {:?}",
+            policy, graphics_family, present_family, families_differ, sharing_mode
+        );
+        (sharing_mode, queue_family_indices)
+    }
+
+    /// Recreates the image views of the swapchain images, without touching the swapchain itself.
+    ///
+    /// The old views are destroyed before the new ones are created, so they must no longer be in
+    /// use (e.g. by a framebuffer or a command buffer in flight).
+    pub(crate) fn recreate_image_views(
+        &mut self,
+        logical_device: &LogicalDevice,
+    ) -> anyhow::Result<()> {
+        debug!("Destroying {} old swapchain image views.", self.image_views.len());
+        self.destroy_image_views(logical_device);
+
+        self.image_views = Self::create_image_views(&self.images, &self.format, logical_device)
+            .with_context(|| {
+                anyhow::anyhow!(
+                    "Failed to recreate image views for swapchain images: {:?}",
+                    self.images
+                )
+            })?;
+        debug!("Created {} new swapchain image views.", self.image_views.len());
+        Ok(())
+    }
+
+    /// Creates one image view per swapchain image.
+    pub(crate) fn create_image_views(
         images: &[vk::Image],
         format: &Format,
         logical_device: &LogicalDevice,
@@ -198,71 +672,161 @@ impl Swapchain {
         images
             .iter()
             .map(|img| {
-                Image::new(img, format, logical_device).with_context(|| {
+                Image::new(img, format, &ImageViewConfig::default(), logical_device).with_context(|| {
                     anyhow::anyhow!("Failed to create image view for swapchain image: {:?}", img)
                 })
             })
             .collect::<anyhow::Result<Vec<Image>>>()
     }
 
-    fn get_surface_format(
-        formats: &[vk::SurfaceFormatKHR],
-    ) -> anyhow::Result<vk::SurfaceFormatKHR> {
-        // TODO: Rank available formats and pick the best one.
-        // - B8G8R8A8_SRGB means BGR and alpha channels with 8 bits each, 32 in total per pixel.
-        // - SRGB_NONLINEAR means that the color space is sRGB with nonlinear gamma correction, which
-        // is the most common color space for images and displays.
-        formats
-            .iter()
-            .cloned()
-            .find(|f| {
-                f.format == vk::Format::B8G8R8A8_SRGB
-                    && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
-            })
-            .or_else(|| Some(formats[0]))
-            .ok_or_else(|| anyhow::anyhow!("Failed to find suitable swapchain format."))
-    }
-    fn get_present_mode(
-        present_modes: &[vk::PresentModeKHR],
-    ) -> anyhow::Result<vk::PresentModeKHR> {
-        // Choosing mailbox if available, otherwise falling back to FIFO which is guaranteed to be supported.
-        // Mailbox is preferred for low latency and no tearing at expense of potentially higher power consumption
-        present_modes
-            .iter()
-            .cloned()
-            .find(|m| *m == vk::PresentModeKHR::MAILBOX)
-            .or_else(|| Some(vk::PresentModeKHR::FIFO))
-            .ok_or_else(|| {
-                anyhow::anyhow!(
-                    "Failed to find suitable swapchain present mode between: {:?}",
-                    present_modes
-                )
-            })
-    }
-    fn get_extent(window: &MyWindow, capabilities: vk::SurfaceCapabilitiesKHR) -> vk::Extent2D {
-        // If the current_extent is not u32::MAX, it means we need to set it to current_extent
-        // otherwise, we can set the windows size ourselves and configure it,
-        // like clamping it to the min and max extents supported by the device.
-        if capabilities.current_extent.width != u32::MAX {
-            capabilities.current_extent
-        } else {
-            vk::Extent2D::builder()
-                .width(window.size().width.clamp(
-                    capabilities.min_image_extent.width,
-                    capabilities.max_image_extent.width,
-                ))
-                .height(window.size().height.clamp(
-                    capabilities.min_image_extent.height,
-                    capabilities.max_image_extent.height,
-                ))
-                .build()
+    /// Destroys the image views and forgets them, the swapchain images are owned by the swapchain.
+    fn destroy_image_views(&mut self, logical_device: &LogicalDevice) {
+        for image_view in self.image_views.drain(..) {
+            image_view.destroy(logical_device)
         }
     }
 
-    pub(crate) fn destroy(&self, logical_device: &LogicalDevice) {
+    /// Destroys the image views and the swapchain, which must happen before destroying the
+    /// `surface` it was created for.
+    pub(crate) fn destroy(&self, logical_device: &LogicalDevice, surface: &Surface) {
         for image_view in &self.image_views {
             image_view.destroy(logical_device)
         }
         logical_device.destroy_swapchain_khr(self.vk_swapchain);
+        surface.unregister_swapchain();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vulkanalia::vk::PresentModeKHR as Mode;
+
+    const SRGB: vk::SurfaceFormatKHR = vk::SurfaceFormatKHR {
+        format: vk::Format::B8G8R8A8_SRGB,
+        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    };
+    const UNORM: vk::SurfaceFormatKHR = vk::SurfaceFormatKHR {
+        format: vk::Format::B8G8R8A8_UNORM,
+        color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+    };
+    const HDR: vk::SurfaceFormatKHR = vk::SurfaceFormatKHR {
+        format: vk::Format::A2B10G10R10_UNORM_PACK32,
+        color_space: vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+    };
+
+    fn support(formats: &[vk::SurfaceFormatKHR], present_modes: &[Mode]) -> SwapchainSupport {
+        SwapchainSupport {
+            capabilities: vk::SurfaceCapabilitiesKHR::default(),
+            formats: formats.to_vec(),
+            present_modes: present_modes.to_vec(),
+        }
+    }
+
+    /// The present mode chosen among `present_modes` with the `policy`.
+    fn present_mode(present_modes: &[Mode], policy: PresentModePolicy, avoid_mailbox: bool) -> Mode {
+        let config = SwapchainConfig {
+            present_mode_policy: policy,
+            ..SwapchainConfig::default()
+        };
+        support(&[], present_modes).best_present_mode(&config, avoid_mailbox).unwrap()
+    }
+
+    #[test]
+    fn best_format_prefers_the_first_supported_preferred_format() {
+        let formats = support(&[HDR, SRGB, UNORM], &[]);
+        let preferred = [
+            (HDR.format, vk::ColorSpaceKHR::SRGB_NONLINEAR),
+            (UNORM.format, UNORM.color_space),
+            (SRGB.format, SRGB.color_space),
+        ];
+        assert_eq!(formats.best_format(&preferred, OutputColorEncoding::AutoSrgb).unwrap(), UNORM);
+    }
+
+    #[test]
+    fn best_format_falls_back_to_the_best_ranked_format_of_the_encoding() {
+        let formats = support(&[HDR, UNORM, SRGB], &[]);
+        let unsupported = [(vk::Format::R16G16B16A16_SFLOAT, vk::ColorSpaceKHR::SRGB_NONLINEAR)];
+        assert_eq!(formats.best_format(&[], OutputColorEncoding::AutoSrgb).unwrap(), SRGB);
+        assert_eq!(formats.best_format(&unsupported, OutputColorEncoding::AutoSrgb).unwrap(), SRGB);
+        assert_eq!(formats.best_format(&[], OutputColorEncoding::ManualInShader).unwrap(), UNORM);
+
+        // Among equally ranked formats, the first reported one wins.
+        let rgba_srgb = vk::SurfaceFormatKHR {
+            format: vk::Format::R8G8B8A8_SRGB,
+            ..SRGB
+        };
+        let tied = support(&[rgba_srgb, SRGB], &[]);
+        assert_eq!(tied.best_format(&[], OutputColorEncoding::AutoSrgb).unwrap(), rgba_srgb);
+        // Without any known format, whatever the surface supports is still used.
+        let hdr_only = support(&[HDR], &[]);
+        assert_eq!(hdr_only.best_format(&[], OutputColorEncoding::AutoSrgb).unwrap(), HDR);
+    }
+
+    #[test]
+    fn best_present_mode_follows_the_policy_and_falls_back_to_fifo() {
+        let all = [Mode::FIFO, Mode::FIFO_RELAXED, Mode::MAILBOX, Mode::IMMEDIATE];
+        assert_eq!(present_mode(&all, PresentModePolicy::LowLatency, false), Mode::MAILBOX);
+        assert_eq!(present_mode(&all, PresentModePolicy::VSync, false), Mode::FIFO);
+        assert_eq!(present_mode(&all, PresentModePolicy::Uncapped, false), Mode::IMMEDIATE);
+        assert_eq!(present_mode(&[Mode::FIFO, Mode::MAILBOX], PresentModePolicy::Uncapped, false), Mode::MAILBOX);
+        assert_eq!(present_mode(&[Mode::FIFO], PresentModePolicy::LowLatency, false), Mode::FIFO);
+        // The workaround skips MAILBOX.
+        assert_eq!(present_mode(&all, PresentModePolicy::LowLatency, true), Mode::FIFO);
+
+        let tearing = SwapchainConfig {
+            present_mode_policy: PresentModePolicy::VSync,
+            allow_late_frame_tearing: true,
+            ..SwapchainConfig::default()
+        };
+        assert_eq!(support(&[], &all).best_present_mode(&tearing, false).unwrap(), Mode::FIFO_RELAXED);
+        assert_eq!(support(&[], &[Mode::FIFO]).best_present_mode(&tearing, false).unwrap(), Mode::FIFO);
+    }
+
+    #[test]
+    fn best_present_mode_uses_the_explicit_mode_only_if_supported() {
+        let modes = support(&[], &[Mode::FIFO, Mode::IMMEDIATE]);
+        let explicit = |mode| SwapchainConfig {
+            present_mode: Some(mode),
+            present_mode_policy: PresentModePolicy::VSync,
+            ..SwapchainConfig::default()
+        };
+        assert_eq!(modes.best_present_mode(&explicit(Mode::IMMEDIATE), false).unwrap(), Mode::IMMEDIATE);
+        assert_eq!(modes.best_present_mode(&explicit(Mode::MAILBOX), false).unwrap(), Mode::FIFO);
+    }
+
+    #[test]
+    fn empty_formats_or_present_modes_are_errors() {
+        let lost = support(&[], &[]);
+        assert!(!lost.is_adequate());
+        let preferred = [(SRGB.format, SRGB.color_space)];
+        assert!(lost.best_format(&preferred, OutputColorEncoding::AutoSrgb).is_err());
+        assert!(lost.best_format(&[], OutputColorEncoding::ManualInShader).is_err());
+        let explicit = SwapchainConfig {
+            present_mode: Some(Mode::FIFO),
+            ..SwapchainConfig::default()
+        };
+        assert!(lost.best_present_mode(&SwapchainConfig::default(), false).is_err());
+        assert!(lost.best_present_mode(&explicit, true).is_err());
+
+        // Either one missing is enough to make the surface unusable.
+        assert!(!support(&[SRGB], &[]).is_adequate());
+        assert!(!support(&[], &[Mode::FIFO]).is_adequate());
+        assert!(support(&[SRGB], &[Mode::FIFO]).is_adequate());
+    }
+
+    #[test]
+    fn choose_extent_uses_the_surface_extent_or_clamps_the_window_one() {
+        let mut surface = support(&[], &[]);
+        surface.capabilities.current_extent = extent2d(800, 600);
+        assert_eq!(surface.choose_extent(extent2d(1920, 1080)), extent2d(800, 600));
+
+        // The surface lets the swapchain decide, within its limits.
+        surface.capabilities.current_extent = extent2d(u32::MAX, u32::MAX);
+        surface.capabilities.min_image_extent = extent2d(64, 64);
+        surface.capabilities.max_image_extent = extent2d(4096, 2048);
+        assert_eq!(surface.choose_extent(extent2d(1920, 1080)), extent2d(1920, 1080));
+        assert_eq!(surface.choose_extent(extent2d(8000, 8000)), extent2d(4096, 2048));
+        assert_eq!(surface.choose_extent(extent2d(0, 100)), extent2d(64, 100));
     }
 }
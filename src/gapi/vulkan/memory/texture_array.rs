@@ -0,0 +1,224 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::guard::Guard;
+use crate::gapi::vulkan::memory::image::{Image, ImageViewConfig};
+use crate::gapi::vulkan::memory::sampler_cache::{SamplerCache, SamplerDesc};
+use crate::gapi::vulkan::memory::staging::StagingUploader;
+use anyhow::{bail, Context};
+use log::debug;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// The textures of the voxel faces, as the layers of a single 2D array image sampled with a
+/// `sampler2DArray` and indexed by material ID in the shader.
+///
+/// Unlike an atlas, the layers can't bleed into each other when filtering, and each could get
+/// its own mip chain. It is bound as a single combined image sampler, see
+/// [`TextureArray::descriptor_binding`].
+///
+/// # Layer limit
+/// A device supports at most `maxImageArrayLayers` layers per image (see
+/// [`TextureArray::max_layers`]), which the spec guarantees to be at least 256, and most
+/// desktop GPUs raise to 2048.
+pub struct TextureArray {
+    vk_image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: Image,
+    /// Owned by the [`SamplerCache`] it was taken from.
+    sampler: vk::Sampler,
+    extent: vk::Extent2D,
+    layer_count: u32,
+}
+
+impl TextureArray {
+    /// How many layers a texture array can have on `real_device`.
+    pub fn max_layers(real_device: &RealDevice) -> u32 {
+        real_device.get_properties().limits.max_image_array_layers
+    }
+
+    /// Checks that there are 1 to `max_layers` layers, each holding exactly the `extent` texels
+    /// of `format`, as the upload copies that many bytes from each.
+    fn check_layers(extent: vk::Extent2D, format: vk::Format, layers: &[&[u8]], max_layers: u32) -> anyhow::Result<()> {
+        if layers.is_empty() || layers.len() > max_layers as usize {
+            bail!(
+                "Failed to create a texture array of {} layers, the device supports 1 to {max_layers}.",
+                layers.len()
+            );
+        }
+        let texel_size = Image::texel_size(format)
+            .with_context(|| format!("Failed to create a texture array of the unsupported format {format:?}"))?;
+        let layer_size = extent.width as vk::DeviceSize * extent.height as vk::DeviceSize * texel_size;
+        if let Some(layer) = layers.iter().position(|layer| layer.len() as vk::DeviceSize != layer_size) {
+            bail!(
+                "Failed to create a texture array, layer {layer} has {} bytes instead of the {layer_size} of {}x{} texels of {format:?}.",
+                layers[layer].len(),
+                extent.width,
+                extent.height
+            );
+        }
+        Ok(())
+    }
+
+    /// Creates an array of `extent` textures in `format`, one layer per element of `layers`,
+    /// which hold the tightly packed texels of each texture, e.g. 4 bytes per texel in
+    /// `R8G8B8A8_SRGB`. It is uploaded with `uploader` and waited for, and sampled with the
+    /// [`SamplerDesc::NEAREST_REPEAT`] sampler of `samplers`.
+    ///
+    /// # Errors
+    /// If there are no layers, more than [`TextureArray::max_layers`], if the texel size of
+    /// `format` is unknown (see [`Image::texel_size`]), or a layer isn't exactly `extent` texels,
+    /// or any Vulkan object fails to be created.
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        uploader: &mut StagingUploader,
+        samplers: &mut SamplerCache,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        layers: &[&[u8]],
+    ) -> anyhow::Result<Self> {
+        Self::check_layers(extent, format, layers, Self::max_layers(real_device))?;
+        let layer_count = layers.len() as u32;
+
+        let info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::_2D)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(layer_count)
+            .format(format)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+            .samples(vk::SampleCountFlags::_1)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        debug!("Created ImageCreateInfo struct: {info:#?}");
+
+        let vk_image = device
+            .create_image(&info)
+            .with_context(|| "Failed to create texture array image")?;
+        let vk_image = Guard::new(vk_image, |image| device.destroy_image(*image));
+
+        let requirements = device.get_image_memory_requirements(*vk_image);
+        let memory_type_index = real_device.find_memory_type_index(
+            requirements.memory_type_bits,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        let allocate_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index);
+        let memory = device
+            .allocate_memory(&allocate_info)
+            .with_context(|| "Failed to allocate texture array memory")?;
+        let memory = Guard::new(memory, |memory| device.free_memory(*memory));
+        device.bind_image_memory(*vk_image, *memory, 0)?;
+
+        uploader
+            .upload_image(
+                real_device,
+                device,
+                layers,
+                *vk_image,
+                extent,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+            )
+            .with_context(|| "Failed to upload texture array")?;
+
+        let view_config = ImageViewConfig {
+            view_type: vk::ImageViewType::_2D_ARRAY,
+            layer_count,
+            ..ImageViewConfig::default()
+        };
+        let view = Image::new(&vk_image, &format, &view_config, device)
+            .with_context(|| "Failed to create texture array view")?;
+        let view = Guard::new(view, |view| view.destroy(device));
+
+        // Nearest filtering keeps the voxel textures sharp, and repeating lets greedy meshes
+        // tile a texture over merged faces.
+        let sampler = samplers
+            .get(device, SamplerDesc::NEAREST_REPEAT)
+            .with_context(|| "Failed to get texture array sampler")?;
+
+        debug!("Created texture array of {layer_count} layers of {extent:?} in {format:?}.");
+        Ok(Self {
+            vk_image: vk_image.into_inner(),
+            memory: memory.into_inner(),
+            view: view.into_inner(),
+            sampler,
+            extent,
+            layer_count,
+        })
+    }
+
+    /// The layout binding of the array, a single combined image sampler read by the fragment
+    /// shader.
+    pub fn descriptor_binding(binding: u32) -> vk::DescriptorSetLayoutBinding {
+        vk::DescriptorSetLayoutBinding::builder()
+            .binding(binding)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+            .build()
+    }
+
+    /// What to write into the descriptor set at [`TextureArray::descriptor_binding`].
+    pub fn descriptor_image_info(&self) -> vk::DescriptorImageInfo {
+        vk::DescriptorImageInfo::builder()
+            .sampler(self.sampler)
+            .image_view(*self.view.get_vk())
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build()
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.extent
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.layer_count
+    }
+
+    /// Destroys the array, which must not be in use by a command buffer in flight. Its sampler
+    /// is left to the [`SamplerCache`].
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.view.destroy(device);
+        device.destroy_image(self.vk_image);
+        device.free_memory(self.memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXTENT: vk::Extent2D = vk::Extent2D { width: 2, height: 3 };
+
+    #[test]
+    fn accepts_layers_of_exactly_the_extent() {
+        let layer = [0u8; 2 * 3 * 4];
+        assert!(TextureArray::check_layers(EXTENT, vk::Format::R8G8B8A8_SRGB, &[&layer, &layer], 256).is_ok());
+    }
+
+    #[test]
+    fn rejects_layers_of_the_wrong_length() {
+        let layer = [0u8; 2 * 3 * 4];
+        let short = [0u8; 2 * 3 * 4 - 1];
+        let long = [0u8; 2 * 3 * 4 + 4];
+        let format = vk::Format::R8G8B8A8_SRGB;
+        assert!(TextureArray::check_layers(EXTENT, format, &[&short, &short], 256).is_err());
+        assert!(TextureArray::check_layers(EXTENT, format, &[&layer, &long], 256).is_err());
+        // Right for 4 bytes per texel, but R16G16B16A16_SFLOAT has 8.
+        assert!(TextureArray::check_layers(EXTENT, vk::Format::R16G16B16A16_SFLOAT, &[&layer], 256).is_err());
+    }
+
+    #[test]
+    fn rejects_no_layers_too_many_or_an_unknown_format() {
+        let layer = [0u8; 2 * 3 * 4];
+        assert!(TextureArray::check_layers(EXTENT, vk::Format::R8G8B8A8_SRGB, &[], 256).is_err());
+        assert!(TextureArray::check_layers(EXTENT, vk::Format::R8G8B8A8_SRGB, &[&layer, &layer], 1).is_err());
+        assert!(TextureArray::check_layers(EXTENT, vk::Format::BC1_RGB_UNORM_BLOCK, &[&layer], 256).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+use crate::gapi::vulkan::commands::command_pool::CommandPool;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::guard::Guard;
+use crate::gapi::vulkan::memory::buffer::Buffer;
+use crate::gapi::vulkan::memory::staging::{UploadQueues, submit_transfer};
+use anyhow::{Context, bail};
+use log::{debug, info, trace};
+use std::time::Duration;
+use vulkanalia::vk;
+use vulkanalia::vk::{Handle, HasBuilder};
+
+/// What [`UploadManager::stage_and_copy`] does when every staging slot is still in flight.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WhenFull {
+    /// Wait for the oldest upload to finish, then reuse its slot.
+    #[default]
+    Block,
+    /// Don't upload, the caller gets `None` back and tries again later, e.g. next frame.
+    Defer,
+}
+
+/// # Ring Size Tuning
+/// The ring bounds both the staging memory, `ring_size * slot_size` of host visible memory, and
+/// the number of uploads in flight at once.
+///
+/// - Streaming many small resources (e.g. one mesh chunk per frame) wants more slots, so the CPU
+///   never waits for the GPU: roughly the number of uploads per frame times the frames in flight.
+/// - A few large resources (e.g. textures) want bigger slots instead, since an upload must fit in
+///   a single slot.
+/// - If [`WhenFull::Block`] shows up in profiles as the CPU waiting on the upload fences, grow the
+///   ring. If memory is tight, shrink it and switch to [`WhenFull::Defer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadManagerConfig {
+    /// Number of staging buffers, i.e. the maximum number of uploads in flight.
+    pub ring_size: usize,
+    /// Size of each staging buffer, the largest upload accepted.
+    pub slot_size: vk::DeviceSize,
+    pub when_full: WhenFull,
+}
+
+impl Default for UploadManagerConfig {
+    fn default() -> Self {
+        Self {
+            ring_size: 4,
+            slot_size: 4 * 1024 * 1024,
+            when_full: WhenFull::Block,
+        }
+    }
+}
+
+/// Identifies an upload started by [`UploadManager::stage_and_copy`], to poll for its completion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadHandle {
+    slot: usize,
+    id: u64,
+}
+
+/// A staging buffer with everything needed to submit an upload from it.
+struct UploadSlot {
+    staging: Buffer,
+    transfer_cb: vk::CommandBuffer,
+    /// Only allocated when the ownership of the destination is transferred.
+    acquire_cb: Option<vk::CommandBuffer>,
+    semaphore: vk::Semaphore,
+    /// Signaled once the upload, including the ownership acquire, is done.
+    fence: vk::Fence,
+    /// The upload the slot was last submitted for, `None` if it is free.
+    upload_id: Option<u64>,
+}
+
+impl UploadSlot {
+    fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        queues: &UploadQueues,
+        slot_size: vk::DeviceSize,
+    ) -> anyhow::Result<Self> {
+        // Guarded until the slot is built, the command buffers are otherwise freed with their pool.
+        let staging = Buffer::new(
+            real_device,
+            device,
+            slot_size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE,
+        )
+        .with_context(|| "Failed to create the staging buffer")?;
+        let staging = Guard::new(staging, |staging| staging.destroy(device));
+        let transfer_cb = Self::allocate_command_buffer(device, &queues.transfer_pool)?;
+        let transfer_cb = Guard::new(transfer_cb, |transfer_cb| {
+            device.free_command_buffers(queues.transfer_pool.get_vk(), &[*transfer_cb]);
+        });
+        let acquire_cb = match &queues.graphics_pool {
+            Some(pool) => {
+                let acquire_cb = Self::allocate_command_buffer(device, pool)?;
+                Some(Guard::new(acquire_cb, |acquire_cb| {
+                    device.free_command_buffers(pool.get_vk(), &[*acquire_cb]);
+                }))
+            }
+            None => None,
+        };
+        let semaphore = match &acquire_cb {
+            Some(_) => device.create_semaphore(&vk::SemaphoreCreateInfo::builder())?,
+            None => vk::Semaphore::null(),
+        };
+        let semaphore = Guard::new(semaphore, |semaphore| {
+            if !semaphore.is_null() {
+                device.destroy_semaphore(*semaphore);
+            }
+        });
+        let fence = device.create_fence(&vk::FenceCreateInfo::builder())?;
+        Ok(Self {
+            staging: staging.into_inner(),
+            transfer_cb: transfer_cb.into_inner(),
+            acquire_cb: acquire_cb.map(Guard::into_inner),
+            semaphore: semaphore.into_inner(),
+            fence,
+            upload_id: None,
+        })
+    }
+
+    fn allocate_command_buffer(
+        device: &LogicalDevice,
+        pool: &CommandPool,
+    ) -> anyhow::Result<vk::CommandBuffer> {
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(pool.get_vk())
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        Ok(device.allocate_command_buffers(&allocate_info)?[0])
+    }
+
+    /// Whether an upload was submitted from this slot and the GPU isn't done with it yet.
+    fn is_busy(&self, device: &LogicalDevice) -> anyhow::Result<bool> {
+        Ok(self.upload_id.is_some() && !device.get_fence_status(self.fence)?)
+    }
+
+    fn destroy(&self, device: &LogicalDevice) {
+        device.destroy_fence(self.fence);
+        if !self.semaphore.is_null() {
+            device.destroy_semaphore(self.semaphore);
+        }
+        self.staging.destroy(device);
+    }
+}
+
+/// Uploads data to device local buffers without waiting for the copies, through a ring of
+/// staging buffers reused once their upload is done.
+///
+/// At most [`UploadManagerConfig::ring_size`] uploads are in flight at once, which bounds the
+/// staging memory. Completion is tracked with a fence per slot, see [`UploadHandle`].
+pub struct UploadManager {
+    queues: UploadQueues,
+    slots: Vec<UploadSlot>,
+    next_slot: usize,
+    next_upload_id: u64,
+    config: UploadManagerConfig,
+}
+
+impl UploadManager {
+    /// How long a blocking upload or [`UploadManager::wait`] waits for the GPU.
+    const WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        config: UploadManagerConfig,
+    ) -> anyhow::Result<Self> {
+        if config.ring_size == 0 || config.slot_size == 0 {
+            bail!("The upload ring needs at least one non-empty slot, got {config:?}");
+        }
+        // The command buffers are re-recorded for every upload from their slot.
+        let queues = UploadQueues::new(device, vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)?;
+        let mut slots = Vec::with_capacity(config.ring_size);
+        for _ in 0..config.ring_size {
+            match UploadSlot::new(real_device, device, &queues, config.slot_size) {
+                Ok(slot) => slots.push(slot),
+                Err(e) => {
+                    slots.iter().for_each(|slot| slot.destroy(device));
+                    queues.destroy(device);
+                    return Err(e.context("Failed to create the upload ring"));
+                }
+            }
+        }
+        info!(
+            "Created upload ring of {} slots of {} bytes.",
+            config.ring_size, config.slot_size
+        );
+        Ok(Self {
+            queues,
+            slots,
+            next_slot: 0,
+            next_upload_id: 0,
+            config,
+        })
+    }
+
+    /// Copies `data` into the next staging slot and submits its copy into `dst` at
+    /// `dst_offset`, without waiting for it.
+    ///
+    /// `dst_stage` and `dst_access` describe the first use of `dst` on the graphics queue, which
+    /// must not happen before the returned handle is complete.
+    ///
+    /// # Returns
+    /// The handle of the upload, or `None` if the ring is full and the config says to
+    /// [`WhenFull::Defer`].
+    ///
+    /// # Errors
+    /// If `data` is empty, as a copy can't be, doesn't fit in a slot, or the copy would write
+    /// past the end of `dst`.
+    pub fn stage_and_copy<T: Copy>(
+        &mut self,
+        device: &LogicalDevice,
+        data: &[T],
+        dst: &Buffer,
+        dst_offset: vk::DeviceSize,
+        dst_stage: vk::PipelineStageFlags,
+        dst_access: vk::AccessFlags,
+    ) -> anyhow::Result<Option<UploadHandle>> {
+        let size = size_of_val(data) as vk::DeviceSize;
+        if size == 0 {
+            bail!("Can't upload empty data, a buffer copy can't be empty.");
+        }
+        if size > self.config.slot_size {
+            bail!(
+                "Upload of {size} bytes doesn't fit in a staging slot of {} bytes",
+                self.config.slot_size
+            );
+        }
+        if dst_offset + size > dst.get_size() {
+            bail!(
+                "Upload of {size} bytes at offset {dst_offset} overflows a buffer of {} bytes",
+                dst.get_size()
+            );
+        }
+
+        let slot_index = self.next_slot;
+        if self.slots[slot_index].is_busy(device)? {
+            match self.config.when_full {
+                WhenFull::Block => {
+                    trace!("Upload ring full, waiting for slot {slot_index}.");
+                    device
+                        .wait_for_fences(&[self.slots[slot_index].fence], true, Self::WAIT_TIMEOUT)
+                        .with_context(|| "Failed to wait for a staging slot")?;
+                }
+                WhenFull::Defer => {
+                    trace!("Upload ring full, deferring the upload.");
+                    return Ok(None);
+                }
+            }
+        }
+
+        let queues = &self.queues;
+        let slot = &mut self.slots[slot_index];
+        // The slot is free from here on, even if the submission below fails.
+        slot.upload_id = None;
+        slot.staging.write(device, data)?;
+
+        let region = vk::BufferCopy::builder()
+            .dst_offset(dst_offset)
+            .size(size)
+            .build();
+        Self::record(device, slot.transfer_cb, |command_buffer| {
+            device.copy_buffer(
+                command_buffer,
+                slot.staging.get_vk(),
+                dst.get_vk(),
+                &[region],
+            );
+            queues.ownership.record_buffer_release(
+                device,
+                command_buffer,
+                dst.get_vk(),
+                dst_stage,
+                dst_access,
+            );
+        })?;
+        if let Some(acquire_cb) = slot.acquire_cb {
+            Self::record(device, acquire_cb, |command_buffer| {
+                queues.ownership.record_buffer_acquire(
+                    device,
+                    command_buffer,
+                    dst.get_vk(),
+                    dst_stage,
+                    dst_access,
+                );
+            })?;
+        }
+
+        device.reset_fences(&[slot.fence])?;
+        submit_transfer(
+            device,
+            (queues.transfer_queue, slot.transfer_cb),
+            slot.acquire_cb
+                .map(|acquire_cb| (queues.graphics_queue, acquire_cb, dst_stage)),
+            slot.semaphore,
+            slot.fence,
+        )
+        .with_context(|| "Failed to submit the upload")?;
+
+        let id = self.next_upload_id;
+        slot.upload_id = Some(id);
+        self.next_upload_id += 1;
+        self.next_slot = (slot_index + 1) % self.slots.len();
+        debug!("Submitted upload {id} of {size} bytes from staging slot {slot_index}.");
+        Ok(Some(UploadHandle {
+            slot: slot_index,
+            id,
+        }))
+    }
+
+    /// Whether the upload of `handle` is done, and its destination buffer can be used.
+    pub fn is_complete(
+        &self,
+        device: &LogicalDevice,
+        handle: UploadHandle,
+    ) -> anyhow::Result<bool> {
+        let slot = &self.slots[handle.slot];
+        // A slot is only reused once its previous upload is done.
+        if slot.upload_id != Some(handle.id) {
+            return Ok(true);
+        }
+        device.get_fence_status(slot.fence)
+    }
+
+    /// Waits for the upload of `handle` to be done.
+    pub fn wait(&self, device: &LogicalDevice, handle: UploadHandle) -> anyhow::Result<()> {
+        let slot = &self.slots[handle.slot];
+        if slot.upload_id != Some(handle.id) {
+            return Ok(());
+        }
+        device
+            .wait_for_fences(&[slot.fence], true, Self::WAIT_TIMEOUT)
+            .with_context(|| format!("Failed to wait for upload {}", handle.id))
+    }
+
+    /// Number of uploads still in flight.
+    pub fn in_flight(&self, device: &LogicalDevice) -> anyhow::Result<usize> {
+        let mut count = 0;
+        for slot in &self.slots {
+            if slot.is_busy(device)? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    fn record(
+        device: &LogicalDevice,
+        command_buffer: vk::CommandBuffer,
+        commands: impl FnOnce(vk::CommandBuffer),
+    ) -> anyhow::Result<()> {
+        // Beginning implicitly resets the command buffer, its pool allows it.
+        let begin_info = vk::CommandBufferBeginInfo::builder()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        device.begin_command_buffer(command_buffer, &begin_info)?;
+        commands(command_buffer);
+        device.end_command_buffer(command_buffer)
+    }
+
+    /// Destroys the ring, the uploads in flight must be done, e.g. after waiting for the device
+    /// to be idle.
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.slots.iter().for_each(|slot| slot.destroy(device));
+        // Destroying the pools frees the command buffers of the slots.
+        self.queues.destroy(device);
+    }
+}
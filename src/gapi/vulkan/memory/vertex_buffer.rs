@@ -0,0 +1,83 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::memory::buffer::Buffer;
+use anyhow::{bail, Context};
+use vulkanalia::vk;
+
+/// A [`Buffer`] of vertices (or instances) of a single type, which remembers the size of that
+/// type to check it against the stride of the pipeline binding it is bound to, see
+/// [`Pipeline::bind_vertex_buffers`](crate::gapi::vulkan::pipeline::pipeline::Pipeline::bind_vertex_buffers).
+pub struct VertexBuffer {
+    buffer: Buffer,
+    /// The size of an element in bytes.
+    stride: u32,
+    /// How many elements fit in the buffer.
+    capacity: usize,
+}
+
+impl VertexBuffer {
+    /// Creates a buffer for `capacity` elements of type `T`, backed by memory with all the
+    /// `properties`.
+    pub fn new<T: Copy>(
+        real_device: &RealDevice,
+        device: &LogicalDevice,
+        capacity: usize,
+        properties: vk::MemoryPropertyFlags,
+    ) -> anyhow::Result<Self> {
+        let stride = size_of::<T>();
+        let buffer = Buffer::new(
+            real_device,
+            device,
+            (capacity * stride) as vk::DeviceSize,
+            vk::BufferUsageFlags::VERTEX_BUFFER,
+            properties,
+        )
+        .with_context(|| format!("Failed to create vertex buffer of {capacity} elements of {stride} bytes"))?;
+        Ok(Self {
+            buffer,
+            stride: stride as u32,
+            capacity,
+        })
+    }
+
+    /// Copies `data` to the start of the buffer, which must be `HOST_VISIBLE`.
+    ///
+    /// # Errors
+    /// If `T` isn't the size of the elements of the buffer, or there are more elements than the
+    /// capacity.
+    pub fn write<T: Copy>(&self, device: &LogicalDevice, data: &[T]) -> anyhow::Result<()> {
+        if size_of::<T>() != self.stride as usize {
+            bail!(
+                "Failed to write elements of {} bytes into vertex buffer {:?} of {}-byte elements.",
+                size_of::<T>(),
+                self.buffer.get_vk(),
+                self.stride
+            );
+        }
+        if data.len() > self.capacity {
+            bail!(
+                "Failed to write {} elements into vertex buffer {:?} of {}.",
+                data.len(),
+                self.buffer.get_vk(),
+                self.capacity
+            );
+        }
+        self.buffer.write(device, data)
+    }
+
+    pub fn get_vk(&self) -> vk::Buffer {
+        self.buffer.get_vk()
+    }
+
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.buffer.destroy(device);
+    }
+}
@@ -1,6 +1,12 @@
-mod config;
+pub(crate) mod config;
 pub(crate) mod enums;
+pub(crate) mod extent;
 pub(crate) mod pipeline;
 pub(crate) mod memory;
 pub(crate) mod core;
 pub(crate) mod commands;
+pub(crate) mod descriptors;
+pub(crate) mod sync;
+pub(crate) mod guard;
+#[cfg(test)]
+pub(crate) mod test_support;
@@ -0,0 +1,212 @@
+use crate::gapi::color::Color;
+use crate::gapi::vulkan::commands::command_buffers::CommandBuffer;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::extent::full_rect;
+use crate::gapi::vulkan::memory::depth_buffer::DepthBuffer;
+use crate::gapi::vulkan::memory::image::Image;
+use crate::gapi::vulkan::pipeline::stages::per_fragment_tests_stage::DepthConvention;
+use log::debug;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// The attachment formats a pipeline draws into with dynamic rendering, which replace the render
+/// pass it would be created for otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderingFormats {
+    pub color: vk::Format,
+    pub depth: Option<vk::Format>,
+}
+
+impl RenderingFormats {
+    /// The info to chain to the pipeline creation, which references `color_formats`: the color
+    /// format, or nothing for a depth-only pipeline.
+    pub fn pipeline_info<'a>(&self, color_formats: &'a [vk::Format]) -> vk::PipelineRenderingCreateInfoBuilder<'a> {
+        let depth_format = self.depth.unwrap_or(vk::Format::UNDEFINED);
+        let stencil_format = match self.depth {
+            Some(depth) if DepthBuffer::has_stencil(depth) => depth,
+            _ => vk::Format::UNDEFINED,
+        };
+        vk::PipelineRenderingCreateInfo::builder()
+            .color_attachment_formats(color_formats)
+            .depth_attachment_format(depth_format)
+            .stencil_attachment_format(stencil_format)
+    }
+}
+
+/// The images a frame is drawn into with [`DynamicRendering`].
+pub struct RenderingAttachments<'a> {
+    /// The image the frame ends up in, e.g. a swapchain image, and its view.
+    pub output: (vk::Image, &'a Image),
+    /// The multisampled color image resolved into `output`, `None` without multisampling.
+    pub multisampled: Option<(vk::Image, &'a Image)>,
+    pub depth: Option<&'a DepthBuffer>,
+}
+
+/// Draws into the attachments directly with `VK_KHR_dynamic_rendering`, instead of through a
+/// [`MyRenderPass`](crate::gapi::vulkan::pipeline::render_pass::MyRenderPass) and its
+/// framebuffers.
+///
+/// A render pass transitions the attachments to the layouts it needs by itself, so here
+/// [`DynamicRendering::begin`] and [`DynamicRendering::end`] record those transitions as barriers.
+/// There are no subpasses either, so there is no depth prepass on this path, and the
+/// multisampled color is always resolved when the rendering ends, like with
+/// [`MsaaResolveMode::InRenderPass`](crate::gapi::vulkan::pipeline::render_pass::MsaaResolveMode).
+///
+/// Needs a device created with
+/// [`DeviceExtension::KhrDynamicRendering`](crate::gapi::vulkan::enums::extensions::DeviceExtension),
+/// see `RealDevice::supports_dynamic_rendering`: Vulkan 1.2 and the extension, as dynamic
+/// rendering is only core since Vulkan 1.3 and the instance targets 1.2.
+#[derive(Clone, Copy, Debug)]
+pub struct DynamicRendering {
+    pub formats: RenderingFormats,
+    /// Selects the depth the depth attachment is cleared to.
+    pub depth_convention: DepthConvention,
+}
+
+impl DynamicRendering {
+    /// Transitions the `attachments` to their attachment layouts, discarding their contents, and
+    /// begins rendering into them over `extent`, cleared to `clear_color`.
+    pub fn begin(
+        &self,
+        device: &LogicalDevice,
+        command_buffer: &CommandBuffer,
+        attachments: &RenderingAttachments,
+        extent: vk::Extent2D,
+        clear_color: Color,
+    ) {
+        let to_color_attachment = |image: vk::Image| {
+            Self::barrier(
+                image,
+                vk::ImageAspectFlags::COLOR,
+                (vk::ImageLayout::UNDEFINED, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+                (vk::AccessFlags::empty(), vk::AccessFlags::COLOR_ATTACHMENT_WRITE),
+            )
+        };
+        let mut color_barriers = vec![to_color_attachment(attachments.output.0)];
+        if let Some((image, _)) = attachments.multisampled {
+            color_barriers.push(to_color_attachment(image));
+        }
+        device.pipeline_barrier(
+            *command_buffer.get_vk(),
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            &[],
+            &color_barriers,
+        );
+        if let Some(depth) = attachments.depth {
+            let to_depth_attachment = Self::barrier(
+                depth.get_image(),
+                Image::aspect_mask(depth.get_format()),
+                (vk::ImageLayout::UNDEFINED, vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+                (
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                ),
+            );
+            // The previous frame may still be testing against the same depth image.
+            let depth_stages = vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+            device.pipeline_barrier(*command_buffer.get_vk(), depth_stages, depth_stages, &[], &[to_depth_attachment]);
+        }
+
+        // With multisampling, the samples are drawn into the multisampled image, and averaged
+        // into the output when the rendering ends. Only the output is stored.
+        let (_, output_view) = attachments.output;
+        let color_attachment = vk::RenderingAttachmentInfo::builder()
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .clear_value(vk::ClearValue {
+                color: clear_color.into(),
+            });
+        let color_attachment = match attachments.multisampled {
+            Some((_, multisampled_view)) => color_attachment
+                .image_view(*multisampled_view.get_vk())
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                .resolve_image_view(*output_view.get_vk())
+                .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            None => color_attachment
+                .image_view(*output_view.get_vk())
+                .store_op(vk::AttachmentStoreOp::STORE),
+        };
+        let color_attachments = &[color_attachment];
+
+        // The depth values are not needed after drawing.
+        let depth_attachment = attachments.depth.map(|depth| {
+            vk::RenderingAttachmentInfo::builder()
+                .image_view(*depth.get_view().get_vk())
+                .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .clear_value(vk::ClearValue {
+                    depth_stencil: vk::ClearDepthStencilValue {
+                        depth: self.depth_convention.clear_depth(),
+                        stencil: 0,
+                    },
+                })
+        });
+
+        let render_area = full_rect(extent);
+        let mut info = vk::RenderingInfo::builder()
+            .render_area(render_area)
+            .layer_count(1)
+            .color_attachments(color_attachments);
+        if let Some(depth_attachment) = &depth_attachment {
+            info = info.depth_attachment(depth_attachment);
+            if attachments.depth.is_some_and(|depth| DepthBuffer::has_stencil(depth.get_format())) {
+                info = info.stencil_attachment(depth_attachment);
+            }
+        }
+        debug!("Created RenderingInfo struct: \n{info:#?}");
+        device.cmd_begin_rendering(*command_buffer.get_vk(), &info);
+    }
+
+    /// Ends the rendering, and transitions `output` to `final_layout`, e.g. `PRESENT_SRC_KHR`.
+    pub fn end(
+        &self,
+        device: &LogicalDevice,
+        command_buffer: &CommandBuffer,
+        output: vk::Image,
+        final_layout: vk::ImageLayout,
+    ) {
+        device.cmd_end_rendering(*command_buffer.get_vk());
+        // Presenting waits on the render finished semaphore, which makes the writes visible.
+        let to_final = Self::barrier(
+            output,
+            vk::ImageAspectFlags::COLOR,
+            (vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL, final_layout),
+            (vk::AccessFlags::COLOR_ATTACHMENT_WRITE, vk::AccessFlags::empty()),
+        );
+        device.pipeline_barrier(
+            *command_buffer.get_vk(),
+            vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            &[],
+            &[to_final],
+        );
+    }
+
+    /// A barrier transitioning the whole single-mip `image` between `layouts`.
+    fn barrier(
+        image: vk::Image,
+        aspect_mask: vk::ImageAspectFlags,
+        (old_layout, new_layout): (vk::ImageLayout, vk::ImageLayout),
+        (src_access, dst_access): (vk::AccessFlags, vk::AccessFlags),
+    ) -> vk::ImageMemoryBarrier {
+        let subresource = vk::ImageSubresourceRange::builder()
+            .aspect_mask(aspect_mask)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1);
+        vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource)
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access)
+            .build()
+    }
+}
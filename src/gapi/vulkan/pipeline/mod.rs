@@ -1,6 +1,8 @@
-mod stages;
+pub mod stages;
 mod shaders;
+pub mod dynamic_rendering;
 pub mod pipeline;
+pub mod pipeline_cache;
 pub mod render_pass;
+pub mod specialization;
 pub mod viewport;
-
@@ -1,39 +1,181 @@
+use crate::gapi::debug_draw::DebugDraw;
+use crate::gapi::voxel_render_mode::VoxelRenderMode;
 use crate::gapi::vulkan::commands::command_buffers::CommandBuffer;
 use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::memory::swapchain::OutputColorEncoding;
+use crate::gapi::vulkan::memory::vertex_buffer::VertexBuffer;
+use crate::gapi::vulkan::descriptors::descriptor_set_layout::DescriptorSetLayout;
+use crate::gapi::vulkan::guard::{failure_point, Guard};
+use crate::gapi::vulkan::pipeline::dynamic_rendering::RenderingFormats;
 use crate::gapi::vulkan::pipeline::render_pass::MyRenderPass;
 use crate::gapi::vulkan::pipeline::shaders::Shader;
+use crate::gapi::vulkan::pipeline::specialization::SpecializationConstants;
 use crate::gapi::vulkan::pipeline::stages::color_blending_stage::ColorBlendingStage;
 use crate::gapi::vulkan::pipeline::stages::input_assembler_stage::InputAssemblerStage;
-use crate::gapi::vulkan::pipeline::stages::per_fragment_tests_stage::PerFragmentTestsStage;
-use crate::gapi::vulkan::pipeline::stages::rasterization_stage::RasterizationStage;
+use crate::gapi::vulkan::pipeline::stages::per_fragment_tests_stage::{DepthConvention, DepthPass, PerFragmentTestsStage, StencilConfig};
+use crate::gapi::vulkan::pipeline::stages::rasterization_stage::{DepthBias, RasterizationStage};
 use crate::gapi::vulkan::pipeline::stages::shader_stage::ShaderStage;
 use crate::gapi::vulkan::pipeline::viewport::Viewport;
 use anyhow::Context;
+use log::error;
 use vulkanalia::vk;
 use vulkanalia::vk::{Handle, HasBuilder, ShaderStageFlags};
 
+/// Which shaders the [`Pipeline`] runs, and the vertex input they read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ShaderProgram {
+    /// Draws the voxels, as selected by [`PipelineConfig::voxel_render_mode`].
+    #[default]
+    Voxels,
+    /// Draws the lines and points of the [`DebugDraw`].
+    DebugPrimitives,
+}
+
+/// Configuration of the fixed-function stages of the [`Pipeline`].
+#[derive(Clone, Debug)]
+pub struct PipelineConfig {
+    /// The kind of primitives the vertices are assembled into.
+    pub topology: vk::PrimitiveTopology,
+    /// How the primitives are rasterized, `LINE` needs the `fill_mode_non_solid` feature.
+    pub polygon_mode: vk::PolygonMode,
+    /// Width of the lines in pixels, of line primitives and of the `LINE` polygon mode. Anything
+    /// but 1 needs the `wide_lines` feature.
+    pub line_width: f32,
+    /// Enables the stencil test, which needs a render pass with a stencil attachment.
+    pub stencil: Option<StencilConfig>,
+    /// Enables the depth bias, e.g. for the wireframe overlay.
+    pub depth_bias: Option<DepthBias>,
+    /// Size of the points in pixels when drawing a point list, passed to the vertex shader as
+    /// the specialization constant [`POINT_SIZE_CONSTANT_ID`].
+    pub point_size: f32,
+    /// Samples per pixel for multisample anti-aliasing, must match the render pass.
+    pub samples: vk::SampleCountFlags,
+    /// Enables sample shading with this minimum fraction of samples shaded per pixel, between 0
+    /// and 1. Needs the `sample_rate_shading` feature.
+    pub min_sample_shading: Option<f32>,
+    /// Selects the vertex shader and its vertex input.
+    pub program: ShaderProgram,
+    /// Selects the vertex shader and its vertex input of [`ShaderProgram::Voxels`].
+    pub voxel_render_mode: VoxelRenderMode,
+    /// How the pipeline uses the depth test, which also selects its subpass. Anything but
+    /// `Disabled` needs a render pass with a depth prepass.
+    pub depth_pass: DepthPass,
+    /// Which end of the depth range is near, must match the render pass.
+    pub depth_convention: DepthConvention,
+    /// Whether the fragment shader gamma-encodes its output, passed to it as the specialization
+    /// constant [`MANUAL_GAMMA_CONSTANT_ID`]. Must match the format of the color attachment.
+    pub output_color_encoding: OutputColorEncoding,
+    /// Creates the pipeline for dynamic rendering into attachments of these formats, instead of
+    /// for the render pass. Needs a device with dynamic rendering, and no depth prepass.
+    pub dynamic_rendering: Option<RenderingFormats>,
+    /// The cache the pipeline is created with, see
+    /// [`PipelineCache`](super::pipeline_cache::PipelineCache). Null creates it without one.
+    pub pipeline_cache: vk::PipelineCache,
+}
+
+/// `constant_id` of `POINT_SIZE` in the vertex shaders, declared in `common.glsl`.
+pub const POINT_SIZE_CONSTANT_ID: u32 = 0;
+
+/// `constant_id` of `MANUAL_GAMMA` in the fragment shader.
+pub const MANUAL_GAMMA_CONSTANT_ID: u32 = 0;
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            topology: vk::PrimitiveTopology::POINT_LIST,
+            polygon_mode: vk::PolygonMode::FILL,
+            line_width: 1.0,
+            stencil: None,
+            depth_bias: None,
+            point_size: 3.0,
+            samples: vk::SampleCountFlags::_1,
+            min_sample_shading: None,
+            program: ShaderProgram::Voxels,
+            voxel_render_mode: VoxelRenderMode::PointSprites,
+            depth_pass: DepthPass::Disabled,
+            depth_convention: DepthConvention::Standard,
+            output_color_encoding: OutputColorEncoding::AutoSrgb,
+            dynamic_rendering: None,
+            pipeline_cache: vk::PipelineCache::null(),
+        }
+    }
+}
+
 pub struct Pipeline {
     vk_pipeline_layout: vk::PipelineLayout,
     vk_pipeline: vk::Pipeline,
+    /// The vertex buffer bindings of the vertex input, to check the bound buffers against.
+    vertex_bindings: Vec<vk::VertexInputBindingDescription>,
 }
 
 impl Pipeline {
+    /// Creates the pipeline of `config`, whose shaders access the descriptor set `i` with the
+    /// layout `descriptor_set_layouts[i]`. The sets are partitioned by update frequency, see
+    /// [`MaterialSet`](crate::gapi::vulkan::descriptors::material_set::MaterialSet).
     pub fn new(
         device: &LogicalDevice,
         viewport: &Viewport,
         render_pass: &MyRenderPass,
+        descriptor_set_layouts: &[&DescriptorSetLayout],
+        config: &PipelineConfig,
     ) -> anyhow::Result<Self> {
-        let vert = include_bytes!(concat!(env!("OUT_DIR"), "/vert.spv"));
+        let vert: &[u8] = match (config.program, config.voxel_render_mode) {
+            (ShaderProgram::DebugPrimitives, _) => {
+                include_bytes!(concat!(env!("OUT_DIR"), "/debug_vert.spv"))
+            }
+            (ShaderProgram::Voxels, VoxelRenderMode::PointSprites | VoxelRenderMode::GreedyMesh) => {
+                include_bytes!(concat!(env!("OUT_DIR"), "/vert.spv"))
+            }
+            (ShaderProgram::Voxels, VoxelRenderMode::InstancedCubes) => {
+                include_bytes!(concat!(env!("OUT_DIR"), "/cube_vert.spv"))
+            }
+        };
         let frag = include_bytes!(concat!(env!("OUT_DIR"), "/frag.spv"));
-        let vert_shader_module = Shader::new(&device, &vert[..])?;
-        let frag_shader_module = Shader::new(&device, &frag[..])?;
+        // The shader modules only need to live until the pipeline is created, the guards destroy
+        // them when leaving, whether it succeeded or not.
+        let destroy_shader = |shader: &Shader| shader.destroy(device);
+        let vert_shader_module = Guard::new(Shader::new(&device, &vert[..])?, destroy_shader);
+        failure_point()?;
+        let frag_shader_module = Guard::new(Shader::new(&device, &frag[..])?, destroy_shader);
+        failure_point()?;
 
-        let input_assembly_stage = InputAssemblerStage::new();
-        let vert_shader_stage = ShaderStage::new(&vert_shader_module, ShaderStageFlags::VERTEX);
-        let rasterization_stage = RasterizationStage::new();
-        let per_frag_tests_stage = PerFragmentTestsStage::new();
-        let frag_shader_stage = ShaderStage::new(&frag_shader_module, ShaderStageFlags::FRAGMENT);
-        let color_blending_stage = ColorBlendingStage::new();
+        let (vertex_bindings, vertex_attributes) = match config.program {
+            ShaderProgram::Voxels => config.voxel_render_mode.vertex_input(),
+            ShaderProgram::DebugPrimitives => DebugDraw::vertex_input(),
+        };
+        let input_assembly_stage =
+            InputAssemblerStage::new(config.topology, vertex_bindings.clone(), vertex_attributes);
+        let vert_constants = SpecializationConstants::new()
+            .with(POINT_SIZE_CONSTANT_ID, config.point_size);
+        let vert_shader_stage = ShaderStage::new(
+            &vert_shader_module,
+            ShaderStageFlags::VERTEX,
+            Some(&vert_constants),
+        );
+        let rasterization_stage = RasterizationStage::new(
+            config.polygon_mode,
+            config.line_width,
+            config.depth_bias,
+            config.samples,
+            config.min_sample_shading,
+        );
+        let per_frag_tests_stage =
+            PerFragmentTestsStage::new(config.stencil, config.depth_pass, config.depth_convention);
+        let manual_gamma = config.output_color_encoding == OutputColorEncoding::ManualInShader;
+        let frag_constants = SpecializationConstants::new()
+            .with(MANUAL_GAMMA_CONSTANT_ID, manual_gamma as vk::Bool32);
+        let frag_shader_stage = ShaderStage::new(
+            &frag_shader_module,
+            ShaderStageFlags::FRAGMENT,
+            Some(&frag_constants),
+        );
+        // The prepass only writes depth, so it has no color attachment to blend into.
+        let depth_only = config.depth_pass == DepthPass::Prepass;
+        let color_blending_stage = if depth_only {
+            ColorBlendingStage::depth_only()
+        } else {
+            ColorBlendingStage::new()
+        };
 
         let vertex_input_state = input_assembly_stage.build_vertex_input_state();
         let input_assembly_state = input_assembly_stage.build_input_assembly_state();
@@ -42,15 +184,39 @@ impl Pipeline {
         let rasterization_state = rasterization_stage.build_rasterization_state();
         let multisample_state = rasterization_stage.build_multisample_state();
         let depth_stencil_state = per_frag_tests_stage.build_depth_stencil_state();
+        // The viewport and scissor are set while recording, so that the same pipeline can draw
+        // into several viewports (e.g. split-screen). The ones in the viewport state are ignored,
+        // only their count is used.
+        let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
 
         let vert_stage = vert_shader_stage.get_stage();
         let frag_stage = frag_shader_stage.get_stage();
 
-        let layout_info = vk::PipelineLayoutCreateInfo::builder();
-        let pipeline_layout = device.create_pipeline_layout(&layout_info)?;
+        // The layouts of the descriptor sets the shaders can access, set `i` uses the layout `i`.
+        let set_layouts = descriptor_set_layouts
+            .iter()
+            .map(|layout| layout.get_vk())
+            .collect::<Vec<_>>();
+        let layout_info = vk::PipelineLayoutCreateInfo::builder().set_layouts(&set_layouts);
+        let pipeline_layout = Guard::new(device.create_pipeline_layout(&layout_info)?, |layout: &vk::PipelineLayout| {
+            device.destroy_pipeline_layout(*layout)
+        });
+        failure_point()?;
 
-        let stages = &[*vert_stage, *frag_stage];
-        let info = vk::GraphicsPipelineCreateInfo::builder()
+        // Without color attachments, the fragment shader would have no effect, so the depth-only
+        // pipeline skips it.
+        let stages: &[vk::PipelineShaderStageCreateInfo] = if depth_only {
+            &[*vert_stage]
+        } else {
+            &[*vert_stage, *frag_stage]
+        };
+        let color_formats: &[vk::Format] = match &config.dynamic_rendering {
+            Some(formats) if !depth_only => std::slice::from_ref(&formats.color),
+            _ => &[],
+        };
+        let mut rendering_info = config.dynamic_rendering.map(|formats| formats.pipeline_info(color_formats));
+        let mut info = vk::GraphicsPipelineCreateInfo::builder()
             .stages(stages)
             .vertex_input_state(&vertex_input_state)
             .input_assembly_state(&input_assembly_state)
@@ -58,24 +224,29 @@ impl Pipeline {
             .rasterization_state(&rasterization_state)
             .multisample_state(&multisample_state)
             .color_blend_state(&color_blend_state)
-            .layout(pipeline_layout)
+            .layout(*pipeline_layout)
             .depth_stencil_state(&depth_stencil_state)
+            .dynamic_state(&dynamic_state)
             .render_pass(render_pass.get_vk())
-            .subpass(0)
+            .subpass(config.depth_pass.subpass(render_pass))
             .base_pipeline_handle(vk::Pipeline::null()) // Optional
             .base_pipeline_index(-1); // Optional
+        // With dynamic rendering, the attachment formats replace the render pass.
+        if let Some(rendering_info) = &mut rendering_info {
+            info = info
+                .render_pass(vk::RenderPass::null())
+                .subpass(0)
+                .push_next(rendering_info);
+        }
 
         let pipeline = device
-            .create_graphics_pipelines(vk::PipelineCache::null(), &[info])
+            .create_graphics_pipelines(config.pipeline_cache, &[info])
             .with_context(|| "Failed to create graphics pipeline")?[0];
 
-        // These need to live past pipeline creation, but can be destroyed immediately after.
-        vert_shader_module.destroy(&device);
-        frag_shader_module.destroy(&device);
-
         Ok(Pipeline {
-            vk_pipeline_layout: pipeline_layout,
+            vk_pipeline_layout: pipeline_layout.into_inner(),
             vk_pipeline: pipeline,
+            vertex_bindings,
         })
     }
 
@@ -87,8 +258,107 @@ impl Pipeline {
         );
     }
 
+    /// Binds `descriptor_sets` starting at set `first_set`, they must match the layouts the
+    /// pipeline was created with.
+    pub fn bind_descriptor_sets(
+        &self,
+        device: &LogicalDevice,
+        command_buffer: &CommandBuffer,
+        first_set: u32,
+        descriptor_sets: &[vk::DescriptorSet],
+    ) {
+        device.bind_descriptor_sets(
+            *command_buffer.get_vk(),
+            vk::PipelineBindPoint::GRAPHICS,
+            self.vk_pipeline_layout,
+            first_set,
+            descriptor_sets,
+        );
+    }
+
+    /// Binds `buffers` to the vertex bindings from `first_binding` on.
+    ///
+    /// In debug builds, logs an error for each buffer whose element size doesn't match the
+    /// stride of its binding, or that is bound to a binding the pipeline doesn't have. Such a
+    /// mismatch doesn't fail anywhere, the vertex shader just reads garbage.
+    pub fn bind_vertex_buffers(
+        &self,
+        device: &LogicalDevice,
+        command_buffer: &CommandBuffer,
+        first_binding: u32,
+        buffers: &[&VertexBuffer],
+        offsets: &[vk::DeviceSize],
+    ) {
+        if cfg!(debug_assertions) {
+            self.validate_vertex_buffers(first_binding, buffers);
+        }
+        let vk_buffers = buffers.iter().map(|buffer| buffer.get_vk()).collect::<Vec<_>>();
+        device.bind_vertex_buffers(*command_buffer.get_vk(), first_binding, &vk_buffers, offsets);
+    }
+
+    fn validate_vertex_buffers(&self, first_binding: u32, buffers: &[&VertexBuffer]) {
+        for (binding, buffer) in (first_binding..).zip(buffers) {
+            match self.vertex_bindings.iter().find(|description| description.binding == binding) {
+                Some(description) if description.stride != buffer.stride() => error!(
+                    "Vertex buffer {:?} of {}-byte elements is bound to binding {} of stride {}.",
+                    buffer.get_vk(),
+                    buffer.stride(),
+                    binding,
+                    description.stride
+                ),
+                Some(_) => {}
+                None => error!(
+                    "Vertex buffer {:?} is bound to binding {}, which the pipeline doesn't have.",
+                    buffer.get_vk(),
+                    binding
+                ),
+            }
+        }
+    }
+
+    pub fn get_layout(&self) -> vk::PipelineLayout {
+        self.vk_pipeline_layout
+    }
+
     pub fn destroy(&self, device: &LogicalDevice) {
         device.destroy_pipeline_layout(self.vk_pipeline_layout);
         device.destroy_pipeline(self.vk_pipeline);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gapi::frame_uniforms::FrameUniforms;
+    use crate::gapi::vulkan::extent::extent2d;
+    use crate::gapi::vulkan::pipeline::viewport::ViewportYFlip;
+    use crate::gapi::vulkan::test_support::{for_each_failure_point, with_headless_device};
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn a_failed_creation_destroys_the_shader_modules_and_the_layout() -> anyhow::Result<()> {
+        with_headless_device(|_, device| {
+            let render_pass = MyRenderPass::offscreen(device, vk::Format::R8G8B8A8_UNORM, None)?;
+            let render_pass = Guard::new(render_pass, |render_pass| render_pass.destroy(device));
+            let layout = DescriptorSetLayout::new(device, &[FrameUniforms::descriptor_binding(0)])?;
+            let layout = Guard::new(layout, |layout| layout.destroy(device));
+            let viewport = Viewport::from_extent(extent2d(4, 4), ViewportYFlip::default());
+            let config = PipelineConfig {
+                program: ShaderProgram::DebugPrimitives,
+                topology: vk::PrimitiveTopology::LINE_LIST,
+                ..PipelineConfig::default()
+            };
+            let live_before = device.resources().leaks();
+            let attempt = || Pipeline::new(device, &viewport, &render_pass, &[&*layout], &config);
+            let check = |fail_at| {
+                assert_eq!(
+                    device.resources().leaks(),
+                    live_before,
+                    "Failing at failure point {fail_at} leaked Vulkan objects."
+                );
+            };
+            for_each_failure_point(attempt, check).destroy(device);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,149 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use anyhow::Context;
+use log::{debug, info, warn};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// # Pipeline cache
+/// Keeps the pipelines compiled by the driver, so that creating them again, e.g. when switching
+/// debug draw modes or at the next launch, skips the shader compilation.
+///
+/// With a path, the cache is loaded from it when created, and written back to it by
+/// [`PipelineCache::save`], which the app does before destroying the device. A file written by
+/// another GPU or driver version is ignored, the driver would reject it anyway.
+#[derive(Debug)]
+pub struct PipelineCache {
+    vk_pipeline_cache: vk::PipelineCache,
+    path: Option<PathBuf>,
+}
+
+impl PipelineCache {
+    /// The size of the version one header at the start of the data, see
+    /// [`PipelineCache::is_compatible`].
+    const HEADER_SIZE: usize = 16 + vk::UUID_SIZE;
+
+    /// Creates the cache, filled with the data of the file at `path` if it exists and was written
+    /// by the same device and driver. `None` keeps the cache in memory only.
+    pub fn new(properties: &vk::PhysicalDeviceProperties, device: &LogicalDevice, path: Option<&Path>) -> anyhow::Result<Self> {
+        let data = path.map(|path| Self::load(properties, path)).unwrap_or_default();
+        let info = vk::PipelineCacheCreateInfo::builder().initial_data(&data);
+        let vk_pipeline_cache = device
+            .create_pipeline_cache(&info)
+            .with_context(|| "Failed to create the pipeline cache")?;
+        Ok(Self {
+            vk_pipeline_cache,
+            path: path.map(Path::to_path_buf),
+        })
+    }
+
+    /// The content of the file at `path`, or nothing if it doesn't exist, can't be read or is
+    /// incompatible, which only costs the compilation of the pipelines.
+    fn load(properties: &vk::PhysicalDeviceProperties, path: &Path) -> Vec<u8> {
+        match fs::read(path) {
+            Ok(data) if Self::is_compatible(&data, properties) => {
+                info!("Loaded {} bytes of pipeline cache from {}.", data.len(), path.display());
+                data
+            }
+            Ok(_) => {
+                warn!("Ignoring the pipeline cache {}, it was written by another device or driver.", path.display());
+                Vec::new()
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                debug!("No pipeline cache at {} yet.", path.display());
+                Vec::new()
+            }
+            Err(e) => {
+                warn!("Failed to read the pipeline cache {}: {e}", path.display());
+                Vec::new()
+            }
+        }
+    }
+
+    /// Whether `data` starts with the version one header of a cache of the device described by
+    /// `properties`: the header size, the version, the vendor and device IDs, then the cache UUID,
+    /// all little-endian.
+    fn is_compatible(data: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+        if data.len() < Self::HEADER_SIZE {
+            return false;
+        }
+        let u32_at = |offset: usize| u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        u32_at(0) as usize == Self::HEADER_SIZE
+            && u32_at(4) == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+            && u32_at(8) == properties.vendor_id
+            && u32_at(12) == properties.device_id
+            && data[16..Self::HEADER_SIZE] == properties.pipeline_cache_uuid[..]
+    }
+
+    pub fn get_vk(&self) -> vk::PipelineCache {
+        self.vk_pipeline_cache
+    }
+
+    /// Writes the cache to its path, if it has one. The device must be idle, or at least not
+    /// creating pipelines.
+    pub fn save(&self, device: &LogicalDevice) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let data = device.get_pipeline_cache_data(self.vk_pipeline_cache)?;
+        fs::write(path, &data).with_context(|| format!("Failed to write the pipeline cache {}", path.display()))?;
+        info!("Saved {} bytes of pipeline cache to {}.", data.len(), path.display());
+        Ok(())
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        device.destroy_pipeline_cache(self.vk_pipeline_cache);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties() -> vk::PhysicalDeviceProperties {
+        vk::PhysicalDeviceProperties {
+            vendor_id: 0x10de,
+            device_id: 0x2684,
+            pipeline_cache_uuid: [7; vk::UUID_SIZE],
+            ..Default::default()
+        }
+    }
+
+    fn header(properties: &vk::PhysicalDeviceProperties) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&(PipelineCache::HEADER_SIZE as u32).to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&properties.vendor_id.to_le_bytes());
+        data.extend_from_slice(&properties.device_id.to_le_bytes());
+        data.extend_from_slice(&properties.pipeline_cache_uuid);
+        data
+    }
+
+    #[test]
+    fn accepts_a_cache_of_the_same_device() {
+        let properties = properties();
+        let mut data = header(&properties);
+        data.extend_from_slice(&[1, 2, 3]);
+        assert!(PipelineCache::is_compatible(&data, &properties));
+    }
+
+    #[test]
+    fn rejects_a_cache_of_another_device_or_a_truncated_one() {
+        let properties = properties();
+        let data = header(&properties);
+        let other_driver = vk::PhysicalDeviceProperties {
+            pipeline_cache_uuid: [8; vk::UUID_SIZE],
+            ..self::properties()
+        };
+        let other_device = vk::PhysicalDeviceProperties {
+            device_id: 0x2704,
+            ..self::properties()
+        };
+        assert!(!PipelineCache::is_compatible(&data, &other_driver));
+        assert!(!PipelineCache::is_compatible(&data, &other_device));
+        assert!(!PipelineCache::is_compatible(&data[..PipelineCache::HEADER_SIZE - 1], &properties));
+        assert!(!PipelineCache::is_compatible(&[], &properties));
+    }
+}
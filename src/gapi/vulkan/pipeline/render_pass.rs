@@ -1,11 +1,36 @@
-use anyhow::Context;
+use crate::gapi::color::Color;
+use anyhow::{bail, Context};
 use log::debug;
 use vulkanalia::vk;
 use vulkanalia::vk::{Format, HasBuilder};
 use crate::gapi::vulkan::commands::command_buffers::{CommandBuffer, CommandBuffers};
 use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::extent::full_rect;
+use crate::gapi::vulkan::memory::depth_buffer::DepthBuffer;
 use crate::gapi::vulkan::memory::framebuffer::Framebuffer;
 use crate::gapi::vulkan::memory::swapchain::Swapchain;
+use crate::gapi::vulkan::pipeline::stages::per_fragment_tests_stage::DepthConvention;
+
+/// Where the multisampled color attachment is resolved into the single-sampled output image.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MsaaResolveMode {
+    /// By the render pass itself, with a resolve attachment. It is the cheapest, tiled GPUs
+    /// resolve on-chip without ever writing the samples to memory.
+    #[default]
+    InRenderPass,
+    /// Later, with `vkCmdResolveImage` or a shader reading the samples. The render pass stores
+    /// the multisampled image, and leaves it in `TRANSFER_SRC_OPTIMAL`.
+    ///
+    /// It is needed when something must run on the samples before they are averaged, e.g. tone
+    /// mapping HDR colors, as averaging first lets a single very bright sample wash out the
+    /// edge, or a custom resolve filter. It costs the bandwidth of storing every sample.
+    Manual,
+}
+
+/// The stages the depth attachment is read and written in.
+const DEPTH_TEST_STAGES: vk::PipelineStageFlags = vk::PipelineStageFlags::from_bits_truncate(
+    vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS.bits() | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS.bits(),
+);
 
 /// RenderPass is a specification of:
 /// - How many color and depth buffers there will be
@@ -13,16 +38,80 @@ use crate::gapi::vulkan::memory::swapchain::Swapchain;
 /// - How their contents should be handled throughout the rendering operations
 pub struct MyRenderPass {
     render_pass_vk: vk::RenderPass,
+    /// Format of the depth (and stencil) attachment, `None` if there is no depth attachment.
+    depth_format: Option<vk::Format>,
+    /// Whether a depth-only subpass runs before the main one.
+    depth_prepass: bool,
+    /// Selects the depth the depth attachment is cleared to.
+    depth_convention: DepthConvention,
 }
 
 impl MyRenderPass {
-    pub fn new(swapchain: &Swapchain, device: &LogicalDevice) -> anyhow::Result<Self> {
+    /// Creates a render pass drawing into the swapchain images, which are then presented.
+    pub fn new(
+        swapchain: &Swapchain,
+        device: &LogicalDevice,
+        depth_format: Option<vk::Format>,
+        samples: vk::SampleCountFlags,
+        resolve: MsaaResolveMode,
+        depth_prepass: bool,
+        depth_convention: DepthConvention,
+    ) -> anyhow::Result<Self> {
+        // The format of the color attachment should match the format of the swapchain images.
+        Self::create(
+            device,
+            swapchain.format,
+            vk::ImageLayout::PRESENT_SRC_KHR,
+            depth_format,
+            (samples, resolve),
+            depth_prepass,
+            depth_convention,
+        )
+    }
 
-        // The format of the color attachment should match the format of the swapchain images,
-        // and we're not doing anything with multisampling yet, so we'll stick to 1 sample.
-        let format = swapchain.format;
+    /// Creates a render pass drawing into an image that later passes sample, e.g. a shadow map
+    /// or a reflection, see [`RenderTarget`](crate::gapi::vulkan::memory::render_target::RenderTarget).
+    /// Its depth attachment uses the standard depth convention.
+    pub fn offscreen(
+        device: &LogicalDevice,
+        color_format: vk::Format,
+        depth_format: Option<vk::Format>,
+    ) -> anyhow::Result<Self> {
+        Self::create(
+            device,
+            color_format,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            depth_format,
+            (vk::SampleCountFlags::_1, MsaaResolveMode::InRenderPass),
+            false,
+            DepthConvention::Standard,
+        )
+    }
+
+    /// Creates a render pass whose single-sampled color image ends in `output_layout`, ready
+    /// for what comes after the render pass (presenting or sampling it).
+    ///
+    /// With [`MsaaResolveMode::Manual`], there is no single-sampled image in the render pass,
+    /// resolving it into the output image and transitioning it to `output_layout` are left to
+    /// the caller.
+    fn create(
+        device: &LogicalDevice,
+        format: vk::Format,
+        output_layout: vk::ImageLayout,
+        depth_format: Option<vk::Format>,
+        (samples, resolve): (vk::SampleCountFlags, MsaaResolveMode),
+        depth_prepass: bool,
+        depth_convention: DepthConvention,
+    ) -> anyhow::Result<Self> {
+        if depth_prepass && depth_format.is_none() {
+            bail!("A depth prepass needs a depth attachment.");
+        }
 
-        let samples = vk::SampleCountFlags::_1;
+        // With multisampling, the color attachment is a separate multisampled image, which is
+        // resolved into the swapchain image at the end of the subpass.
+        let multisampled = samples != vk::SampleCountFlags::_1;
+        let resolve_in_pass = multisampled && resolve == MsaaResolveMode::InRenderPass;
+        let resolve_manually = multisampled && resolve == MsaaResolveMode::Manual;
 
         // The load_op and store_op determine what to do with the data in the attachment before
         // rendering and after rendering.
@@ -42,8 +131,13 @@ impl MyRenderPass {
         // - vk::AttachmentStoreOp::DONT_CARE – Contents of the framebuffer will be undefined
         // after the rendering operation
         // We're interested in seeing the rendered triangle on the screen, so we're going with
-        // the store operation here.
-        let store_op = vk::AttachmentStoreOp::STORE;
+        // the store operation here. When resolving in the render pass, only the resolved image is
+        // stored.
+        let store_op = if resolve_in_pass {
+            vk::AttachmentStoreOp::DONT_CARE
+        } else {
+            vk::AttachmentStoreOp::STORE
+        };
 
         // The load_op and store_op apply to color and depth data, and
         // stencil_load_op / stencil_store_op apply to stencil data.
@@ -70,7 +164,14 @@ impl MyRenderPass {
         let initial_layout = vk::ImageLayout::UNDEFINED;
 
         // final_layout specifies the layout to automatically transition to when the render pass finishes
-        let final_layout = vk::ImageLayout::PRESENT_SRC_KHR;
+        // The multisampled image is never presented, only the swapchain image it resolves to.
+        let final_layout = if resolve_in_pass {
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        } else if resolve_manually {
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+        } else {
+            output_layout
+        };
 
 
         let color_attachment = vk::AttachmentDescription::builder()
@@ -86,8 +187,8 @@ impl MyRenderPass {
 
 
         // The attachment parameter specifies which attachment to reference by its index in the
-        // attachment descriptions array. Our array consists of a single vk::AttachmentDescription,
-        // so its index is 0.
+        // attachment descriptions array. The color attachment always comes first, so its index
+        // is 0.
         let attachment = 0;
         // The layout specifies which layout we would like the attachment to have during a
         // subpass that uses this reference.
@@ -120,20 +221,143 @@ impl MyRenderPass {
         let color_attachments = &[color_attachment_ref];
 
 
-        let subpass = vk::SubpassDescription::builder()
+        let mut attachments = vec![color_attachment.build()];
+
+        // The depth attachment goes right after the color attachment (index 1).
+        // Unlike the color attachment, its stencil ops matter when the format has a stencil
+        // component, which is cleared at the start like the depth values.
+        let depth_attachment_ref = depth_format.map(|depth_format| {
+            let (stencil_load_op, stencil_store_op) = if DepthBuffer::has_stencil(depth_format) {
+                (vk::AttachmentLoadOp::CLEAR, vk::AttachmentStoreOp::DONT_CARE)
+            } else {
+                (vk::AttachmentLoadOp::DONT_CARE, vk::AttachmentStoreOp::DONT_CARE)
+            };
+            let depth_attachment = vk::AttachmentDescription::builder()
+                .format(depth_format)
+                .samples(samples)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                // The depth values are not needed after drawing.
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .stencil_load_op(stencil_load_op)
+                .stencil_store_op(stencil_store_op)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .build();
+            debug!("Created depth AttachmentDescription struct with config: \n{depth_attachment:#?}");
+            attachments.push(depth_attachment);
+
+            vk::AttachmentReference::builder()
+                .attachment(1)
+                .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .build()
+        });
+
+        // The resolve attachment is the output image, and goes after the depth attachment.
+        let resolve_attachment_refs = if resolve_in_pass {
+            let resolve_attachment = vk::AttachmentDescription::builder()
+                .format(format)
+                .samples(vk::SampleCountFlags::_1)
+                .load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(output_layout)
+                .build();
+            debug!("Created resolve AttachmentDescription struct with config: \n{resolve_attachment:#?}");
+            attachments.push(resolve_attachment);
+
+            vec![vk::AttachmentReference::builder()
+                .attachment(attachments.len() as u32 - 1)
+                .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .build()]
+        } else {
+            vec![]
+        };
+
+        let mut subpass = vk::SubpassDescription::builder()
             // Vulkan may also support compute subpasses in the future, so we have to be explicit
             // about this being a graphics subpass.
             .pipeline_bind_point(pipeline_bind_point)
-            .color_attachments(color_attachments)
-            .build();
+            .color_attachments(color_attachments);
+        // Each color attachment is resolved into the resolve attachment at the same index.
+        if resolve_in_pass {
+            subpass = subpass.resolve_attachments(&resolve_attachment_refs);
+        }
+        // Unlike color attachments, a subpass can only use a single depth (+stencil) attachment.
+        if let Some(depth_attachment_ref) = &depth_attachment_ref {
+            subpass = subpass.depth_stencil_attachment(depth_attachment_ref);
+        }
+        let subpass = subpass.build();
 
         debug!("Created Subpass struct: \n{subpass:#?}");
 
-        let attachments = &[color_attachment];
-        let subpasses = &[subpass];
+        // The depth prepass is a subpass before the main one that only uses the depth attachment.
+        // The depth is cleared when the render pass begins, filled by the prepass, and the main
+        // subpass tests against it, so the attachment load and store ops stay the same.
+        let mut subpasses = vec![];
+        let mut dependencies = vec![];
+        if depth_prepass {
+            let mut prepass = vk::SubpassDescription::builder()
+                .pipeline_bind_point(pipeline_bind_point);
+            if let Some(depth_attachment_ref) = &depth_attachment_ref {
+                prepass = prepass.depth_stencil_attachment(depth_attachment_ref);
+            }
+            let prepass = prepass.build();
+            debug!("Created depth prepass Subpass struct: \n{prepass:#?}");
+            subpasses.push(prepass);
+
+            // The main subpass can only test against the depths once the prepass wrote them.
+            // Fragments that discard are tested late, so both test stages wait.
+            let dependency = vk::SubpassDependency::builder()
+                .src_subpass(0)
+                .dst_subpass(1)
+                .src_stage_mask(vk::PipelineStageFlags::LATE_FRAGMENT_TESTS)
+                .dst_stage_mask(DEPTH_TEST_STAGES)
+                .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ)
+                .dependency_flags(vk::DependencyFlags::BY_REGION)
+                .build();
+            debug!("Created depth prepass SubpassDependency struct: \n{dependency:#?}");
+            dependencies.push(dependency);
+        }
+        subpasses.push(subpass);
+        let main_subpass = subpasses.len() as u32 - 1;
+        let sampled_output = output_layout == vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+        dependencies.extend(Self::incoming_dependencies(main_subpass, depth_format.is_some(), sampled_output));
+
+        // Sampling the image in a later render pass must wait for the color to be written.
+        if sampled_output {
+            let dependency = vk::SubpassDependency::builder()
+                .src_subpass(subpasses.len() as u32 - 1)
+                .dst_subpass(vk::SUBPASS_EXTERNAL)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .build();
+            debug!("Created sampled output SubpassDependency struct: \n{dependency:#?}");
+            dependencies.push(dependency);
+        }
+
+        // The manual resolve reads the samples once they are all written.
+        if resolve_manually {
+            let dependency = vk::SubpassDependency::builder()
+                .src_subpass(subpasses.len() as u32 - 1)
+                .dst_subpass(vk::SUBPASS_EXTERNAL)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_stage_mask(vk::PipelineStageFlags::TRANSFER)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .build();
+            debug!("Created manual resolve SubpassDependency struct: \n{dependency:#?}");
+            dependencies.push(dependency);
+        }
+
         let render_pass = vk::RenderPassCreateInfo::builder()
-            .attachments(attachments)
-            .subpasses(subpasses)
+            .attachments(&attachments)
+            .subpasses(&subpasses)
+            .dependencies(&dependencies)
             .build();
 
         debug!("Created RenderPass struct: \n{render_pass:#?}");
@@ -144,36 +368,111 @@ impl MyRenderPass {
 
         Ok(Self {
             render_pass_vk: render_pass,
+            depth_format,
+            depth_prepass,
+            depth_convention,
         })
     }
 
+    /// The dependencies on the commands submitted before the render pass, e.g. the previous
+    /// frame, which uses the same attachments except for the swapchain image.
+    ///
+    /// # Attachment dependency chain
+    /// Within a frame, the attachments are written and read in this order:
+    /// 1. Before the render pass: the image available semaphore is waited at the color attachment
+    ///    output stage. The previous frame may still test against the same depth buffer and
+    ///    write the same multisampled color buffer, or sample the same offscreen image.
+    /// 2. The depth prepass subpass, if any, writes the depth buffer.
+    /// 3. The main subpass tests against the depths, see the dependency between the subpasses,
+    ///    and writes the color. The scene, the debug primitives and the overlay are all drawn in
+    ///    it, in submission order, which the rasterization order already keeps for the
+    ///    attachments.
+    /// 4. After the render pass: the output is presented, sampled, or resolved manually, see the
+    ///    outgoing dependencies.
+    ///
+    /// The first of these dependencies covers the color: its layout transition must wait for the
+    /// acquired image, instead of happening at the top of the pipe before the semaphore wait,
+    /// and for the previous frame to be done sampling an offscreen image. The second covers the
+    /// depth buffer, which the previous frame may still be writing.
+    fn incoming_dependencies(main_subpass: u32, depth: bool, sampled_output: bool) -> Vec<vk::SubpassDependency> {
+        let mut color_src_stages = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
+        if sampled_output {
+            color_src_stages |= vk::PipelineStageFlags::FRAGMENT_SHADER;
+        }
+        let color = vk::SubpassDependency::builder()
+            .src_subpass(vk::SUBPASS_EXTERNAL)
+            .dst_subpass(main_subpass)
+            .src_stage_mask(color_src_stages)
+            .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .build();
+        debug!("Created incoming color SubpassDependency struct: \n{color:#?}");
+        let mut dependencies = vec![color];
+        // The prepass, when there is one, is the first subpass to use the depth.
+        if depth {
+            let depth = vk::SubpassDependency::builder()
+                .src_subpass(vk::SUBPASS_EXTERNAL)
+                .dst_subpass(0)
+                .src_stage_mask(DEPTH_TEST_STAGES)
+                .dst_stage_mask(DEPTH_TEST_STAGES)
+                .src_access_mask(vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                .dst_access_mask(
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                )
+                .build();
+            debug!("Created incoming depth SubpassDependency struct: \n{depth:#?}");
+            dependencies.push(depth);
+        }
+        dependencies
+    }
+
     pub fn get_vk(&self) -> vk::RenderPass {
         self.render_pass_vk
     }
 
+    pub fn has_depth_prepass(&self) -> bool {
+        self.depth_prepass
+    }
+
+    /// Index of the subpass that draws the color, the last one.
+    pub fn main_subpass(&self) -> u32 {
+        self.depth_prepass as u32
+    }
+
+    /// Moves from the depth prepass to the main subpass.
+    pub fn next_subpass(&self, device: &LogicalDevice, command_buffer: vk::CommandBuffer) {
+        device.next_subpass(command_buffer, vk::SubpassContents::INLINE);
+    }
+
     pub fn begin(&self, device: &LogicalDevice,
                  framebuffer: &Framebuffer,
                  command_buffer: &CommandBuffer,
-                 swapchain: &Swapchain) {
+                 extent: vk::Extent2D,
+                 clear_color: Color) {
 
         let clear_color = vk::ClearValue {
-            color: vk::ClearColorValue {
-                float32: [0.0, 0.0, 0.0, 1.0],
-            },
+            color: clear_color.into(),
         };
         debug!("Created ClearValue struct: \n{clear_color:#?}");
 
-        let clear_values = &[clear_color];
-        let render_area = vk::Rect2D {
-            offset: vk::Offset2D { x: 0, y: 0 },
-            extent: swapchain.extent,
-        };
+        // The clear values are indexed like the attachments, so the depth clear goes second.
+        let mut clear_values = vec![clear_color];
+        if self.depth_format.is_some() {
+            clear_values.push(vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: self.depth_convention.clear_depth(),
+                    stencil: 0,
+                },
+            });
+        }
+        let render_area = full_rect(extent);
         debug!("Created Rect2D struct for render area: \n{render_area:#?}");
         let info = vk::RenderPassBeginInfo::builder()
             .render_pass(self.render_pass_vk)
             .framebuffer(framebuffer.get_vk())
             .render_area(render_area)
-            .clear_values(clear_values)
+            .clear_values(&clear_values)
             .build();
         debug!("Created RenderPassBeginInfo struct: \n{info:#?}");
         unsafe {
@@ -191,3 +490,117 @@ impl MyRenderPass {
         device.destroy_render_pass(self.render_pass_vk);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gapi::debug_draw::DebugVertex;
+    use crate::gapi::frame_uniforms::FrameUniforms;
+    use crate::gapi::viewport_layout::ViewportRegion;
+    use crate::gapi::vulkan::core::real_device::RealDevice;
+    use crate::gapi::vulkan::descriptors::descriptor_set_layout::DescriptorSetLayout;
+    use crate::gapi::vulkan::guard::Guard;
+    use crate::gapi::vulkan::memory::color_buffer::ColorBuffer;
+    use crate::gapi::vulkan::memory::depth_buffer::DepthFormatPreference;
+    use crate::gapi::vulkan::memory::render_target::RenderTarget;
+    use crate::gapi::vulkan::memory::vertex_buffer::VertexBuffer;
+    use crate::gapi::vulkan::pipeline::pipeline::{Pipeline, PipelineConfig, ShaderProgram};
+    use crate::gapi::vulkan::pipeline::stages::per_fragment_tests_stage::DepthPass;
+    use crate::gapi::vulkan::pipeline::viewport::{Viewport, ViewportYFlip};
+    use crate::gapi::vulkan::test_support::{submit_and_wait, with_headless_device};
+
+    const FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+
+    /// Draws a triangle in the depth prepass, then in the main subpass, like a frame of the app
+    /// with [`AppConfig::depth_prepass`](crate::gapi::app::AppConfig::depth_prepass), into an offscreen
+    /// target.
+    fn draw_multipass_frame(real_device: &RealDevice, device: &LogicalDevice, samples: vk::SampleCountFlags) -> anyhow::Result<()> {
+        let extent = vk::Extent2D { width: 16, height: 16 };
+        let output = RenderTarget::new(real_device, device, extent, FORMAT, None)?;
+        let output = Guard::new(output, |output| output.destroy(device));
+        let color_buffer = (samples != vk::SampleCountFlags::_1)
+            .then(|| ColorBuffer::new(real_device, device, extent, FORMAT, samples, MsaaResolveMode::InRenderPass))
+            .transpose()?;
+        let color_buffer = Guard::new(color_buffer, |color_buffer| {
+            if let Some(color_buffer) = color_buffer {
+                color_buffer.destroy(device);
+            }
+        });
+        let depth_buffer = DepthBuffer::new(real_device, device, extent, false, DepthFormatPreference::HighPrecision, samples)?;
+        let depth_buffer = Guard::new(depth_buffer, |depth_buffer| depth_buffer.destroy(device));
+        let render_pass = MyRenderPass::create(
+            device,
+            FORMAT,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            Some(depth_buffer.get_format()),
+            (samples, MsaaResolveMode::InRenderPass),
+            true,
+            DepthConvention::Standard,
+        )?;
+        let render_pass = Guard::new(render_pass, |render_pass| render_pass.destroy(device));
+        // In the order of the attachments: color, depth, then resolve.
+        let mut attachments = vec![color_buffer.as_ref().map_or(output.color_view(), ColorBuffer::get_view)];
+        attachments.push(depth_buffer.get_view());
+        if color_buffer.is_some() {
+            attachments.push(output.color_view());
+        }
+        let framebuffer = Framebuffer::with_extent(&render_pass, &attachments, extent, device)?;
+        let framebuffer = Guard::new(framebuffer, |framebuffer| framebuffer.destroy(device));
+
+        let layout = DescriptorSetLayout::new(device, &[FrameUniforms::descriptor_binding(0)])?;
+        let layout = Guard::new(layout, |layout| layout.destroy(device));
+        let viewport = Viewport::from_extent(extent, ViewportYFlip::default());
+        let config = |depth_pass| PipelineConfig {
+            program: ShaderProgram::DebugPrimitives,
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            samples,
+            depth_pass,
+            ..PipelineConfig::default()
+        };
+        let prepass = Pipeline::new(device, &viewport, &render_pass, &[&*layout], &config(DepthPass::Prepass))?;
+        let prepass = Guard::new(prepass, |pipeline| pipeline.destroy(device));
+        let main = Pipeline::new(device, &viewport, &render_pass, &[&*layout], &config(DepthPass::AfterPrepass))?;
+        let main = Guard::new(main, |pipeline| pipeline.destroy(device));
+
+        let triangle = [[-1.0, -1.0, 0.5], [3.0, -1.0, 0.5], [-1.0, 3.0, 0.5]].map(|position| DebugVertex {
+            position,
+            color: [1.0, 0.0, 0.0],
+        });
+        let properties = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+        let vertices = VertexBuffer::new::<DebugVertex>(real_device, device, triangle.len(), properties)?;
+        let vertices = Guard::new(vertices, |vertices| vertices.destroy(device));
+        vertices.write(device, &triangle)?;
+
+        submit_and_wait(device, |command_buffer| {
+            let vk_command_buffer = *command_buffer.get_vk();
+            render_pass.begin(device, &framebuffer, command_buffer, extent, Color::BLACK);
+            let (vk_viewport, scissor) = ViewportRegion::FULL.to_vk(extent, ViewportYFlip::default());
+            device.set_viewport(vk_command_buffer, &[vk_viewport]);
+            device.set_scissor(vk_command_buffer, &[scissor]);
+            let draw_triangle = |pipeline: &Pipeline| {
+                pipeline.bind(device, command_buffer);
+                pipeline.bind_vertex_buffers(device, command_buffer, 0, &[&*vertices], &[0]);
+                device.draw(vk_command_buffer, triangle.len() as u32, 1, 0, 0);
+            };
+            draw_triangle(&prepass);
+            render_pass.next_subpass(device, vk_command_buffer);
+            draw_triangle(&main);
+            render_pass.end(device, vk_command_buffer);
+            Ok(())
+        })
+    }
+
+    /// The dependencies between the prepass, the main subpass and the resolve, checked by the
+    /// synchronization validation, see [`lock_gpu`](crate::gapi::vulkan::test_support::lock_gpu).
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn the_depth_prepass_and_the_main_subpass_are_synchronized() -> anyhow::Result<()> {
+        with_headless_device(|real_device, device| {
+            // Every device supports 4 samples for color and depth attachments.
+            for samples in [vk::SampleCountFlags::_1, vk::SampleCountFlags::_4] {
+                draw_multipass_frame(real_device, device, samples)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,54 @@
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// Values for the specialization constants of a shader stage, which let us tweak a shader (e.g.
+/// the voxel size, the max number of lights) when creating the pipeline, without recompiling
+/// the SPIR-V.
+///
+/// In GLSL, a specialization constant is declared with an ID and a default value:
+/// ```glsl
+/// layout(constant_id = 0) const float POINT_SIZE = 3.0;
+/// ```
+///
+/// The values are packed one after another in a data blob, and each map entry tells Vulkan the
+/// offset and size of the constant with a given ID. Vulkan reads the blob as raw bytes, so each
+/// value must have the exact layout of the GLSL type: use `f32`/`i32`/`u32` for
+/// `float`/`int`/`uint`, `vk::Bool32` for `bool`, and `#[repr(C)]` for any custom struct.
+/// Rust's default layout is unspecified and must not be used.
+#[derive(Clone, Debug, Default)]
+pub struct SpecializationConstants {
+    map_entries: Vec<vk::SpecializationMapEntry>,
+    data: Vec<u8>,
+}
+
+impl SpecializationConstants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the constant with `constant_id` to `value`, see the layout requirements above.
+    pub fn with<T: Copy>(mut self, constant_id: u32, value: T) -> Self {
+        let size = size_of::<T>();
+        let bytes = unsafe { std::slice::from_raw_parts((&value as *const T).cast::<u8>(), size) };
+        let entry = vk::SpecializationMapEntry::builder()
+            .constant_id(constant_id)
+            .offset(self.data.len() as u32)
+            .size(size)
+            .build();
+        self.map_entries.push(entry);
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map_entries.is_empty()
+    }
+
+    /// Builds the info pointing to the constants, which must outlive it.
+    pub fn build_info(&self) -> vk::SpecializationInfo {
+        vk::SpecializationInfo::builder()
+            .map_entries(&self.map_entries)
+            .data(&self.data)
+            .build()
+    }
+}
@@ -1,3 +1,4 @@
+use crate::gapi::color::Color;
 use log::{debug, info};
 use vulkanalia::vk;
 use vulkanalia::vk::HasBuilder;
@@ -52,11 +53,20 @@ impl ColorBlendingStage {
         }
     }
 
+    /// For pipelines that don't write any color, like the depth prepass, whose subpass has no
+    /// color attachments.
+    pub fn depth_only() -> Self {
+        info!("Configuring color blending without color attachments");
+        Self {
+            attachments: vec![],
+        }
+    }
+
     pub fn build_color_blend_state(&self) -> vk::PipelineColorBlendStateCreateInfo {
 
         let logic_op_enable = false;
         let logic_op = vk::LogicOp::COPY;
-        let blend_constants = [0.0, 0.0, 0.0, 0.0];
+        let blend_constants = Color::TRANSPARENT.to_array();
         // The second structure references the array of structures for all of the framebuffers and
         // allows you to set blend constants that you can use as blend factors in the aforementioned
         // calculations.
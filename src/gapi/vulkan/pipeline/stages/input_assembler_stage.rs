@@ -4,15 +4,21 @@ use vulkanalia::vk::HasBuilder;
 pub struct InputAssemblerStage{
     vertex_binding_descriptions: Vec<vk::VertexInputBindingDescription>,
     vertex_attribute_descriptions: Vec<vk::VertexInputAttributeDescription>,
+    topology: vk::PrimitiveTopology,
 }
 
 impl InputAssemblerStage {
-    pub fn new() -> Self {
-        let vertex_binding_descriptions = (&[] as &[vk::VertexInputBindingDescription]).to_vec();
-        let vertex_attribute_descriptions = (&[] as &[vk::VertexInputAttributeDescription]).to_vec();
+    /// `vertex_binding_descriptions` describe the vertex buffers (stride, per vertex or per
+    /// instance), and `vertex_attribute_descriptions` the shader inputs read from them.
+    pub fn new(
+        topology: vk::PrimitiveTopology,
+        vertex_binding_descriptions: Vec<vk::VertexInputBindingDescription>,
+        vertex_attribute_descriptions: Vec<vk::VertexInputAttributeDescription>,
+    ) -> Self {
         Self {
             vertex_binding_descriptions,
             vertex_attribute_descriptions,
+            topology,
         }
     }
 
@@ -25,7 +31,7 @@ impl InputAssemblerStage {
 
     pub fn build_input_assembly_state(&self) -> vk::PipelineInputAssemblyStateCreateInfo {
         vk::PipelineInputAssemblyStateCreateInfo::builder()
-            .topology(vk::PrimitiveTopology::POINT_LIST)
+            .topology(self.topology)
             .primitive_restart_enable(false)
             .build()
     }
@@ -1,12 +1,153 @@
 use log::debug;
 use vulkanalia::vk;
 use vulkanalia::vk::HasBuilder;
+use crate::gapi::vulkan::pipeline::render_pass::MyRenderPass;
 
-pub struct PerFragmentTestsStage {}
+/// Stencil test configuration for the [`PerFragmentTestsStage`].
+///
+/// The stencil buffer stores an integer per pixel that fragments can be tested against and write
+/// to. For example, to draw an outline around a selected voxel, we first draw the voxel writing a
+/// reference value ([`StencilConfig::write`]), and then draw a slightly bigger version of it only
+/// where the stencil doesn't hold that value ([`StencilConfig::outline`]).
+#[derive(Clone, Copy, Debug)]
+pub struct StencilConfig {
+    /// Stencil operations for front-facing primitives.
+    pub front: vk::StencilOpState,
+    /// Stencil operations for back-facing primitives.
+    pub back: vk::StencilOpState,
+}
+
+impl StencilConfig {
+    /// Always passes the stencil test and writes `reference` to the stencil buffer.
+    pub fn write(reference: u32) -> Self {
+        let op = vk::StencilOpState::builder()
+            .fail_op(vk::StencilOp::KEEP)
+            .pass_op(vk::StencilOp::REPLACE)
+            .depth_fail_op(vk::StencilOp::KEEP)
+            .compare_op(vk::CompareOp::ALWAYS)
+            .compare_mask(0xFF)
+            .write_mask(0xFF)
+            .reference(reference)
+            .build();
+        Self { front: op, back: op }
+    }
+
+    /// Only passes fragments where the stencil buffer doesn't hold `reference`, without writing.
+    pub fn outline(reference: u32) -> Self {
+        let op = vk::StencilOpState::builder()
+            .fail_op(vk::StencilOp::KEEP)
+            .pass_op(vk::StencilOp::KEEP)
+            .depth_fail_op(vk::StencilOp::KEEP)
+            .compare_op(vk::CompareOp::NOT_EQUAL)
+            .compare_mask(0xFF)
+            .write_mask(0x00)
+            .reference(reference)
+            .build();
+        Self { front: op, back: op }
+    }
+}
+
+/// How a pipeline uses the depth test.
+///
+/// With a depth prepass, the scene is drawn twice: first only into the depth buffer, which is
+/// cheap as no fragment is shaded, then with the full fragment shader, where only the closest
+/// fragment of each pixel passes the test. The second draw must produce the exact same depths,
+/// so both pipelines draw the same geometry with the same vertex shader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthPass {
+    /// No depth test.
+    #[default]
+    Disabled,
+    /// The depth-only draw of the prepass: keeps the closest fragments and writes their depth.
+    Prepass,
+    /// The main draw after a prepass: only passes the fragments whose depth equals the one the
+    /// prepass wrote, without writing it again.
+    AfterPrepass,
+}
+
+impl DepthPass {
+    /// Index of the subpass the pipeline draws in, the prepass has its own subpass before the
+    /// main one, see [`MyRenderPass::new`]. Pipelines without depth test draw in the main one.
+    pub fn subpass(self, render_pass: &MyRenderPass) -> u32 {
+        match self {
+            DepthPass::Prepass => 0,
+            DepthPass::Disabled | DepthPass::AfterPrepass => render_pass.main_subpass(),
+        }
+    }
+}
+
+/// Which end of the depth range is near the camera.
+///
+/// # Reverse-Z
+/// A perspective projection maps depth hyperbolically, so most depth values crowd near the
+/// camera. Floats are also densest near 0, so mapping the near plane to 1 and the far plane to 0
+/// spreads their precision over the distance instead of wasting it twice in the same spot,
+/// which removes most of the z-fighting on distant voxels. This only pays off with a float
+/// depth buffer, i.e. `D32_SFLOAT`
+/// ([`DepthFormatPreference::HighPrecision`](crate::gapi::vulkan::memory::depth_buffer::DepthFormatPreference)),
+/// with a UNORM one it is as precise as the standard convention.
+///
+/// The projection, the depth clear value and the depth compare op must all agree on the
+/// convention, see [`DepthConvention::apply_to_projection`], [`DepthConvention::clear_depth`] and
+/// [`DepthConvention::closer_compare_op`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthConvention {
+    /// Near at 0, far at 1.
+    #[default]
+    Standard,
+    /// Near at 1, far at 0.
+    ReverseZ,
+}
+
+impl DepthConvention {
+    /// The depth the depth buffer is cleared to, the farthest one.
+    pub fn clear_depth(self) -> f32 {
+        match self {
+            DepthConvention::Standard => 1.0,
+            DepthConvention::ReverseZ => 0.0,
+        }
+    }
+
+    /// The compare op passing the fragments closer than the stored depth.
+    pub fn closer_compare_op(self) -> vk::CompareOp {
+        match self {
+            DepthConvention::Standard => vk::CompareOp::LESS,
+            DepthConvention::ReverseZ => vk::CompareOp::GREATER,
+        }
+    }
+
+    /// Adapts a column-major projection matrix with Vulkan's depth from 0 (near) to 1 (far) to
+    /// the convention, by replacing the depth `z` by `w - z` for reverse-Z.
+    pub fn apply_to_projection(self, projection: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+        match self {
+            DepthConvention::Standard => projection,
+            DepthConvention::ReverseZ => projection.map(|mut column| {
+                column[2] = column[3] - column[2];
+                column
+            }),
+        }
+    }
+}
+
+pub struct PerFragmentTestsStage {
+    /// The stencil test is disabled if `None`.
+    stencil: Option<StencilConfig>,
+    depth_pass: DepthPass,
+    depth_convention: DepthConvention,
+}
 
 impl PerFragmentTestsStage {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(stencil: Option<StencilConfig>, depth_pass: DepthPass, depth_convention: DepthConvention) -> Self {
+        Self {
+            stencil,
+            depth_pass,
+            depth_convention,
+        }
+    }
+
+    /// Whether the depth attachment needs a stencil aspect for this stage.
+    pub fn requires_stencil(&self) -> bool {
+        self.stencil.is_some()
     }
 
     pub fn build_depth_stencil_state(&self) -> vk::PipelineDepthStencilStateCreateInfo {
@@ -14,11 +155,31 @@ impl PerFragmentTestsStage {
         // buffer to determine if they should be discarded or not. This is essential for proper
         // rendering of 3D scenes, as it ensures that closer objects are rendered in front of
         // farther ones.
-        // It is disabled for now.
-        let depth_test_enable = false;
+        // It is only enabled with a depth prepass for now.
+        let depth_test_enable = self.depth_pass != DepthPass::Disabled;
+        // The prepass writes the closest depths, the main pass only compares against them.
+        let (depth_write_enable, depth_compare_op) = match self.depth_pass {
+            DepthPass::Disabled => (false, vk::CompareOp::ALWAYS),
+            DepthPass::Prepass => (true, self.depth_convention.closer_compare_op()),
+            DepthPass::AfterPrepass => (false, vk::CompareOp::EQUAL),
+        };
+
+        // The stencil test compares the fragment against the stencil buffer with the `front` and
+        // `back` operations, depending on the facing of the primitive.
+        // It is disabled unless configured.
+        let stencil_test_enable = self.stencil.is_some();
+        let (front, back) = self
+            .stencil
+            .map(|stencil| (stencil.front, stencil.back))
+            .unwrap_or_default();
 
         let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
             .depth_test_enable(depth_test_enable)
+            .depth_write_enable(depth_write_enable)
+            .depth_compare_op(depth_compare_op)
+            .stencil_test_enable(stencil_test_enable)
+            .front(front)
+            .back(back)
             .build();
 
         debug!(
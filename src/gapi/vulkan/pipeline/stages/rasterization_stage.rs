@@ -2,12 +2,54 @@ use log::debug;
 use vulkanalia::vk;
 use vulkanalia::vk::HasBuilder;
 
+/// Offsets the depth of the fragments, to draw coplanar geometry over other geometry without
+/// z-fighting, e.g. the wireframe edges over solid voxels.
+///
+/// The bias added to each depth is `constant_factor * r + slope_factor * max_slope`, where `r`
+/// is the smallest resolvable depth difference and `max_slope` how steep the polygon is relative
+/// to the screen. With a standard depth range (0 near, 1 far), negative factors push the
+/// fragments toward the camera. Values around -1.0 for both factors are usually enough for edges.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthBias {
+    pub constant_factor: f32,
+    /// Maximum (or minimum, if negative) bias, 0.0 for no clamp.
+    /// Any other value requires the `depth_bias_clamp` GPU feature.
+    pub clamp: f32,
+    pub slope_factor: f32,
+}
+
+impl DepthBias {
+    /// Pulls edges slightly toward the camera, to draw them over the faces they belong to.
+    pub const EDGE_OVERLAY: Self = Self {
+        constant_factor: -1.0,
+        clamp: 0.0,
+        slope_factor: -1.0,
+    };
+}
+
 pub struct RasterizationStage {
+    polygon_mode: vk::PolygonMode,
+    line_width: f32,
+    depth_bias: Option<DepthBias>,
+    samples: vk::SampleCountFlags,
+    min_sample_shading: Option<f32>,
 }
 
 impl RasterizationStage {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(
+        polygon_mode: vk::PolygonMode,
+        line_width: f32,
+        depth_bias: Option<DepthBias>,
+        samples: vk::SampleCountFlags,
+        min_sample_shading: Option<f32>,
+    ) -> Self {
+        Self {
+            polygon_mode,
+            line_width,
+            depth_bias,
+            samples,
+            min_sample_shading,
+        }
     }
 
     pub fn build_rasterization_state(&self) -> vk::PipelineRasterizationStateCreateInfo {
@@ -22,14 +64,14 @@ impl RasterizationStage {
         // vk::PolygonMode::FILL – fill the area of the polygon with fragments
         // vk::PolygonMode::LINE – polygon edges are drawn as lines
         // vk::PolygonMode::POINT – polygon vertices are drawn as points
-        // We use fill mode to render our voxels as solid cubes, but line or point mode could
-        // be useful for debugging purposes.
-        let polygon_mode = vk::PolygonMode::FILL;
+        // We use fill mode to render our voxels as solid cubes, but line or point mode are
+        // useful for debugging purposes. Any mode other than fill requires enabling a GPU feature.
+        let polygon_mode = self.polygon_mode;
         // line_width describes the thickness of lines in terms of number of fragments.
         // The maximum line width that is supported depends on the hardware and any line thicker
         // than 1.0 requires you to enable the wide_lines GPU feature.
-        // This only affects the rendering if polygon_mode is set to LINE.
-        let line_width = 1.0;
+        // This only affects the rendering of line primitives, or if polygon_mode is set to LINE.
+        let line_width = self.line_width;
         // The cull_mode variable determines the type of face culling to use.
         // You can disable culling, cull the front faces, cull the back faces or both.
         let cull_mode = vk::CullModeFlags::BACK;
@@ -37,9 +79,14 @@ impl RasterizationStage {
         // front-facing and can be clockwise or counterclockwise.
         let front_face = vk::FrontFace::CLOCKWISE;
         // The rasterizer can alter the depth values by adding a constant value or biasing them
-        // based on a fragment's slope. This is used for shadow mapping to prevent shadow acne.
-        // For now, it is disabled.
-        let depth_bias_enable = false;
+        // based on a fragment's slope. This is used for shadow mapping to prevent shadow acne,
+        // or to draw edges over faces. It is disabled unless configured.
+        let depth_bias_enable = self.depth_bias.is_some();
+        let depth_bias = self.depth_bias.unwrap_or(DepthBias {
+            constant_factor: 0.0,
+            clamp: 0.0,
+            slope_factor: 0.0,
+        });
 
         // Rasterization
         // The rasterizer takes the geometry that is shaped by the vertices from the vertex shader
@@ -52,6 +99,9 @@ impl RasterizationStage {
             .cull_mode(cull_mode)
             .front_face(front_face)
             .depth_bias_enable(depth_bias_enable)
+            .depth_bias_constant_factor(depth_bias.constant_factor)
+            .depth_bias_clamp(depth_bias.clamp)
+            .depth_bias_slope_factor(depth_bias.slope_factor)
             .build();
 
         debug!(
@@ -62,8 +112,14 @@ impl RasterizationStage {
         rasterization_state
     }
     pub fn build_multisample_state(&self) -> vk::PipelineMultisampleStateCreateInfo {
-        let sample_shading_enable = false;
-        let rasterization_samples = vk::SampleCountFlags::_1;
+        // Sample shading runs the fragment shader for (at least) a fraction of the samples of
+        // each pixel, instead of once per pixel. Plain MSAA only smooths the edges of the
+        // polygons, this also smooths aliasing inside them (e.g. high frequency textures), at the
+        // cost of up to `samples` times more fragment shader invocations. 1.0 shades every sample.
+        // It requires the `sample_rate_shading` GPU feature.
+        let sample_shading_enable = self.min_sample_shading.is_some();
+        let min_sample_shading = self.min_sample_shading.unwrap_or(0.0);
+        let rasterization_samples = self.samples;
         // Multisampling
         // The vk::PipelineMultisampleStateCreateInfo struct configures multisampling, which is one
         // of the ways to perform anti-aliasing. It works by combining the fragment shader results
@@ -71,10 +127,10 @@ impl RasterizationStage {
         // which is also where the most noticeable aliasing artifacts occur. Because it doesn't need
         // to run the fragment shader multiple times if only one polygon maps to a pixel, it is
         // significantly less expensive than simply rendering to a higher resolution and then
-        // downscaling. Enabling it requires enabling a GPU feature.
-        // For now it is disabled.
+        // downscaling. The sample count must match the one of the render pass attachments.
         let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
             .sample_shading_enable(sample_shading_enable)
+            .min_sample_shading(min_sample_shading)
             .rasterization_samples(rasterization_samples)
             .build();
         debug!(
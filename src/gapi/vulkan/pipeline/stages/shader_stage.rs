@@ -2,6 +2,7 @@ use log::debug;
 use vulkanalia::vk;
 use vulkanalia::vk::{HasBuilder, ShaderModule, ShaderStageFlags};
 use crate::gapi::vulkan::pipeline::shaders::Shader;
+use crate::gapi::vulkan::pipeline::specialization::SpecializationConstants;
 
 #[derive(Debug)]
 struct ShaderStageConfig {
@@ -11,24 +12,44 @@ struct ShaderStageConfig {
 }
 pub struct ShaderStage {
     stage: vk::PipelineShaderStageCreateInfo,
+    /// The constants and info `stage` points to. The info is boxed so its address doesn't change
+    /// when the stage is moved, the constants' data already lives in the heap.
+    _specialization: Option<(SpecializationConstants, Box<vk::SpecializationInfo>)>,
 }
 
 impl ShaderStage {
-    pub fn new(shader: &Shader, stage_flag: ShaderStageFlags) -> Self {
+    pub fn new(
+        shader: &Shader,
+        stage_flag: ShaderStageFlags,
+        specialization: Option<&SpecializationConstants>,
+    ) -> Self {
         let stage = stage_flag;
         let shader = shader.get_vk();
         let name = "main\0".as_bytes();
 
-        let stage = vk::PipelineShaderStageCreateInfo::builder()
+        let mut builder = vk::PipelineShaderStageCreateInfo::builder()
             .stage(stage)
             .module(shader)
-            .name(name)
-            .build();
+            .name(name);
+
+        // Constants that are not specialized keep the default value declared in the shader.
+        let specialization = specialization
+            .filter(|constants| !constants.is_empty())
+            .map(|constants| {
+                let constants = constants.clone();
+                let info = Box::new(constants.build_info());
+                (constants, info)
+            });
+        if let Some((_, info)) = &specialization {
+            builder = builder.specialization_info(info.as_ref());
+        }
+        let stage = builder.build();
 
         debug!("Creating PipelineShaderStageCreateInfo struct: {stage:#?}");
 
         Self {
             stage: stage,
+            _specialization: specialization,
         }
     }
 
@@ -1,8 +1,60 @@
 use log::debug;
 use vulkanalia::vk;
 use vulkanalia::vk::HasBuilder;
+use crate::gapi::vulkan::extent::full_rect;
 use crate::gapi::vulkan::memory::swapchain::Swapchain;
 
+/// Where the Y axis is flipped from the usual Y up convention to Vulkan's clip space.
+///
+/// Vulkan's clip space has Y pointing down, unlike OpenGL's, so a projection written for Y up
+/// renders upside down. Either the projection or the viewport can flip it back, but doing both
+/// flips twice, so the app only ever does it in the one place configured here.
+///
+/// The built-in shaders don't flip anything: they write `frame.view_projection * position`
+/// (or the positions as is) to `gl_Position`, assuming Vulkan's Y down clip space.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViewportYFlip {
+    /// The projection matrix maps Y up to Y down, see [`ViewportYFlip::apply_to_projection`].
+    /// The viewports are left as is.
+    #[default]
+    ProjectionMatrix,
+    /// The viewports have a negative height, starting from their bottom edge, which flips Y
+    /// during the viewport transform, so the projection can stay Y up like in OpenGL. Core since
+    /// Vulkan 1.1 (`VK_KHR_maintenance1`).
+    ///
+    /// A projection flipped by [`ViewportYFlip::apply_to_projection`] and an unflipped one with
+    /// this give the same image, with the same winding. Only the positions written straight in
+    /// clip space, like the ones of the built-in triangle, end up upside down.
+    NegativeHeightViewport,
+}
+
+impl ViewportYFlip {
+    /// Flips the Y axis of a column-major projection matrix with Y up, by negating its clip
+    /// space `y` with [`ViewportYFlip::ProjectionMatrix`].
+    pub fn apply_to_projection(self, projection: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+        match self {
+            ViewportYFlip::ProjectionMatrix => projection.map(|mut column| {
+                column[1] = -column[1];
+                column
+            }),
+            ViewportYFlip::NegativeHeightViewport => projection,
+        }
+    }
+
+    /// Flips `viewport` with [`ViewportYFlip::NegativeHeightViewport`], covering the same
+    /// pixels from its bottom edge upward.
+    pub fn apply_to_viewport(self, viewport: vk::Viewport) -> vk::Viewport {
+        match self {
+            ViewportYFlip::ProjectionMatrix => viewport,
+            ViewportYFlip::NegativeHeightViewport => vk::Viewport {
+                y: viewport.y + viewport.height,
+                height: -viewport.height,
+                ..viewport
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Viewport {
     /// Viewport must be kept alive as long as the pipeline that uses it is alive, because the
@@ -14,20 +66,27 @@ pub struct Viewport {
 }
 
 impl Viewport {
-    pub fn new(swapchain: &Swapchain) -> Self {
+    pub fn new(swapchain: &Swapchain, y_flip: ViewportYFlip) -> Self {
+        Self::from_extent(swapchain.extent, y_flip)
+    }
+
+    /// Covers the whole `extent`, e.g. of a [`RenderTarget`](crate::gapi::vulkan::memory::render_target::RenderTarget)
+    /// instead of the swapchain.
+    pub fn from_extent(extent: vk::Extent2D, y_flip: ViewportYFlip) -> Self {
         // Viewport
         // The viewport is the region of the framebuffer that the output will be rendered to.
         // This will almost always be (0, 0) to (width, height)
         let viewport = vk::Viewport::builder()
             .x(0.0)
             .y(0.0)
-            .width(swapchain.extent.width as f32)
-            .height(swapchain.extent.height as f32)
+            .width(extent.width as f32)
+            .height(extent.height as f32)
             // The min_depth and max_depth values specify the range of depth values to use for the
             // framebuffer.
             .min_depth(0.0)
             .max_depth(1.0)
             .build();
+        let viewport = y_flip.apply_to_viewport(viewport);
         debug!("Created Viewport struct: \n{viewport:#?}");
 
         // Scissor
@@ -35,10 +94,7 @@ impl Viewport {
         // rendering operations. Basically acts like a filter for pixels.
         // Could be used for example to render only to a specific part of the screen, or to
         // implement a split-screen effect.
-        let scissor = vk::Rect2D::builder()
-            .offset(vk::Offset2D { x: 0, y: 0 })
-            .extent(swapchain.extent)
-            .build();
+        let scissor = full_rect(extent);
         debug!("Created Scissor (Rect2D) struct: \n{scissor:#?}");
 
         Self {
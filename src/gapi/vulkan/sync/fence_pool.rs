@@ -0,0 +1,78 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use anyhow::Context;
+use log::trace;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// Reuses fences across single-time submissions, instead of creating and destroying one per
+/// submission, which adds up when streaming thousands of chunks.
+///
+/// # Reclamation
+/// Released fences may still be pending on the GPU, so they are not reset right away. Instead,
+/// [`FencePool::acquire`] checks the released fences without blocking, resets the signaled ones
+/// and hands one of them out, and only creates a new fence if none is signaled yet. The pool
+/// therefore grows to the number of submissions in flight at once, and never shrinks until it
+/// is destroyed.
+#[derive(Debug, Default)]
+pub struct FencePool {
+    /// Reset fences, ready to be handed out.
+    free: Vec<vk::Fence>,
+    /// Released fences that may not be signaled yet.
+    pending: Vec<vk::Fence>,
+}
+
+impl FencePool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hands out an unsignaled fence, to pass to a queue submission.
+    pub fn acquire(&mut self, device: &LogicalDevice) -> anyhow::Result<vk::Fence> {
+        self.reclaim(device)?;
+        if let Some(fence) = self.free.pop() {
+            return Ok(fence);
+        }
+        trace!("No free fence in the pool, creating one.");
+        device
+            .create_fence(&vk::FenceCreateInfo::builder())
+            .with_context(|| "Failed to create a pooled fence")
+    }
+
+    /// Hands back a fence that was submitted, it is reused once signaled.
+    pub fn release(&mut self, fence: vk::Fence) {
+        self.pending.push(fence);
+    }
+
+    /// Hands back a fence that was never submitted, e.g. because the submission failed. Such a
+    /// fence would never be signaled, so it is reusable right away.
+    pub fn release_unused(&mut self, fence: vk::Fence) {
+        self.free.push(fence);
+    }
+
+    /// Resets the signaled pending fences and makes them free.
+    fn reclaim(&mut self, device: &LogicalDevice) -> anyhow::Result<()> {
+        let mut signaled = Vec::new();
+        let mut index = 0;
+        while index < self.pending.len() {
+            if device.get_fence_status(self.pending[index])? {
+                signaled.push(self.pending.swap_remove(index));
+            } else {
+                index += 1;
+            }
+        }
+        if !signaled.is_empty() {
+            device.reset_fences(&signaled)?;
+            self.free.extend(signaled);
+        }
+        Ok(())
+    }
+
+    /// Destroys every fence of the pool, none of them can be pending anymore, e.g. after waiting
+    /// for the device to be idle.
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.free
+            .iter()
+            .chain(&self.pending)
+            .for_each(|&fence| device.destroy_fence(fence));
+    }
+}
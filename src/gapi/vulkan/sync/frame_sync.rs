@@ -0,0 +1,257 @@
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use anyhow::Context;
+use log::debug;
+use std::time::Duration;
+use vulkanalia::vk;
+use vulkanalia::vk::{Handle, HasBuilder};
+
+/// The synchronization objects needed to render up to `frames_in_flight` frames at once.
+///
+/// The CPU records frame `n + 1` while the GPU is still rendering frame `n`, so every resource
+/// written by the CPU each frame (command buffers, uniform buffers, descriptor sets...) must be
+/// duplicated per frame in flight, and only touched once the frame's fence is signaled.
+///
+/// # Semaphore indexing
+/// A binary semaphore can only be signaled again once the wait on its previous signal has
+/// completed, and the only way to know that is a fence covering the wait:
+/// - The image available semaphores are signaled by the acquire and waited on by the submit of
+///   the same frame, which the in-flight fence of the frame covers. So they are indexed by
+///   **frame in flight**: once the fence of the frame is signaled, its semaphore is free again.
+/// - The render finished semaphores are signaled by the submit and waited on by the present,
+///   which no fence covers. The present of an image is only known to be done when that same
+///   image is acquired again, so they are indexed by **swapchain image**. Indexing them by frame
+///   in flight reuses a semaphore the presentation engine may still wait on whenever there are
+///   more swapchain images than frames in flight, which the validation layer reports.
+pub struct FrameSync {
+    /// Signaled when the swapchain image acquired for the frame is ready to be rendered to, one
+    /// per frame in flight.
+    image_available: Vec<vk::Semaphore>,
+    /// Signaled when the frame rendered into the image can be presented, one per swapchain
+    /// image.
+    render_finished: Vec<vk::Semaphore>,
+    /// Signaled when the GPU finished executing the frame's command buffer.
+    in_flight_fences: Vec<vk::Fence>,
+    /// The fence of the frame currently using each swapchain image, or null if none.
+    /// There may be more swapchain images than frames in flight, or the images may be acquired
+    /// out of order, so a single image can be used by different frames.
+    images_in_flight: Vec<vk::Fence>,
+}
+
+impl FrameSync {
+    pub fn new(
+        device: &LogicalDevice,
+        frames_in_flight: usize,
+        swapchain_image_count: usize,
+    ) -> anyhow::Result<Self> {
+        let semaphore_info = vk::SemaphoreCreateInfo::builder();
+        // The fences start signaled, otherwise the first wait on each of them would never return.
+        let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
+        debug!("Created FenceCreateInfo struct: {:#?}", fence_info);
+
+        let mut image_available = Vec::with_capacity(frames_in_flight);
+        let mut in_flight_fences = Vec::with_capacity(frames_in_flight);
+        for _ in 0..frames_in_flight {
+            image_available.push(
+                device
+                    .create_semaphore(&semaphore_info)
+                    .with_context(|| "Failed to create image available semaphore")?,
+            );
+            in_flight_fences.push(
+                device
+                    .create_fence(&fence_info)
+                    .with_context(|| "Failed to create in flight fence")?,
+            );
+        }
+        let render_finished = Self::create_render_finished(device, swapchain_image_count)?;
+        debug!(
+            "Created {} image available and {} render finished semaphores.",
+            image_available.len(),
+            render_finished.len()
+        );
+
+        Ok(Self {
+            image_available,
+            render_finished,
+            in_flight_fences,
+            images_in_flight: vec![vk::Fence::null(); swapchain_image_count],
+        })
+    }
+
+    fn create_render_finished(
+        device: &LogicalDevice,
+        swapchain_image_count: usize,
+    ) -> anyhow::Result<Vec<vk::Semaphore>> {
+        let semaphore_info = vk::SemaphoreCreateInfo::builder();
+        (0..swapchain_image_count)
+            .map(|_| {
+                device
+                    .create_semaphore(&semaphore_info)
+                    .with_context(|| "Failed to create render finished semaphore")
+            })
+            .collect()
+    }
+
+    pub fn frames_in_flight(&self) -> usize {
+        self.in_flight_fences.len()
+    }
+
+    /// The semaphore the acquire of the frame in flight `frame` signals, see the
+    /// [indexing rule](Self#semaphore-indexing).
+    pub fn image_available(&self, frame: usize) -> vk::Semaphore {
+        self.image_available[frame]
+    }
+
+    /// The semaphore the present of the swapchain image `image_index` waits on, see the
+    /// [indexing rule](Self#semaphore-indexing).
+    pub fn render_finished(&self, image_index: usize) -> vk::Semaphore {
+        self.render_finished[image_index]
+    }
+
+    pub fn in_flight_fence(&self, frame: usize) -> vk::Fence {
+        self.in_flight_fences[frame]
+    }
+
+    /// Replaces the fence of the frame in flight `frame` with a signaled one, after it was reset
+    /// for a submit that failed. A failed submit doesn't signal the fence, so the next wait on it
+    /// would never return, and a fence can't be signaled from the host.
+    pub fn replace_in_flight_fence(&mut self, device: &LogicalDevice, frame: usize) -> anyhow::Result<()> {
+        let fence_info = vk::FenceCreateInfo::builder().flags(vk::FenceCreateFlags::SIGNALED);
+        let fence = device
+            .create_fence(&fence_info)
+            .with_context(|| "Failed to create in flight fence")?;
+        let old_fence = std::mem::replace(&mut self.in_flight_fences[frame], fence);
+        // The image claimed by the failed frame isn't used by anything.
+        self.images_in_flight
+            .iter_mut()
+            .filter(|image_fence| **image_fence == old_fence)
+            .for_each(|image_fence| *image_fence = vk::Fence::null());
+        device.destroy_fence(old_fence);
+        Ok(())
+    }
+
+    /// Waits until the previous frame using the swapchain image `image_index` is done, and marks
+    /// the image as used by `frame`.
+    pub fn claim_image(
+        &mut self,
+        device: &LogicalDevice,
+        image_index: usize,
+        frame: usize,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
+        let image_fence = self.images_in_flight[image_index];
+        if !image_fence.is_null() {
+            device
+                .wait_for_fences(&[image_fence], true, timeout)
+                .with_context(|| format!("Failed to wait for swapchain image {image_index}"))?;
+        }
+        self.images_in_flight[image_index] = self.in_flight_fences[frame];
+        Ok(())
+    }
+
+    /// Forgets which frames use which swapchain images, and recreates the render finished
+    /// semaphores, after recreating the swapchain with `swapchain_image_count` images.
+    /// The device must be idle, so no frame is still using the old images nor semaphores.
+    pub fn reset_images(&mut self, device: &LogicalDevice, swapchain_image_count: usize) -> anyhow::Result<()> {
+        self.render_finished
+            .drain(..)
+            .for_each(|semaphore| device.destroy_semaphore(semaphore));
+        self.render_finished = Self::create_render_finished(device, swapchain_image_count)?;
+        self.images_in_flight = vec![vk::Fence::null(); swapchain_image_count];
+        Ok(())
+    }
+
+    pub fn destroy(&self, device: &LogicalDevice) {
+        self.image_available
+            .iter()
+            .chain(&self.render_finished)
+            .for_each(|semaphore| device.destroy_semaphore(*semaphore));
+        self.in_flight_fences
+            .iter()
+            .for_each(|fence| device.destroy_fence(*fence));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gapi::vulkan::test_support::with_device;
+
+    /// Runs the submissions of a frame per swapchain image index of `acquired_images`, like
+    /// `App::render`. Without a swapchain, the acquire and the present are empty submits
+    /// signaling and waiting on the same semaphores.
+    fn run_frames(device: &LogicalDevice, sync: &mut FrameSync, acquired_images: &[usize]) -> anyhow::Result<()> {
+        let queue = device.get_queues().graphics[0];
+        let timeout = Duration::from_secs(5);
+        for (n, &image_index) in acquired_images.iter().enumerate() {
+            let frame = n % sync.frames_in_flight();
+            let fence = sync.in_flight_fence(frame);
+            device.wait_for_fences(&[fence], true, timeout)?;
+
+            let image_available = &[sync.image_available(frame)];
+            let acquire = vk::SubmitInfo::builder().signal_semaphores(image_available);
+            device.queue_submit(queue, &[acquire], vk::Fence::null())?;
+            sync.claim_image(device, image_index, frame, timeout)?;
+
+            let render_finished = &[sync.render_finished(image_index)];
+            let wait_stages = &[vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+            let render = vk::SubmitInfo::builder()
+                .wait_semaphores(image_available)
+                .wait_dst_stage_mask(wait_stages)
+                .signal_semaphores(render_finished);
+            device.reset_fences(&[fence])?;
+            device.queue_submit(queue, &[render], fence)?;
+
+            let wait_stages = &[vk::PipelineStageFlags::ALL_COMMANDS];
+            let present = vk::SubmitInfo::builder()
+                .wait_semaphores(render_finished)
+                .wait_dst_stage_mask(wait_stages);
+            device.queue_submit(queue, &[present], vk::Fence::null())?;
+        }
+        device.device_wait_idle()
+    }
+
+    /// More swapchain images than frames in flight, acquired out of order.
+    #[test]
+    #[ignore = "needs a Vulkan device and a display"]
+    fn frames_never_reuse_a_pending_semaphore() -> anyhow::Result<()> {
+        with_device(|device| {
+            let (frames_in_flight, image_count) = (2, 3);
+            let mut sync = FrameSync::new(device, frames_in_flight, image_count)?;
+            let result = (|| -> anyhow::Result<()> {
+                assert_eq!(sync.frames_in_flight(), frames_in_flight);
+                assert_eq!(sync.render_finished.len(), image_count);
+                run_frames(device, &mut sync, &[0, 1, 2, 2, 0, 1, 1, 0, 2, 0, 0, 1])?;
+
+                // A swapchain recreated with more images gets a render finished semaphore for
+                // each.
+                sync.reset_images(device, image_count + 1)?;
+                assert_eq!(sync.render_finished.len(), image_count + 1);
+                assert!(sync.images_in_flight.iter().all(|fence| fence.is_null()));
+                run_frames(device, &mut sync, &[3, 2, 1, 0, 3, 3])
+            })();
+            sync.destroy(device);
+            result
+        })
+    }
+
+    /// A frame whose submit failed after its fence was reset doesn't block the next ones.
+    #[test]
+    #[ignore = "needs a Vulkan device and a display"]
+    fn a_replaced_fence_is_signaled_and_releases_its_image() -> anyhow::Result<()> {
+        with_device(|device| {
+            let mut sync = FrameSync::new(device, 2, 3)?;
+            let result = (|| -> anyhow::Result<()> {
+                let timeout = Duration::from_secs(5);
+                sync.claim_image(device, 1, 0, timeout)?;
+                device.reset_fences(&[sync.in_flight_fence(0)])?;
+
+                sync.replace_in_flight_fence(device, 0)?;
+                assert!(sync.images_in_flight[1].is_null());
+                device.wait_for_fences(&[sync.in_flight_fence(0)], true, timeout)?;
+                run_frames(device, &mut sync, &[1, 0, 2])
+            })();
+            sync.destroy(device);
+            result
+        })
+    }
+}
@@ -0,0 +1,4 @@
+pub mod fence_pool;
+pub mod frame_sync;
+pub mod pass_scheduler;
+pub mod render_result;
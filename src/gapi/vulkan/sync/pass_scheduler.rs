@@ -0,0 +1,283 @@
+use crate::gapi::vulkan::commands::command_buffers::CommandBuffer;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use log::trace;
+use std::collections::HashMap;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+
+/// The accesses that write to memory, the others only read.
+const WRITE_ACCESS: vk::AccessFlags = vk::AccessFlags::from_bits_truncate(
+    vk::AccessFlags::SHADER_WRITE.bits()
+        | vk::AccessFlags::COLOR_ATTACHMENT_WRITE.bits()
+        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE.bits()
+        | vk::AccessFlags::TRANSFER_WRITE.bits()
+        | vk::AccessFlags::HOST_WRITE.bits()
+        | vk::AccessFlags::MEMORY_WRITE.bits(),
+);
+
+/// A resource the [`Scheduler`] tracks the state of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceHandle {
+    /// The whole buffer.
+    Buffer(vk::Buffer),
+    /// Every mip level and array layer of the `aspect_mask` of the image.
+    Image {
+        image: vk::Image,
+        aspect_mask: vk::ImageAspectFlags,
+    },
+}
+
+/// How a pass uses a resource.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PassResource {
+    pub handle: ResourceHandle,
+    /// Every access of the pass to the resource, e.g. `SHADER_READ`, or
+    /// `COLOR_ATTACHMENT_READ | COLOR_ATTACHMENT_WRITE` for blending.
+    pub access: vk::AccessFlags,
+    /// Every stage accessing the resource.
+    pub stage: vk::PipelineStageFlags,
+    /// The layout the image must be in during the pass, `UNDEFINED` for buffers.
+    pub layout: vk::ImageLayout,
+}
+
+impl PassResource {
+    pub fn buffer(buffer: vk::Buffer, access: vk::AccessFlags, stage: vk::PipelineStageFlags) -> Self {
+        Self {
+            handle: ResourceHandle::Buffer(buffer),
+            access,
+            stage,
+            layout: vk::ImageLayout::UNDEFINED,
+        }
+    }
+
+    pub fn image(
+        image: vk::Image,
+        aspect_mask: vk::ImageAspectFlags,
+        layout: vk::ImageLayout,
+        access: vk::AccessFlags,
+        stage: vk::PipelineStageFlags,
+    ) -> Self {
+        Self {
+            handle: ResourceHandle::Image { image, aspect_mask },
+            access,
+            stage,
+            layout,
+        }
+    }
+
+    fn writes(&self) -> bool {
+        self.access.intersects(WRITE_ACCESS)
+    }
+}
+
+/// Records the commands of a [`Pass`].
+pub type PassRecorder<'a> = Box<dyn Fn(&CommandBuffer, &LogicalDevice) + 'a>;
+
+/// A step of the frame, e.g. a shadow map or a post-processing pass, with every resource it
+/// reads or writes.
+pub struct Pass<'a> {
+    /// To tell the passes apart in the logs.
+    pub name: &'a str,
+    pub resources: Vec<PassResource>,
+    pub record: PassRecorder<'a>,
+}
+
+/// What the [`Scheduler`] knows of a resource since its last barrier.
+#[derive(Clone, Copy, Debug)]
+struct ResourceState {
+    layout: vk::ImageLayout,
+    /// The stages and accesses of the last write, which the next accesses must wait for.
+    write_stage: vk::PipelineStageFlags,
+    write_access: vk::AccessFlags,
+    /// The stages and accesses the last write is already visible to.
+    visible_stage: vk::PipelineStageFlags,
+    visible_access: vk::AccessFlags,
+    /// The stages that read the resource since the last write, which the next write must wait
+    /// for.
+    read_stage: vk::PipelineStageFlags,
+}
+
+impl ResourceState {
+    /// Before the first pass, the contents of images are discarded.
+    const UNUSED: Self = Self {
+        layout: vk::ImageLayout::UNDEFINED,
+        write_stage: vk::PipelineStageFlags::empty(),
+        write_access: vk::AccessFlags::empty(),
+        visible_stage: vk::PipelineStageFlags::empty(),
+        visible_access: vk::AccessFlags::empty(),
+        read_stage: vk::PipelineStageFlags::empty(),
+    };
+
+    /// The stages and accesses `usage` must wait for, `None` if it doesn't need a barrier.
+    fn hazard(&self, usage: &PassResource) -> Option<(vk::PipelineStageFlags, vk::AccessFlags)> {
+        let is_image = matches!(usage.handle, ResourceHandle::Image { .. });
+        if is_image && usage.layout != self.layout {
+            // The transition writes the whole image, after every previous access.
+            return Some((self.write_stage | self.read_stage, self.write_access));
+        }
+        if usage.writes() {
+            // A write after reads only needs them to be done, not their memory.
+            let wait_stage = self.write_stage | self.read_stage;
+            return (!wait_stage.is_empty()).then_some((wait_stage, self.write_access));
+        }
+        let visible = self.visible_stage.contains(usage.stage) && self.visible_access.contains(usage.access);
+        (!self.write_stage.is_empty() && !visible).then_some((self.write_stage, self.write_access))
+    }
+
+    /// The state after `usage`, once its barrier (if any) was recorded.
+    fn after(&self, usage: &PassResource, had_barrier: bool) -> Self {
+        if usage.writes() {
+            return Self {
+                layout: usage.layout,
+                write_stage: usage.stage,
+                write_access: usage.access & WRITE_ACCESS,
+                visible_stage: vk::PipelineStageFlags::empty(),
+                visible_access: vk::AccessFlags::empty(),
+                read_stage: vk::PipelineStageFlags::empty(),
+            };
+        }
+        if usage.layout != self.layout {
+            // The transition is the last write, its memory is visible to the reader.
+            return Self {
+                layout: usage.layout,
+                write_stage: usage.stage,
+                write_access: vk::AccessFlags::empty(),
+                visible_stage: usage.stage,
+                visible_access: usage.access,
+                read_stage: usage.stage,
+            };
+        }
+        let (visible_stage, visible_access) = if had_barrier {
+            (self.visible_stage | usage.stage, self.visible_access | usage.access)
+        } else {
+            (self.visible_stage, self.visible_access)
+        };
+        Self {
+            visible_stage,
+            visible_access,
+            read_stage: self.read_stage | usage.stage,
+            ..*self
+        }
+    }
+}
+
+/// Inserts the barriers between the passes of a frame from the resources they declare, instead
+/// of writing each barrier by hand.
+///
+/// For every resource, it tracks the last write and the reads since, and before each pass
+/// records the barriers the pass needs: read after write, write after read or write, and the
+/// layout transitions of the images. All the barriers of a pass go in a single
+/// `vkCmdPipelineBarrier`, and reads of an already visible write get none.
+///
+/// # Scope
+/// This is not a render graph:
+/// - The passes run in the given linear order, they are never reordered or culled.
+/// - Every resource is a distinct allocation, memory aliasing between resources isn't supported.
+/// - Whole buffers and images only, not ranges, mip levels or layers.
+/// - A single queue, there are no ownership transfers or semaphores between queues.
+/// - Render pass attachments are transitioned by the render pass itself, so they must be
+///   declared in the layout the render pass leaves them in.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    states: HashMap<ResourceHandle, ResourceState>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares the state a resource is in before the first pass, e.g. a texture uploaded
+    /// earlier in `SHADER_READ_ONLY_OPTIMAL`. Undeclared resources start with nothing to wait
+    /// for, and images in the `UNDEFINED` layout, which discards their contents.
+    pub fn import(&mut self, resource: PassResource) {
+        let state = ResourceState {
+            layout: resource.layout,
+            write_stage: resource.stage,
+            write_access: resource.access & WRITE_ACCESS,
+            ..ResourceState::UNUSED
+        };
+        self.states.insert(resource.handle, state);
+    }
+
+    /// Records the `passes` in order into `command_buffer`, each preceded by the barriers it
+    /// needs.
+    pub fn insert_barriers(&mut self, device: &LogicalDevice, command_buffer: &CommandBuffer, passes: &[Pass]) {
+        for pass in passes {
+            self.barriers_before(device, command_buffer, pass);
+            (pass.record)(command_buffer, device);
+        }
+    }
+
+    /// The layout of the image of `handle` after the passes recorded so far.
+    pub fn layout(&self, handle: ResourceHandle) -> vk::ImageLayout {
+        self.states
+            .get(&handle)
+            .map_or(vk::ImageLayout::UNDEFINED, |state| state.layout)
+    }
+
+    /// Records the barriers `pass` needs, and updates the states of its resources.
+    fn barriers_before(&mut self, device: &LogicalDevice, command_buffer: &CommandBuffer, pass: &Pass) {
+        let mut src_stage = vk::PipelineStageFlags::empty();
+        let mut dst_stage = vk::PipelineStageFlags::empty();
+        let mut buffer_barriers = Vec::new();
+        let mut image_barriers = Vec::new();
+        for usage in &pass.resources {
+            let state = self.states.get(&usage.handle).copied().unwrap_or(ResourceState::UNUSED);
+            let hazard = state.hazard(usage);
+            if let Some((wait_stage, wait_access)) = hazard {
+                src_stage |= wait_stage;
+                dst_stage |= usage.stage;
+                match usage.handle {
+                    ResourceHandle::Buffer(buffer) => buffer_barriers.push(
+                        vk::BufferMemoryBarrier::builder()
+                            .src_access_mask(wait_access)
+                            .dst_access_mask(usage.access)
+                            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                            .buffer(buffer)
+                            .offset(0)
+                            .size(vk::WHOLE_SIZE)
+                            .build(),
+                    ),
+                    ResourceHandle::Image { image, aspect_mask } => {
+                        let subresource = vk::ImageSubresourceRange::builder()
+                            .aspect_mask(aspect_mask)
+                            .base_mip_level(0)
+                            .level_count(vk::REMAINING_MIP_LEVELS)
+                            .base_array_layer(0)
+                            .layer_count(vk::REMAINING_ARRAY_LAYERS);
+                        image_barriers.push(
+                            vk::ImageMemoryBarrier::builder()
+                                .old_layout(state.layout)
+                                .new_layout(usage.layout)
+                                .src_access_mask(wait_access)
+                                .dst_access_mask(usage.access)
+                                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                                .image(image)
+                                .subresource_range(subresource)
+                                .build(),
+                        );
+                    }
+                }
+            }
+            self.states.insert(usage.handle, state.after(usage, hazard.is_some()));
+        }
+        if buffer_barriers.is_empty() && image_barriers.is_empty() {
+            trace!("Pass {} needs no barrier.", pass.name);
+            return;
+        }
+        // A first use only transitions the layout, after nothing.
+        if src_stage.is_empty() {
+            src_stage = vk::PipelineStageFlags::TOP_OF_PIPE;
+        }
+        trace!(
+            "Pass {} waits on {src_stage:?} with {} buffer and {} image barriers.",
+            pass.name,
+            buffer_barriers.len(),
+            image_barriers.len()
+        );
+        device.pipeline_barrier(*command_buffer.get_vk(), src_stage, dst_stage, &buffer_barriers, &image_barriers);
+    }
+}
@@ -0,0 +1,59 @@
+use vulkanalia::vk;
+
+/// The outcome of the Vulkan calls of the render loop (acquire, submit and present), so
+/// [`App::render`](crate::gapi::app::App::render) branches on what it has to do rather than on
+/// raw result codes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderResult {
+    Success,
+    /// The call succeeded, but the swapchain no longer matches the surface exactly, e.g. after
+    /// a resize on some platforms. An acquired image can still be rendered and presented.
+    ///
+    /// Recreating the swapchain right away or after presenting both work, but recreating right
+    /// after acquiring throws the acquired image away and waits for the device to be idle in the
+    /// middle of the frame, a visible hitch. The render loop presents the frame first, and
+    /// recreates the swapchain before the next one.
+    Suboptimal,
+    /// The swapchain no longer matches the surface, and can't be presented to anymore.
+    OutOfDate,
+    /// No image became available in time, the GPU is probably hung.
+    Timeout,
+    /// The device was lost, e.g. after a driver reset. Nothing created from it can be used
+    /// anymore.
+    DeviceLost,
+    /// Any other error, which the render loop doesn't handle.
+    Error(vk::ErrorCode),
+}
+
+impl RenderResult {
+    /// Whether rendering can go on, possibly after recreating the swapchain, see
+    /// [`RenderResult::requires_recreate`].
+    pub fn is_recoverable(self) -> bool {
+        matches!(self, Self::Success | Self::Suboptimal | Self::OutOfDate)
+    }
+
+    /// Whether the swapchain must be recreated before presenting again.
+    pub fn requires_recreate(self) -> bool {
+        matches!(self, Self::Suboptimal | Self::OutOfDate)
+    }
+}
+
+impl From<vk::SuccessCode> for RenderResult {
+    fn from(code: vk::SuccessCode) -> Self {
+        match code {
+            vk::SuccessCode::SUBOPTIMAL_KHR => Self::Suboptimal,
+            vk::SuccessCode::TIMEOUT | vk::SuccessCode::NOT_READY => Self::Timeout,
+            _ => Self::Success,
+        }
+    }
+}
+
+impl From<vk::ErrorCode> for RenderResult {
+    fn from(code: vk::ErrorCode) -> Self {
+        match code {
+            vk::ErrorCode::OUT_OF_DATE_KHR => Self::OutOfDate,
+            vk::ErrorCode::DEVICE_LOST => Self::DeviceLost,
+            _ => Self::Error(code),
+        }
+    }
+}
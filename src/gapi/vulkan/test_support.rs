@@ -0,0 +1,264 @@
+use crate::gapi::app::{App, AppBuilder};
+use crate::gapi::vulkan::commands::command_buffers::CommandBuffer;
+use crate::gapi::vulkan::commands::command_pool::CommandPool;
+use crate::gapi::vulkan::core::debug::Debugger;
+use crate::gapi::vulkan::core::entry::Entry;
+use crate::gapi::vulkan::core::instance::Instance;
+use crate::gapi::vulkan::core::logical_device::LogicalDevice;
+use crate::gapi::vulkan::core::queues::{QueueCapability, QueueRequest, Queues};
+use crate::gapi::vulkan::core::real_device::RealDevice;
+use crate::gapi::vulkan::core::resource_tracker::ResourceTracker;
+use crate::gapi::vulkan::core::surface::Surface;
+use crate::gapi::vulkan::guard::Guard;
+use crate::window::MyWindow;
+use anyhow::{anyhow, bail, Context};
+use std::cell::Cell;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, MutexGuard, Once, OnceLock, PoisonError};
+use std::thread;
+use std::time::Duration;
+use vulkanalia::vk;
+use vulkanalia::vk::HasBuilder;
+use winit::event_loop::{EventLoop, EventLoopBuilder};
+
+/// # GPU tests
+/// The tests using the GPU run one at a time, so they don't compete for the device and the
+/// window thread of [`create_window`], and the validation errors counted during one aren't
+/// reported by another, see [`Debugger::validation_error_count`].
+///
+/// They need a Vulkan driver and the validation layer, and a display for those with a window,
+/// so they are ignored by default, run them with `cargo test -- --ignored`.
+///
+/// The validation layer also checks the synchronization, e.g. the dependencies between the
+/// passes, see [`enable_synchronization_validation`].
+pub(crate) fn lock_gpu() -> MutexGuard<'static, ()> {
+    static GPU: Mutex<()> = Mutex::new(());
+    // A failed test poisons the lock, which doesn't matter to the next one.
+    let gpu = GPU.lock().unwrap_or_else(PoisonError::into_inner);
+    enable_synchronization_validation();
+    gpu
+}
+
+/// Asks the validation layer for the synchronization checks, which are off by default as they
+/// are slow. The layer reads its settings from the environment when an instance is created.
+fn enable_synchronization_validation() {
+    const SETTING: &str = "VK_KHRONOS_VALIDATION_VALIDATE_SYNC";
+    static ENABLED: Once = Once::new();
+    ENABLED.call_once(|| {
+        if std::env::var_os(SETTING).is_none() {
+            // SAFETY: it runs once, before the first GPU test creates an instance or the window
+            // thread. The tests only read the environment while holding the GPU lock.
+            unsafe { std::env::set_var(SETTING, "true") };
+        }
+    });
+}
+
+/// Runs `test` with a device of the first GPU that has a graphics queue, created without a
+/// surface and with validation, then checks that `test` destroyed everything it created and
+/// that the validation layer reported no error.
+pub(crate) fn with_headless_device<F>(test: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&RealDevice, &LogicalDevice) -> anyhow::Result<()>,
+{
+    let _gpu = lock_gpu();
+    let entry = Entry::new()?;
+    let instance = Instance::new_headless(&entry, true)?;
+    let instance = Guard::new(instance, Instance::destroy);
+    let instance_ref: &Instance = &instance;
+    let debugger = Debugger::new(&instance, Debugger::DEFAULT_MESSAGE_TYPES)?;
+    let debugger = Guard::new(debugger, |debugger| debugger.destroy(instance_ref));
+    let requests = [QueueRequest {
+        capabilities: vec![QueueCapability::Graphics],
+        require_present: false,
+        count: 1,
+    }];
+    let real_device = instance
+        .enumerate_real_devices()?
+        .into_iter()
+        .find(|real_device| Queues::resolve_headless_requests(real_device, &requests).is_ok())
+        .with_context(|| "No GPU has a graphics queue")?;
+    let device = LogicalDevice::new_headless(&real_device, &instance, &requests, &[])?;
+    let device = Guard::new(device, LogicalDevice::destroy);
+
+    let errors_before = Debugger::validation_error_count();
+    let result = test(&real_device, &device);
+    device.device_wait_idle()?;
+    let leaks = device.resources().leaks();
+    drop(device);
+    drop(debugger);
+    drop(instance);
+    result?;
+    assert!(leaks.is_empty(), "Vulkan objects leaked: {leaks:?}");
+    assert_eq!(
+        Debugger::validation_error_count(),
+        errors_before,
+        "The validation layer reported errors, see the logs."
+    );
+    Ok(())
+}
+
+/// Records `commands` into a command buffer of the graphics queue, submits it and waits for it.
+pub(crate) fn submit_and_wait<F>(device: &LogicalDevice, commands: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&CommandBuffer) -> anyhow::Result<()>,
+{
+    let pool = CommandPool::for_family(device, device.graphics_family_index(), vk::CommandPoolCreateFlags::TRANSIENT)?;
+    let pool = Guard::new(pool, |pool| pool.destroy(device));
+    let allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_pool(pool.get_vk())
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1);
+    let command_buffer = CommandBuffer::new(device.allocate_command_buffers(&allocate_info)?[0]);
+    command_buffer.begin(device)?;
+    commands(&command_buffer)?;
+    command_buffer.end(device)?;
+
+    let fence = device.create_fence(&vk::FenceCreateInfo::builder())?;
+    let fence = Guard::new(fence, |fence| device.destroy_fence(*fence));
+    let command_buffers = &[*command_buffer.get_vk()];
+    let submit_info = vk::SubmitInfo::builder().command_buffers(command_buffers);
+    device.queue_submit(device.get_queues().graphics_queue()?, &[submit_info], *fence)?;
+    device.wait_for_fences(&[*fence], true, Duration::from_secs(5))
+}
+
+/// Creates a window for the tests needing a whole [`App`].
+///
+/// winit allows a single event loop per process, which must be created on the main thread
+/// unless told otherwise, while each test runs on a thread of its own. So the event loop lives
+/// on a dedicated thread, building the windows the tests ask for. It is never run, the windows
+/// only need to exist for the surface to be created and presented to.
+pub(crate) fn create_window() -> anyhow::Result<MyWindow> {
+    static WINDOW_REQUESTS: OnceLock<Sender<Sender<anyhow::Result<MyWindow>>>> = OnceLock::new();
+    let requests = WINDOW_REQUESTS.get_or_init(|| {
+        let (requests, received) = mpsc::channel::<Sender<anyhow::Result<MyWindow>>>();
+        thread::spawn(move || {
+            let event_loop = build_any_thread_event_loop().map_err(|e| e.to_string());
+            for reply in received {
+                let window = match &event_loop {
+                    Ok(event_loop) => MyWindow::new(event_loop),
+                    Err(e) => Err(anyhow!("Failed to create the event loop of the tests: {e}")),
+                };
+                let _ = reply.send(window);
+            }
+        });
+        requests
+    });
+    let (reply, window) = mpsc::channel();
+    requests.send(reply).map_err(|_| anyhow!("The window thread of the tests stopped"))?;
+    window.recv().map_err(|_| anyhow!("The window thread of the tests stopped"))?
+}
+
+fn build_any_thread_event_loop() -> Result<EventLoop<()>, winit::error::EventLoopError> {
+    let mut builder = EventLoopBuilder::new();
+    #[cfg(all(unix, not(target_os = "macos")))]
+    winit::platform::x11::EventLoopBuilderExtX11::with_any_thread(&mut builder, true);
+    #[cfg(windows)]
+    winit::platform::windows::EventLoopBuilderExtWindows::with_any_thread(&mut builder, true);
+    builder.build()
+}
+
+/// Builds an app with validation from `builder` in a new window, runs `test` with it, destroys
+/// it, then checks that it destroyed everything it created, in debug builds, and that the
+/// validation layer reported no error.
+pub(crate) fn with_app<F>(builder: AppBuilder, test: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&mut App, &MyWindow) -> anyhow::Result<()>,
+{
+    let _gpu = lock_gpu();
+    let window = create_window()?;
+    let errors_before = Debugger::validation_error_count();
+    let leak_reports_before = ResourceTracker::leak_report_count();
+    let mut app = builder.validation(true).build(&window)?;
+    let result = test(&mut app, &window);
+    app.destroy();
+    result?;
+    assert_eq!(
+        ResourceTracker::leak_report_count(),
+        leak_reports_before,
+        "Vulkan objects leaked, see the logs."
+    );
+    assert_eq!(
+        Debugger::validation_error_count(),
+        errors_before,
+        "The validation layer reported errors, see the logs."
+    );
+    Ok(())
+}
+
+/// Runs `test` with a device of the first GPU that has a graphics queue able to present to a new
+/// window, created with validation, without the rest of an [`App`].
+pub(crate) fn with_device<F>(test: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&LogicalDevice) -> anyhow::Result<()>,
+{
+    let _gpu = lock_gpu();
+    let window = create_window()?;
+    let entry = Entry::new()?;
+    let instance = Instance::new(&entry, &window, true)?;
+    let instance = Guard::new(instance, Instance::destroy);
+    let surface = Surface::new(&instance, &window)?;
+    let surface = Guard::new(surface, |surface| surface.destroy(&instance));
+    let requests = [QueueRequest {
+        capabilities: vec![QueueCapability::Graphics],
+        require_present: true,
+        count: 1,
+    }];
+    let real_device = instance
+        .enumerate_real_devices()?
+        .into_iter()
+        .find(|real_device| Queues::resolve_queue_requests(real_device, &surface, &requests).is_ok())
+        .with_context(|| "No GPU has a graphics queue able to present")?;
+    let device = LogicalDevice::new(&real_device, &instance, &surface, &requests, &[])?;
+    let device = Guard::new(device, LogicalDevice::destroy);
+
+    let result = test(&device);
+    device.device_wait_idle()?;
+    result
+}
+
+thread_local! {
+    /// The failure point to fail at, counted from 0, and how many were passed so far, see
+    /// [`for_each_failure_point`].
+    static INJECTED_FAILURE: Cell<(Option<usize>, usize)> = const { Cell::new((None, 0)) };
+}
+
+/// Fails at the failure point set by [`for_each_failure_point`], see
+/// [`failure_point`](crate::gapi::vulkan::guard::failure_point).
+pub(crate) fn fail_if_injected() -> anyhow::Result<()> {
+    INJECTED_FAILURE.with(|injected| {
+        let (fail_at, passed) = injected.get();
+        injected.set((fail_at, passed + 1));
+        if fail_at == Some(passed) {
+            bail!("Injected failure at failure point {passed}.");
+        }
+        Ok(())
+    })
+}
+
+/// Runs `attempt` once per [`failure_point`](crate::gapi::vulkan::guard::failure_point) it
+/// passes, failing at a later one each time, then runs `check` with the failure point, e.g. to
+/// check that nothing leaked. Returns what `attempt` created once it passed them all.
+///
+/// # Panics
+/// If `attempt` fails with another error than the injected one, or has no failure point.
+pub(crate) fn for_each_failure_point<T>(
+    mut attempt: impl FnMut() -> anyhow::Result<T>,
+    mut check: impl FnMut(usize),
+) -> T {
+    let mut fail_at = 0;
+    loop {
+        INJECTED_FAILURE.with(|injected| injected.set((Some(fail_at), 0)));
+        let result = attempt();
+        INJECTED_FAILURE.with(|injected| injected.set((None, 0)));
+        match result {
+            Err(e) => assert!(e.to_string().starts_with("Injected failure"), "Unexpected error: {e:?}"),
+            // Past the last failure point.
+            Ok(created) => {
+                assert!(fail_at > 0, "No failure point was passed.");
+                return created;
+            }
+        }
+        check(fail_at);
+        fail_at += 1;
+    }
+}
@@ -112,3 +112,27 @@ macro_rules! info_warning
 #[macro_export]
 macro_rules! warn_warning
 { ($($arg:tt)*) => { ::log::warn! (target: "warning",  "[WARNING] {}",  format!($($arg)*)); }; }
+
+/// Traces a call into the Vulkan API, only when the `vk-trace` feature is enabled.
+///
+/// The `LogicalDevice` wrappers trace every call with its arguments, which means formatting
+/// create infos and handles with `Debug` on hot paths like `cmd_draw`, several times per draw
+/// and per frame. That formatting isn't skipped by the log level filter, since traces are always
+/// enabled, so without the feature the call is compiled out entirely: the condition is a
+/// constant and the branch is removed, while the arguments are still type checked.
+///
+/// The frame time saved hasn't been measured yet. To measure it, render the same scene for a
+/// fixed number of frames in a release build with the trace level enabled, once with
+/// `--features vk-trace` and once without, and compare the mean frame time, i.e.
+/// [`FrameStats::elapsed_seconds`] over [`FrameStats::frame_index`] from `App::frame_stats`.
+///
+/// [`FrameStats::elapsed_seconds`]: crate::gapi::frame_uniforms::FrameStats::elapsed_seconds
+/// [`FrameStats::frame_index`]: crate::gapi::frame_uniforms::FrameStats::frame_index
+#[macro_export]
+macro_rules! trace_vk {
+    ($($arg:tt)*) => {
+        if $crate::gapi::vulkan::config::VK_TRACE_ENABLED {
+            ::log::trace!($($arg)*);
+        }
+    };
+}
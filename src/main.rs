@@ -5,11 +5,12 @@ mod gapi;
 mod log;
 mod window;
 
-use crate::gapi::app::App as GraphicApp;
+use crate::gapi::app::{AppBuilder, RedrawMode};
 use crate::log::log::init_log;
 use anyhow::{Context, Result};
-use winit::event::{Event, WindowEvent};
-use winit::event_loop::EventLoop;
+use winit::event::{ElementState, Event, KeyEvent, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+use winit::event_loop::{ControlFlow, EventLoop};
 use crate::window::MyWindow;
 
 fn main() -> Result<()> {
@@ -40,15 +41,101 @@ fn run() -> Result<()> {
 
     // App
     debug!("Creating App...");
-    let mut app = GraphicApp::new(&window)?;
+    let mut app = AppBuilder::default().build(&window)?;
     info_success!("App Created!");
+    let redraw_mode = app.redraw_mode();
+    debug!("Redraw mode: {redraw_mode:?}");
+    // Poll keeps the loop spinning, while Wait sleeps until the next event arrives, which is
+    // also what a paused app needs.
+    let control_flow = |paused: bool| match redraw_mode {
+        RedrawMode::Continuous if !paused => ControlFlow::Poll,
+        _ => ControlFlow::Wait,
+    };
+    event_loop.set_control_flow(control_flow(app.is_paused()));
     event_loop.run(move |event, elwt| {
         match event {
             // Request a redrawing when all events were processed.
-            Event::AboutToWait => window.request_redraw(),
+            Event::AboutToWait
+                if redraw_mode == RedrawMode::Continuous && !app.is_paused() && !window.is_minimized() =>
+            {
+                window.request_redraw()
+            }
             Event::WindowEvent { event, .. } => match event {
-                // Render a frame if our Vulkan app is not being destroyed.
-                WindowEvent::RedrawRequested if !elwt.exiting() => app.render(&window).unwrap(),
+                // Render a frame if our Vulkan app is not being destroyed nor paused.
+                // A failed frame leaves the app unusable, so it exits like a close request.
+                WindowEvent::RedrawRequested if !elwt.exiting() && !app.is_paused() => {
+                    if let Err(err) = app.render(&window) {
+                        error!("Failed to render a frame, exiting: {err:?}");
+                        elwt.exit();
+                        app.destroy();
+                    }
+                }
+                // Pauses the rendering while unfocused, if configured.
+                WindowEvent::Focused(focused) => {
+                    app.set_focused(focused);
+                    elwt.set_control_flow(control_flow(app.is_paused()));
+                    if !app.is_paused() {
+                        window.request_redraw();
+                    }
+                }
+                // Sleeps while minimized, until the resize of the restore wakes the loop up again.
+                WindowEvent::Resized(_) if window.is_minimized() => {
+                    debug!("Window minimized, waiting for it to be restored.");
+                    elwt.set_control_flow(ControlFlow::Wait);
+                }
+                WindowEvent::Resized(_) if redraw_mode == RedrawMode::Continuous => {
+                    elwt.set_control_flow(control_flow(app.is_paused()));
+                    window.request_redraw();
+                }
+                // Moving the window to a monitor with a different scale factor changes its size in
+                // physical pixels, so the swapchain must be recreated to match it.
+                WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                    debug!("Scale factor changed to {scale_factor}.");
+                    app.request_swapchain_recreation();
+                    window.request_redraw();
+                }
+                // F3 cycles through the debug draw modes (points, wireframe, solid).
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key: PhysicalKey::Code(KeyCode::F3),
+                            state: ElementState::Pressed,
+                            repeat: false,
+                            ..
+                        },
+                    ..
+                } => {
+                    if let Err(err) = app.cycle_debug_draw_mode() {
+                        error!("Failed to change the debug draw mode: {err:?}");
+                    }
+                    window.request_redraw();
+                }
+                // F4 cycles through the supported present modes, in debug builds.
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key: PhysicalKey::Code(KeyCode::F4),
+                            state: ElementState::Pressed,
+                            repeat: false,
+                            ..
+                        },
+                    ..
+                } if cfg!(debug_assertions) => {
+                    if let Err(err) = app.cycle_present_mode() {
+                        error!("Failed to change the present mode: {err:?}");
+                    }
+                    window.request_redraw();
+                }
+                // On demand, only input and size changes can change what is shown.
+                WindowEvent::Resized(_)
+                | WindowEvent::KeyboardInput { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. }
+                | WindowEvent::CursorMoved { .. }
+                    if redraw_mode == RedrawMode::OnDemand =>
+                {
+                    window.request_redraw()
+                }
                 // Destroy our Vulkan app.
                 WindowEvent::CloseRequested => {
                     elwt.exit();
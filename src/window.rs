@@ -1,6 +1,8 @@
+use anyhow::{anyhow, bail};
 use vulkanalia::vk::ExtensionName;
 use vulkanalia::window as vk_window;
 use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
 use winit::window::{Window, WindowBuilder};
 
 pub struct MyWindow {
@@ -21,17 +23,51 @@ impl MyWindow {
         })
     }
 
-    pub fn get_required_extensions(&self) -> &'static [&'static ExtensionName] {
-        vk_window::get_required_instance_extensions(&self.winit_window)
+    /// Returns the instance extensions the windowing backend needs to create a surface.
+    ///
+    /// # Errors
+    /// - If the window handle can't be retrieved.
+    /// - If the windowing backend is not supported by Vulkan surface creation (vulkanalia panics
+    /// on these, so we check the handle beforehand).
+    pub fn get_required_extensions(&self) -> anyhow::Result<&'static [&'static ExtensionName]> {
+        let handle = self
+            .winit_window
+            .window_handle()
+            .map_err(|e| anyhow!("Failed to get the window handle: {}", e))?;
+        match handle.as_raw() {
+            RawWindowHandle::Wayland(_)
+            | RawWindowHandle::Xcb(_)
+            | RawWindowHandle::Xlib(_)
+            | RawWindowHandle::AppKit(_)
+            | RawWindowHandle::Win32(_) => {
+                Ok(vk_window::get_required_instance_extensions(&self.winit_window))
+            }
+            other => bail!("Unsupported windowing backend: {:?}", other),
+        }
     }
     pub fn get_winnit(&self) -> &Window {
         &self.winit_window
     }
 
+    /// Returns the size of the window's client area in physical pixels, which is what the
+    /// swapchain extent must match. On HiDPI displays this is the logical size times the scale
+    /// factor.
+    ///
+    /// On Wayland with fractional scaling (e.g. 1.5), the compositor may scale the buffer itself,
+    /// so the size can be briefly off until the next `Resized` event is received.
     pub fn size(&self) -> PhysicalSize<u32> {
         self.winit_window.inner_size()
     }
 
+    /// Whether the window is minimized, in which case nothing can be presented to it.
+    ///
+    /// Some platforms can't tell (e.g. Wayland), and Windows reports a zero size instead of
+    /// (or besides) the minimized flag, so a zero size counts as minimized too.
+    pub fn is_minimized(&self) -> bool {
+        let size = self.size();
+        self.winit_window.is_minimized().unwrap_or(false) || size.width == 0 || size.height == 0
+    }
+
     pub fn request_redraw(&self) {
         self.winit_window.request_redraw();
     }
@@ -0,0 +1,283 @@
+// The crate is a binary, so the color utilities under test are compiled into this test as is.
+#[allow(dead_code)]
+#[path = "../src/gapi/color.rs"]
+mod color;
+
+use anyhow::{anyhow, Context};
+use color::Color;
+use vulkanalia::loader::{LibloadingLoader, LIBRARY};
+use vulkanalia::prelude::v1_0::*;
+
+/// The swapchain format the app picks when the surface supports it.
+const FORMAT: vk::Format = vk::Format::B8G8R8A8_SRGB;
+const EXTENT: vk::Extent3D = vk::Extent3D { width: 4, height: 4, depth: 1 };
+const TEXEL_SIZE: vk::DeviceSize = 4;
+
+/// Clearing a `B8G8R8A8_SRGB` image to a linear color stores the sRGB encoding of the color,
+/// which is what the screen shows. The clear value is built like the one of the render pass,
+/// through the `ClearColorValue` conversion of [`Color`].
+///
+/// It only needs a Vulkan driver, no window: the image is cleared and copied back to the CPU
+/// without a surface. Run it with `cargo test -- --ignored`.
+#[test]
+#[ignore = "needs a Vulkan device"]
+fn srgb_image_stores_the_encoded_clear_color() -> anyhow::Result<()> {
+    let linear = Color::rgb(0.5, 0.2, 0.0);
+    let pixels = clear_and_read_back(linear)?;
+
+    // Linear 0.5 is 0.735 once encoded, not the 128 of a UNORM image.
+    let [r, g, b, a] = linear.linear_to_srgb().to_array().map(|c| (c * 255.0).round() as u8);
+    assert_eq!([r, g, b, a], [188, 124, 0, 255]);
+    assert_eq!(pixels.len() as vk::DeviceSize, texels() * TEXEL_SIZE);
+    for texel in pixels.chunks_exact(TEXEL_SIZE as usize) {
+        // Drivers may round the encoded value either way.
+        let expected = [b, g, r, a];
+        let close = texel.iter().zip(expected).all(|(&byte, expected)| byte.abs_diff(expected) <= 1);
+        assert!(close, "Read back {texel:?}, expected {expected:?} in BGRA order.");
+    }
+    Ok(())
+}
+
+fn texels() -> vk::DeviceSize {
+    EXTENT.width as vk::DeviceSize * EXTENT.height as vk::DeviceSize
+}
+
+/// Clears an [`FORMAT`] image to `color` on the first device with a graphics queue, and returns
+/// its texels, row by row.
+fn clear_and_read_back(color: Color) -> anyhow::Result<Vec<u8>> {
+    let loader = unsafe { LibloadingLoader::new(LIBRARY) }
+        .with_context(|| format!("Failed to load Vulkan library: {}", LIBRARY))?;
+    let entry = unsafe { Entry::new(loader) }.map_err(|b| anyhow!("Failed to load Vulkan entry: {}", b))?;
+    let application = vk::ApplicationInfo::builder().api_version(vk::make_version(1, 0, 0));
+    let instance = unsafe { entry.create_instance(&vk::InstanceCreateInfo::builder().application_info(&application), None) }?;
+
+    let result = clear_and_read_back_with(&instance, color);
+    unsafe { instance.destroy_instance(None) };
+    result
+}
+
+fn clear_and_read_back_with(instance: &Instance, color: Color) -> anyhow::Result<Vec<u8>> {
+    let (real_device, family_index) = unsafe { instance.enumerate_physical_devices() }?
+        .into_iter()
+        .find_map(|real_device| {
+            let families = unsafe { instance.get_physical_device_queue_family_properties(real_device) };
+            let family_index = families
+                .iter()
+                .position(|family| family.queue_flags.contains(vk::QueueFlags::GRAPHICS))?;
+            Some((real_device, family_index as u32))
+        })
+        .with_context(|| "No GPU has a graphics queue")?;
+    let priorities = [1.0];
+    let queue_infos = [vk::DeviceQueueCreateInfo::builder()
+        .queue_family_index(family_index)
+        .queue_priorities(&priorities)];
+    let device_info = vk::DeviceCreateInfo::builder().queue_create_infos(&queue_infos);
+    let device = unsafe { instance.create_device(real_device, &device_info, None) }?;
+    let memory_properties = unsafe { instance.get_physical_device_memory_properties(real_device) };
+
+    let result = record_and_read_back(&device, &memory_properties, family_index, color);
+    unsafe { device.destroy_device(None) };
+    result
+}
+
+/// The first memory type among the `allowed` ones that has the `properties`.
+fn memory_type(
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    allowed: u32,
+    properties: vk::MemoryPropertyFlags,
+) -> anyhow::Result<u32> {
+    (0..memory_properties.memory_type_count)
+        .find(|&i| {
+            allowed & (1 << i) != 0
+                && memory_properties.memory_types[i as usize].property_flags.contains(properties)
+        })
+        .with_context(|| format!("No memory type is {properties:?}"))
+}
+
+fn record_and_read_back(
+    device: &Device,
+    memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    family_index: u32,
+    color: Color,
+) -> anyhow::Result<Vec<u8>> {
+    let image_info = vk::ImageCreateInfo::builder()
+        .image_type(vk::ImageType::_2D)
+        .format(FORMAT)
+        .extent(EXTENT)
+        .mip_levels(1)
+        .array_layers(1)
+        .samples(vk::SampleCountFlags::_1)
+        .tiling(vk::ImageTiling::OPTIMAL)
+        .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::TRANSFER_SRC)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE)
+        .initial_layout(vk::ImageLayout::UNDEFINED);
+    let image = unsafe { device.create_image(&image_info, None) }?;
+    let requirements = unsafe { device.get_image_memory_requirements(image) };
+    let image_memory_info = vk::MemoryAllocateInfo::builder()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type(memory_properties, requirements.memory_type_bits, vk::MemoryPropertyFlags::empty())?);
+    let image_memory = unsafe { device.allocate_memory(&image_memory_info, None) }?;
+    unsafe { device.bind_image_memory(image, image_memory, 0) }?;
+
+    let size = texels() * TEXEL_SIZE;
+    let buffer_info = vk::BufferCreateInfo::builder()
+        .size(size)
+        .usage(vk::BufferUsageFlags::TRANSFER_DST)
+        .sharing_mode(vk::SharingMode::EXCLUSIVE);
+    let buffer = unsafe { device.create_buffer(&buffer_info, None) }?;
+    let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+    let host_visible = vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+    let buffer_memory_info = vk::MemoryAllocateInfo::builder()
+        .allocation_size(requirements.size)
+        .memory_type_index(memory_type(memory_properties, requirements.memory_type_bits, host_visible)?);
+    let buffer_memory = unsafe { device.allocate_memory(&buffer_memory_info, None) }?;
+    unsafe { device.bind_buffer_memory(buffer, buffer_memory, 0) }?;
+
+    let pool_info = vk::CommandPoolCreateInfo::builder()
+        .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+        .queue_family_index(family_index);
+    let pool = unsafe { device.create_command_pool(&pool_info, None) }?;
+    let fence = unsafe { device.create_fence(&vk::FenceCreateInfo::builder(), None) }?;
+
+    let result = submit_clear_and_copy(device, pool, fence, family_index, image, buffer, color)
+        .and_then(|()| {
+            let ptr = unsafe { device.map_memory(buffer_memory, 0, size, vk::MemoryMapFlags::empty()) }?;
+            let bytes = unsafe { std::slice::from_raw_parts(ptr.cast::<u8>(), size as usize) }.to_vec();
+            unsafe { device.unmap_memory(buffer_memory) };
+            Ok(bytes)
+        });
+
+    unsafe {
+        device.destroy_fence(fence, None);
+        device.destroy_command_pool(pool, None);
+        device.destroy_buffer(buffer, None);
+        device.free_memory(buffer_memory, None);
+        device.destroy_image(image, None);
+        device.free_memory(image_memory, None);
+    }
+    result
+}
+
+/// Clears the `image` to `color`, copies it to the `buffer`, then waits for it.
+fn submit_clear_and_copy(
+    device: &Device,
+    pool: vk::CommandPool,
+    fence: vk::Fence,
+    family_index: u32,
+    image: vk::Image,
+    buffer: vk::Buffer,
+    color: Color,
+) -> anyhow::Result<()> {
+    let allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_pool(pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1);
+    let command_buffer = unsafe { device.allocate_command_buffers(&allocate_info) }?[0];
+    let begin_info = vk::CommandBufferBeginInfo::builder().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+    unsafe { device.begin_command_buffer(command_buffer, &begin_info) }?;
+
+    let range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build();
+    let layout_barrier = |old_layout, new_layout, src_access, dst_access| {
+        vk::ImageMemoryBarrier::builder()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_access_mask(src_access)
+            .dst_access_mask(dst_access)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(range)
+            .build()
+    };
+    let to_transfer_dst = layout_barrier(
+        vk::ImageLayout::UNDEFINED,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::AccessFlags::empty(),
+        vk::AccessFlags::TRANSFER_WRITE,
+    );
+    let to_transfer_src = layout_barrier(
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        vk::AccessFlags::TRANSFER_WRITE,
+        vk::AccessFlags::TRANSFER_READ,
+    );
+    // The values are linear, the format encodes them to sRGB when they are written.
+    let clear_value: vk::ClearColorValue = color.into();
+    let region = vk::BufferImageCopy::builder()
+        .buffer_offset(0)
+        .buffer_row_length(0)
+        .buffer_image_height(0)
+        .image_subresource(vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        })
+        .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+        .image_extent(EXTENT);
+    let to_host = vk::BufferMemoryBarrier::builder()
+        .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+        .dst_access_mask(vk::AccessFlags::HOST_READ)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .buffer(buffer)
+        .offset(0)
+        .size(vk::WHOLE_SIZE);
+    unsafe {
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &[to_transfer_dst],
+        );
+        device.cmd_clear_color_image(
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &clear_value,
+            &[range],
+        );
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[] as &[vk::BufferMemoryBarrier],
+            &[to_transfer_src],
+        );
+        device.cmd_copy_image_to_buffer(
+            command_buffer,
+            image,
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            buffer,
+            &[region],
+        );
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[] as &[vk::MemoryBarrier],
+            &[to_host],
+            &[] as &[vk::ImageMemoryBarrier],
+        );
+        device.end_command_buffer(command_buffer)?;
+
+        let queue = device.get_device_queue(family_index, 0);
+        let command_buffers = [command_buffer];
+        let submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers);
+        device.queue_submit(queue, &[submit_info], fence)?;
+        device.wait_for_fences(&[fence], true, u64::MAX)?;
+    }
+    Ok(())
+}